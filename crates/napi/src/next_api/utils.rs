@@ -12,7 +12,7 @@ use serde::Serialize;
 use turbo_tasks::{
     trace::TraceRawVcs, ReadRef, TaskId, TryJoinIterExt, TurboTasks, UpdateInfo, Vc,
 };
-use turbo_tasks_backend::{default_backing_storage, DefaultBackingStorage};
+use turbo_tasks_backend::{default_backing_storage, BackendOptions, DefaultBackingStorage};
 use turbo_tasks_fs::FileContent;
 use turbopack_core::{
     diagnostics::{Diagnostic, DiagnosticContextExt, PlainDiagnostic},
@@ -128,9 +128,10 @@ pub fn create_turbo_tasks(
 ) -> Result<NextTurboTasks> {
     Ok(if persistent_caching {
         NextTurboTasks::PersistentCaching(TurboTasks::new(
-            turbo_tasks_backend::TurboTasksBackend::new(default_backing_storage(
-                &output_path.join("cache/turbopack"),
-            )?),
+            turbo_tasks_backend::TurboTasksBackend::new(
+                BackendOptions::default(),
+                default_backing_storage(&output_path.join("cache/turbopack"))?,
+            ),
         ))
     } else {
         NextTurboTasks::Memory(TurboTasks::new(turbo_tasks_memory::MemoryBackend::new(
@@ -8,7 +8,6 @@ use next_core::{
     },
     get_edge_resolve_options_context, get_next_package,
     next_app::{
-        app_client_references_chunks::get_app_server_reference_modules,
         get_app_client_references_chunks, get_app_client_shared_chunk_group, get_app_page_entry,
         get_app_route_entry, include_modules_module::IncludeModulesModule,
         metadata::route::get_app_metadata_route_entry, AppEntry, AppPage,
@@ -18,7 +17,8 @@ use next_core::{
         get_client_runtime_entries, ClientContextType, RuntimeEntries,
     },
     next_client_reference::{
-        client_reference_graph, find_server_entries, ClientReferenceGraphResult,
+        client_reference_graph, client_reference_graph_json, find_server_entries,
+        incremental_client_reference_graph, ClientReferenceGraphResult, LayoutSegmentTraversal,
         NextEcmascriptClientReferenceTransition, ServerEntries, VisitedClientReferenceGraphNodes,
     },
     next_config::NextConfig,
@@ -858,254 +858,278 @@ impl AppEndpoint {
             None
         };
 
-        let (
-            app_server_reference_modules,
-            client_dynamic_imports,
-            client_references,
-            client_references_chunks,
-        ) = if process_client_components {
-            let client_shared_chunk_group = get_app_client_shared_chunk_group(
-                AssetIdent::from_path(this.app_project.project().project_path())
-                    .with_modifier(client_shared_chunks()),
-                this.app_project.client_runtime_entries(),
-                client_chunking_context,
-            )
-            .await?;
+        let (client_dynamic_imports, client_references, client_references_chunks) =
+            if process_client_components {
+                let client_shared_chunk_group = get_app_client_shared_chunk_group(
+                    AssetIdent::from_path(this.app_project.project().project_path())
+                        .with_modifier(client_shared_chunks()),
+                    this.app_project.client_runtime_entries(),
+                    client_chunking_context,
+                )
+                .await?;
 
-            let mut client_shared_chunks_paths = vec![];
-            for chunk in client_shared_chunk_group.assets.await?.iter().copied() {
-                client_assets.insert(chunk);
+                let mut client_shared_chunks_paths = vec![];
+                for chunk in client_shared_chunk_group.assets.await?.iter().copied() {
+                    client_assets.insert(chunk);
 
-                let chunk_path = chunk.ident().path().await?;
-                if chunk_path.extension_ref() == Some("js") {
-                    if let Some(chunk_path) = client_relative_path_ref.get_path_to(&chunk_path) {
-                        client_shared_chunks_paths.push(chunk_path.into());
+                    let chunk_path = chunk.ident().path().await?;
+                    if chunk_path.extension_ref() == Some("js") {
+                        if let Some(chunk_path) = client_relative_path_ref.get_path_to(&chunk_path)
+                        {
+                            client_shared_chunks_paths.push(chunk_path.into());
+                        }
                     }
                 }
-            }
-            let client_shared_availability_info = client_shared_chunk_group.availability_info;
-
-            let client_references = {
-                let ServerEntries {
-                    server_component_entries,
-                    server_utils,
-                } = &*find_server_entries(rsc_entry).await?;
+                let client_shared_availability_info = client_shared_chunk_group.availability_info;
+
+                let client_references = {
+                    let ServerEntries {
+                        server_component_entries,
+                        server_utils,
+                    } = &*find_server_entries(rsc_entry, runtime).await?;
+                    let limits = this
+                        .app_project
+                        .project()
+                        .next_config()
+                        .client_reference_traversal_limits();
+
+                    let mut client_references = client_reference_graph(
+                        server_utils.clone(),
+                        VisitedClientReferenceGraphNodes::empty(),
+                        runtime,
+                        limits,
+                    )
+                    .await?
+                    .clone_value();
 
-                let mut client_references = client_reference_graph(
-                    server_utils.clone(),
-                    VisitedClientReferenceGraphNodes::empty(),
-                )
-                .await?
-                .clone_value();
+                    let server_component_client_references = incremental_client_reference_graph(
+                        server_component_entries
+                            .iter()
+                            .map(|m| Vc::upcast::<Box<dyn Module>>(*m))
+                            .chain(std::iter::once(rsc_entry))
+                            .collect(),
+                        runtime,
+                        limits,
+                        LayoutSegmentTraversal::Sharded,
+                    )
+                    .await?;
+                    client_references.extend(&server_component_client_references);
+                    client_references.dedupe_css_client_references().await?;
+                    client_references
+                };
+                let client_references_cell = client_references.clone().cell();
+                client_references_cell
+                    .check_budget(
+                        this.app_project
+                            .project()
+                            .next_config()
+                            .client_reference_budget(),
+                        rsc_entry.ident().path(),
+                    )
+                    .await?;
+                server_assets.insert(client_reference_graph_json(
+                    client_references_cell,
+                    node_root.join(
+                        format!(
+                            "diagnostics/app{}/client-reference-graph.json",
+                            app_entry.original_name
+                        )
+                        .into(),
+                    ),
+                ));
 
-                for module in server_component_entries
-                    .iter()
-                    .map(|m| Vc::upcast::<Box<dyn Module>>(*m))
-                    .chain(std::iter::once(rsc_entry))
-                {
-                    let current_client_references =
-                        client_reference_graph(vec![module], client_references.visited_nodes)
-                            .await?;
+                let client_dynamic_imports = {
+                    let mut client_dynamic_imports = FxIndexMap::default();
+                    let mut visited_modules = VisitedDynamicImportModules::empty();
+
+                    for refs in client_references
+                        .client_references_by_server_component
+                        .values()
+                    {
+                        let result = collect_next_dynamic_imports(
+                            refs.clone(),
+                            Vc::upcast(this.app_project.client_module_context()),
+                            visited_modules,
+                        )
+                        .await?;
+                        client_dynamic_imports.extend(
+                            result
+                                .client_dynamic_imports
+                                .iter()
+                                .map(|(k, v)| (*k, v.clone())),
+                        );
+                        visited_modules = result.visited_modules;
+                    }
 
-                    client_references.extend(&current_client_references);
-                }
-                client_references
-            };
-            let client_references_cell = client_references.clone().cell();
+                    client_dynamic_imports
+                };
 
-            let client_dynamic_imports = {
-                let mut client_dynamic_imports = FxIndexMap::default();
-                let mut visited_modules = VisitedDynamicImportModules::empty();
+                let client_references_chunks = get_app_client_references_chunks(
+                    client_references_cell,
+                    client_chunking_context,
+                    Value::new(client_shared_availability_info),
+                    ssr_chunking_context,
+                );
+                let client_references_chunks_ref = client_references_chunks.await?;
 
-                for refs in client_references
-                    .client_references_by_server_component
+                let mut entry_client_chunks = FxIndexSet::default();
+                // TODO(alexkirsz) In which manifest does this go?
+                let mut entry_ssr_chunks = FxIndexSet::default();
+                for chunks in client_references_chunks_ref
+                    .layout_segment_client_chunks
                     .values()
                 {
-                    let result = collect_next_dynamic_imports(
-                        refs.clone(),
-                        Vc::upcast(this.app_project.client_module_context()),
-                        visited_modules,
-                    )
-                    .await?;
-                    client_dynamic_imports.extend(
-                        result
-                            .client_dynamic_imports
-                            .iter()
-                            .map(|(k, v)| (*k, v.clone())),
-                    );
-                    visited_modules = result.visited_modules;
+                    entry_client_chunks.extend(chunks.await?.iter().copied());
+                }
+                for (chunks, _) in client_references_chunks_ref
+                    .client_component_client_chunks
+                    .values()
+                {
+                    client_assets.extend(chunks.await?.iter().copied());
+                }
+                for (chunks, _) in client_references_chunks_ref
+                    .client_component_ssr_chunks
+                    .values()
+                {
+                    entry_ssr_chunks.extend(chunks.await?.iter().copied());
                 }
 
-                client_dynamic_imports
-            };
-
-            let client_references_chunks = get_app_client_references_chunks(
-                client_references_cell,
-                client_chunking_context,
-                Value::new(client_shared_availability_info),
-                ssr_chunking_context,
-            );
-            let client_references_chunks_ref = client_references_chunks.await?;
-
-            let mut entry_client_chunks = FxIndexSet::default();
-            // TODO(alexkirsz) In which manifest does this go?
-            let mut entry_ssr_chunks = FxIndexSet::default();
-            for chunks in client_references_chunks_ref
-                .layout_segment_client_chunks
-                .values()
-            {
-                entry_client_chunks.extend(chunks.await?.iter().copied());
-            }
-            for (chunks, _) in client_references_chunks_ref
-                .client_component_client_chunks
-                .values()
-            {
-                client_assets.extend(chunks.await?.iter().copied());
-            }
-            for (chunks, _) in client_references_chunks_ref
-                .client_component_ssr_chunks
-                .values()
-            {
-                entry_ssr_chunks.extend(chunks.await?.iter().copied());
-            }
+                client_assets.extend(entry_client_chunks.iter().copied());
+                server_assets.extend(entry_ssr_chunks.iter().copied());
 
-            client_assets.extend(entry_client_chunks.iter().copied());
-            server_assets.extend(entry_ssr_chunks.iter().copied());
+                let entry_client_chunks_paths = entry_client_chunks
+                    .iter()
+                    .map(|chunk| chunk.ident().path())
+                    .try_join()
+                    .await?;
+                let mut entry_client_chunks_paths = entry_client_chunks_paths
+                    .iter()
+                    .map(|path| {
+                        Ok(client_relative_path_ref
+                            .get_path_to(path)
+                            .context("asset path should be inside client root")?
+                            .into())
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                entry_client_chunks_paths.extend(client_shared_chunks_paths.iter().cloned());
+
+                let app_build_manifest = AppBuildManifest {
+                    pages: [(app_entry.original_name.clone(), entry_client_chunks_paths)]
+                        .into_iter()
+                        .collect(),
+                };
+                let manifest_path_prefix = &app_entry.original_name;
+                let app_build_manifest_output = Vc::upcast(VirtualOutputAsset::new(
+                    node_root.join(
+                        format!("server/app{manifest_path_prefix}/app-build-manifest.json",).into(),
+                    ),
+                    AssetContent::file(
+                        File::from(serde_json::to_string_pretty(&app_build_manifest)?).into(),
+                    ),
+                ));
+                server_assets.insert(app_build_manifest_output);
 
-            let entry_client_chunks_paths = entry_client_chunks
-                .iter()
-                .map(|chunk| chunk.ident().path())
-                .try_join()
-                .await?;
-            let mut entry_client_chunks_paths = entry_client_chunks_paths
-                .iter()
-                .map(|path| {
-                    Ok(client_relative_path_ref
-                        .get_path_to(path)
-                        .context("asset path should be inside client root")?
-                        .into())
-                })
-                .collect::<anyhow::Result<Vec<_>>>()?;
-            entry_client_chunks_paths.extend(client_shared_chunks_paths.iter().cloned());
+                // polyfill-nomodule.js is a pre-compiled asset distributed as part of next,
+                // load it as a RawModule.
+                let next_package = get_next_package(this.app_project.project().project_path());
+                let polyfill_source = FileSource::new(
+                    next_package.join("dist/build/polyfills/polyfill-nomodule.js".into()),
+                );
+                let polyfill_output_path =
+                    client_chunking_context.chunk_path(polyfill_source.ident(), ".js".into());
+                let polyfill_output_asset =
+                    RawOutput::new(polyfill_output_path, Vc::upcast(polyfill_source));
+                let polyfill_client_path = client_relative_path_ref
+                    .get_path_to(&*polyfill_output_path.await?)
+                    .context("failed to resolve client-relative path to polyfill")?
+                    .into();
+                let polyfill_client_paths = vec![polyfill_client_path];
+                client_assets.insert(Vc::upcast(polyfill_output_asset));
+
+                if *this
+                    .app_project
+                    .project()
+                    .should_create_webpack_stats()
+                    .await?
+                {
+                    let webpack_stats =
+                        generate_webpack_stats(app_entry.original_name.clone(), &client_assets)
+                            .await?;
+                    let stats_output: Vc<Box<dyn OutputAsset>> =
+                        Vc::upcast(VirtualOutputAsset::new(
+                            node_root.join(
+                                format!("server/app{manifest_path_prefix}/webpack-stats.json",)
+                                    .into(),
+                            ),
+                            AssetContent::file(
+                                File::from(serde_json::to_string_pretty(&webpack_stats)?).into(),
+                            ),
+                        ));
+                    server_assets.insert(Vc::upcast(stats_output));
+                }
 
-            let app_build_manifest = AppBuildManifest {
-                pages: [(app_entry.original_name.clone(), entry_client_chunks_paths)]
-                    .into_iter()
-                    .collect(),
-            };
-            let manifest_path_prefix = &app_entry.original_name;
-            let app_build_manifest_output = Vc::upcast(VirtualOutputAsset::new(
-                node_root.join(
-                    format!("server/app{manifest_path_prefix}/app-build-manifest.json",).into(),
-                ),
-                AssetContent::file(
-                    File::from(serde_json::to_string_pretty(&app_build_manifest)?).into(),
-                ),
-            ));
-            server_assets.insert(app_build_manifest_output);
-
-            // polyfill-nomodule.js is a pre-compiled asset distributed as part of next,
-            // load it as a RawModule.
-            let next_package = get_next_package(this.app_project.project().project_path());
-            let polyfill_source = FileSource::new(
-                next_package.join("dist/build/polyfills/polyfill-nomodule.js".into()),
-            );
-            let polyfill_output_path =
-                client_chunking_context.chunk_path(polyfill_source.ident(), ".js".into());
-            let polyfill_output_asset =
-                RawOutput::new(polyfill_output_path, Vc::upcast(polyfill_source));
-            let polyfill_client_path = client_relative_path_ref
-                .get_path_to(&*polyfill_output_path.await?)
-                .context("failed to resolve client-relative path to polyfill")?
-                .into();
-            let polyfill_client_paths = vec![polyfill_client_path];
-            client_assets.insert(Vc::upcast(polyfill_output_asset));
-
-            if *this
-                .app_project
-                .project()
-                .should_create_webpack_stats()
-                .await?
-            {
-                let webpack_stats =
-                    generate_webpack_stats(app_entry.original_name.clone(), &client_assets).await?;
-                let stats_output: Vc<Box<dyn OutputAsset>> = Vc::upcast(VirtualOutputAsset::new(
+                let build_manifest = BuildManifest {
+                    root_main_files: client_shared_chunks_paths,
+                    polyfill_files: polyfill_client_paths,
+                    ..Default::default()
+                };
+                let build_manifest_output = Vc::upcast(VirtualOutputAsset::new(
                     node_root.join(
-                        format!("server/app{manifest_path_prefix}/webpack-stats.json",).into(),
+                        format!("server/app{manifest_path_prefix}/build-manifest.json",).into(),
                     ),
                     AssetContent::file(
-                        File::from(serde_json::to_string_pretty(&webpack_stats)?).into(),
+                        File::from(serde_json::to_string_pretty(&build_manifest)?).into(),
                     ),
                 ));
-                server_assets.insert(Vc::upcast(stats_output));
-            }
+                server_assets.insert(build_manifest_output);
 
-            let build_manifest = BuildManifest {
-                root_main_files: client_shared_chunks_paths,
-                polyfill_files: polyfill_client_paths,
-                ..Default::default()
-            };
-            let build_manifest_output = Vc::upcast(VirtualOutputAsset::new(
-                node_root
-                    .join(format!("server/app{manifest_path_prefix}/build-manifest.json",).into()),
-                AssetContent::file(
-                    File::from(serde_json::to_string_pretty(&build_manifest)?).into(),
-                ),
-            ));
-            server_assets.insert(build_manifest_output);
-
-            if runtime == NextRuntime::Edge {
-                // as the edge runtime doesn't support chunk loading we need to add all client
-                // references to the middleware manifest so they get loaded during runtime
-                // initialization
-                let client_references_chunks = &*client_references_chunks.await?;
+                if runtime == NextRuntime::Edge {
+                    // as the edge runtime doesn't support chunk loading we need to add all client
+                    // references to the middleware manifest so they get loaded during runtime
+                    // initialization
+                    let client_references_chunks = &*client_references_chunks.await?;
 
-                for (ssr_chunks, _) in client_references_chunks
-                    .client_component_ssr_chunks
-                    .values()
-                {
-                    let ssr_chunks = ssr_chunks.await?;
+                    for (ssr_chunks, _) in client_references_chunks
+                        .client_component_ssr_chunks
+                        .values()
+                    {
+                        let ssr_chunks = ssr_chunks.await?;
 
-                    middleware_assets.extend(ssr_chunks);
+                        middleware_assets.extend(ssr_chunks);
+                    }
                 }
-            }
 
-            (
-                Some(get_app_server_reference_modules(
-                    client_references_cell.types(),
-                )),
-                Some(client_dynamic_imports),
-                Some(client_references_cell),
-                Some(client_references_chunks),
-            )
-        } else {
-            (None, None, None, None)
-        };
-
-        let server_action_manifest_loader =
-            if let Some(app_server_reference_modules) = app_server_reference_modules {
-                let server_action_manifest = create_server_actions_manifest(
-                    Vc::upcast(app_entry.rsc_entry),
-                    app_server_reference_modules,
-                    this.app_project.project().project_path(),
-                    node_root,
-                    app_entry.original_name.clone(),
-                    runtime,
-                    match runtime {
-                        NextRuntime::Edge => Vc::upcast(this.app_project.edge_rsc_module_context()),
-                        NextRuntime::NodeJs => Vc::upcast(this.app_project.rsc_module_context()),
-                    },
-                    this.app_project
-                        .project()
-                        .runtime_chunking_context(process_client_assets, runtime),
+                (
+                    Some(client_dynamic_imports),
+                    Some(client_references_cell),
+                    Some(client_references_chunks),
                 )
-                .await?;
-                server_assets.insert(server_action_manifest.manifest);
-                Some(server_action_manifest.loader)
             } else {
-                None
+                (None, None, None)
             };
 
+        let server_action_manifest_loader = if process_client_components {
+            let server_action_manifest = create_server_actions_manifest(
+                Vc::upcast(app_entry.rsc_entry),
+                this.app_project.project().project_path(),
+                node_root,
+                app_entry.original_name.clone(),
+                runtime,
+                match runtime {
+                    NextRuntime::Edge => Vc::upcast(this.app_project.edge_rsc_module_context()),
+                    NextRuntime::NodeJs => Vc::upcast(this.app_project.rsc_module_context()),
+                },
+                this.app_project
+                    .project()
+                    .runtime_chunking_context(process_client_assets, runtime),
+            )
+            .await?;
+            server_assets.insert(server_action_manifest.manifest);
+            Some(server_action_manifest.loader)
+        } else {
+            None
+        };
+
         let (app_entry_chunks, app_entry_chunks_availability) = &*self
             .app_entry_chunks(
                 client_references,
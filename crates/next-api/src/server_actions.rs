@@ -1,42 +1,26 @@
-use std::{collections::BTreeMap, io::Write, iter::once};
+use std::io::Write;
 
 use anyhow::{bail, Context, Result};
 use indexmap::map::Entry;
 use next_core::{
+    next_client_reference::{action_reference_graph, ActionReference},
     next_manifests::{ActionLayer, ActionManifestWorkerEntry, ServerReferenceManifest},
     util::NextRuntime,
 };
-use swc_core::{
-    atoms::Atom,
-    common::comments::Comments,
-    ecma::{
-        ast::{Decl, ExportSpecifier, Id, ModuleDecl, ModuleItem, Program},
-        utils::find_pat_ids,
-    },
-};
-use tracing::Instrument;
-use turbo_tasks::{
-    graph::{GraphTraversal, NonDeterministic},
-    FxIndexMap, RcStr, TryFlatJoinIterExt, Value, ValueToString, Vc,
-};
+use turbo_tasks::{FxIndexMap, RcStr, Value, ValueToString, Vc};
 use turbo_tasks_fs::{self, rope::RopeBuilder, File, FileSystemPath};
 use turbopack_core::{
     asset::AssetContent,
     chunk::{ChunkItemExt, ChunkableModule, ChunkingContext, EvaluatableAsset},
     context::AssetContext,
     file_source::FileSource,
-    module::{Module, Modules},
+    module::Module,
     output::OutputAsset,
-    reference::primary_referenced_modules,
     reference_type::{EcmaScriptModulesReferenceSubType, ReferenceType},
-    resolve::ModulePart,
     virtual_output::VirtualOutputAsset,
     virtual_source::VirtualSource,
 };
-use turbopack_ecmascript::{
-    chunk::EcmascriptChunkPlaceable, parse::ParseResult,
-    tree_shake::asset::EcmascriptModulePartAsset, EcmascriptParsable,
-};
+use turbopack_ecmascript::chunk::EcmascriptChunkPlaceable;
 
 #[turbo_tasks::value]
 pub(crate) struct ServerActionsManifest {
@@ -54,7 +38,6 @@ pub(crate) struct ServerActionsManifest {
 #[turbo_tasks::function]
 pub(crate) async fn create_server_actions_manifest(
     rsc_entry: Vc<Box<dyn Module>>,
-    server_reference_modules: Vc<Modules>,
     project_path: Vc<FileSystemPath>,
     node_root: Vc<FileSystemPath>,
     page_name: RcStr,
@@ -62,7 +45,7 @@ pub(crate) async fn create_server_actions_manifest(
     asset_context: Vc<Box<dyn AssetContext>>,
     chunking_context: Vc<Box<dyn ChunkingContext>>,
 ) -> Result<Vc<ServerActionsManifest>> {
-    let actions = get_actions(rsc_entry, server_reference_modules, asset_context);
+    let actions = get_actions(rsc_entry, asset_context);
     let loader =
         build_server_actions_loader(project_path, page_name.clone(), actions, asset_context);
     let evaluable = Vc::try_resolve_sidecast::<Box<dyn EvaluatableAsset>>(loader)
@@ -176,65 +159,48 @@ async fn build_manifest(
     )))
 }
 
-/// Traverses the entire module graph starting from [Module], looking for magic
-/// comment which identifies server actions. Every found server action will be
-/// returned along with the module which exports that action.
+/// Collects the server actions found by [action_reference_graph] starting from
+/// the RSC entry. Every found server action will be returned along with the
+/// module which exports that action.
 #[turbo_tasks::function]
 async fn get_actions(
     rsc_entry: Vc<Box<dyn Module>>,
-    server_reference_modules: Vc<Modules>,
     asset_context: Vc<Box<dyn AssetContext>>,
 ) -> Result<Vc<AllActions>> {
-    async move {
-        let actions = NonDeterministic::new()
-            .skip_duplicates()
-            .visit(
-                once((ActionLayer::Rsc, rsc_entry)).chain(
-                    server_reference_modules
-                        .await?
-                        .iter()
-                        .map(|m| (ActionLayer::ActionBrowser, *m)),
-                ),
-                get_referenced_modules,
-            )
-            .await
-            .completed()?
-            .into_inner()
-            .into_iter()
-            .map(parse_actions_filter_map)
-            .try_flat_join()
-            .await?;
-
-        // Actions can be imported by both Client and RSC layers, in which case we need
-        // to use the RSC layer's module. We do that by merging the hashes (which match
-        // in both layers) and preferring the RSC layer's action.
-        let mut all_actions: HashToLayerNameModule = FxIndexMap::default();
-        for ((layer, module), actions_map) in actions.iter() {
-            let module = if *layer == ActionLayer::Rsc {
-                *module
-            } else {
-                to_rsc_context(*module, asset_context).await?
-            };
+    let action_references = action_reference_graph(vec![rsc_entry]).await?;
+
+    // Actions can be imported by both Client and RSC layers, in which case we need
+    // to use the RSC layer's module. We do that by merging the hashes (which match
+    // in both layers) and preferring the RSC layer's action.
+    let mut all_actions: HashToLayerNameModule = FxIndexMap::default();
+    for &ActionReference {
+        layer,
+        module,
+        actions,
+    } in action_references.action_references()
+    {
+        let module = if layer == ActionLayer::Rsc {
+            module
+        } else {
+            to_rsc_context(module, asset_context).await?
+        };
 
-            for (hash_id, name) in &*actions_map.await? {
-                match all_actions.entry(hash_id.to_owned()) {
-                    Entry::Occupied(e) => {
-                        if e.get().0 == ActionLayer::ActionBrowser {
-                            *e.into_mut() = (*layer, name.to_string(), module);
-                        }
-                    }
-                    Entry::Vacant(e) => {
-                        e.insert((*layer, name.to_string(), module));
+        for (hash_id, name) in &*actions.await? {
+            match all_actions.entry(hash_id.to_owned()) {
+                Entry::Occupied(e) => {
+                    if e.get().0 == ActionLayer::ActionBrowser {
+                        *e.into_mut() = (layer, name.to_string(), module);
                     }
                 }
+                Entry::Vacant(e) => {
+                    e.insert((layer, name.to_string(), module));
+                }
             }
         }
-
-        all_actions.sort_keys();
-        Ok(Vc::cell(all_actions))
     }
-    .instrument(tracing::info_span!("find server actions"))
-    .await
+
+    all_actions.sort_keys();
+    Ok(Vc::cell(all_actions))
 }
 
 /// The ActionBrowser layer's module is in the Client context, and we need to
@@ -255,169 +221,6 @@ async fn to_rsc_context(
     Ok(module)
 }
 
-/// Our graph traversal visitor, which finds the primary modules directly
-/// referenced by parent.
-async fn get_referenced_modules(
-    (layer, module): (ActionLayer, Vc<Box<dyn Module>>),
-) -> Result<impl Iterator<Item = (ActionLayer, Vc<Box<dyn Module>>)> + Send> {
-    primary_referenced_modules(module)
-        .await
-        .map(|modules| modules.into_iter().map(move |&m| (layer, m)))
-}
-
-/// Parses the Server Actions comment for all exported action function names.
-///
-/// Action names are stored in a leading BlockComment prefixed by
-/// `__next_internal_action_entry_do_not_use__`.
-pub fn parse_server_actions(
-    program: &Program,
-    comments: &dyn Comments,
-) -> Option<BTreeMap<String, String>> {
-    let byte_pos = match program {
-        Program::Module(m) => m.span.lo,
-        Program::Script(s) => s.span.lo,
-    };
-    comments.get_leading(byte_pos).and_then(|comments| {
-        comments.iter().find_map(|c| {
-            c.text
-                .split_once("__next_internal_action_entry_do_not_use__")
-                .and_then(|(_, actions)| match serde_json::from_str(actions) {
-                    Ok(v) => Some(v),
-                    Err(_) => None,
-                })
-        })
-    })
-}
-/// Inspects the comments inside [Module] looking for the magic actions comment.
-/// If found, we return the mapping of every action's hashed id to the name of
-/// the exported action function. If not, we return a None.
-#[turbo_tasks::function]
-async fn parse_actions(module: Vc<Box<dyn Module>>) -> Result<Vc<OptionActionMap>> {
-    let Some(ecmascript_asset) =
-        Vc::try_resolve_sidecast::<Box<dyn EcmascriptParsable>>(module).await?
-    else {
-        return Ok(OptionActionMap::none());
-    };
-
-    if let Some(module) = Vc::try_resolve_downcast_type::<EcmascriptModulePartAsset>(module).await?
-    {
-        if matches!(
-            &*module.await?.part.await?,
-            ModulePart::Evaluation
-                | ModulePart::Exports
-                | ModulePart::Facade
-                | ModulePart::Internal(..)
-        ) {
-            return Ok(OptionActionMap::none());
-        }
-    }
-
-    let original_parsed = ecmascript_asset.parse_original().resolve().await?;
-
-    let ParseResult::Ok {
-        program: original,
-        comments,
-        ..
-    } = &*original_parsed.await?
-    else {
-        // The file might be parse-able, but this is reported separately.
-        return Ok(OptionActionMap::none());
-    };
-
-    let Some(mut actions) = parse_server_actions(original, comments) else {
-        return Ok(OptionActionMap::none());
-    };
-
-    let fragment = ecmascript_asset.failsafe_parse().resolve().await?;
-
-    if fragment != original_parsed {
-        let ParseResult::Ok {
-            program: fragment, ..
-        } = &*fragment.await?
-        else {
-            // The file might be be parse-able, but this is reported separately.
-            return Ok(OptionActionMap::none());
-        };
-
-        let all_exports = all_export_names(fragment);
-        actions.retain(|_, name| all_exports.iter().any(|export| export == name));
-    }
-
-    let mut actions = FxIndexMap::from_iter(actions.into_iter());
-    actions.sort_keys();
-    Ok(Vc::cell(Some(Vc::cell(actions))))
-}
-
-fn all_export_names(program: &Program) -> Vec<Atom> {
-    match program {
-        Program::Module(m) => {
-            let mut exports = Vec::new();
-            for item in m.body.iter() {
-                match item {
-                    ModuleItem::ModuleDecl(
-                        ModuleDecl::ExportDefaultExpr(..) | ModuleDecl::ExportDefaultDecl(..),
-                    ) => {
-                        exports.push("default".into());
-                    }
-                    ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(decl)) => match &decl.decl {
-                        Decl::Class(c) => {
-                            exports.push(c.ident.sym.clone());
-                        }
-                        Decl::Fn(f) => {
-                            exports.push(f.ident.sym.clone());
-                        }
-                        Decl::Var(v) => {
-                            let ids: Vec<Id> = find_pat_ids(v);
-                            exports.extend(ids.into_iter().map(|id| id.0));
-                        }
-                        _ => {}
-                    },
-                    ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(decl)) => {
-                        for s in decl.specifiers.iter() {
-                            match s {
-                                ExportSpecifier::Named(named) => {
-                                    exports.push(
-                                        named
-                                            .exported
-                                            .as_ref()
-                                            .unwrap_or(&named.orig)
-                                            .atom()
-                                            .clone(),
-                                    );
-                                }
-                                ExportSpecifier::Default(_) => {
-                                    exports.push("default".into());
-                                }
-                                ExportSpecifier::Namespace(e) => {
-                                    exports.push(e.name.atom().clone());
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
-            exports
-        }
-
-        _ => {
-            vec![]
-        }
-    }
-}
-
-/// Converts our cached [parse_actions] call into a data type suitable for
-/// collecting into a flat-mapped [FxIndexMap].
-async fn parse_actions_filter_map(
-    (layer, module): (ActionLayer, Vc<Box<dyn Module>>),
-) -> Result<Option<((ActionLayer, Vc<Box<dyn Module>>), Vc<ActionMap>)>> {
-    parse_actions(module).await.map(|option_action_map| {
-        option_action_map
-            .clone_value()
-            .map(|action_map| ((layer, module), action_map))
-    })
-}
-
 type HashToLayerNameModule = FxIndexMap<String, (ActionLayer, String, Vc<Box<dyn Module>>)>;
 
 /// A mapping of every module which exports a Server Action, with the hashed id
@@ -432,19 +235,3 @@ impl AllActions {
         Vc::cell(FxIndexMap::default())
     }
 }
-
-/// Maps the hashed action id to the action's exported function name.
-#[turbo_tasks::value(transparent)]
-struct ActionMap(FxIndexMap<String, String>);
-
-/// An Option wrapper around [ActionMap].
-#[turbo_tasks::value(transparent)]
-struct OptionActionMap(Option<Vc<ActionMap>>);
-
-#[turbo_tasks::value_impl]
-impl OptionActionMap {
-    #[turbo_tasks::function]
-    pub fn none() -> Vc<Self> {
-        Vc::cell(None)
-    }
-}
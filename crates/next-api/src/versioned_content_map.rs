@@ -1,18 +1,27 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{bail, Result};
 use next_core::emit_assets;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
-    debug::ValueDebugFormat, trace::TraceRawVcs, Completion, FxIndexSet, RcStr, State,
+    debug::ValueDebugFormat, trace::TraceRawVcs, Completion, FxIndexMap, FxIndexSet, RcStr, State,
     TryFlatJoinIterExt, TryJoinIterExt, ValueDefault, ValueToString, Vc,
 };
-use turbo_tasks_fs::FileSystemPath;
+use turbo_tasks_fs::{glob::Glob, File, FileSystemPath};
 use turbopack_core::{
-    asset::Asset,
-    output::{OptionOutputAsset, OutputAsset, OutputAssets},
+    asset::{Asset, AssetContent},
+    output::{OutputAsset, OutputAssets},
     source_map::{GenerateSourceMap, OptionSourceMap},
-    version::OptionVersionedContent,
+    version::{OptionVersionedContent, Version, VersionedContent},
+    virtual_output::VirtualOutputAsset,
 };
 
 /// An unresolved output assets operation. We need to pass an operation here as
@@ -27,38 +36,356 @@ struct MapEntry {
     side_effects: Vc<Completion>,
     /// Precomputed map for quick access to output asset by filepath
     path_to_asset: HashMap<Vc<FileSystemPath>, Vc<Box<dyn OutputAsset>>>,
+    /// Needed to emit single assets of the operation
+    node_root: Vc<FileSystemPath>,
+    client_relative_path: Vc<FileSystemPath>,
+    client_output_path: Vc<FileSystemPath>,
 }
 
 #[turbo_tasks::value(transparent)]
 struct OptionMapEntry(Option<MapEntry>);
 
-type PathToOutputOperation = HashMap<Vc<FileSystemPath>, FxIndexSet<Vc<OutputAssets>>>;
+/// The result of looking up the output asset at a path.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub enum OutputAssetLookup {
+    Found(Vc<Box<dyn OutputAsset>>),
+    /// The operation that emitted the path doesn't emit it anymore. The path is removed from the
+    /// map when the operation is emitted again, unless it comes back.
+    RemovedAwaitingReemit,
+    /// No operation emits the path.
+    NeverEmitted,
+}
+
+impl OutputAssetLookup {
+    /// The asset at `path`, or `None` if it was never emitted. Fails if the asset was removed.
+    async fn found_or_bail(
+        &self,
+        path: Vc<FileSystemPath>,
+    ) -> Result<Option<Vc<Box<dyn OutputAsset>>>> {
+        match self {
+            OutputAssetLookup::Found(asset) => Ok(Some(*asset)),
+            OutputAssetLookup::RemovedAwaitingReemit => {
+                let path = path.to_string().await?;
+                bail!(
+                    "could not find asset for path {} (asset has been removed)",
+                    path,
+                );
+            }
+            OutputAssetLookup::NeverEmitted => Ok(None),
+        }
+    }
+}
+
+/// The results of [VersionedContentMap::get_assets], in the order of the requested paths.
+#[turbo_tasks::value(transparent)]
+pub struct OutputAssetLookups(Vec<OutputAssetLookup>);
+
+/// A bi-directional multimap between file paths and the output assets operations emitting them.
+#[derive(Default, Debug, PartialEq, Eq, TraceRawVcs, ValueDebugFormat, Serialize, Deserialize)]
+struct PathToOutputOperation {
+    path_to_ops: HashMap<Vc<FileSystemPath>, FxIndexSet<Vc<OutputAssets>>>,
+    op_to_paths: HashMap<Vc<OutputAssets>, FxIndexSet<Vc<FileSystemPath>>>,
+    /// The paths in `path_to_ops` by their path string, for prefix lookups.
+    #[turbo_tasks(debug_ignore)]
+    path_index: BTreeMap<RcStr, FxIndexSet<Vc<FileSystemPath>>>,
+    path_keys: HashMap<Vc<FileSystemPath>, RcStr>,
+}
+
+impl PathToOutputOperation {
+    /// Replaces the paths emitted by `op`, along with their path strings. Only the paths of `op`
+    /// are touched. Returns whether the map changed.
+    fn set_paths(
+        &mut self,
+        op: Vc<OutputAssets>,
+        paths: FxIndexMap<Vc<FileSystemPath>, RcStr>,
+    ) -> bool {
+        let mut changed = false;
+
+        let stale_paths = self.op_to_paths.remove(&op).unwrap_or_default();
+        for path in stale_paths.iter().filter(|path| !paths.contains_key(*path)) {
+            if let Some(ops) = self.path_to_ops.get_mut(path) {
+                // Keep the order, the last operation of a path wins
+                changed |= ops.shift_remove(&op);
+                if ops.is_empty() {
+                    self.path_to_ops.remove(path);
+                    self.unindex(*path);
+                }
+            }
+        }
+
+        for (path, key) in paths.iter() {
+            changed |= self.path_to_ops.entry(*path).or_default().insert(op);
+            if !self.path_keys.contains_key(path) {
+                self.path_keys.insert(*path, key.clone());
+                self.path_index
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(*path);
+            }
+        }
+        if !paths.is_empty() {
+            self.op_to_paths.insert(op, paths.into_keys().collect());
+        }
+        changed
+    }
+
+    /// Removes `op` and all of its paths. Returns whether the map changed.
+    fn remove(&mut self, op: Vc<OutputAssets>) -> bool {
+        self.set_paths(op, FxIndexMap::default())
+    }
+
+    fn unindex(&mut self, path: Vc<FileSystemPath>) {
+        let Some(key) = self.path_keys.remove(&path) else {
+            return;
+        };
+        if let Some(paths) = self.path_index.get_mut(&key) {
+            paths.swap_remove(&path);
+            if paths.is_empty() {
+                self.path_index.remove(&key);
+            }
+        }
+    }
+
+    /// The paths inside of the directory `root`, without scanning the other paths.
+    fn paths_in(&self, root: &FileSystemPath) -> Vec<Vc<FileSystemPath>> {
+        let prefix = if root.path.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", root.path)
+        };
+        self.path_index
+            .range::<str, _>((Bound::Included(prefix.as_str()), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(&prefix))
+            .flat_map(|(_, paths)| paths.iter().copied())
+            .collect()
+    }
+
+    /// The operations sharing a path with `op` that no longer provide any of their paths, since
+    /// later operations emit all of them.
+    fn superseded_by(&self, op: Vc<OutputAssets>) -> FxIndexSet<Vc<OutputAssets>> {
+        let Some(paths) = self.op_to_paths.get(&op) else {
+            return FxIndexSet::default();
+        };
+        paths
+            .iter()
+            .filter_map(|path| self.path_to_ops.get(path))
+            .flatten()
+            .copied()
+            .filter(|&other| other != op)
+            .filter(|other| {
+                self.op_to_paths[other].iter().all(|path| {
+                    self.path_to_ops
+                        .get(path)
+                        .and_then(|ops| ops.last())
+                        .is_some_and(|last| last != other)
+                })
+            })
+            .collect()
+    }
+}
+
+/// A file in the manifest written by [VersionedContentMap::asset_manifest].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetManifestEntry {
+    size: Option<u64>,
+    /// The version of the content, usually a content hash.
+    hash: Option<RcStr>,
+    /// Whether the file is served from this operation or from a later one.
+    source_operation: SourceOperation,
+}
+
+/// Which operation serves a file of an [AssetManifestEntry].
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum SourceOperation {
+    /// The file is served from the operation of the manifest.
+    Current,
+    /// An operation that was inserted later emits the same path, or the path isn't registered.
+    Superseded,
+}
+
 // A precomputed map for quick access to output asset by filepath
 type OutputOperationToComputeEntry = HashMap<Vc<OutputAssets>, Vc<OptionMapEntry>>;
+// The operations removed by VersionedContentMap::prune, until they are inserted again
+type PrunedOperations = FxIndexSet<Vc<OutputAssets>>;
+// The version of the asset that was last written to each path
+type PathToEmittedVersion = HashMap<Vc<FileSystemPath>, RcStr>;
+// The source maps by path and section, along with the version of the asset they belong to
+type SourceMapCache = HashMap<(Vc<FileSystemPath>, Option<RcStr>), (RcStr, Vc<OptionSourceMap>)>;
+
+/// A snapshot of the counters of a [VersionedContentMap], see [VersionedContentMap::metrics].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionedContentMapMetrics {
+    /// The number of paths emitted by any operation.
+    pub paths: usize,
+    /// The number of output assets operations emitting at least one path.
+    pub operations: usize,
+    /// The number of asset lookups. Lookups served from the turbo-tasks cache are not counted.
+    pub lookups: u64,
+    /// The lookups that didn't find an asset.
+    pub misses: u64,
+    /// The total time lookups spent waiting for assets to be emitted.
+    pub side_effects_wait_time: Duration,
+}
+
+#[derive(Default)]
+struct Metrics {
+    lookups: AtomicU64,
+    misses: AtomicU64,
+    side_effects_wait_micros: AtomicU64,
+}
+
+impl Metrics {
+    async fn await_side_effects(&self, side_effects: Vc<Completion>) -> Result<()> {
+        let start = Instant::now();
+        let result = side_effects.await;
+        self.side_effects_wait_micros
+            .fetch_add(start.elapsed().as_micros() as u64, Ordering::Relaxed);
+        result?;
+        Ok(())
+    }
+}
 
-#[turbo_tasks::value]
+#[turbo_tasks::value(cell = "new", eq = "manual")]
 pub struct VersionedContentMap {
-    // TODO: turn into a bi-directional multimap, OutputAssets -> FxIndexSet<FileSystemPath>
     map_path_to_op: State<PathToOutputOperation>,
     map_op_to_compute_entry: State<OutputOperationToComputeEntry>,
+    pruned_ops: State<PrunedOperations>,
+    source_maps: State<SourceMapCache>,
+    emitted_versions: State<PathToEmittedVersion>,
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    #[serde(skip)]
+    metrics: Arc<Metrics>,
 }
 
 impl ValueDefault for VersionedContentMap {
     fn value_default() -> Vc<Self> {
         VersionedContentMap {
-            map_path_to_op: State::new(HashMap::new()),
+            map_path_to_op: State::new(PathToOutputOperation::default()),
             map_op_to_compute_entry: State::new(HashMap::new()),
+            pruned_ops: State::new(PrunedOperations::default()),
+            source_maps: State::new(HashMap::new()),
+            emitted_versions: State::new(HashMap::new()),
+            metrics: Default::default(),
         }
         .cell()
     }
 }
 
 impl VersionedContentMap {
+    async fn lookup_asset(
+        self: Vc<Self>,
+        path: Vc<FileSystemPath>,
+        prioritized: bool,
+    ) -> Result<Vc<OutputAssetLookup>> {
+        let this = self.await?;
+        let metrics = &this.metrics;
+        metrics.lookups.fetch_add(1, Ordering::Relaxed);
+        let result = self.raw_get(path).await?;
+        let Some(MapEntry {
+            side_effects,
+            path_to_asset,
+            node_root,
+            client_relative_path,
+            client_output_path,
+            ..
+        }) = &*result
+        else {
+            metrics.misses.fetch_add(1, Ordering::Relaxed);
+            return Ok(OutputAssetLookup::NeverEmitted.cell());
+        };
+        let Some(&asset) = path_to_asset.get(&path) else {
+            metrics.misses.fetch_add(1, Ordering::Relaxed);
+            metrics.await_side_effects(*side_effects).await?;
+            return Ok(OutputAssetLookup::RemovedAwaitingReemit.cell());
+        };
+
+        if prioritized {
+            // Write the requested asset first, and remember its version like any other emit. The
+            // writes of single assets are shared with the emit of the whole operation.
+            self.emit_changed_assets(
+                Vc::cell(vec![asset]),
+                *node_root,
+                *client_relative_path,
+                *client_output_path,
+            )
+            .await?;
+            // The remaining assets are emitted in the background, without waiting for them
+            Vc::connect(*side_effects);
+        } else {
+            metrics.await_side_effects(*side_effects).await?;
+        }
+        Ok(OutputAssetLookup::Found(asset).cell())
+    }
+
+    /// The current counters of the map, for diagnosing slow lookups.
+    pub async fn metrics(self: Vc<Self>) -> Result<VersionedContentMapMetrics> {
+        let this = self.await?;
+        let (paths, operations) = {
+            let map = this.map_path_to_op.get_untracked();
+            (map.path_to_ops.len(), map.op_to_paths.len())
+        };
+        Ok(VersionedContentMapMetrics {
+            paths,
+            operations,
+            lookups: this.metrics.lookups.load(Ordering::Relaxed),
+            misses: this.metrics.misses.load(Ordering::Relaxed),
+            side_effects_wait_time: Duration::from_micros(
+                this.metrics
+                    .side_effects_wait_micros
+                    .load(Ordering::Relaxed),
+            ),
+        })
+    }
+
     // NOTE(alexkirsz) This must not be a `#[turbo_tasks::function]` because it
     // should be a singleton for each project.
     pub fn new() -> Vc<Self> {
         Self::value_default()
     }
+
+    /// Removes the operations and all of their paths from the map. Paths that are emitted by
+    /// other operations too remain. Must be called from a task, since it updates the states.
+    fn remove_operations(&self, ops: &FxIndexSet<Vc<OutputAssets>>) {
+        if ops.is_empty() {
+            return;
+        }
+        self.map_path_to_op.update_conditionally(|map| {
+            let mut changed = false;
+            for &op in ops {
+                changed |= map.remove(op);
+            }
+            changed
+        });
+        self.map_op_to_compute_entry.update_conditionally(|map| {
+            let len = map.len();
+            map.retain(|op, _| !ops.contains(op));
+            map.len() != len
+        });
+        let paths = self.map_path_to_op.get_untracked();
+        self.source_maps.update_conditionally(|map| {
+            let len = map.len();
+            map.retain(|(path, _), _| paths.path_to_ops.contains_key(path));
+            map.len() != len
+        });
+    }
+
+    /// Removes an output assets operation and all of its paths from the map, e.g. when its route
+    /// was removed. Paths that are emitted by other operations too remain. Inserting the
+    /// operation again registers it again.
+    ///
+    /// This is not a turbo-tasks function, so that pruning an operation again after it was
+    /// inserted again isn't served from the cache. Must be called from a task.
+    pub async fn prune(self: Vc<Self>, assets_operation: Vc<OutputAssetsOperation>) -> Result<()> {
+        let assets = *assets_operation.await?;
+        let this = self.await?;
+        this.remove_operations(&FxIndexSet::from_iter([assets]));
+        this.pruned_ops
+            .update_conditionally(|ops| ops.insert(assets));
+        Ok(())
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -87,14 +414,43 @@ impl VersionedContentMap {
         let Some(entry) = &*compute_entry.await? else {
             unreachable!("compute_entry always returns Some(MapEntry)")
         };
+        let paths = entry
+            .path_to_asset
+            .keys()
+            .map(|&path| async move { Ok((path, path.await?.path.clone())) })
+            .try_join()
+            .await?;
+        this.map_path_to_op
+            .update_conditionally(|map| map.set_paths(assets, paths.into_iter().collect()));
+
+        // Operations that are superseded would never be returned from `raw_get` again. They are
+        // only removed once this operation has registered its paths, so that the paths are
+        // served until then. Their own insert tasks don't depend on that, so they don't register
+        // them again.
+        let superseded = this.map_path_to_op.get_untracked().superseded_by(assets);
+        this.remove_operations(&superseded);
+
+        // Pruning this operation invalidates this task, so that the operation is registered
+        // again the next time it's inserted
+        this.pruned_ops
+            .update_conditionally(|ops| ops.shift_remove(&assets));
+        self.is_pruned(assets).await?;
         Ok(entry.side_effects)
     }
 
+    /// Whether `assets` has been removed by [VersionedContentMap::prune] and not been inserted
+    /// again since.
+    #[turbo_tasks::function]
+    fn is_pruned(&self, assets: Vc<OutputAssets>) -> Vc<bool> {
+        Vc::cell(self.pruned_ops.get().contains(&assets))
+    }
+
     /// Creates a ComputEntry (a pre-computed map for optimized lookup) for an output assets
-    /// operation. When assets change, map_path_to_op is updated.
+    /// operation. The paths are registered in map_path_to_op by the insert task, so that they
+    /// are registered again when the operation is inserted again after it was removed.
     #[turbo_tasks::function]
     async fn compute_entry(
-        &self,
+        self: Vc<Self>,
         assets_operation: Vc<OutputAssetsOperation>,
         node_root: Vc<FileSystemPath>,
         client_relative_path: Vc<FileSystemPath>,
@@ -116,110 +472,295 @@ impl VersionedContentMap {
             Ok(entries)
         }
         let entries = get_entries(assets).await.unwrap_or_default();
+        let this = self.await?;
 
-        self.map_path_to_op.update_conditionally(|map| {
-            let mut changed = false;
-
-            // get current map's keys, subtract keys that don't exist in operation
-            let mut stale_assets = map.keys().copied().collect::<HashSet<_>>();
-
-            for (k, _) in entries.iter() {
-                let res = map.entry(*k).or_default().insert(assets);
-                stale_assets.remove(k);
-                changed = changed || res;
-            }
-
-            // Make more efficient with reverse map
-            for k in &stale_assets {
-                let res = map
-                    .get_mut(k)
-                    // guaranteed
-                    .unwrap()
-                    .remove(&assets);
-                changed = changed || res
-            }
-            changed
-        });
+        // Assets with the same version as the last one written to their path don't need to be
+        // written again. Failing to compute a version is reported by emitting the asset.
+        let version_ids =
+            entries
+                .iter()
+                .map(|&(_, asset)| async move {
+                    Ok(asset.versioned_content().version().id().await.ok())
+                })
+                .try_join()
+                .await?;
+        let changed_assets = {
+            // Untracked, since this task updates the versions once the assets are written
+            let emitted_versions = this.emitted_versions.get_untracked();
+            entries
+                .iter()
+                .zip(version_ids.iter())
+                .filter(|((path, _), version_id)| {
+                    !version_id
+                        .as_ref()
+                        .is_some_and(|version_id| emitted_versions.get(path) == Some(&**version_id))
+                })
+                .map(|((_, asset), _)| *asset)
+                .collect::<Vec<_>>()
+        };
+        tracing::debug!(
+            written = changed_assets.len(),
+            skipped = entries.len() - changed_assets.len(),
+            "emitting output assets"
+        );
 
         // Make sure all written client assets are up-to-date
-        let side_effects = emit_assets(assets, node_root, client_relative_path, client_output_path);
+        let side_effects = self.emit_changed_assets(
+            Vc::cell(changed_assets),
+            node_root,
+            client_relative_path,
+            client_output_path,
+        );
         let map_entry = Vc::cell(Some(MapEntry {
             assets_operation: assets,
             side_effects,
             path_to_asset: entries.into_iter().collect(),
+            node_root,
+            client_relative_path,
+            client_output_path,
         }));
         Ok(map_entry)
     }
 
+    /// Emits the `assets` and remembers their versions, so that they are only written again once
+    /// their content changes.
+    #[turbo_tasks::function]
+    async fn emit_changed_assets(
+        self: Vc<Self>,
+        assets: Vc<OutputAssets>,
+        node_root: Vc<FileSystemPath>,
+        client_relative_path: Vc<FileSystemPath>,
+        client_output_path: Vc<FileSystemPath>,
+    ) -> Result<Vc<Completion>> {
+        emit_assets(assets, node_root, client_relative_path, client_output_path).await?;
+
+        let versions = assets
+            .await?
+            .iter()
+            .map(|&asset| async move {
+                Ok((
+                    asset.ident().path().resolve().await?,
+                    asset
+                        .versioned_content()
+                        .version()
+                        .id()
+                        .await?
+                        .clone_value(),
+                ))
+            })
+            .try_join()
+            .await?;
+        self.await?.emitted_versions.update_conditionally(|map| {
+            let mut changed = false;
+            for (path, version_id) in versions {
+                changed |= map.insert(path, version_id.clone()) != Some(version_id);
+            }
+            changed
+        });
+        Ok(Completion::new())
+    }
+
     #[turbo_tasks::function]
     pub async fn get(
         self: Vc<Self>,
         path: Vc<FileSystemPath>,
     ) -> Result<Vc<OptionVersionedContent>> {
         Ok(Vc::cell(
-            (*self.get_asset(path).await?).map(|a| a.versioned_content()),
+            self.get_asset(path)
+                .await?
+                .found_or_bail(path)
+                .await?
+                .map(|a| a.versioned_content()),
         ))
     }
 
+    /// Completes whenever the asset at `path` changes its version or is removed, so that consumers
+    /// can subscribe to changes instead of polling [VersionedContentMap::get] and comparing
+    /// versions. The asset might not be emitted yet when this completes.
+    #[turbo_tasks::function]
+    pub async fn subscribe(self: Vc<Self>, path: Vc<FileSystemPath>) -> Result<Vc<Completion>> {
+        // Only changes of the version id invalidate this task
+        self.get_version_id(path).await?;
+        Ok(Completion::new())
+    }
+
+    /// The version identifier (usually a content hash) of the asset at `path`, e.g. to be used as
+    /// an ETag. `None` if there is no asset at `path`, including assets that have been removed.
+    ///
+    /// Doesn't wait for the asset to be emitted. The version is the one the compute entry uses to
+    /// decide whether to write the asset, so it isn't computed again.
+    #[turbo_tasks::function]
+    pub async fn get_version_id(
+        self: Vc<Self>,
+        path: Vc<FileSystemPath>,
+    ) -> Result<Vc<Option<RcStr>>> {
+        let result = self.raw_get(path).await?;
+        let Some(&asset) = result
+            .as_ref()
+            .and_then(|entry| entry.path_to_asset.get(&path))
+        else {
+            return Ok(Vc::cell(None));
+        };
+        Ok(Vc::cell(Some(
+            asset
+                .versioned_content()
+                .version()
+                .id()
+                .await?
+                .clone_value(),
+        )))
+    }
+
     #[turbo_tasks::function]
     pub async fn get_source_map(
         self: Vc<Self>,
         path: Vc<FileSystemPath>,
         section: Option<RcStr>,
     ) -> Result<Vc<OptionSourceMap>> {
-        let Some(asset) = &*self.get_asset(path).await? else {
+        let Some(asset) = self.get_asset(path).await?.found_or_bail(path).await? else {
             return Ok(Vc::cell(None));
         };
 
-        if let Some(generate_source_map) =
-            Vc::try_resolve_sidecast::<Box<dyn GenerateSourceMap>>(*asset).await?
-        {
-            Ok(if let Some(section) = section {
-                generate_source_map.by_section(section)
-            } else {
-                generate_source_map.generate_source_map()
-            })
-        } else {
+        // Resolving stack frames requests the same source maps over and over again. They are
+        // reused until the asset is emitted with a different version. The cache is read untracked,
+        // since this task already depends on the version.
+        let this = self.await?;
+        let version_id = asset.versioned_content().version().id().await?;
+        let key = (path, section.clone());
+        let cached = {
+            let map = this.source_maps.get_untracked();
+            map.get(&key)
+                .filter(|(cached_version_id, _)| *cached_version_id == *version_id)
+                .map(|&(_, source_map)| source_map)
+        };
+        if let Some(source_map) = cached {
+            // Need to reconnect the operation to the map
+            Vc::connect(source_map);
+            return Ok(source_map);
+        }
+
+        let Some(generate_source_map) =
+            Vc::try_resolve_sidecast::<Box<dyn GenerateSourceMap>>(asset).await?
+        else {
             let path = path.to_string().await?;
             bail!("no source map for path {}", path);
-        }
+        };
+        let source_map = if let Some(section) = section {
+            generate_source_map.by_section(section)
+        } else {
+            generate_source_map.generate_source_map()
+        };
+        this.source_maps.update_conditionally(|map| {
+            let entry = (version_id.clone_value(), source_map);
+            map.insert(key, entry.clone()) != Some(entry)
+        });
+        Ok(source_map)
     }
 
     #[turbo_tasks::function]
     pub async fn get_asset(
         self: Vc<Self>,
         path: Vc<FileSystemPath>,
-    ) -> Result<Vc<OptionOutputAsset>> {
-        let result = self.raw_get(path).await?;
-        if let Some(MapEntry {
-            assets_operation: _,
-            side_effects,
-            path_to_asset,
-        }) = &*result
-        {
-            side_effects.await?;
+    ) -> Result<Vc<OutputAssetLookup>> {
+        self.lookup_asset(path, false).await
+    }
 
-            if let Some(asset) = path_to_asset.get(&path) {
-                return Ok(Vc::cell(Some(*asset)));
-            } else {
-                let path = path.to_string().await?;
-                bail!(
-                    "could not find asset for path {} (asset has been removed)",
-                    path,
-                );
+    /// Like [VersionedContentMap::get_asset], but only waits until the asset at `path` is
+    /// emitted. The other assets of its operation are emitted in the background, so that the
+    /// requested file doesn't have to wait for them.
+    #[turbo_tasks::function]
+    pub async fn get_asset_prioritized(
+        self: Vc<Self>,
+        path: Vc<FileSystemPath>,
+    ) -> Result<Vc<OutputAssetLookup>> {
+        self.lookup_asset(path, true).await
+    }
+
+    /// Like [VersionedContentMap::get_asset] for many paths at once. The paths are grouped by the
+    /// operation emitting them, so that every operation is only awaited once.
+    #[turbo_tasks::function]
+    pub async fn get_assets(
+        &self,
+        paths: Vec<Vc<FileSystemPath>>,
+    ) -> Result<Vc<OutputAssetLookups>> {
+        let paths = paths.iter().map(|path| path.resolve()).try_join().await?;
+
+        let mut paths_by_op = FxIndexMap::<_, Vec<_>>::default();
+        {
+            let map = self.map_path_to_op.get();
+            for (index, path) in paths.iter().enumerate() {
+                if let Some(&assets) = map.path_to_ops.get(path).and_then(|ops| ops.last()) {
+                    paths_by_op.entry(assets).or_default().push(index);
+                }
             }
         }
+        let compute_entries = {
+            let map = self.map_op_to_compute_entry.get();
+            paths_by_op
+                .keys()
+                .map(|assets| map.get(assets).copied())
+                .collect::<Vec<_>>()
+        };
+
+        let found = paths_by_op
+            .into_iter()
+            .zip(compute_entries)
+            .map(|((assets, indices), compute_entry)| {
+                let paths = &paths;
+                let metrics = &self.metrics;
+                async move {
+                    let Some(compute_entry) = compute_entry else {
+                        return Ok(vec![]);
+                    };
+                    // Need to reconnect the operations to the map
+                    Vc::connect(assets);
+                    Vc::connect(compute_entry);
+                    let Some(MapEntry {
+                        side_effects,
+                        path_to_asset,
+                        ..
+                    }) = &*compute_entry.await?
+                    else {
+                        return Ok(vec![]);
+                    };
+                    metrics.await_side_effects(*side_effects).await?;
 
-        Ok(Vc::cell(None))
+                    Ok(indices
+                        .into_iter()
+                        .map(|index| {
+                            let lookup = match path_to_asset.get(&paths[index]) {
+                                Some(asset) => OutputAssetLookup::Found(*asset),
+                                None => OutputAssetLookup::RemovedAwaitingReemit,
+                            };
+                            (index, lookup)
+                        })
+                        .collect::<Vec<_>>())
+                }
+            })
+            .try_flat_join()
+            .await?;
+
+        let mut lookups = vec![OutputAssetLookup::NeverEmitted; paths.len()];
+        for (index, lookup) in found {
+            lookups[index] = lookup;
+        }
+        let misses = lookups
+            .iter()
+            .filter(|lookup| !matches!(lookup, OutputAssetLookup::Found(..)))
+            .count();
+        self.metrics
+            .lookups
+            .fetch_add(lookups.len() as u64, Ordering::Relaxed);
+        self.metrics
+            .misses
+            .fetch_add(misses as u64, Ordering::Relaxed);
+        Ok(Vc::cell(lookups))
     }
 
     #[turbo_tasks::function]
     pub async fn keys_in_path(&self, root: Vc<FileSystemPath>) -> Result<Vc<Vec<RcStr>>> {
-        let keys = {
-            let map = self.map_path_to_op.get();
-            map.keys().copied().collect::<Vec<_>>()
-        };
         let root = &root.await?;
+        let keys = self.map_path_to_op.get().paths_in(root);
         let keys = keys
             .into_iter()
             .map(|path| async move { Ok(root.get_path_to(&*path.await?).map(RcStr::from)) })
@@ -228,11 +769,109 @@ impl VersionedContentMap {
         Ok(Vc::cell(keys))
     }
 
+    /// Returns the output assets whose paths relative to `root` match the `glob`.
+    #[turbo_tasks::function]
+    pub async fn get_assets_matching(
+        self: Vc<Self>,
+        root: Vc<FileSystemPath>,
+        glob: Vc<Glob>,
+    ) -> Result<Vc<OutputAssets>> {
+        let this = self.await?;
+        let root = &root.await?;
+        let paths = this.map_path_to_op.get().paths_in(root);
+        let glob = &glob.await?;
+        let assets = paths
+            .into_iter()
+            .map(|path| async move {
+                let matches = root
+                    .get_path_to(&*path.await?)
+                    .is_some_and(|relative_path| glob.execute(relative_path));
+                if !matches {
+                    return Ok(None);
+                }
+                Ok(match *self.get_asset(path).await? {
+                    OutputAssetLookup::Found(asset) => Some(asset),
+                    OutputAssetLookup::RemovedAwaitingReemit | OutputAssetLookup::NeverEmitted => {
+                        None
+                    }
+                })
+            })
+            .try_flat_join()
+            .await?;
+        Ok(Vc::cell(assets))
+    }
+
+    /// Writes a JSON manifest of the files emitted by `assets_operation` to `path`. The files
+    /// are keyed by their path relative to `root`.
+    #[turbo_tasks::function]
+    pub async fn asset_manifest(
+        self: Vc<Self>,
+        assets_operation: Vc<OutputAssetsOperation>,
+        root: Vc<FileSystemPath>,
+        path: Vc<FileSystemPath>,
+    ) -> Result<Vc<Box<dyn OutputAsset>>> {
+        let assets = *assets_operation.await?;
+        let root = &root.await?;
+        let files = assets
+            .await?
+            .iter()
+            .map(|&asset| async move {
+                let asset_path = asset.ident().path().resolve().await?;
+                let asset_path_ref = asset_path.await?;
+                let key = match root.get_path_to(&asset_path_ref) {
+                    Some(relative_path) => relative_path.into(),
+                    None => asset_path_ref.path.clone(),
+                };
+                let entry = AssetManifestEntry {
+                    size: *asset.size_bytes().await?,
+                    hash: asset
+                        .versioned_content()
+                        .version()
+                        .id()
+                        .await
+                        .ok()
+                        .map(|id| id.clone_value()),
+                    source_operation: SourceOperation::Superseded,
+                };
+                Ok((asset_path, key, entry))
+            })
+            .try_join()
+            .await?;
+
+        let this = self.await?;
+        let manifest = {
+            let map = this.map_path_to_op.get();
+            files
+                .into_iter()
+                .map(|(asset_path, key, mut entry)| {
+                    let is_current = map
+                        .path_to_ops
+                        .get(&asset_path)
+                        .and_then(|ops| ops.last())
+                        .is_some_and(|&op| op == assets);
+                    entry.source_operation = if is_current {
+                        SourceOperation::Current
+                    } else {
+                        SourceOperation::Superseded
+                    };
+                    (key, entry)
+                })
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        Ok(Vc::upcast(VirtualOutputAsset::new(
+            path,
+            AssetContent::file(File::from(serde_json::to_string_pretty(&manifest)?).into()),
+        )))
+    }
+
     #[turbo_tasks::function]
     fn raw_get(&self, path: Vc<FileSystemPath>) -> Vc<OptionMapEntry> {
         let assets = {
             let map = self.map_path_to_op.get();
-            map.get(&path).and_then(|m| m.iter().last().copied())
+            map.path_to_ops
+                .get(&path)
+                .and_then(|m| m.iter().last().copied())
         };
         let Some(assets) = assets else {
             return Vc::cell(None);
@@ -253,3 +892,66 @@ impl VersionedContentMap {
         compute_entry
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use turbo_tasks::{run_once, TurboTasks};
+    use turbo_tasks_fs::{DiskFileSystem, FileSystem};
+    use turbo_tasks_memory::MemoryBackend;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn pruned_operation_is_found_after_inserting_it_again() {
+        crate::register();
+        let dir = tempfile::tempdir().unwrap();
+        let root: RcStr = dir.path().to_str().unwrap().into();
+        let tt = TurboTasks::new(MemoryBackend::default());
+
+        let (map, assets_operation, node_root, client_root, path) =
+            run_once(tt.clone(), async move {
+                let fs = DiskFileSystem::new("project".into(), root, vec![]);
+                let node_root = fs.root().join("server".into()).resolve().await?;
+                let client_root = fs.root().join("static".into()).resolve().await?;
+                let path = node_root.join("page.js".into()).resolve().await?;
+                let asset = VirtualOutputAsset::new(
+                    path,
+                    AssetContent::file(File::from("console.log(1)").into()),
+                );
+                let assets_operation: Vc<OutputAssetsOperation> =
+                    Vc::cell(OutputAssets::new(vec![Vc::upcast(asset)]));
+                let map = VersionedContentMap::new();
+
+                map.insert_output_assets(assets_operation, node_root, client_root, client_root)
+                    .strongly_consistent()
+                    .await?;
+                let lookup = map.get_asset(path).strongly_consistent().await?;
+                assert!(matches!(*lookup, OutputAssetLookup::Found(_)));
+                Ok((map, assets_operation, node_root, client_root, path))
+            })
+            .await
+            .unwrap();
+
+        // Each step runs in its own root task, like the callers do, so the insert task isn't
+        // executed again before it's called again
+        run_once(tt.clone(), async move {
+            map.prune(assets_operation).await?;
+            let lookup = map.get_asset(path).strongly_consistent().await?;
+            assert!(matches!(*lookup, OutputAssetLookup::NeverEmitted));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        run_once(tt, async move {
+            map.insert_output_assets(assets_operation, node_root, client_root, client_root)
+                .strongly_consistent()
+                .await?;
+            let lookup = map.get_asset(path).strongly_consistent().await?;
+            assert!(matches!(*lookup, OutputAssetLookup::Found(_)));
+            Ok(())
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -5,7 +5,7 @@ use turbo_tasks::{
 };
 use turbopack_core::{
     chunk::{availability_info::AvailabilityInfo, ChunkingContext, ChunkingContextExt},
-    module::{Module, Modules},
+    module::Module,
     output::OutputAssets,
 };
 
@@ -13,7 +13,6 @@ use super::include_modules_module::IncludeModulesModule;
 use crate::{
     next_client_reference::{
         visit_client_reference::ClientReferenceGraphResult, ClientReferenceType,
-        ClientReferenceTypes,
     },
     next_server_component::server_component_module::NextServerComponentModule,
 };
@@ -108,6 +107,19 @@ pub async fn get_app_client_references_chunks(
                                     None,
                                 )
                             }
+                            ClientReferenceType::WorkerClientReference(worker_loader) => {
+                                let client_chunk_group = client_chunking_context
+                                    .root_chunk_group(Vc::upcast(worker_loader))
+                                    .await?;
+
+                                (
+                                    (
+                                        client_chunk_group.assets,
+                                        client_chunk_group.availability_info,
+                                    ),
+                                    None,
+                                )
+                            }
                         },
                     ))
                 })
@@ -228,6 +240,9 @@ pub async fn get_app_client_references_chunks(
                             ClientReferenceType::CssClientReference(css_module) => {
                                 Vc::upcast(*css_module)
                             }
+                            ClientReferenceType::WorkerClientReference(worker_loader) => {
+                                Vc::upcast(*worker_loader)
+                            }
                         })
                     })
                     .try_join()
@@ -313,30 +328,3 @@ pub async fn get_app_client_references_chunks(
     .instrument(tracing::info_span!("process client references"))
     .await
 }
-
-/// Crawls all modules emitted in the client transition, returning a list of all
-/// client JS modules.
-#[turbo_tasks::function]
-pub async fn get_app_server_reference_modules(
-    app_client_reference_types: Vc<ClientReferenceTypes>,
-) -> Result<Vc<Modules>> {
-    Ok(Vc::cell(
-        app_client_reference_types
-            .await?
-            .iter()
-            .map(|client_reference_ty| async move {
-                Ok(match client_reference_ty {
-                    ClientReferenceType::EcmascriptClientReference {
-                        module: ecmascript_client_reference,
-                        ..
-                    } => {
-                        let ecmascript_client_reference_ref = ecmascript_client_reference.await?;
-                        Some(Vc::upcast(ecmascript_client_reference_ref.client_module))
-                    }
-                    _ => None,
-                })
-            })
-            .try_flat_join()
-            .await?,
-    ))
-}
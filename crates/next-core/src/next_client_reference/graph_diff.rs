@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{
+    debug::ValueDebugFormat, trace::TraceRawVcs, RcStr, TryJoinIterExt, ValueToString, Vc,
+};
+use turbopack_core::module::Module;
+
+use super::ClientReferenceGraphResult;
+
+/// The client boundaries that were added to or removed from a single server component.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs, ValueDebugFormat)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerComponentClientReferenceDiff {
+    /// The ident of the server component, `None` for client references outside of any layout
+    /// segment.
+    pub server_component: Option<RcStr>,
+    pub added: Vec<RcStr>,
+    pub removed: Vec<RcStr>,
+}
+
+/// The changes between two client reference graphs. Modules are compared by their idents, so
+/// graphs of different builds of the same app can be compared.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct ClientReferenceGraphDiff {
+    /// Only server components with changed client boundaries are listed, sorted by ident.
+    pub server_components: Vec<ServerComponentClientReferenceDiff>,
+}
+
+impl ClientReferenceGraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.server_components.is_empty()
+    }
+
+    /// Whether a client boundary was added anywhere, e.g. because a module that was only used on
+    /// the server is now imported from a client component.
+    pub fn has_added(&self) -> bool {
+        self.server_components
+            .iter()
+            .any(|server_component| !server_component.added.is_empty())
+    }
+}
+
+/// The client boundaries of every server component, by ident.
+async fn client_references_by_server_component(
+    graph: Vc<ClientReferenceGraphResult>,
+) -> Result<BTreeMap<Option<RcStr>, BTreeSet<RcStr>>> {
+    let client_references = graph
+        .await?
+        .client_references
+        .iter()
+        .map(|client_reference| async move {
+            let server_component = match client_reference.server_component() {
+                Some(server_component) => {
+                    Some(server_component.ident().to_string().await?.clone_value())
+                }
+                None => None,
+            };
+            let ident = client_reference
+                .ty()
+                .module()
+                .ident()
+                .to_string()
+                .await?
+                .clone_value();
+            Ok((server_component, ident))
+        })
+        .try_join()
+        .await?;
+
+    let mut by_server_component: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for (server_component, ident) in client_references {
+        by_server_component
+            .entry(server_component)
+            .or_default()
+            .insert(ident);
+    }
+    Ok(by_server_component)
+}
+
+/// Compares the client boundaries of each server component in `previous` and `current`.
+#[turbo_tasks::function]
+pub async fn diff_client_reference_graphs(
+    previous: Vc<ClientReferenceGraphResult>,
+    current: Vc<ClientReferenceGraphResult>,
+) -> Result<Vc<ClientReferenceGraphDiff>> {
+    let previous = client_references_by_server_component(previous).await?;
+    let current = client_references_by_server_component(current).await?;
+
+    let empty = BTreeSet::new();
+    let server_components = previous
+        .keys()
+        .chain(current.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|server_component| {
+            let previous = previous.get(server_component).unwrap_or(&empty);
+            let current = current.get(server_component).unwrap_or(&empty);
+            let added = current.difference(previous).cloned().collect::<Vec<_>>();
+            let removed = previous.difference(current).cloned().collect::<Vec<_>>();
+            if added.is_empty() && removed.is_empty() {
+                return None;
+            }
+            Some(ServerComponentClientReferenceDiff {
+                server_component: server_component.clone(),
+                added,
+                removed,
+            })
+        })
+        .collect();
+
+    Ok(ClientReferenceGraphDiff { server_components }.cell())
+}
@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks::{RcStr, TryJoinIterExt, ValueToString, Vc};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack_core::{
+    asset::AssetContent, module::Module, output::OutputAsset, virtual_output::VirtualOutputAsset,
+};
+
+use super::{ClientReferenceGraphResult, ClientReferenceType};
+use crate::util::NextRuntime;
+
+/// Incremented whenever the document changes in a way that breaks its consumers.
+const VERSION: u32 = 1;
+
+/// The document written by [client_reference_graph_json]. All lists are sorted, so the output
+/// is stable between builds of the same app.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientReferenceGraphJson {
+    version: u32,
+    server_components: BTreeSet<RcStr>,
+    server_utils: BTreeSet<RcStr>,
+    client_references: BTreeSet<ClientReferenceJson>,
+    edges: BTreeSet<ClientReferenceEdgeJson>,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct ClientReferenceJson {
+    #[serde(rename = "type")]
+    ty: &'static str,
+    ident: RcStr,
+}
+
+/// A server component referencing a client boundary. `from` is `None` for client references
+/// outside of any layout segment. `runtime` is the runtime the server component is rendered in.
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct ClientReferenceEdgeJson {
+    from: Option<RcStr>,
+    to: RcStr,
+    runtime: NextRuntime,
+}
+
+/// Serializes the client reference graph into a JSON document at `path`, for bundle analysis
+/// tools that can't link against next-core.
+#[turbo_tasks::function]
+pub async fn client_reference_graph_json(
+    graph: Vc<ClientReferenceGraphResult>,
+    path: Vc<FileSystemPath>,
+) -> Result<Vc<Box<dyn OutputAsset>>> {
+    let graph = graph.await?;
+
+    let server_components = graph
+        .server_component_entries
+        .iter()
+        .map(|server_component| async move {
+            Ok(server_component.ident().to_string().await?.clone_value())
+        })
+        .try_join()
+        .await?;
+    let server_utils = graph
+        .server_utils
+        .iter()
+        .map(|server_util| async move { Ok(server_util.ident().to_string().await?.clone_value()) })
+        .try_join()
+        .await?;
+    let client_references = graph
+        .client_references
+        .iter()
+        .map(|client_reference| async move {
+            let ty = match client_reference.ty() {
+                ClientReferenceType::EcmascriptClientReference { .. } => "ecmascript",
+                ClientReferenceType::CssClientReference(..) => "css",
+                ClientReferenceType::WorkerClientReference(..) => "worker",
+            };
+            let ident = client_reference.ty().module().ident();
+            let from = match client_reference.server_component() {
+                Some(server_component) => {
+                    Some(server_component.ident().to_string().await?.clone_value())
+                }
+                None => None,
+            };
+            Ok((
+                ClientReferenceJson {
+                    ty,
+                    ident: ident.to_string().await?.clone_value(),
+                },
+                from,
+                client_reference.runtime(),
+            ))
+        })
+        .try_join()
+        .await?;
+
+    let mut json = ClientReferenceGraphJson {
+        version: VERSION,
+        server_components: server_components.into_iter().collect(),
+        server_utils: server_utils.into_iter().collect(),
+        client_references: BTreeSet::new(),
+        edges: BTreeSet::new(),
+    };
+    for (client_reference, from, runtime) in client_references {
+        json.edges.insert(ClientReferenceEdgeJson {
+            from,
+            to: client_reference.ident.clone(),
+            runtime,
+        });
+        json.client_references.insert(client_reference);
+    }
+
+    Ok(Vc::upcast(VirtualOutputAsset::new(
+        path,
+        AssetContent::file(File::from(serde_json::to_string_pretty(&json)?).into()),
+    )))
+}
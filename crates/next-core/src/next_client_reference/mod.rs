@@ -1,11 +1,20 @@
 pub(crate) mod ecmascript_client_reference;
+mod graph_diff;
+mod graph_json;
 pub(crate) mod visit_client_reference;
 
 pub use ecmascript_client_reference::{
     ecmascript_client_reference_module::EcmascriptClientReferenceModule,
     ecmascript_client_reference_transition::NextEcmascriptClientReferenceTransition,
 };
+pub use graph_diff::{
+    diff_client_reference_graphs, ClientReferenceGraphDiff, ServerComponentClientReferenceDiff,
+};
+pub use graph_json::client_reference_graph_json;
 pub use visit_client_reference::{
-    client_reference_graph, find_server_entries, ClientReference, ClientReferenceGraphResult,
-    ClientReferenceType, ClientReferenceTypes, ServerEntries, VisitedClientReferenceGraphNodes,
+    action_reference_graph, client_reference_graph, client_reference_import_chain,
+    find_server_entries, incremental_client_reference_graph, ActionReference,
+    ActionReferenceGraphResult, ClientReference, ClientReferenceGraphResult,
+    ClientReferenceImportChain, ClientReferenceType, ClientReferenceTypes, LayoutSegmentTraversal,
+    OptionClientReferenceImportChain, ServerEntries, VisitedClientReferenceGraphNodes,
 };
@@ -1,22 +1,40 @@
-use std::{collections::HashSet, future::Future};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    sync::{Arc, Mutex},
+};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use tracing::Instrument;
 use turbo_tasks::{
     debug::ValueDebugFormat,
     graph::{AdjacencyMap, GraphTraversal, Visit, VisitControlFlow, VisitedNodes},
     trace::TraceRawVcs,
-    FxIndexMap, FxIndexSet, RcStr, ReadRef, TryJoinIterExt, ValueToString, Vc,
+    Completion, FxIndexMap, FxIndexSet, RcStr, ReadRef, TaskInput, TryFlatJoinIterExt,
+    TryJoinIterExt, ValueToString, Vc,
 };
 use turbo_tasks_fs::FileSystemPath;
 use turbopack::css::CssModuleAsset;
-use turbopack_core::{module::Module, reference::primary_referenced_modules};
+use turbopack_core::{
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    module::{Module, Modules},
+    reference::primary_referenced_modules,
+};
+use turbopack_ecmascript::{
+    chunk::{EcmascriptChunkPlaceable, EcmascriptExports},
+    references::esm::EsmExport,
+    worker_chunk::module::WorkerLoaderModule,
+};
 
 use super::ecmascript_client_reference::ecmascript_client_reference_module::EcmascriptClientReferenceModule;
 use crate::{
     next_client_reference::ecmascript_client_reference::ecmascript_client_reference_proxy_module::EcmascriptClientReferenceProxyModule,
+    next_config::{ClientReferenceBudget, ClientReferenceTraversalLimits},
+    next_manifests::ActionLayer,
+    next_server_actions::{parse_actions, ActionMap},
     next_server_component::server_component_module::NextServerComponentModule,
+    util::NextRuntime,
 };
 
 #[derive(
@@ -24,6 +42,7 @@ use crate::{
 )]
 pub struct ClientReference {
     server_component: Option<Vc<NextServerComponentModule>>,
+    runtime: NextRuntime,
     ty: ClientReferenceType,
 }
 
@@ -32,13 +51,28 @@ impl ClientReference {
         self.server_component
     }
 
+    /// The runtime of the server component (or entry) referencing the client module.
+    pub fn runtime(&self) -> NextRuntime {
+        self.runtime
+    }
+
     pub fn ty(&self) -> ClientReferenceType {
         self.ty
     }
 }
 
 #[derive(
-    Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Debug, ValueDebugFormat, TraceRawVcs,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Hash,
+    Serialize,
+    Deserialize,
+    Debug,
+    ValueDebugFormat,
+    TraceRawVcs,
+    TaskInput,
 )]
 pub enum ClientReferenceType {
     EcmascriptClientReference {
@@ -46,6 +80,20 @@ pub enum ClientReferenceType {
         module: Vc<EcmascriptClientReferenceModule>,
     },
     CssClientReference(Vc<CssModuleAsset>),
+    /// A `new Worker(new URL(...))`. The worker's module graph belongs to the client and is
+    /// chunked as a separate chunk group.
+    WorkerClientReference(Vc<WorkerLoaderModule>),
+}
+
+impl ClientReferenceType {
+    /// The module marking the client boundary.
+    pub fn module(&self) -> Vc<Box<dyn Module>> {
+        match *self {
+            ClientReferenceType::EcmascriptClientReference { module, .. } => Vc::upcast(module),
+            ClientReferenceType::CssClientReference(css_module) => Vc::upcast(css_module),
+            ClientReferenceType::WorkerClientReference(worker_loader) => Vc::upcast(worker_loader),
+        }
+    }
 }
 
 #[turbo_tasks::value(shared)]
@@ -55,9 +103,17 @@ pub struct ClientReferenceGraphResult {
     /// Only the [`ClientReferenceType::EcmascriptClientReference`]s are listed in this map.
     #[allow(clippy::type_complexity)]
     pub client_references_by_server_component:
-        FxIndexMap<Option<Vc<NextServerComponentModule>>, Vec<Vc<Box<dyn Module>>>>,
+        FxIndexMap<(Option<Vc<NextServerComponentModule>>, NextRuntime), Vec<Vc<Box<dyn Module>>>>,
     pub server_component_entries: Vec<Vc<NextServerComponentModule>>,
+    /// The server components nested directly in each server component. Server components that
+    /// are imported from outside of any server component are listed under `None`.
+    #[allow(clippy::type_complexity)]
+    pub server_component_children:
+        FxIndexMap<Option<Vc<NextServerComponentModule>>, Vec<Vc<NextServerComponentModule>>>,
     pub server_utils: Vec<Vc<Box<dyn Module>>>,
+    /// The modules importing each of the `server_utils`, i.e. the entries or server components
+    /// that use them.
+    pub server_util_referrers: FxIndexMap<Vc<Box<dyn Module>>, Vec<Vc<Box<dyn Module>>>>,
     pub visited_nodes: Vc<VisitedClientReferenceGraphNodes>,
 }
 
@@ -67,7 +123,9 @@ impl Default for ClientReferenceGraphResult {
             client_references: Default::default(),
             client_references_by_server_component: Default::default(),
             server_component_entries: Default::default(),
+            server_component_children: Default::default(),
             server_utils: Default::default(),
+            server_util_referrers: Default::default(),
             visited_nodes: VisitedClientReferenceGraphNodes::empty(),
         }
     }
@@ -98,117 +156,639 @@ impl ClientReferenceGraphResult {
                 .collect::<FxIndexSet<_>>(),
         )
     }
+
+    /// Emits a [ClientReferenceBudgetIssue] for every server component with more client
+    /// references than the `budget` allows, and one for the entry at `entry_path` if it has more
+    /// client modules than allowed.
+    #[turbo_tasks::function]
+    pub async fn check_budget(
+        self: Vc<Self>,
+        budget: Vc<ClientReferenceBudget>,
+        entry_path: Vc<FileSystemPath>,
+    ) -> Result<Vc<Completion>> {
+        let ClientReferenceBudget {
+            max_client_references_per_server_component,
+            max_client_modules_per_entry,
+        } = *budget.await?;
+
+        if let Some(max) = max_client_references_per_server_component {
+            let this = self.await?;
+            let mut counts = FxIndexMap::default();
+            for client_reference in this.client_references.iter() {
+                if let Some(server_component) = client_reference.server_component {
+                    *counts.entry(server_component).or_insert(0) += 1;
+                }
+            }
+            for (server_component, count) in counts {
+                if count > max {
+                    ClientReferenceBudgetIssue {
+                        path: server_component.ident().path(),
+                        title: format!(
+                            "Server component {} has {count} client references, the budget is \
+                             {max}",
+                            server_component.ident().to_string().await?
+                        )
+                        .into(),
+                    }
+                    .cell()
+                    .emit();
+                }
+            }
+        }
+
+        if let Some(max) = max_client_modules_per_entry {
+            let count = self.types().await?.len();
+            if count > max {
+                ClientReferenceBudgetIssue {
+                    path: entry_path,
+                    title: format!(
+                        "Entry {} has {count} client modules, the budget is {max}",
+                        entry_path.to_string().await?
+                    )
+                    .into(),
+                }
+                .cell()
+                .emit();
+            }
+        }
+
+        Ok(Completion::new())
+    }
+}
+
+#[turbo_tasks::value(shared)]
+struct ClientReferenceBudgetIssue {
+    path: Vc<FileSystemPath>,
+    title: RcStr,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ClientReferenceBudgetIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.into()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Analysis.into()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(self.title.clone()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(
+                "Every client reference adds to the JavaScript sent to the browser. Consider \
+                 moving client boundaries further down the tree, or adjust \
+                 `experimental.turbo.clientReferenceBudget` in next.config.js."
+                    .into(),
+            )
+            .cell(),
+        ))
+    }
+}
+
+/// A server component that imports itself through other modules.
+#[turbo_tasks::value(shared)]
+struct ClientReferenceCycleIssue {
+    cycle: Vec<Vc<Box<dyn Module>>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ClientReferenceCycleIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.into()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Analysis.into()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.cycle[0].ident().path()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text("Server component is part of an import cycle".into()).cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<Vc<OptionStyledString>> {
+        let paths = self
+            .cycle
+            .iter()
+            .map(|module| async move { Ok(module.ident().path().await?.path.clone()) })
+            .try_join()
+            .await?;
+        Ok(Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Text(
+                    "The server component imports itself through the following modules. Its \
+                     client references can't be assigned to a single layout segment, which can \
+                     cause errors while chunking. Break the cycle to fix this."
+                        .into(),
+                ),
+                StyledString::Text(paths.join(" -> ").into()),
+            ])
+            .cell(),
+        )))
+    }
 }
 
 impl ClientReferenceGraphResult {
-    /// Merges multiple return values of client_reference_graph together.
+    /// Merges multiple return values of client_reference_graph together. Entries that are
+    /// already part of `self` are skipped, since graphs that were traversed separately can
+    /// overlap.
     pub fn extend(&mut self, other: &Self) {
-        self.client_references
-            .extend(other.client_references.iter().copied());
+        extend_unique(&mut self.client_references, &other.client_references);
         for (k, v) in other.client_references_by_server_component.iter() {
-            self.client_references_by_server_component
-                .entry(*k)
-                .or_insert_with(Vec::new)
-                .extend(v);
+            extend_unique(
+                self.client_references_by_server_component
+                    .entry(*k)
+                    .or_insert_with(Vec::new),
+                v,
+            );
+        }
+        extend_unique(
+            &mut self.server_component_entries,
+            &other.server_component_entries,
+        );
+        for (k, v) in other.server_component_children.iter() {
+            extend_unique(
+                self.server_component_children
+                    .entry(*k)
+                    .or_insert_with(Vec::new),
+                v,
+            );
+        }
+        extend_unique(&mut self.server_utils, &other.server_utils);
+        for (k, v) in other.server_util_referrers.iter() {
+            extend_unique(
+                self.server_util_referrers
+                    .entry(*k)
+                    .or_insert_with(Vec::new),
+                v,
+            );
         }
-        self.server_component_entries
-            .extend(other.server_component_entries.iter().copied());
-        self.server_utils.extend(other.server_utils.iter().copied());
         // This is merged already by `client_reference_graph` itself
         self.visited_nodes = other.visited_nodes;
     }
+
+    /// The same CSS module is discovered once per server component that imports it, possibly as
+    /// different module instances. Replaces every CSS client reference with one canonical module
+    /// per ident and removes the resulting duplicates, so that shared CSS is only processed once
+    /// downstream. The association with each server component is kept.
+    pub async fn dedupe_css_client_references(&mut self) -> Result<()> {
+        let idents = self
+            .client_references
+            .iter()
+            .map(|client_reference| async move {
+                Ok(match client_reference.ty {
+                    ClientReferenceType::CssClientReference(css_module) => {
+                        Some(css_module.ident().to_string().await?)
+                    }
+                    ClientReferenceType::EcmascriptClientReference { .. }
+                    | ClientReferenceType::WorkerClientReference(..) => None,
+                })
+            })
+            .try_join()
+            .await?;
+
+        let mut css_modules = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut client_references = Vec::with_capacity(self.client_references.len());
+        for (mut client_reference, ident) in self.client_references.iter().copied().zip(idents) {
+            if let (ClientReferenceType::CssClientReference(css_module), Some(ident)) =
+                (client_reference.ty, ident)
+            {
+                client_reference.ty = ClientReferenceType::CssClientReference(
+                    *css_modules.entry(ident).or_insert(css_module),
+                );
+            }
+            if seen.insert(client_reference) {
+                client_references.push(client_reference);
+            }
+        }
+        self.client_references = client_references;
+        Ok(())
+    }
+
+    /// Lists every client reference only under the outermost server component that references
+    /// it, since the client references of a layout segment are available in all segments nested
+    /// in it. This matches a traversal that visits the server components from the outside in and
+    /// skips modules that it has visited before. Client references outside of any server
+    /// component are outermost.
+    fn remove_client_references_of_ancestors(&mut self) {
+        let mut types_by_server_component = FxIndexMap::<_, Vec<_>>::default();
+        for client_reference in &self.client_references {
+            types_by_server_component
+                .entry(client_reference.server_component)
+                .or_default()
+                .push((client_reference.runtime, client_reference.ty));
+        }
+        let outermost_types =
+            outermost_items(&self.server_component_children, &types_by_server_component);
+        self.client_references.retain(|client_reference| {
+            outermost_types
+                .get(&client_reference.server_component)
+                .map_or(true, |types| {
+                    types.contains(&(client_reference.runtime, client_reference.ty))
+                })
+        });
+
+        let mut modules_by_server_component = FxIndexMap::<_, Vec<_>>::default();
+        for (&(server_component, runtime), modules) in &self.client_references_by_server_component {
+            modules_by_server_component
+                .entry(server_component)
+                .or_default()
+                .extend(modules.iter().map(|&module| (runtime, module)));
+        }
+        let outermost_modules = outermost_items(
+            &self.server_component_children,
+            &modules_by_server_component,
+        );
+        for (&(server_component, runtime), modules) in
+            self.client_references_by_server_component.iter_mut()
+        {
+            if let Some(outermost_modules) = outermost_modules.get(&server_component) {
+                modules.retain(|&module| outermost_modules.contains(&(runtime, module)));
+            }
+        }
+    }
+}
+
+/// Removes the `items` of every segment that one of its ancestors already has. The segments are
+/// visited from the outside in, starting at `None`, and a segment that is nested in several
+/// segments belongs to the first one it's reached from. Segments that aren't reachable from
+/// `None` keep all of their items.
+fn outermost_items<S, T>(
+    children: &FxIndexMap<Option<S>, Vec<S>>,
+    items: &FxIndexMap<Option<S>, Vec<T>>,
+) -> FxIndexMap<Option<S>, FxIndexSet<T>>
+where
+    S: Copy + Eq + std::hash::Hash,
+    T: Copy + Eq + std::hash::Hash,
+{
+    let mut outermost = FxIndexMap::default();
+    // The items of every visited segment and its ancestors
+    let mut available = HashMap::<Option<S>, HashSet<T>>::new();
+    let mut queue = VecDeque::from([(None, None)]);
+    while let Some((segment, parent)) = queue.pop_front() {
+        if available.contains_key(&segment) {
+            continue;
+        }
+        let mut segment_available = parent
+            .and_then(|parent| available.get(&parent))
+            .cloned()
+            .unwrap_or_default();
+        let segment_items = items
+            .get(&segment)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|item| !segment_available.contains(item))
+            .collect::<FxIndexSet<_>>();
+        segment_available.extend(segment_items.iter().copied());
+        available.insert(segment, segment_available);
+        outermost.insert(segment, segment_items);
+        for &child in children.get(&segment).into_iter().flatten() {
+            queue.push_back((Some(child), Some(segment)));
+        }
+    }
+    for (segment, segment_items) in items {
+        outermost
+            .entry(*segment)
+            .or_insert_with(|| segment_items.iter().copied().collect());
+    }
+    outermost
+}
+
+fn extend_unique<T: Copy + Eq + std::hash::Hash>(items: &mut Vec<T>, other: &[T]) {
+    let mut known = items.iter().copied().collect::<HashSet<_>>();
+    items.extend(other.iter().copied().filter(|item| known.insert(*item)));
 }
 
+/// Finds the client references reachable from the `entries`, skipping the `visited_nodes`.
 #[turbo_tasks::function]
 pub async fn client_reference_graph(
     entries: Vec<Vc<Box<dyn Module>>>,
     visited_nodes: Vc<VisitedClientReferenceGraphNodes>,
+    runtime: NextRuntime,
+    limits: Vc<ClientReferenceTraversalLimits>,
 ) -> Result<Vc<ClientReferenceGraphResult>> {
-    async move {
-        let mut client_references = vec![];
-        let mut server_component_entries = vec![];
-        let mut server_utils = vec![];
+    Ok(
+        traverse_client_references(entries, visited_nodes, false, runtime, limits)
+            .instrument(tracing::info_span!("find client references"))
+            .await?
+            .cell(),
+    )
+}
 
-        let mut client_references_by_server_component = FxIndexMap::default();
-        // Make sure None (for the various internal next/dist/esm/client/components/*) is listed
-        // first
-        client_references_by_server_component.insert(None, Vec::new());
+/// How [incremental_client_reference_graph] traverses the layout segments.
+#[derive(
+    Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs, TaskInput,
+)]
+pub enum LayoutSegmentTraversal {
+    /// One layout segment after another.
+    Sequential,
+    /// The layout segments of the same nesting depth in parallel, each in its own task. Every
+    /// segment stops at its nested segments, so modules are only traversed again when they are
+    /// imported by several segments.
+    Sharded,
+}
 
-        let (graph, visited_nodes) = AdjacencyMap::new()
-            .skip_duplicates_with_visited_nodes(VisitedNodes(visited_nodes.await?.0.clone()))
-            .visit(
-                entries
-                    .iter()
-                    .copied()
-                    .map(|module| async move {
-                        Ok(VisitClientReferenceNode {
-                            state: if let Some(server_component) =
-                                Vc::try_resolve_downcast_type::<NextServerComponentModule>(module)
-                                    .await?
-                            {
-                                VisitClientReferenceNodeState::InServerComponent {
-                                    server_component,
-                                }
-                            } else {
-                                VisitClientReferenceNodeState::Entry {
-                                    entry_path: module.ident().path().resolve().await?,
-                                }
-                            },
-                            ty: VisitClientReferenceNodeType::Internal(
-                                module,
-                                module.ident().to_string().await?,
-                            ),
-                        })
-                    })
+/// Finds the client references of the `entries` like [`client_reference_graph`], but traverses
+/// every server component in a separate task. A change only causes the server components whose
+/// part of the graph is affected to be traversed again. Like in a single traversal, a client
+/// reference is only listed under the outermost server component that references it.
+#[turbo_tasks::function]
+pub async fn incremental_client_reference_graph(
+    entries: Vec<Vc<Box<dyn Module>>>,
+    runtime: NextRuntime,
+    limits: Vc<ClientReferenceTraversalLimits>,
+    traversal: LayoutSegmentTraversal,
+) -> Result<Vc<ClientReferenceGraphResult>> {
+    let mut result = ClientReferenceGraphResult::default();
+    let mut merged_visited_nodes = HashSet::new();
+    let mut visited = HashSet::new();
+    let mut pending = entries;
+    while !pending.is_empty() {
+        let mut modules = Vec::new();
+        for module in pending {
+            let module = module.resolve().await?;
+            if visited.insert(module) {
+                modules.push(module);
+            }
+        }
+        let subgraphs = match traversal {
+            LayoutSegmentTraversal::Sequential => {
+                let mut subgraphs = Vec::with_capacity(modules.len());
+                for module in modules {
+                    subgraphs.push(client_reference_subgraph(module, runtime, limits).await?);
+                }
+                subgraphs
+            }
+            LayoutSegmentTraversal::Sharded => {
+                modules
+                    .into_iter()
+                    .map(|module| client_reference_subgraph(module, runtime, limits))
                     .try_join()
-                    .await?,
-                VisitClientReference {
-                    stop_at_server_entries: false,
-                },
-            )
-            .await
-            .completed()?
-            .into_inner_with_visited();
+                    .await?
+            }
+        };
+        pending = Vec::new();
+        for subgraph in subgraphs.iter() {
+            result.extend(subgraph);
+            merged_visited_nodes.extend(subgraph.visited_nodes.await?.0.iter().cloned());
+            // Nested server components are traversed on their own
+            pending.extend(
+                subgraph
+                    .server_component_entries
+                    .iter()
+                    .map(|&server_component| Vc::upcast(server_component)),
+            );
+        }
+    }
+    result.visited_nodes = VisitedClientReferenceGraphNodes(merged_visited_nodes).cell();
+    result.dedupe_css_client_references().await?;
+    result.remove_client_references_of_ancestors();
+    Ok(result.cell())
+}
 
-        for node in graph.into_reverse_topological() {
-            match &node.ty {
-                VisitClientReferenceNodeType::Internal(_asset, _) => {
-                    // No-op. These nodes are only useful during graph
-                    // traversal.
-                }
-                VisitClientReferenceNodeType::ClientReference(client_reference, _) => {
-                    client_references.push(*client_reference);
+/// The part of the client reference graph of `entry` that doesn't belong to a nested server
+/// component.
+#[turbo_tasks::function]
+async fn client_reference_subgraph(
+    entry: Vc<Box<dyn Module>>,
+    runtime: NextRuntime,
+    limits: Vc<ClientReferenceTraversalLimits>,
+) -> Result<Vc<ClientReferenceGraphResult>> {
+    Ok(traverse_client_references(
+        vec![entry],
+        VisitedClientReferenceGraphNodes::empty(),
+        true,
+        runtime,
+        limits,
+    )
+    .instrument(tracing::info_span!(
+        "find client references of server component"
+    ))
+    .await?
+    .cell())
+}
 
-                    if let ClientReferenceType::EcmascriptClientReference {
-                        module: entry, ..
-                    } = client_reference.ty()
-                    {
-                        client_references_by_server_component
-                            .entry(client_reference.server_component)
-                            .or_insert_with(Vec::new)
-                            .push(Vc::upcast::<Box<dyn Module>>(entry.await?.ssr_module));
+async fn traverse_client_references(
+    entries: Vec<Vc<Box<dyn Module>>>,
+    visited_nodes: Vc<VisitedClientReferenceGraphNodes>,
+    stop_at_server_components: bool,
+    runtime: NextRuntime,
+    limits: Vc<ClientReferenceTraversalLimits>,
+) -> Result<ClientReferenceGraphResult> {
+    let mut client_references = vec![];
+    let mut server_component_entries = vec![];
+    let mut server_utils = vec![];
+
+    let mut client_references_by_server_component = FxIndexMap::default();
+    // Make sure None (for the various internal next/dist/esm/client/components/*) is listed
+    // first
+    client_references_by_server_component.insert((None, runtime), Vec::new());
+
+    let entry_nodes = entry_nodes(&entries, runtime).await?;
+    let limits = TraversalLimits::new(&*limits.await?, &entry_nodes);
+    let (graph, visited_nodes) = AdjacencyMap::new()
+        .skip_duplicates_with_visited_nodes(VisitedNodes(visited_nodes.await?.0.clone()))
+        .visit(
+            entry_nodes,
+            VisitClientReference {
+                stop_at_server_entries: false,
+                stop_at_server_components,
+                limits: Some(limits),
+            },
+        )
+        .await
+        .completed()?
+        .into_inner_with_visited();
+
+    let mut server_component_children = FxIndexMap::default();
+    let mut server_util_referrers = FxIndexMap::default();
+    for node in graph.reverse_topological() {
+        let Some(children) = graph.get(node) else {
+            continue;
+        };
+        let parent = node.state.server_component();
+        let parent_module = match node.ty {
+            VisitClientReferenceNodeType::ClientReference(..) => continue,
+            VisitClientReferenceNodeType::Internal(module, _)
+            | VisitClientReferenceNodeType::ServerUtilEntry(module, _) => module,
+            VisitClientReferenceNodeType::ServerComponentEntry(module, _) => Vc::upcast(module),
+        };
+        for child in children {
+            match child.ty {
+                VisitClientReferenceNodeType::ServerComponentEntry(server_component, _) => {
+                    if parent != Some(server_component) {
+                        let siblings: &mut Vec<_> =
+                            server_component_children.entry(parent).or_default();
+                        if !siblings.contains(&server_component) {
+                            siblings.push(server_component);
+                        }
                     }
                 }
                 VisitClientReferenceNodeType::ServerUtilEntry(server_util, _) => {
-                    server_utils.push(*server_util);
+                    let referrers: &mut Vec<_> =
+                        server_util_referrers.entry(server_util).or_default();
+                    if !referrers.contains(&parent_module) {
+                        referrers.push(parent_module);
+                    }
                 }
-                VisitClientReferenceNodeType::ServerComponentEntry(server_component, _) => {
-                    server_component_entries.push(*server_component);
+                VisitClientReferenceNodeType::ClientReference(..)
+                | VisitClientReferenceNodeType::Internal(..) => {}
+            }
+        }
+    }
+
+    for node in graph.into_reverse_topological() {
+        match &node.ty {
+            VisitClientReferenceNodeType::Internal(_asset, _) => {
+                // No-op. These nodes are only useful during graph
+                // traversal.
+            }
+            VisitClientReferenceNodeType::ClientReference(client_reference, _) => {
+                client_references.push(*client_reference);
+
+                if let ClientReferenceType::EcmascriptClientReference { module: entry, .. } =
+                    client_reference.ty()
+                {
+                    client_references_by_server_component
+                        .entry((client_reference.server_component, client_reference.runtime))
+                        .or_insert_with(Vec::new)
+                        .push(Vc::upcast::<Box<dyn Module>>(entry.await?.ssr_module));
                 }
             }
+            VisitClientReferenceNodeType::ServerUtilEntry(server_util, _) => {
+                server_utils.push(*server_util);
+            }
+            VisitClientReferenceNodeType::ServerComponentEntry(server_component, _) => {
+                server_component_entries.push(*server_component);
+            }
         }
+    }
+
+    let mut result = ClientReferenceGraphResult {
+        client_references,
+        client_references_by_server_component,
+        server_component_entries,
+        server_component_children,
+        server_utils,
+        server_util_referrers,
+        visited_nodes: VisitedClientReferenceGraphNodes(visited_nodes.0).cell(),
+    };
+    result.dedupe_css_client_references().await?;
+    Ok(result)
+}
+
+/// The root nodes of a traversal starting at the `entries`.
+async fn entry_nodes(
+    entries: &[Vc<Box<dyn Module>>],
+    runtime: NextRuntime,
+) -> Result<Vec<VisitClientReferenceNode>> {
+    entries
+        .iter()
+        .copied()
+        .map(|module| async move {
+            Ok(VisitClientReferenceNode {
+                state: if let Some(server_component) =
+                    Vc::try_resolve_downcast_type::<NextServerComponentModule>(module).await?
+                {
+                    VisitClientReferenceNodeState::InServerComponent {
+                        server_component,
+                        runtime,
+                    }
+                } else {
+                    VisitClientReferenceNodeState::Entry {
+                        entry_path: module.ident().path().resolve().await?,
+                        runtime,
+                    }
+                },
+                ty: VisitClientReferenceNodeType::Internal(
+                    module,
+                    module.ident().to_string().await?,
+                ),
+            })
+        })
+        .try_join()
+        .await
+}
+
+/// The names of the modules along an import path, starting at an entry and ending at a client
+/// reference.
+#[turbo_tasks::value(transparent)]
+pub struct ClientReferenceImportChain(Vec<RcStr>);
+
+#[turbo_tasks::value(transparent)]
+pub struct OptionClientReferenceImportChain(Option<Vc<ClientReferenceImportChain>>);
+
+/// Finds the shortest import path from one of the `entries` to the given client reference. This
+/// explains why a module ends up in the client bundle.
+#[turbo_tasks::function]
+pub async fn client_reference_import_chain(
+    entries: Vec<Vc<Box<dyn Module>>>,
+    runtime: NextRuntime,
+    client_reference_ty: ClientReferenceType,
+) -> Result<Vc<OptionClientReferenceImportChain>> {
+    let graph = AdjacencyMap::new()
+        .skip_duplicates()
+        .visit(
+            entry_nodes(&entries, runtime).await?,
+            VisitClientReference {
+                stop_at_server_entries: false,
+                stop_at_server_components: false,
+                limits: None,
+            },
+        )
+        .await
+        .completed()?
+        .into_inner();
 
-        Ok(ClientReferenceGraphResult {
-            client_references,
-            client_references_by_server_component,
-            server_component_entries,
-            server_utils,
-            visited_nodes: VisitedClientReferenceGraphNodes(visited_nodes.0).cell(),
+    // Breadth-first search, remembering the parent of every node to reconstruct the path
+    let mut parents = HashMap::new();
+    let mut visited = graph.roots().collect::<HashSet<_>>();
+    let mut queue = graph.roots().collect::<VecDeque<_>>();
+    while let Some(node) = queue.pop_front() {
+        if let VisitClientReferenceNodeType::ClientReference(client_reference, _) = &node.ty {
+            if client_reference.ty == client_reference_ty {
+                let mut chain = vec![node.ty.name().clone_value()];
+                let mut current = node;
+                while let Some(&parent) = parents.get(current) {
+                    chain.push(parent.ty.name().clone_value());
+                    current = parent;
+                }
+                chain.reverse();
+                return Ok(Vc::cell(Some(Vc::cell(chain))));
+            }
+        }
+        for child in graph.get(node).into_iter().flatten() {
+            if visited.insert(child) {
+                parents.insert(child, node);
+                queue.push_back(child);
+            }
         }
-        .cell())
     }
-    .instrument(tracing::info_span!("find client references"))
-    .await
+
+    Ok(Vc::cell(None))
 }
 
 #[turbo_tasks::value(shared)]
@@ -219,7 +799,10 @@ pub struct ServerEntries {
 }
 
 #[turbo_tasks::function]
-pub async fn find_server_entries(entry: Vc<Box<dyn Module>>) -> Result<Vc<ServerEntries>> {
+pub async fn find_server_entries(
+    entry: Vc<Box<dyn Module>>,
+    runtime: NextRuntime,
+) -> Result<Vc<ServerEntries>> {
     let graph = AdjacencyMap::new()
         .skip_duplicates()
         .visit(
@@ -227,12 +810,15 @@ pub async fn find_server_entries(entry: Vc<Box<dyn Module>>) -> Result<Vc<Server
                 state: {
                     VisitClientReferenceNodeState::Entry {
                         entry_path: entry.ident().path().resolve().await?,
+                        runtime,
                     }
                 },
                 ty: VisitClientReferenceNodeType::Internal(entry, entry.ident().to_string().await?),
             }],
             VisitClientReference {
                 stop_at_server_entries: true,
+                stop_at_server_components: false,
+                limits: None,
             },
         )
         .await
@@ -264,6 +850,152 @@ pub async fn find_server_entries(entry: Vc<Box<dyn Module>>) -> Result<Vc<Server
 struct VisitClientReference {
     /// Used to discover ServerComponents and ServerUtils
     stop_at_server_entries: bool,
+    /// Used to traverse every ServerComponent on its own
+    stop_at_server_components: bool,
+    limits: Option<Arc<TraversalLimits>>,
+}
+
+/// Enforces the [ClientReferenceTraversalLimits] of a traversal. Tracks the depth and the parent
+/// of every visited node, so that the deepest import chain and import cycles can be reported.
+struct TraversalLimits {
+    max_depth: Option<usize>,
+    max_modules: Option<usize>,
+    #[allow(clippy::type_complexity)]
+    parents: Mutex<HashMap<VisitClientReferenceNode, (usize, Option<VisitClientReferenceNode>)>>,
+}
+
+impl TraversalLimits {
+    /// The `entries` are the roots of the traversal. They keep a depth of 0 and no parent when
+    /// they are imported again through a cycle.
+    fn new(
+        limits: &ClientReferenceTraversalLimits,
+        entries: &[VisitClientReferenceNode],
+    ) -> Arc<Self> {
+        Arc::new(TraversalLimits {
+            max_depth: limits.max_depth,
+            max_modules: limits.max_modules,
+            parents: Mutex::new(
+                entries
+                    .iter()
+                    .map(|entry| (entry.clone(), (0, None)))
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Records the edges from `parent` to its `children`, failing if a limit is exceeded.
+    fn record(
+        &self,
+        parent: &VisitClientReferenceNode,
+        children: &[VisitClientReferenceNode],
+    ) -> Result<()> {
+        let mut parents = self.parents.lock().unwrap();
+        for child in children {
+            let child_depth = record_parent(&mut parents, parent, child);
+            if let Some(max_depth) = self.max_depth {
+                if child_depth > max_depth {
+                    bail!(
+                        "Finding client references exceeded the maximum import depth of \
+                         {max_depth}. This is usually caused by modules re-exporting each other. \
+                         The deepest import chain is:\n{}",
+                        import_chain(&parents, child, |node| node.ty.name().to_string())
+                    );
+                }
+            }
+        }
+        if let Some(max_modules) = self.max_modules {
+            if parents.len() > max_modules {
+                let (deepest, _) = parents
+                    .iter()
+                    .max_by_key(|(_, (depth, _))| *depth)
+                    .expect("there are more than max_modules nodes");
+                bail!(
+                    "Finding client references exceeded the maximum of {max_modules} modules. The \
+                     deepest import chain is:\n{}",
+                    import_chain(&parents, deepest, |node| node.ty.name().to_string())
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds the `children` that import the server component `parent` belongs to again. Returns
+    /// the modules of every such cycle, starting and ending with the server component.
+    fn server_component_cycles(
+        &self,
+        parent: &VisitClientReferenceNode,
+        children: &[VisitClientReferenceNode],
+    ) -> Vec<Vec<Vc<Box<dyn Module>>>> {
+        let Some(server_component) = parent.state.server_component() else {
+            return vec![];
+        };
+        let parents = self.parents.lock().unwrap();
+        children
+            .iter()
+            .filter(|child| {
+                matches!(
+                    child.ty,
+                    VisitClientReferenceNodeType::ServerComponentEntry(child_server_component, _)
+                        if child_server_component == server_component
+                )
+            })
+            .map(|child| {
+                let server_component_module = child.ty.module();
+                let mut cycle = vec![server_component_module];
+                let mut current = parent;
+                // Walk up to the node of the server component itself
+                while current.ty.module() != server_component_module {
+                    cycle.push(current.ty.module());
+                    match parents.get(current) {
+                        Some((_, Some(next)))
+                            if next.state.server_component() == Some(server_component) =>
+                        {
+                            current = next;
+                        }
+                        _ => break,
+                    }
+                }
+                cycle.push(server_component_module);
+                cycle.reverse();
+                cycle
+            })
+            .collect()
+    }
+}
+
+/// Records `parent` as the parent of `child` if `child` hasn't been reached before. Returns the
+/// depth of `child`.
+fn record_parent<N: Clone + Eq + std::hash::Hash>(
+    parents: &mut HashMap<N, (usize, Option<N>)>,
+    parent: &N,
+    child: &N,
+) -> usize {
+    let depth = parents.get(parent).map_or(0, |&(depth, _)| depth) + 1;
+    parents
+        .entry(child.clone())
+        .or_insert_with(|| (depth, Some(parent.clone())))
+        .0
+}
+
+/// Follows the recorded parents from `node` up to an entry. Stops when a module is reached again,
+/// so that a cycle in the parents can't make this loop forever.
+fn import_chain<N: Eq + std::hash::Hash>(
+    parents: &HashMap<N, (usize, Option<N>)>,
+    node: &N,
+    name: impl Fn(&N) -> String,
+) -> String {
+    let mut chain = vec![name(node)];
+    let mut seen = HashSet::from([node]);
+    let mut current = node;
+    while let Some((_, Some(parent))) = parents.get(current) {
+        if !seen.insert(parent) {
+            break;
+        }
+        chain.push(name(parent));
+        current = parent;
+    }
+    chain.reverse();
+    chain.join("\n  -> ")
 }
 
 #[derive(
@@ -280,20 +1012,32 @@ struct VisitClientReferenceNode {
 enum VisitClientReferenceNodeState {
     Entry {
         entry_path: Vc<FileSystemPath>,
+        runtime: NextRuntime,
     },
     InServerComponent {
         server_component: Vc<NextServerComponentModule>,
+        runtime: NextRuntime,
+    },
+    InServerUtil {
+        runtime: NextRuntime,
     },
-    InServerUtil,
 }
 impl VisitClientReferenceNodeState {
     fn server_component(&self) -> Option<Vc<NextServerComponentModule>> {
         match self {
             VisitClientReferenceNodeState::Entry { .. } => None,
-            VisitClientReferenceNodeState::InServerComponent { server_component } => {
-                Some(*server_component)
-            }
-            VisitClientReferenceNodeState::InServerUtil => None,
+            VisitClientReferenceNodeState::InServerComponent {
+                server_component, ..
+            } => Some(*server_component),
+            VisitClientReferenceNodeState::InServerUtil { .. } => None,
+        }
+    }
+
+    fn runtime(&self) -> NextRuntime {
+        match self {
+            VisitClientReferenceNodeState::Entry { runtime, .. }
+            | VisitClientReferenceNodeState::InServerComponent { runtime, .. }
+            | VisitClientReferenceNodeState::InServerUtil { runtime } => *runtime,
         }
     }
 }
@@ -308,6 +1052,135 @@ enum VisitClientReferenceNodeType {
     Internal(Vc<Box<dyn Module>>, ReadRef<RcStr>),
 }
 
+/// The modules referenced by `module`, with barrel modules (see [is_barrel_module]) replaced by
+/// the modules they reference. This avoids a node for every barrel module in every state, which
+/// adds up for icon and UI libraries.
+#[turbo_tasks::function]
+async fn referenced_modules_without_barrels(module: Vc<Box<dyn Module>>) -> Result<Vc<Modules>> {
+    let referenced_modules = primary_referenced_modules(module)
+        .await?
+        .iter()
+        .map(|module| async move {
+            let module = module.resolve().await?;
+            Ok(if *is_barrel_module(module).await? {
+                barrel_targets(module).await?.clone_value()
+            } else {
+                vec![module]
+            })
+        })
+        .try_flat_join()
+        .await?;
+    Ok(Vc::cell(
+        referenced_modules
+            .into_iter()
+            .collect::<FxIndexSet<_>>()
+            .into_iter()
+            .collect(),
+    ))
+}
+
+/// The modules that the `barrel` module re-exports, following nested barrel modules. This is a
+/// separate function, so every barrel is only expanded once, no matter how many modules import
+/// it.
+#[turbo_tasks::function]
+async fn barrel_targets(barrel: Vc<Box<dyn Module>>) -> Result<Vc<Modules>> {
+    let mut referenced_modules = Vec::new();
+    let mut seen = HashSet::from([barrel]);
+    let mut current = primary_referenced_modules(barrel).await?.clone_value();
+    while !current.is_empty() {
+        let modules = current
+            .iter()
+            .map(|module| async move {
+                let module = module.resolve().await?;
+                Ok((module, *is_barrel_module(module).await?))
+            })
+            .try_join()
+            .await?;
+
+        let mut barrel_modules = Vec::new();
+        for (module, is_barrel) in modules {
+            if !seen.insert(module) {
+                continue;
+            }
+            if is_barrel {
+                barrel_modules.push(module);
+            } else {
+                referenced_modules.push(module);
+            }
+        }
+
+        current = barrel_modules
+            .into_iter()
+            .map(
+                |module| async move { Ok(primary_referenced_modules(module).await?.clone_value()) },
+            )
+            .try_flat_join()
+            .await?;
+    }
+    Ok(Vc::cell(referenced_modules))
+}
+
+/// Whether `module` is an ecmascript module that only re-exports other modules, e.g. `export *
+/// from "./icon"`. The traversal follows its references as if the importing module referenced
+/// them directly.
+#[turbo_tasks::function]
+async fn is_barrel_module(module: Vc<Box<dyn Module>>) -> Result<Vc<bool>> {
+    // These are boundaries of the traversal and must remain nodes
+    if Vc::try_resolve_downcast_type::<EcmascriptClientReferenceProxyModule>(module)
+        .await?
+        .is_some()
+        || Vc::try_resolve_downcast_type::<NextServerComponentModule>(module)
+            .await?
+            .is_some()
+    {
+        return Ok(Vc::cell(false));
+    }
+
+    let Some(placeable) =
+        Vc::try_resolve_sidecast::<Box<dyn EcmascriptChunkPlaceable>>(module).await?
+    else {
+        return Ok(Vc::cell(false));
+    };
+    let EcmascriptExports::EsmExports(exports) = &*placeable.get_exports().await? else {
+        return Ok(Vc::cell(false));
+    };
+    let exports = exports.await?;
+
+    Ok(Vc::cell(
+        !(exports.exports.is_empty() && exports.star_exports.is_empty())
+            && exports.exports.values().all(|export| {
+                matches!(
+                    export,
+                    EsmExport::ImportedBinding(..) | EsmExport::ImportedNamespace(..)
+                )
+            }),
+    ))
+}
+
+impl VisitClientReferenceNodeType {
+    fn module(&self) -> Vc<Box<dyn Module>> {
+        match self {
+            VisitClientReferenceNodeType::ClientReference(client_reference, _) => {
+                client_reference.ty().module()
+            }
+            VisitClientReferenceNodeType::ServerComponentEntry(server_component, _) => {
+                Vc::upcast(*server_component)
+            }
+            VisitClientReferenceNodeType::ServerUtilEntry(module, _)
+            | VisitClientReferenceNodeType::Internal(module, _) => *module,
+        }
+    }
+
+    fn name(&self) -> &ReadRef<RcStr> {
+        match self {
+            VisitClientReferenceNodeType::ClientReference(_, name)
+            | VisitClientReferenceNodeType::ServerComponentEntry(_, name)
+            | VisitClientReferenceNodeType::ServerUtilEntry(_, name)
+            | VisitClientReferenceNodeType::Internal(_, name) => name,
+        }
+    }
+}
+
 impl Visit<VisitClientReferenceNode> for VisitClientReference {
     type Edge = VisitClientReferenceNode;
     type EdgesIntoIter = Vec<Self::Edge>;
@@ -323,6 +1196,14 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
         {
             return VisitControlFlow::Skip(edge);
         }
+        if self.stop_at_server_components
+            && matches!(
+                edge.ty,
+                VisitClientReferenceNodeType::ServerComponentEntry(..)
+            )
+        {
+            return VisitControlFlow::Skip(edge);
+        }
 
         match edge.ty {
             VisitClientReferenceNodeType::ClientReference(..) => VisitControlFlow::Skip(edge),
@@ -336,6 +1217,7 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
 
     fn edges(&mut self, node: &VisitClientReferenceNode) -> Self::EdgesFuture {
         let node = node.clone();
+        let limits = self.limits.clone();
         async move {
             let parent_module = match node.ty {
                 // This should never occur since we always skip visiting these
@@ -346,7 +1228,7 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
                 VisitClientReferenceNodeType::ServerComponentEntry(module, _) => Vc::upcast(module),
             };
 
-            let referenced_modules = primary_referenced_modules(parent_module).await?;
+            let referenced_modules = referenced_modules_without_barrels(parent_module).await?;
 
             let referenced_modules = referenced_modules.iter().map(|module| async move {
                 let module = module.resolve().await?;
@@ -358,6 +1240,7 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
                         ty: VisitClientReferenceNodeType::ClientReference(
                             ClientReference {
                                 server_component: node.state.server_component(),
+                                runtime: node.state.runtime(),
                                 ty: ClientReferenceType::EcmascriptClientReference {
                                     parent_module: Vc::try_resolve_downcast_type::<
                                         EcmascriptClientReferenceProxyModule,
@@ -382,6 +1265,7 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
                         ty: VisitClientReferenceNodeType::ClientReference(
                             ClientReference {
                                 server_component: node.state.server_component(),
+                                runtime: node.state.runtime(),
                                 ty: ClientReferenceType::CssClientReference(
                                     css_client_reference_asset,
                                 ),
@@ -391,12 +1275,29 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
                     });
                 }
 
+                if let Some(worker_loader) =
+                    Vc::try_resolve_downcast_type::<WorkerLoaderModule>(module).await?
+                {
+                    return Ok(VisitClientReferenceNode {
+                        state: node.state,
+                        ty: VisitClientReferenceNodeType::ClientReference(
+                            ClientReference {
+                                server_component: node.state.server_component(),
+                                runtime: node.state.runtime(),
+                                ty: ClientReferenceType::WorkerClientReference(worker_loader),
+                            },
+                            worker_loader.ident().to_string().await?,
+                        ),
+                    });
+                }
+
                 if let Some(server_component_asset) =
                     Vc::try_resolve_downcast_type::<NextServerComponentModule>(module).await?
                 {
                     return Ok(VisitClientReferenceNode {
                         state: VisitClientReferenceNodeState::InServerComponent {
                             server_component: server_component_asset,
+                            runtime: node.state.runtime(),
                         },
                         ty: VisitClientReferenceNodeType::ServerComponentEntry(
                             server_component_asset,
@@ -405,10 +1306,14 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
                     });
                 }
 
-                if let VisitClientReferenceNodeState::Entry { entry_path } = &node.state {
-                    if module.ident().path().resolve().await? != *entry_path {
+                if let VisitClientReferenceNodeState::Entry {
+                    entry_path,
+                    runtime,
+                } = node.state
+                {
+                    if module.ident().path().resolve().await? != entry_path {
                         return Ok(VisitClientReferenceNode {
-                            state: VisitClientReferenceNodeState::InServerUtil,
+                            state: VisitClientReferenceNodeState::InServerUtil { runtime },
                             ty: VisitClientReferenceNodeType::ServerUtilEntry(
                                 module,
                                 module.ident().to_string().await?,
@@ -428,6 +1333,13 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
 
             let assets = referenced_modules.try_join().await?;
 
+            if let Some(limits) = &limits {
+                limits.record(&node, &assets)?;
+                for cycle in limits.server_component_cycles(&node, &assets) {
+                    ClientReferenceCycleIssue { cycle }.cell().emit();
+                }
+            }
+
             Ok(assets)
         }
     }
@@ -449,3 +1361,331 @@ impl Visit<VisitClientReferenceNode> for VisitClientReference {
         }
     }
 }
+
+/// A module exporting server actions, along with the layer it was found in.
+#[derive(
+    Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, Debug, ValueDebugFormat, TraceRawVcs,
+)]
+pub struct ActionReference {
+    pub layer: ActionLayer,
+    pub module: Vc<Box<dyn Module>>,
+    pub actions: Vc<ActionMap>,
+}
+
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct ActionReferenceGraphResult {
+    /// The modules exporting server actions, keyed by the layout segment they are reachable
+    /// from. Actions that are reachable from the entry or a server util are listed under `None`.
+    #[allow(clippy::type_complexity)]
+    pub actions_by_server_component:
+        FxIndexMap<Option<Vc<NextServerComponentModule>>, Vec<ActionReference>>,
+}
+
+impl ActionReferenceGraphResult {
+    pub fn action_references(&self) -> impl Iterator<Item = &ActionReference> {
+        self.actions_by_server_component.values().flatten()
+    }
+}
+
+/// Finds the modules exporting server actions that are reachable from the `entries`, either
+/// from a server component (the RSC layer) or from a client component (the action-browser
+/// layer). Every module is visited once per layer, and is listed under the outermost layout
+/// segment it's reachable from.
+#[turbo_tasks::function]
+pub async fn action_reference_graph(
+    entries: Vec<Vc<Box<dyn Module>>>,
+) -> Result<Vc<ActionReferenceGraphResult>> {
+    async move {
+        let graph = AdjacencyMap::new()
+            .skip_duplicates()
+            .visit(
+                entries
+                    .iter()
+                    .map(|module| async move {
+                        let module = module.resolve().await?;
+                        Ok(VisitActionReferenceNode {
+                            server_component: Vc::try_resolve_downcast_type::<
+                                NextServerComponentModule,
+                            >(module)
+                            .await?,
+                            layer: ActionLayer::Rsc,
+                            module,
+                        })
+                    })
+                    .try_join()
+                    .await?,
+                VisitActionReference,
+            )
+            .await
+            .completed()?
+            .into_inner();
+
+        let server_components = outermost_segments(
+            graph.roots().copied(),
+            |node| graph.get(&node).into_iter().flatten().copied().collect(),
+            |node| node.server_component,
+        );
+
+        let actions = graph
+            .into_reverse_topological()
+            .map(|node| async move {
+                Ok(parse_actions(node.module)
+                    .await?
+                    .clone_value()
+                    .map(|actions| (node, actions)))
+            })
+            .try_flat_join()
+            .await?;
+
+        let mut actions_by_server_component = FxIndexMap::default();
+        // Make sure None (for actions outside of any layout segment) is listed first
+        actions_by_server_component.insert(None, Vec::new());
+        for (node, actions) in actions {
+            actions_by_server_component
+                .entry(server_components.get(&node).copied().flatten())
+                .or_insert_with(Vec::new)
+                .push(ActionReference {
+                    layer: node.layer,
+                    module: node.module,
+                    actions,
+                });
+        }
+
+        Ok(ActionReferenceGraphResult {
+            actions_by_server_component,
+        }
+        .cell())
+    }
+    .instrument(tracing::info_span!("find server actions"))
+    .await
+}
+
+/// Assigns every node to the outermost segment it's reachable from. A node that starts a segment
+/// belongs to that segment, every other node to the segment of the first node it's reached from
+/// while visiting the graph breadth first from the `roots`.
+fn outermost_segments<N, S>(
+    roots: impl IntoIterator<Item = N>,
+    children: impl Fn(N) -> Vec<N>,
+    own_segment: impl Fn(N) -> Option<S>,
+) -> HashMap<N, Option<S>>
+where
+    N: Copy + Eq + std::hash::Hash,
+    S: Copy,
+{
+    let mut segments = HashMap::new();
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if !segments.contains_key(&root) {
+            segments.insert(root, own_segment(root));
+            queue.push_back(root);
+        }
+    }
+    while let Some(node) = queue.pop_front() {
+        let segment = segments[&node];
+        for child in children(node) {
+            if !segments.contains_key(&child) {
+                segments.insert(child, own_segment(child).or(segment));
+                queue.push_back(child);
+            }
+        }
+    }
+    segments
+}
+
+struct VisitActionReference;
+
+/// A module in one of the layers. Modules that are shared between layout segments are only
+/// visited once, the segments are assigned afterwards with [outermost_segments].
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
+struct VisitActionReferenceNode {
+    /// Set when the module is a server component, i.e. starts a layout segment. This only
+    /// depends on the module, so it doesn't cause a module to be visited more than once.
+    server_component: Option<Vc<NextServerComponentModule>>,
+    layer: ActionLayer,
+    module: Vc<Box<dyn Module>>,
+}
+
+impl Visit<VisitActionReferenceNode> for VisitActionReference {
+    type Edge = VisitActionReferenceNode;
+    type EdgesIntoIter = Vec<Self::Edge>;
+    type EdgesFuture = impl Future<Output = Result<Self::EdgesIntoIter>>;
+
+    fn visit(&mut self, edge: Self::Edge) -> VisitControlFlow<VisitActionReferenceNode> {
+        VisitControlFlow::Continue(edge)
+    }
+
+    fn edges(&mut self, node: &VisitActionReferenceNode) -> Self::EdgesFuture {
+        let node = *node;
+        async move {
+            let referenced_modules = primary_referenced_modules(node.module).await?;
+
+            let referenced_modules = referenced_modules.iter().map(|module| async move {
+                let module = module.resolve().await?;
+                if node.layer == ActionLayer::Rsc {
+                    // Actions imported by a client component are bundled for the browser, so
+                    // continue with the client module of the client reference.
+                    if let Some(client_reference_module) =
+                        Vc::try_resolve_downcast_type::<EcmascriptClientReferenceModule>(module)
+                            .await?
+                    {
+                        let client_module = client_reference_module.await?.client_module;
+                        return Ok(VisitActionReferenceNode {
+                            server_component: None,
+                            layer: ActionLayer::ActionBrowser,
+                            module: Vc::upcast::<Box<dyn Module>>(client_module)
+                                .resolve()
+                                .await?,
+                        });
+                    }
+
+                    if let Some(server_component) =
+                        Vc::try_resolve_downcast_type::<NextServerComponentModule>(module).await?
+                    {
+                        return Ok(VisitActionReferenceNode {
+                            server_component: Some(server_component),
+                            layer: ActionLayer::Rsc,
+                            module,
+                        });
+                    }
+                }
+
+                Ok(VisitActionReferenceNode {
+                    server_component: None,
+                    layer: node.layer,
+                    module,
+                })
+            });
+
+            referenced_modules.try_join().await
+        }
+    }
+
+    fn span(&mut self, node: &VisitActionReferenceNode) -> tracing::Span {
+        match node.layer {
+            ActionLayer::Rsc => tracing::info_span!("module"),
+            ActionLayer::ActionBrowser => tracing::info_span!("client module"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use turbo_tasks::{FxIndexMap, FxIndexSet};
+
+    use super::{import_chain, outermost_items, outermost_segments, record_parent};
+
+    fn items(
+        items: FxIndexMap<Option<u32>, FxIndexSet<&'static str>>,
+    ) -> Vec<(Option<u32>, Vec<&'static str>)> {
+        items
+            .into_iter()
+            .map(|(segment, items)| (segment, items.into_iter().collect()))
+            .collect()
+    }
+
+    #[test]
+    fn client_references_are_listed_under_the_outermost_segment() {
+        // The entry renders the root layout 1, which renders the pages 2 and 3
+        let children = FxIndexMap::from_iter([(None, vec![1]), (Some(1), vec![2, 3])]);
+        let client_references = FxIndexMap::from_iter([
+            (None, vec!["util"]),
+            (Some(1), vec!["button", "util"]),
+            (Some(2), vec!["button", "dialog", "util"]),
+            (Some(3), vec!["dialog", "menu"]),
+        ]);
+
+        // Same as traversing the segments from the outside in, skipping the modules that were
+        // visited in an ancestor. Siblings don't share their client references.
+        assert_eq!(
+            items(outermost_items(&children, &client_references)),
+            vec![
+                (None, vec!["util"]),
+                (Some(1), vec!["button"]),
+                (Some(2), vec!["dialog"]),
+                (Some(3), vec!["dialog", "menu"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_segments_belong_to_the_first_parent() {
+        // The page 3 is rendered by both layouts 1 and 2, and layout 2 is nested in layout 1
+        let children = FxIndexMap::from_iter([
+            (None, vec![1, 4]),
+            (Some(1), vec![2, 3]),
+            (Some(2), vec![3]),
+        ]);
+        let client_references = FxIndexMap::from_iter([
+            (Some(1), vec!["button"]),
+            (Some(2), vec!["dialog"]),
+            (Some(3), vec!["button", "dialog"]),
+            (Some(4), vec!["button"]),
+            // Not reachable from the entry
+            (Some(5), vec!["button"]),
+        ]);
+
+        assert_eq!(
+            items(outermost_items(&children, &client_references)),
+            vec![
+                (None, vec![]),
+                (Some(1), vec!["button"]),
+                (Some(4), vec!["button"]),
+                (Some(2), vec!["dialog"]),
+                (Some(3), vec!["dialog"]),
+                (Some(5), vec!["button"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn actions_imported_from_client_components_belong_to_the_outermost_segment() {
+        // Both layouts render the same client component, which imports the actions. The action
+        // module is only reachable through the client component.
+        let edges = HashMap::from([
+            ("entry", vec!["layout", "util"]),
+            ("layout", vec!["page", "client (rsc)"]),
+            ("page", vec!["client (rsc)"]),
+            ("client (rsc)", vec!["client (browser)"]),
+            ("client (browser)", vec!["actions (browser)"]),
+            ("util", vec!["server actions"]),
+        ]);
+        let segments = outermost_segments(
+            ["entry"],
+            |node| edges.get(node).cloned().unwrap_or_default(),
+            |node| matches!(node, "layout" | "page").then_some(node),
+        );
+
+        assert_eq!(segments.len(), 8);
+        assert_eq!(segments["entry"], None);
+        assert_eq!(segments["server actions"], None);
+        assert_eq!(segments["page"], Some("page"));
+        assert_eq!(segments["client (browser)"], Some("layout"));
+        assert_eq!(segments["actions (browser)"], Some("layout"));
+    }
+
+    #[test]
+    fn cyclic_barrels_keep_the_entry_as_root() {
+        // The page imports a barrel, which re-exports an icon that imports the page again
+        let mut parents = HashMap::from([("page", (0, None))]);
+        assert_eq!(record_parent(&mut parents, &"page", &"barrel"), 1);
+        assert_eq!(record_parent(&mut parents, &"barrel", &"icon"), 2);
+        assert_eq!(record_parent(&mut parents, &"icon", &"page"), 0);
+        assert_eq!(record_parent(&mut parents, &"icon", &"barrel"), 1);
+        assert_eq!(
+            import_chain(&parents, &"icon", |node| node.to_string()),
+            "page\n  -> barrel\n  -> icon"
+        );
+    }
+
+    #[test]
+    fn import_chain_stops_at_a_cycle() {
+        let parents = HashMap::from([("barrel", (1, Some("icon"))), ("icon", (2, Some("barrel")))]);
+        assert_eq!(
+            import_chain(&parents, &"barrel", |node| node.to_string()),
+            "icon\n  -> barrel"
+        );
+    }
+}
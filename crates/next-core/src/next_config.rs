@@ -403,6 +403,8 @@ pub struct ExperimentalTurboConfig {
     pub use_swc_css: Option<bool>,
     pub tree_shaking: Option<bool>,
     pub module_id_strategy: Option<ModuleIdStrategy>,
+    pub client_reference_budget: Option<ClientReferenceBudget>,
+    pub client_reference_traversal_limits: Option<ClientReferenceTraversalLimits>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
@@ -446,6 +448,30 @@ pub enum ModuleIdStrategy {
 #[turbo_tasks::value(transparent)]
 pub struct OptionModuleIdStrategy(pub Option<ModuleIdStrategy>);
 
+/// Thresholds for the client references of an app entry. Exceeding them is reported as a
+/// warning.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientReferenceBudget {
+    /// The maximum number of client references of a single server component.
+    pub max_client_references_per_server_component: Option<usize>,
+    /// The maximum number of client modules of a single entry.
+    pub max_client_modules_per_entry: Option<usize>,
+}
+
+/// Limits for the traversal of the module graph when looking for client references. Exceeding
+/// them fails the build instead of letting the traversal run for a pathological module graph.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientReferenceTraversalLimits {
+    /// The maximum length of an import chain.
+    pub max_depth: Option<usize>,
+    /// The maximum number of modules visited by a single traversal.
+    pub max_modules: Option<usize>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, TraceRawVcs)]
 #[serde(untagged)]
 pub enum MdxRsOptions {
@@ -1228,6 +1254,26 @@ impl NextConfig {
         };
         Vc::cell(Some(module_id_strategy.clone()))
     }
+
+    #[turbo_tasks::function]
+    pub fn client_reference_budget(&self) -> Vc<ClientReferenceBudget> {
+        self.experimental
+            .turbo
+            .as_ref()
+            .and_then(|t| t.client_reference_budget.clone())
+            .unwrap_or_default()
+            .cell()
+    }
+
+    #[turbo_tasks::function]
+    pub fn client_reference_traversal_limits(&self) -> Vc<ClientReferenceTraversalLimits> {
+        self.experimental
+            .turbo
+            .as_ref()
+            .and_then(|t| t.client_reference_traversal_limits.clone())
+            .unwrap_or_default()
+            .cell()
+    }
 }
 
 /// A subset of ts/jsconfig that next.js implicitly
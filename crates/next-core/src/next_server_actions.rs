@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use swc_core::{
+    atoms::Atom,
+    common::comments::Comments,
+    ecma::{
+        ast::{Decl, ExportSpecifier, Id, ModuleDecl, ModuleItem, Program},
+        utils::find_pat_ids,
+    },
+};
+use turbo_tasks::{FxIndexMap, Vc};
+use turbopack_core::{module::Module, resolve::ModulePart};
+use turbopack_ecmascript::{
+    parse::ParseResult, tree_shake::asset::EcmascriptModulePartAsset, EcmascriptParsable,
+};
+
+/// Maps the hashed action id to the action's exported function name.
+#[turbo_tasks::value(transparent)]
+pub struct ActionMap(FxIndexMap<String, String>);
+
+/// An Option wrapper around [ActionMap].
+#[turbo_tasks::value(transparent)]
+pub struct OptionActionMap(Option<Vc<ActionMap>>);
+
+#[turbo_tasks::value_impl]
+impl OptionActionMap {
+    #[turbo_tasks::function]
+    pub fn none() -> Vc<Self> {
+        Vc::cell(None)
+    }
+}
+
+/// Parses the Server Actions comment for all exported action function names.
+///
+/// Action names are stored in a leading BlockComment prefixed by
+/// `__next_internal_action_entry_do_not_use__`.
+pub fn parse_server_actions(
+    program: &Program,
+    comments: &dyn Comments,
+) -> Option<BTreeMap<String, String>> {
+    let byte_pos = match program {
+        Program::Module(m) => m.span.lo,
+        Program::Script(s) => s.span.lo,
+    };
+    comments.get_leading(byte_pos).and_then(|comments| {
+        comments.iter().find_map(|c| {
+            c.text
+                .split_once("__next_internal_action_entry_do_not_use__")
+                .and_then(|(_, actions)| match serde_json::from_str(actions) {
+                    Ok(v) => Some(v),
+                    Err(_) => None,
+                })
+        })
+    })
+}
+
+/// Inspects the comments inside [Module] looking for the magic actions comment.
+/// If found, we return the mapping of every action's hashed id to the name of
+/// the exported action function. If not, we return a None.
+#[turbo_tasks::function]
+pub async fn parse_actions(module: Vc<Box<dyn Module>>) -> Result<Vc<OptionActionMap>> {
+    let Some(ecmascript_asset) =
+        Vc::try_resolve_sidecast::<Box<dyn EcmascriptParsable>>(module).await?
+    else {
+        return Ok(OptionActionMap::none());
+    };
+
+    if let Some(module) = Vc::try_resolve_downcast_type::<EcmascriptModulePartAsset>(module).await?
+    {
+        if matches!(
+            &*module.await?.part.await?,
+            ModulePart::Evaluation
+                | ModulePart::Exports
+                | ModulePart::Facade
+                | ModulePart::Internal(..)
+        ) {
+            return Ok(OptionActionMap::none());
+        }
+    }
+
+    let original_parsed = ecmascript_asset.parse_original().resolve().await?;
+
+    let ParseResult::Ok {
+        program: original,
+        comments,
+        ..
+    } = &*original_parsed.await?
+    else {
+        // The file might be parse-able, but this is reported separately.
+        return Ok(OptionActionMap::none());
+    };
+
+    let Some(mut actions) = parse_server_actions(original, comments) else {
+        return Ok(OptionActionMap::none());
+    };
+
+    let fragment = ecmascript_asset.failsafe_parse().resolve().await?;
+
+    if fragment != original_parsed {
+        let ParseResult::Ok {
+            program: fragment, ..
+        } = &*fragment.await?
+        else {
+            // The file might be be parse-able, but this is reported separately.
+            return Ok(OptionActionMap::none());
+        };
+
+        let all_exports = all_export_names(fragment);
+        actions.retain(|_, name| all_exports.iter().any(|export| export == name));
+    }
+
+    let mut actions = FxIndexMap::from_iter(actions.into_iter());
+    actions.sort_keys();
+    Ok(Vc::cell(Some(Vc::cell(actions))))
+}
+
+fn all_export_names(program: &Program) -> Vec<Atom> {
+    match program {
+        Program::Module(m) => {
+            let mut exports = Vec::new();
+            for item in m.body.iter() {
+                match item {
+                    ModuleItem::ModuleDecl(
+                        ModuleDecl::ExportDefaultExpr(..) | ModuleDecl::ExportDefaultDecl(..),
+                    ) => {
+                        exports.push("default".into());
+                    }
+                    ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(decl)) => match &decl.decl {
+                        Decl::Class(c) => {
+                            exports.push(c.ident.sym.clone());
+                        }
+                        Decl::Fn(f) => {
+                            exports.push(f.ident.sym.clone());
+                        }
+                        Decl::Var(v) => {
+                            let ids: Vec<Id> = find_pat_ids(v);
+                            exports.extend(ids.into_iter().map(|id| id.0));
+                        }
+                        _ => {}
+                    },
+                    ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(decl)) => {
+                        for s in decl.specifiers.iter() {
+                            match s {
+                                ExportSpecifier::Named(named) => {
+                                    exports.push(
+                                        named
+                                            .exported
+                                            .as_ref()
+                                            .unwrap_or(&named.orig)
+                                            .atom()
+                                            .clone(),
+                                    );
+                                }
+                                ExportSpecifier::Default(_) => {
+                                    exports.push("default".into());
+                                }
+                                ExportSpecifier::Namespace(e) => {
+                                    exports.push(e.name.atom().clone());
+                                }
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            exports
+        }
+
+        _ => {
+            vec![]
+        }
+    }
+}
@@ -406,6 +406,18 @@ export const configSchema: zod.ZodType<NextConfig> = z.lazy(() =>
               .optional(),
             memoryLimit: z.number().optional(),
             moduleIdStrategy: z.enum(['named', 'deterministic']).optional(),
+            clientReferenceBudget: z
+              .object({
+                maxClientReferencesPerServerComponent: z.number().optional(),
+                maxClientModulesPerEntry: z.number().optional(),
+              })
+              .optional(),
+            clientReferenceTraversalLimits: z
+              .object({
+                maxDepth: z.number().optional(),
+                maxModules: z.number().optional(),
+              })
+              .optional(),
           })
           .optional(),
         optimizePackageImports: z.array(z.string()).optional(),
@@ -174,6 +174,38 @@ export interface ExperimentalTurboOptions {
    */
   moduleIdStrategy?: 'named' | 'deterministic'
 
+  /**
+   * Thresholds for the client references of an App Router entry. Turbopack
+   * shows a warning naming the offending server components when they are
+   * exceeded.
+   */
+  clientReferenceBudget?: {
+    /**
+     * The maximum number of client references of a single server component.
+     */
+    maxClientReferencesPerServerComponent?: number
+    /**
+     * The maximum number of client modules of a single entry.
+     */
+    maxClientModulesPerEntry?: number
+  }
+
+  /**
+   * Limits for the module graph traversal that finds client references.
+   * Exceeding them fails the build with the deepest import chain instead of
+   * letting the traversal run for pathological module graphs.
+   */
+  clientReferenceTraversalLimits?: {
+    /**
+     * The maximum length of an import chain.
+     */
+    maxDepth?: number
+    /**
+     * The maximum number of modules visited by a single traversal.
+     */
+    maxModules?: number
+  }
+
   /**
    * This is the repo root usually and only files above this
    * directory can be resolved by turbopack.
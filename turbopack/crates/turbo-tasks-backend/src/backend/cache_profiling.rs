@@ -0,0 +1,106 @@
+use std::{fmt::Write, hash::BuildHasherDefault};
+
+use dashmap::{DashMap, DashSet};
+use rustc_hash::FxHasher;
+use turbo_tasks::{registry, FunctionId, TaskId};
+
+/// Why a task had to be executed instead of using the cached output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecomputeReason {
+    /// The task has never been computed before.
+    NewTask,
+    /// The task was invalidated in this session, e.g. because a dependency changed.
+    DependencyDirty,
+    /// The task depends on session state and is dirty in every new session.
+    SessionDependent,
+    /// The task was restored from the backing storage, but the restored state was not usable.
+    RestoredInvalid,
+}
+
+/// How often the tasks of a function were served from the cache and how often, and why, they
+/// were recomputed. Every task is only counted once per session.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheEffectiveness {
+    pub hits: usize,
+    pub new_task: usize,
+    pub dependency_dirty: usize,
+    pub session_dependent: usize,
+    pub restored_invalid: usize,
+}
+
+impl CacheEffectiveness {
+    pub fn recomputations(&self) -> usize {
+        self.new_task + self.dependency_dirty + self.session_dependent + self.restored_invalid
+    }
+}
+
+#[derive(Default)]
+pub struct CacheProfiler {
+    per_function: DashMap<FunctionId, CacheEffectiveness, BuildHasherDefault<FxHasher>>,
+    /// Tasks that have already been counted in this session.
+    counted: DashSet<TaskId, BuildHasherDefault<FxHasher>>,
+    /// Tasks that have been made dirty in this session.
+    invalidated: DashSet<TaskId, BuildHasherDefault<FxHasher>>,
+}
+
+impl CacheProfiler {
+    pub fn invalidated(&self, task_id: TaskId) {
+        self.invalidated.insert(task_id);
+    }
+
+    pub fn was_invalidated(&self, task_id: TaskId) -> bool {
+        self.invalidated.contains(&task_id)
+    }
+
+    /// Called when the output of a task is read without executing it.
+    pub fn hit(&self, task_id: TaskId, fn_type: FunctionId) {
+        if self.counted.insert(task_id) {
+            self.per_function.entry(fn_type).or_default().hits += 1;
+        }
+    }
+
+    pub fn recompute(&self, task_id: TaskId, fn_type: FunctionId, reason: RecomputeReason) {
+        if !self.counted.insert(task_id) {
+            return;
+        }
+        let mut effectiveness = self.per_function.entry(fn_type).or_default();
+        match reason {
+            RecomputeReason::NewTask => effectiveness.new_task += 1,
+            RecomputeReason::DependencyDirty => effectiveness.dependency_dirty += 1,
+            RecomputeReason::SessionDependent => effectiveness.session_dependent += 1,
+            RecomputeReason::RestoredInvalid => effectiveness.restored_invalid += 1,
+        }
+    }
+
+    pub fn summary(&self) -> Vec<(FunctionId, CacheEffectiveness)> {
+        let mut summary = self
+            .per_function
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect::<Vec<_>>();
+        summary.sort_by_key(|(_, effectiveness)| std::cmp::Reverse(effectiveness.recomputations()));
+        summary
+    }
+
+    pub fn format_summary(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:<60} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "function", "hits", "new", "dirty", "session", "restored"
+        );
+        for (fn_type, effectiveness) in self.summary() {
+            let _ = writeln!(
+                out,
+                "{:<60} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                registry::get_function(fn_type).name,
+                effectiveness.hits,
+                effectiveness.new_task,
+                effectiveness.dependency_dirty,
+                effectiveness.session_dependent,
+                effectiveness.restored_invalid,
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,338 @@
+//! Checks the invariants of the in-memory task storage, to find the operation that broke the
+//! aggregation graph instead of noticing the drift much later, e.g. as a strongly consistent read
+//! that never completes.
+//!
+//! The invariants only hold while no operation is in progress. Enable the `verify_consistency`
+//! feature to check them every time the last running operation completes.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_tasks::{CellId, SessionId, TaskId, ValueTypeId};
+
+use super::{
+    operation::is_aggregating_node, storage::Storage, TaskDataCategory, TurboTasksBackend,
+    TurboTasksBackendInner,
+};
+use crate::{
+    backing_storage::BackingStorage,
+    data::{CachedDataItem, CachedDataItemKey, CachedDataItemValue},
+};
+
+/// The items of a task that are relevant for the invariants.
+#[derive(Default)]
+struct TaskSummary {
+    aggregation_number: u32,
+    /// `None` when the data of the task hasn't been restored.
+    children: Option<Vec<TaskId>>,
+    followers: FxHashSet<TaskId>,
+    uppers: FxHashSet<TaskId>,
+    dirty: bool,
+    dirty_containers: FxHashMap<TaskId, i32>,
+    dirty_container_count: i32,
+    cells: Vec<CellId>,
+    cell_type_max_indices: FxHashMap<ValueTypeId, u32>,
+}
+
+impl TaskSummary {
+    fn is_aggregating(&self) -> bool {
+        is_aggregating_node(self.aggregation_number)
+    }
+
+    /// The tasks that are connected to the uppers of this task.
+    fn followers(&self) -> Box<dyn Iterator<Item = TaskId> + '_> {
+        if self.is_aggregating() {
+            Box::new(self.followers.iter().copied())
+        } else {
+            Box::new(self.children.iter().flatten().copied())
+        }
+    }
+
+    /// Whether the task contributes a dirty container to its uppers.
+    fn has_dirty_container(&self) -> bool {
+        self.dirty || (self.is_aggregating() && self.dirty_container_count > 0)
+    }
+}
+
+impl Storage<TaskId, CachedDataItem> {
+    /// Checks the invariants of the aggregation graph and the cells of all tasks in memory and
+    /// describes every violation found:
+    ///
+    /// - Every follower of a task (its children for leaf tasks) is connected to all uppers of the
+    ///   task, either as inner node or as follower, and every child of an aggregating task is
+    ///   connected to the task. Conversely, every `Upper` and `Follower` edge has such a reason,
+    ///   which is only checked when all tasks in memory are fully restored.
+    /// - The aggregated dirty containers of a task match the inner nodes that are dirty or contain
+    ///   dirty tasks, and the aggregated dirty container count matches the dirty containers.
+    /// - Every cell of a task is within the `CellTypeMaxIndex` of its type.
+    ///
+    /// The caller has to make sure that no operation is in progress.
+    pub fn verify_consistency(&self, session_id: SessionId) -> Vec<String> {
+        let mut tasks = FxHashMap::default();
+        let mut fully_restored = true;
+        self.for_each(|&task_id, task| {
+            let state = task.persistance_state();
+            if !state.is_restored(TaskDataCategory::Meta) {
+                fully_restored = false;
+                return;
+            }
+            let data_restored = state.is_restored(TaskDataCategory::Data);
+            fully_restored &= data_restored;
+            let mut summary = TaskSummary {
+                children: data_restored.then(Vec::new),
+                ..Default::default()
+            };
+            for (key, value) in task.iter_all() {
+                match (key, value) {
+                    (CachedDataItemKey::Child { task }, _) => {
+                        if let Some(children) = &mut summary.children {
+                            children.push(*task);
+                        }
+                    }
+                    (
+                        CachedDataItemKey::Follower { task },
+                        CachedDataItemValue::Follower { value },
+                    ) if *value > 0 => {
+                        summary.followers.insert(*task);
+                    }
+                    (CachedDataItemKey::Upper { task }, CachedDataItemValue::Upper { value })
+                        if *value > 0 =>
+                    {
+                        summary.uppers.insert(*task);
+                    }
+                    (_, CachedDataItemValue::AggregationNumber { value }) => {
+                        summary.aggregation_number = value.effective;
+                    }
+                    (_, CachedDataItemValue::Dirty { value }) => {
+                        summary.dirty = value.get(session_id);
+                    }
+                    (
+                        CachedDataItemKey::AggregatedDirtyContainer { task },
+                        CachedDataItemValue::AggregatedDirtyContainer { value },
+                    ) => {
+                        summary
+                            .dirty_containers
+                            .insert(*task, value.get(session_id));
+                    }
+                    (_, CachedDataItemValue::AggregatedDirtyContainerCount { value }) => {
+                        summary.dirty_container_count = value.get(session_id);
+                    }
+                    (
+                        CachedDataItemKey::CellData { cell }
+                        | CachedDataItemKey::SerializedCellData { cell }
+                        | CachedDataItemKey::SpilledCell { cell },
+                        _,
+                    ) => summary.cells.push(*cell),
+                    (
+                        CachedDataItemKey::CellTypeMaxIndex { cell_type },
+                        CachedDataItemValue::CellTypeMaxIndex { value },
+                    ) => {
+                        summary.cell_type_max_indices.insert(*cell_type, *value);
+                    }
+                    _ => {}
+                }
+            }
+            tasks.insert(task_id, summary);
+        });
+
+        let mut inconsistencies = Vec::new();
+        verify_edges(&tasks, fully_restored, &mut inconsistencies);
+        verify_dirty_containers(&tasks, &mut inconsistencies);
+        verify_cells(&tasks, &mut inconsistencies);
+        inconsistencies.sort();
+        inconsistencies
+    }
+}
+
+fn is_connected(tasks: &FxHashMap<TaskId, TaskSummary>, task_id: TaskId, upper_id: TaskId) -> bool {
+    tasks
+        .get(&task_id)
+        .is_none_or(|task| task.uppers.contains(&upper_id))
+        || tasks
+            .get(&upper_id)
+            .is_none_or(|upper| upper.followers.contains(&task_id))
+}
+
+fn verify_edges(
+    tasks: &FxHashMap<TaskId, TaskSummary>,
+    fully_restored: bool,
+    inconsistencies: &mut Vec<String>,
+) {
+    // Every `Upper` and `Follower` edge that is expected by the children and uppers of a task
+    let mut expected_edges = FxHashSet::default();
+    for (&task_id, task) in tasks.iter() {
+        if task.is_aggregating() {
+            for child_id in task.children.iter().flatten().copied() {
+                expected_edges.insert((child_id, task_id));
+                if !is_connected(tasks, child_id, task_id) {
+                    inconsistencies.push(format!(
+                        "{task_id} has child {child_id}, which is neither an inner node nor a \
+                         follower of it"
+                    ));
+                }
+            }
+        }
+        for &upper_id in task.uppers.iter() {
+            if tasks
+                .get(&upper_id)
+                .is_some_and(|upper| !upper.is_aggregating())
+            {
+                inconsistencies.push(format!(
+                    "{task_id} has upper {upper_id}, which is not an aggregating node"
+                ));
+            }
+            for follower_id in task.followers() {
+                expected_edges.insert((follower_id, upper_id));
+                if !is_connected(tasks, follower_id, upper_id) {
+                    inconsistencies.push(format!(
+                        "{task_id} has follower {follower_id}, which is not connected to its \
+                         upper {upper_id}"
+                    ));
+                }
+            }
+        }
+    }
+    if !fully_restored {
+        return;
+    }
+    for (&task_id, task) in tasks.iter() {
+        for &upper_id in task.uppers.iter() {
+            if !expected_edges.contains(&(task_id, upper_id)) {
+                inconsistencies.push(format!(
+                    "{task_id} has upper {upper_id}, but is neither its child nor a follower of \
+                     one of its inner nodes"
+                ));
+            }
+        }
+        if task.is_aggregating() {
+            for &follower_id in task.followers.iter() {
+                if !expected_edges.contains(&(follower_id, task_id)) {
+                    inconsistencies.push(format!(
+                        "{task_id} has follower {follower_id}, which is neither its child nor a \
+                         follower of one of its inner nodes"
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn verify_dirty_containers(
+    tasks: &FxHashMap<TaskId, TaskSummary>,
+    inconsistencies: &mut Vec<String>,
+) {
+    for (&task_id, task) in tasks.iter() {
+        let has_dirty_container = task.has_dirty_container();
+        for &upper_id in task.uppers.iter() {
+            let Some(upper) = tasks.get(&upper_id) else {
+                continue;
+            };
+            let aggregated = upper
+                .dirty_containers
+                .get(&task_id)
+                .is_some_and(|&count| count > 0);
+            if aggregated != has_dirty_container {
+                inconsistencies.push(format!(
+                    "{upper_id} aggregates {task_id} as {}, but it {}",
+                    if aggregated {
+                        "dirty container"
+                    } else {
+                        "clean"
+                    },
+                    if has_dirty_container {
+                        "is dirty or contains dirty tasks"
+                    } else {
+                        "is clean"
+                    }
+                ));
+            }
+        }
+    }
+    for (&task_id, task) in tasks.iter() {
+        let dirty_containers = task
+            .dirty_containers
+            .values()
+            .filter(|&&count| count > 0)
+            .count() as i32;
+        if dirty_containers != task.dirty_container_count {
+            inconsistencies.push(format!(
+                "{task_id} has an aggregated dirty container count of {}, but aggregates {} dirty \
+                 containers",
+                task.dirty_container_count, dirty_containers
+            ));
+        }
+        for (&container_id, &count) in task.dirty_containers.iter() {
+            if count > 0
+                && tasks
+                    .get(&container_id)
+                    .is_some_and(|container| !container.uppers.contains(&task_id))
+            {
+                inconsistencies.push(format!(
+                    "{task_id} aggregates dirty container {container_id}, which is not an inner \
+                     node of it"
+                ));
+            }
+        }
+    }
+}
+
+fn verify_cells(tasks: &FxHashMap<TaskId, TaskSummary>, inconsistencies: &mut Vec<String>) {
+    for (&task_id, task) in tasks.iter() {
+        for cell in task.cells.iter() {
+            match task.cell_type_max_indices.get(&cell.type_id) {
+                None => inconsistencies.push(format!(
+                    "{task_id} has cell {cell:?}, but no CellTypeMaxIndex for its type"
+                )),
+                Some(&max_index) if cell.index > max_index => inconsistencies.push(format!(
+                    "{task_id} has cell {cell:?} beyond the CellTypeMaxIndex {max_index}"
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Checks the invariants of the aggregation graph and the cells of the tasks in memory. Waits
+    /// until no operation is in progress. Returns a description of every violation found.
+    pub fn verify_consistency(&self) -> Vec<String> {
+        loop {
+            if let Some(inconsistencies) = self.0.try_verify_consistency() {
+                return inconsistencies;
+            }
+            std::thread::yield_now();
+        }
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    /// Checks the invariants while all operations are suspended. Returns `None` when an operation
+    /// was suspended in the middle of its execution, as the invariants don't hold in that case.
+    fn try_verify_consistency(&self) -> Option<Vec<String>> {
+        let snapshot_lock = self.snapshot_lock.lock();
+        let suspended_operations = self.suspend_operations();
+        let inconsistencies = suspended_operations
+            .is_empty()
+            .then(|| self.storage.verify_consistency(self.session_id));
+        self.resume_operations();
+        drop(snapshot_lock);
+        inconsistencies
+    }
+
+    /// Called when the last running operation has completed, with the `verify_consistency`
+    /// feature enabled.
+    #[cfg(feature = "verify_consistency")]
+    pub(super) fn verify_consistency_after_operation(&self) {
+        if std::thread::panicking() {
+            return;
+        }
+        let Some(inconsistencies) = self.try_verify_consistency() else {
+            // Another operation started in the meantime, it will be checked when it completes
+            return;
+        };
+        if !inconsistencies.is_empty() {
+            panic!(
+                "The task storage is inconsistent after an operation:\n{}",
+                inconsistencies.join("\n")
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::hash::BuildHasherDefault;
+
+use dashmap::DashMap;
+use rustc_hash::FxHasher;
+use turbo_tasks::{CellId, TaskId, TraitTypeId};
+
+use super::TaskDirtyCause;
+
+/// Something a task has read during its execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskDependency {
+    Output(TaskId),
+    Cell(TaskId, CellId),
+    Collectibles(TaskId, TraitTypeId),
+}
+
+/// How the dependencies of a task differ between its last execution and the execution before,
+/// see [`super::TurboTasksBackend::dependency_diff`].
+#[derive(Debug, Clone, Default)]
+pub struct DependencyDiff {
+    /// Why the task was made dirty before the last execution.
+    pub dirty_cause: Option<TaskDirtyCause>,
+    /// Dependencies that changed and invalidated the task before the last execution.
+    pub changed: Vec<TaskDependency>,
+    /// Dependencies that were read for the first time in the last execution.
+    pub added: Vec<TaskDependency>,
+    /// Dependencies that were read in the execution before, but not in the last execution.
+    pub removed: Vec<TaskDependency>,
+}
+
+type TaskMap<T> = DashMap<TaskId, T, BuildHasherDefault<FxHasher>>;
+
+#[derive(Default)]
+pub struct DependencyTracker {
+    /// Dependencies that changed since the task started its last execution.
+    changed: TaskMap<Vec<TaskDependency>>,
+    /// The state of tasks that are currently executing.
+    executing: TaskMap<DependencyDiff>,
+    /// The diff of the last completed execution of each task.
+    completed: TaskMap<DependencyDiff>,
+}
+
+impl DependencyTracker {
+    pub fn dependency_changed(&self, task_ids: &[TaskId], dependency: TaskDependency) {
+        for &task_id in task_ids {
+            let mut changed = self.changed.entry(task_id).or_default();
+            if !changed.contains(&dependency) {
+                changed.push(dependency);
+            }
+        }
+    }
+
+    pub fn execution_started(&self, task_id: TaskId) {
+        let changed = self
+            .changed
+            .remove(&task_id)
+            .map(|(_, changed)| changed)
+            .unwrap_or_default();
+        self.executing.insert(
+            task_id,
+            DependencyDiff {
+                changed,
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn dependency_added(&self, task_id: TaskId, dependency: TaskDependency) {
+        if let Some(mut diff) = self.executing.get_mut(&task_id) {
+            diff.added.push(dependency);
+        }
+    }
+
+    pub fn execution_completed(
+        &self,
+        task_id: TaskId,
+        dirty_cause: Option<TaskDirtyCause>,
+        removed: Vec<TaskDependency>,
+    ) {
+        let Some((_, mut diff)) = self.executing.remove(&task_id) else {
+            return;
+        };
+        diff.dirty_cause = dirty_cause;
+        diff.removed = removed;
+        self.completed.insert(task_id, diff);
+    }
+
+    pub fn get(&self, task_id: TaskId) -> Option<DependencyDiff> {
+        self.completed.get(&task_id).map(|diff| diff.clone())
+    }
+}
@@ -0,0 +1,160 @@
+use tokio::{sync::broadcast, time::Duration};
+use turbo_tasks::{CellId, TaskId};
+
+use super::HangingChain;
+
+/// Number of events buffered per subscriber. Subscribers that fall further behind miss events
+/// and receive [`broadcast::error::RecvError::Lagged`].
+const EVENT_CHANNEL_CAPACITY: usize = 4096;
+
+/// Number of lifecycle events buffered per subscriber. They are rare, so only subscribers that
+/// stopped receiving fall behind.
+const LIFECYCLE_CHANNEL_CAPACITY: usize = 64;
+
+/// A change in the lifecycle of a task, see [`super::TurboTasksBackend::subscribe_task_events`].
+#[derive(Debug, Clone)]
+pub enum TaskEvent {
+    Scheduled {
+        task_id: TaskId,
+    },
+    Started {
+        task_id: TaskId,
+    },
+    Finished {
+        task_id: TaskId,
+        duration: Duration,
+    },
+    Invalidated {
+        task_id: TaskId,
+        reason: String,
+    },
+    /// The time spent polling a task execution exceeded the
+    /// [`BackendOptions::execution_timeout`][crate::BackendOptions::execution_timeout]. Reported
+    /// once per execution.
+    ExecutionTimeout {
+        task_id: TaskId,
+        description: String,
+        polling_time: Duration,
+        /// The execution fails with an error.
+        strict: bool,
+    },
+    /// A cell restored from the backing storage couldn't be deserialized. It's treated as missing,
+    /// so the task is recomputed when the cell is read.
+    CellDeserializationFailed {
+        task_id: TaskId,
+        cell: CellId,
+        error: String,
+    },
+    /// A snapshot has been written to the backing storage.
+    Persisted {
+        /// Number of changes written.
+        items: usize,
+        duration: Duration,
+    },
+    /// Saving snapshots failed
+    /// [`BackendOptions::max_consecutive_snapshot_failures`][crate::BackendOptions::max_consecutive_snapshot_failures]
+    /// times in a row, so nothing is persisted for the rest of the session.
+    PersistenceDisabled {
+        failures: usize,
+        /// The error of the last failed snapshot.
+        error: String,
+    },
+    /// The serialized data of a task written with a snapshot exceeds
+    /// [`BackendOptions::oversized_task_threshold`][crate::BackendOptions::oversized_task_threshold].
+    OversizedTask {
+        task_id: TaskId,
+        /// The name of the function of the task. `None` for root and once tasks.
+        function: Option<String>,
+        /// The serialized size in bytes.
+        size: usize,
+    },
+    /// An aggregation update exceeds
+    /// [`BackendOptions::aggregation_fan_out_threshold`][crate::BackendOptions::aggregation_fan_out_threshold]
+    /// or
+    /// [`BackendOptions::aggregation_queue_threshold`][crate::BackendOptions::aggregation_queue_threshold].
+    /// Only reported once per operation.
+    AggregationFanOut {
+        /// The task whose change caused the update.
+        task_id: TaskId,
+        /// Number of upper tasks updated by a single job.
+        uppers: usize,
+        /// Number of jobs queued at that time.
+        queued_jobs: usize,
+    },
+    /// Tasks have been waiting for each other for longer than
+    /// [`BackendOptions::hanging_detection_timeout`][crate::BackendOptions::hanging_detection_timeout].
+    /// Every chain is only reported once.
+    Hanging {
+        chain: HangingChain,
+    },
+    /// Progress of replaying the uncompleted operations of the last session on startup. Emitted
+    /// once before the first operation and after every operation.
+    StartupProgress {
+        replayed_operations: usize,
+        total_operations: usize,
+        /// Number of replayed operations that panicked. They are dropped and the tasks they
+        /// would have modified are invalidated.
+        failed_operations: usize,
+        /// Number of tasks restored from the backing storage so far.
+        restored_tasks: usize,
+    },
+}
+
+pub struct TaskEvents {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl Default for TaskEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl TaskEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Sends the event to all subscribers. The event is only created when there are any.
+    pub fn emit(&self, event: impl FnOnce() -> TaskEvent) {
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(event());
+        }
+    }
+}
+
+/// A change in the state of the backend, see
+/// [`super::TurboTasksBackend::subscribe_lifecycle_events`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    /// No tasks are executing anymore. The backend uses this to write snapshots, embedders can use
+    /// it for their own background work.
+    IdleStart,
+    /// Tasks are executing again after [`LifecycleEvent::IdleStart`].
+    IdleEnd,
+    /// The backend is stopping. Emitted once.
+    Stopping,
+}
+
+pub struct LifecycleEvents {
+    sender: broadcast::Sender<LifecycleEvent>,
+}
+
+impl Default for LifecycleEvents {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(LIFECYCLE_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl LifecycleEvents {
+    pub fn subscribe(&self) -> broadcast::Receiver<LifecycleEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn emit(&self, event: LifecycleEvent) {
+        // Sending only fails when there are no subscribers
+        let _ = self.sender.send(event);
+    }
+}
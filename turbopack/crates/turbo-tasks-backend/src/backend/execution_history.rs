@@ -0,0 +1,64 @@
+use std::{collections::VecDeque, hash::BuildHasherDefault, time::SystemTime};
+
+use dashmap::DashMap;
+use rustc_hash::FxHasher;
+use tokio::time::Duration;
+use turbo_tasks::TaskId;
+
+use super::TaskDirtyCause;
+
+/// How an execution of a task ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionOutcome {
+    /// The task returned a value.
+    Output,
+    /// The task returned an error.
+    Error,
+    /// The task panicked.
+    Panic,
+    /// The execution was cancelled and its result discarded.
+    Cancelled,
+    /// The task was invalidated while executing, so it's executed again right away.
+    Stale,
+}
+
+/// A completed execution of a task, see [`super::TurboTasksBackend::execution_history`].
+#[derive(Debug, Clone)]
+pub struct TaskExecution {
+    pub finished_at: SystemTime,
+    pub duration: Duration,
+    pub outcome: ExecutionOutcome,
+    /// Why the task was dirty when it was executed. `None` for the first execution.
+    pub dirty_cause: Option<TaskDirtyCause>,
+}
+
+/// The last executions of every task that has been executed in this session. Only kept in memory.
+pub struct ExecutionHistory {
+    executions_per_task: usize,
+    executions: DashMap<TaskId, VecDeque<TaskExecution>, BuildHasherDefault<FxHasher>>,
+}
+
+impl ExecutionHistory {
+    pub fn new(executions_per_task: usize) -> Self {
+        Self {
+            executions_per_task,
+            executions: DashMap::default(),
+        }
+    }
+
+    pub fn record(&self, task_id: TaskId, execution: TaskExecution) {
+        let mut executions = self.executions.entry(task_id).or_default();
+        if executions.len() >= self.executions_per_task {
+            executions.pop_front();
+        }
+        executions.push_back(execution);
+    }
+
+    /// Returns the recorded executions of the task, oldest first.
+    pub fn get(&self, task_id: TaskId) -> Vec<TaskExecution> {
+        self.executions
+            .get(&task_id)
+            .map(|executions| executions.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
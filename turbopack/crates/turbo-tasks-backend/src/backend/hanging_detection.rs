@@ -0,0 +1,342 @@
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    hash::BuildHasherDefault,
+};
+
+use dashmap::DashMap;
+use rustc_hash::FxHasher;
+use tokio::time::{Duration, Instant};
+use turbo_tasks::{CellId, TaskId, TurboTasksBackendApi};
+
+use super::{TurboTasksBackend, TurboTasksBackendInner};
+use crate::{
+    backend::{
+        operation::ExecuteContext,
+        storage::{get, TaskDataCategory},
+    },
+    backing_storage::BackingStorage,
+    data::InProgressState,
+};
+
+/// What a reader is waiting for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitKind {
+    Output,
+    Cell(CellId),
+    /// Waiting for all dirty tasks in the subgraph to settle.
+    StronglyConsistent,
+}
+
+impl Display for WaitKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitKind::Output => write!(f, "output"),
+            WaitKind::Cell(cell) => write!(f, "cell {cell}"),
+            WaitKind::StronglyConsistent => write!(f, "strongly consistent output"),
+        }
+    }
+}
+
+struct Wait {
+    task: TaskId,
+    kind: WaitKind,
+    since: Instant,
+    reported: bool,
+}
+
+/// Remembers the last read that returned a listener for every reader task. The entries are not
+/// removed when the listener resolves, so they are validated against the current state of the
+/// awaited task before being reported.
+///
+/// Waits are only recorded when enabled, since every read that has to wait would pay for it.
+pub struct WaitingTasks {
+    enabled: bool,
+    waits: DashMap<TaskId, Wait, BuildHasherDefault<FxHasher>>,
+}
+
+impl WaitingTasks {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            waits: Default::default(),
+        }
+    }
+
+    pub fn start_waiting(&self, reader: TaskId, task: TaskId, kind: WaitKind) {
+        if !self.enabled {
+            return;
+        }
+        self.waits.insert(
+            reader,
+            Wait {
+                task,
+                kind,
+                since: Instant::now(),
+                reported: false,
+            },
+        );
+    }
+
+    pub fn stop_waiting(&self, reader: TaskId) {
+        if !self.enabled {
+            return;
+        }
+        self.waits.remove(&reader);
+    }
+}
+
+/// A single task in a [`HangingChain`].
+#[derive(Debug, Clone)]
+pub struct HangingChainEntry {
+    pub task_id: TaskId,
+    pub description: String,
+    /// What the task is waiting for on the next task in the chain, and for how long.
+    pub waiting_for: Option<(WaitKind, Duration)>,
+    /// The state of the last task in the chain, which is not waiting for another task.
+    pub state: Option<String>,
+}
+
+/// A chain of tasks waiting for each other, starting with the reader that has been waiting for
+/// the longest time.
+#[derive(Debug, Clone)]
+pub struct HangingChain {
+    pub entries: Vec<HangingChainEntry>,
+    /// The last task is waiting for a task that is already part of the chain.
+    pub cycle: bool,
+}
+
+impl Display for HangingChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Task seems to hang:")?;
+        for entry in &self.entries {
+            write!(f, "  {}", entry.description)?;
+            if let Some((kind, duration)) = entry.waiting_for {
+                write!(f, " waits {duration:?} for {kind} of")?;
+            }
+            if let Some(state) = &entry.state {
+                write!(f, " ({state})")?;
+            }
+            writeln!(f)?;
+        }
+        if self.cycle {
+            writeln!(f, "  ... which closes a cycle")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single wait in a [`WaitCycleError`].
+#[derive(Debug, Clone)]
+pub struct WaitCycleEntry {
+    pub task_id: TaskId,
+    pub description: String,
+    pub waiting_for: WaitKind,
+    /// The task that is awaited. For strongly consistent reads the next entry in the cycle is a
+    /// dirty task below this task.
+    pub awaited_task_id: TaskId,
+    pub awaited_description: String,
+}
+
+/// Returned by a strongly consistent read that would never settle because the tasks are waiting
+/// for each other. The last entry waits for the first one.
+#[derive(Debug, Clone)]
+pub struct WaitCycleError {
+    pub entries: Vec<WaitCycleEntry>,
+}
+
+impl Display for WaitCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Strongly consistent read would wait for itself:")?;
+        for entry in &self.entries {
+            writeln!(
+                f,
+                "  {} waits for {} of {}",
+                entry.description, entry.waiting_for, entry.awaited_description
+            )?;
+        }
+        write!(f, "  ... which closes the cycle")
+    }
+}
+
+impl std::error::Error for WaitCycleError {}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    /// Checks if the current wait of `reader` transitively waits for `reader` again. Strongly
+    /// consistent reads wait for all dirty tasks below the read task, other reads only for the
+    /// read task. Gives up after visiting `MAX_VISITED_TASKS` tasks.
+    pub(super) fn find_wait_cycle(
+        &self,
+        reader: TaskId,
+        ctx: &mut impl ExecuteContext<'_>,
+    ) -> Option<WaitCycleError> {
+        let mut path = Vec::new();
+        let mut visited = HashSet::new();
+        visited.insert(reader);
+        if !self.find_wait_cycle_from(reader, reader, &mut path, &mut visited, ctx) {
+            return None;
+        }
+        let entries = path
+            .into_iter()
+            .map(|(task_id, waiting_for, awaited_task_id)| WaitCycleEntry {
+                task_id,
+                description: self.get_task_desc_fn(task_id)(),
+                waiting_for,
+                awaited_task_id,
+                awaited_description: self.get_task_desc_fn(awaited_task_id)(),
+            })
+            .collect();
+        Some(WaitCycleError { entries })
+    }
+
+    fn find_wait_cycle_from(
+        &self,
+        task_id: TaskId,
+        reader: TaskId,
+        path: &mut Vec<(TaskId, WaitKind, TaskId)>,
+        visited: &mut HashSet<TaskId>,
+        ctx: &mut impl ExecuteContext<'_>,
+    ) -> bool {
+        const MAX_VISITED_TASKS: usize = 1000;
+        let Some((awaited, kind)) = self
+            .waiting_tasks
+            .waits
+            .get(&task_id)
+            .map(|wait| (wait.task, wait.kind))
+        else {
+            return false;
+        };
+        if !self.is_pending(ctx, awaited, kind) {
+            return false;
+        }
+        path.push((task_id, kind, awaited));
+        let next_tasks = match kind {
+            WaitKind::StronglyConsistent => {
+                self.pending_dirty_tasks(awaited, MAX_VISITED_TASKS, ctx)
+            }
+            WaitKind::Output | WaitKind::Cell(_) => vec![awaited],
+        };
+        for next in next_tasks {
+            if next == reader {
+                return true;
+            }
+            if visited.len() < MAX_VISITED_TASKS
+                && visited.insert(next)
+                && self.find_wait_cycle_from(next, reader, path, visited, ctx)
+            {
+                return true;
+            }
+        }
+        path.pop();
+        false
+    }
+
+    /// Returns the chains of all waits that are pending for longer than `timeout` and have not
+    /// been reported before.
+    pub(super) fn detect_hanging_tasks(
+        &self,
+        timeout: Duration,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<HangingChain> {
+        let now = Instant::now();
+        let mut candidates = self
+            .waiting_tasks
+            .waits
+            .iter()
+            .filter(|wait| !wait.reported && now.duration_since(wait.since) > timeout)
+            .map(|wait| (*wait.key(), wait.since))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&(_, since)| since);
+
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut reported = HashSet::new();
+        let mut chains = Vec::new();
+        for (reader, _) in candidates {
+            if reported.contains(&reader) {
+                continue;
+            }
+            let mut entries = Vec::new();
+            let mut visited = HashSet::new();
+            let mut current = reader;
+            let cycle = loop {
+                if !visited.insert(current) {
+                    break true;
+                }
+                let description = self.get_task_desc_fn(current)();
+                let wait = self
+                    .waiting_tasks
+                    .waits
+                    .get(&current)
+                    .map(|wait| (wait.task, wait.kind, wait.since));
+                match wait {
+                    Some((task_id, kind, since)) if self.is_pending(&mut ctx, task_id, kind) => {
+                        entries.push(HangingChainEntry {
+                            task_id: current,
+                            description,
+                            waiting_for: Some((kind, now.duration_since(since))),
+                            state: None,
+                        });
+                        current = task_id;
+                    }
+                    _ => {
+                        let state = self.describe_state(&mut ctx, current);
+                        entries.push(HangingChainEntry {
+                            task_id: current,
+                            description,
+                            waiting_for: None,
+                            state: Some(state),
+                        });
+                        break false;
+                    }
+                }
+            };
+            // A single entry means the awaited task is no longer pending
+            if entries.len() < 2 {
+                continue;
+            }
+            for entry in &entries {
+                if let Some(mut wait) = self.waiting_tasks.waits.get_mut(&entry.task_id) {
+                    wait.reported = true;
+                }
+                reported.insert(entry.task_id);
+            }
+            chains.push(HangingChain { entries, cycle });
+        }
+        chains
+    }
+
+    fn is_pending(
+        &self,
+        ctx: &mut impl ExecuteContext<'_>,
+        task_id: TaskId,
+        kind: WaitKind,
+    ) -> bool {
+        let task = ctx.task(task_id, TaskDataCategory::All);
+        match kind {
+            WaitKind::Output => get!(task, InProgress).is_some(),
+            WaitKind::Cell(cell) => get!(task, InProgressCell { cell }).is_some(),
+            WaitKind::StronglyConsistent => {
+                get!(task, Dirty).map_or(false, |dirty| dirty.get(self.session_id))
+                    || get!(task, AggregatedDirtyContainerCount)
+                        .map_or(false, |count| count.get(self.session_id) > 0)
+            }
+        }
+    }
+
+    fn describe_state(&self, ctx: &mut impl ExecuteContext<'_>, task_id: TaskId) -> String {
+        let task = ctx.task(task_id, TaskDataCategory::All);
+        let dirty_tasks =
+            get!(task, AggregatedDirtyContainerCount).map_or(0, |count| count.get(self.session_id));
+        let state = match get!(task, InProgress) {
+            Some(InProgressState::Scheduled { .. }) => "scheduled, but not started",
+            Some(InProgressState::InProgress { stale: true, .. }) => "in progress, stale",
+            Some(InProgressState::InProgress { .. }) => "in progress",
+            None => "not in progress",
+        };
+        if dirty_tasks > 0 {
+            format!("{state}, {dirty_tasks} dirty tasks in the subgraph")
+        } else {
+            state.to_string()
+        }
+    }
+}
@@ -0,0 +1,443 @@
+//! Read-only views of the in-memory task graph for debugging tools.
+//!
+//! All operations are suspended while the graph is collected, so the returned graph is
+//! consistent. Tasks that are only stored in the backing storage and haven't been restored into
+//! memory are not included.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Write},
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks::{backend::CachedTaskType, CellId, TaskId, TaskIdValue, TraitTypeId};
+
+use super::{TaskDirtyCause, TaskExecution, TurboTasksBackend, TurboTasksBackendInner};
+use crate::{
+    backing_storage::BackingStorage,
+    data::{CachedDataItemKey, CachedDataItemValue, InProgressState},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Done,
+    Scheduled,
+    InProgress,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaskAggregationNumber {
+    pub base: u32,
+    pub distance: u32,
+    pub effective: u32,
+}
+
+/// The state of a single task at the time the graph was collected.
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub task_id: TaskId,
+    /// The type of persistent or transient cached tasks. `None` for root and once tasks.
+    pub task_type: Option<Arc<CachedTaskType>>,
+    pub state: TaskState,
+    /// The task is dirty in the current session and needs to be recomputed.
+    pub dirty: bool,
+    pub children: Vec<TaskId>,
+    pub output_dependencies: Vec<TaskId>,
+    pub cell_dependencies: Vec<(TaskId, CellId)>,
+    pub collectibles_dependencies: Vec<(TaskId, TraitTypeId)>,
+    pub aggregation_number: Option<TaskAggregationNumber>,
+    pub uppers: Vec<TaskId>,
+    pub followers: Vec<TaskId>,
+    /// Number of dirty tasks aggregated into this task.
+    pub aggregated_dirty_tasks: i32,
+}
+
+/// The aggregation state of a single task, see [`TurboTasksBackend::inspect_aggregation`]. Counts
+/// are reported as stored, so negative or zero counts that would be ignored otherwise are visible.
+#[derive(Debug, Clone)]
+pub struct TaskAggregationInfo {
+    pub task_id: TaskId,
+    pub aggregation_number: Option<TaskAggregationNumber>,
+    /// The task is dirty in the current session.
+    pub dirty: bool,
+    pub uppers: Vec<(TaskId, i32)>,
+    pub followers: Vec<(TaskId, i32)>,
+    /// Number of dirty tasks aggregated into this task.
+    pub aggregated_dirty_tasks: i32,
+    /// Number of dirty tasks aggregated into this task per aggregated task.
+    pub aggregated_dirty_containers: Vec<(TaskId, i32)>,
+    /// Number of aggregated collectibles per collectible type.
+    pub aggregated_collectibles: Vec<(TraitTypeId, i32)>,
+}
+
+/// A consistent view of all tasks in memory.
+#[derive(Debug, Clone, Default)]
+pub struct TaskGraph {
+    pub tasks: Vec<TaskInfo>,
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Collects the state of all tasks currently in memory.
+    pub fn introspect(&self) -> TaskGraph {
+        self.0.introspect()
+    }
+
+    /// Exports the dependency and aggregation graph of the tasks in memory.
+    pub fn export_graph(&self, filter: &GraphFilter) -> GraphDump {
+        GraphDump::new(&self.introspect(), filter)
+    }
+
+    /// Returns the last executions of the task, oldest first. Empty unless
+    /// [`BackendOptions::execution_history`][crate::BackendOptions::execution_history] is set.
+    pub fn execution_history(&self, task_id: TaskId) -> Vec<TaskExecution> {
+        self.0
+            .execution_history
+            .as_ref()
+            .map(|history| history.get(task_id))
+            .unwrap_or_default()
+    }
+
+    /// Returns the aggregation state of the task, or `None` when it's not in memory. Unlike
+    /// [`Self::introspect`], this doesn't suspend operations.
+    pub fn inspect_aggregation(&self, task_id: TaskId) -> Option<TaskAggregationInfo> {
+        self.0.inspect_aggregation(task_id)
+    }
+
+    /// Returns why the task is dirty in the current session, or `None` when it's clean or not in
+    /// memory.
+    pub fn task_dirty_cause(&self, task_id: TaskId) -> Option<TaskDirtyCause> {
+        self.0.task_dirty_cause(task_id)
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    fn task_dirty_cause(&self, task_id: TaskId) -> Option<TaskDirtyCause> {
+        let task = self.storage.access_mut(task_id);
+        let Some(CachedDataItemValue::Dirty { value: dirty }) =
+            task.get(&CachedDataItemKey::Dirty {})
+        else {
+            return None;
+        };
+        if !dirty.get(self.session_id) {
+            return None;
+        }
+        if let Some(CachedDataItemValue::DirtyCause { value }) =
+            task.get(&CachedDataItemKey::DirtyCause {})
+        {
+            return Some(value.clone());
+        }
+        // The cause is only kept in memory, so tasks that were already dirty when restored from
+        // the backing storage have none.
+        Some(if dirty.clean_in_session.is_some() {
+            TaskDirtyCause::SessionStart
+        } else {
+            TaskDirtyCause::Unknown
+        })
+    }
+
+    fn inspect_aggregation(&self, task_id: TaskId) -> Option<TaskAggregationInfo> {
+        let task = self.storage.access(&task_id)?;
+        let mut info = TaskAggregationInfo {
+            task_id,
+            aggregation_number: None,
+            dirty: false,
+            uppers: Vec::new(),
+            followers: Vec::new(),
+            aggregated_dirty_tasks: 0,
+            aggregated_dirty_containers: Vec::new(),
+            aggregated_collectibles: Vec::new(),
+        };
+        let mut aggregated_collectibles = HashMap::new();
+        for (key, value) in task.iter_all() {
+            match (key, value) {
+                (CachedDataItemKey::Upper { task }, CachedDataItemValue::Upper { value }) => {
+                    info.uppers.push((*task, *value))
+                }
+                (CachedDataItemKey::Follower { task }, CachedDataItemValue::Follower { value }) => {
+                    info.followers.push((*task, *value))
+                }
+                (
+                    CachedDataItemKey::AggregatedDirtyContainer { task },
+                    CachedDataItemValue::AggregatedDirtyContainer { value },
+                ) => info
+                    .aggregated_dirty_containers
+                    .push((*task, value.get(self.session_id))),
+                (
+                    CachedDataItemKey::AggregatedCollectible { collectible },
+                    CachedDataItemValue::AggregatedCollectible { value },
+                ) => {
+                    *aggregated_collectibles
+                        .entry(collectible.collectible_type)
+                        .or_default() += *value
+                }
+                (_, CachedDataItemValue::Dirty { value }) => {
+                    info.dirty = value.get(self.session_id)
+                }
+                (_, CachedDataItemValue::AggregationNumber { value }) => {
+                    info.aggregation_number = Some(TaskAggregationNumber {
+                        base: value.base,
+                        distance: value.distance,
+                        effective: value.effective,
+                    })
+                }
+                (_, CachedDataItemValue::AggregatedDirtyContainerCount { value }) => {
+                    info.aggregated_dirty_tasks = value.get(self.session_id)
+                }
+                _ => {}
+            }
+        }
+        drop(task);
+        info.uppers.sort_unstable();
+        info.followers.sort_unstable();
+        info.aggregated_dirty_containers.sort_unstable();
+        info.aggregated_collectibles = aggregated_collectibles.into_iter().collect();
+        info.aggregated_collectibles.sort_unstable();
+        Some(info)
+    }
+
+    fn introspect(&self) -> TaskGraph {
+        let snapshot_lock = self.snapshot_lock.lock();
+        self.suspend_operations();
+        let mut tasks = Vec::new();
+        self.storage.for_each(|&task_id, task| {
+            let mut info = TaskInfo {
+                task_id,
+                task_type: None,
+                state: TaskState::Done,
+                dirty: false,
+                children: Vec::new(),
+                output_dependencies: Vec::new(),
+                cell_dependencies: Vec::new(),
+                collectibles_dependencies: Vec::new(),
+                aggregation_number: None,
+                uppers: Vec::new(),
+                followers: Vec::new(),
+                aggregated_dirty_tasks: 0,
+            };
+            for (key, value) in task.iter_all() {
+                match (key, value) {
+                    (CachedDataItemKey::Child { task }, _) => info.children.push(*task),
+                    (CachedDataItemKey::OutputDependency { target }, _) => {
+                        info.output_dependencies.push(*target)
+                    }
+                    (CachedDataItemKey::CellDependency { target }, _) => {
+                        info.cell_dependencies.push((target.task, target.cell))
+                    }
+                    (CachedDataItemKey::CollectiblesDependency { target }, _) => info
+                        .collectibles_dependencies
+                        .push((target.task, target.collectible_type)),
+                    (CachedDataItemKey::Upper { task }, CachedDataItemValue::Upper { value })
+                        if *value > 0 =>
+                    {
+                        info.uppers.push(*task)
+                    }
+                    (
+                        CachedDataItemKey::Follower { task },
+                        CachedDataItemValue::Follower { value },
+                    ) if *value > 0 => info.followers.push(*task),
+                    (_, CachedDataItemValue::Dirty { value }) => {
+                        info.dirty = value.get(self.session_id)
+                    }
+                    (_, CachedDataItemValue::AggregationNumber { value }) => {
+                        info.aggregation_number = Some(TaskAggregationNumber {
+                            base: value.base,
+                            distance: value.distance,
+                            effective: value.effective,
+                        })
+                    }
+                    (_, CachedDataItemValue::AggregatedDirtyContainerCount { value }) => {
+                        info.aggregated_dirty_tasks = value.get(self.session_id)
+                    }
+                    (_, CachedDataItemValue::InProgress { value }) => {
+                        info.state = match value {
+                            InProgressState::Scheduled { .. } => TaskState::Scheduled,
+                            InProgressState::InProgress { .. } => TaskState::InProgress,
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            tasks.push(info);
+        });
+        self.resume_operations();
+        drop(snapshot_lock);
+
+        // Resolving the task types may read from the backing storage, so it's done after the
+        // operations have been resumed.
+        for info in tasks.iter_mut() {
+            info.task_type = self.lookup_task_type(info.task_id);
+        }
+        tasks.sort_by_key(|info| info.task_id);
+        TaskGraph { tasks }
+    }
+}
+
+/// Selects the tasks included in a [`GraphDump`].
+#[derive(Debug, Clone, Default)]
+pub struct GraphFilter {
+    /// Only include tasks whose function name contains this string.
+    pub function_name: Option<String>,
+    /// Also include the direct neighbors of the matched tasks.
+    pub include_neighbors: bool,
+}
+
+impl GraphFilter {
+    fn matches(&self, task: &TaskInfo) -> bool {
+        let Some(function_name) = &self.function_name else {
+            return true;
+        };
+        task.task_type
+            .as_ref()
+            .is_some_and(|task_type| task_type.get_name().contains(function_name.as_str()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphEdgeKind {
+    Child,
+    OutputDependency,
+    CellDependency,
+    CollectiblesDependency,
+    /// The source is aggregated into the target.
+    Upper,
+}
+
+impl GraphEdgeKind {
+    fn dot_style(&self) -> &'static str {
+        match self {
+            GraphEdgeKind::Child => "solid",
+            GraphEdgeKind::OutputDependency
+            | GraphEdgeKind::CellDependency
+            | GraphEdgeKind::CollectiblesDependency => "dashed",
+            GraphEdgeKind::Upper => "dotted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphNode {
+    pub id: TaskIdValue,
+    pub name: String,
+    pub dirty: bool,
+    pub aggregation_number: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub from: TaskIdValue,
+    pub to: TaskIdValue,
+    pub kind: GraphEdgeKind,
+}
+
+/// A serializable task graph, created by [`TurboTasksBackend::export_graph`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GraphDump {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl GraphDump {
+    pub fn new(graph: &TaskGraph, filter: &GraphFilter) -> Self {
+        let mut included: HashSet<TaskId> = graph
+            .tasks
+            .iter()
+            .filter(|task| filter.matches(task))
+            .map(|task| task.task_id)
+            .collect();
+        if filter.include_neighbors {
+            let mut neighbors = Vec::new();
+            for task in graph.tasks.iter() {
+                for (to, _) in edges(task) {
+                    if included.contains(&task.task_id) {
+                        neighbors.push(to);
+                    } else if included.contains(&to) {
+                        neighbors.push(task.task_id);
+                    }
+                }
+            }
+            included.extend(neighbors);
+        }
+
+        let mut dump = GraphDump::default();
+        for task in graph.tasks.iter() {
+            if !included.contains(&task.task_id) {
+                continue;
+            }
+            dump.nodes.push(GraphNode {
+                id: *task.task_id,
+                name: task.task_type.as_ref().map_or_else(
+                    || format!("{} (transient)", task.task_id),
+                    |task_type| task_type.get_name().into_owned(),
+                ),
+                dirty: task.dirty,
+                aggregation_number: task.aggregation_number.map(|number| number.effective),
+            });
+            for (to, kind) in edges(task) {
+                if included.contains(&to) {
+                    dump.edges.push(GraphEdge {
+                        from: *task.task_id,
+                        to: *to,
+                        kind,
+                    });
+                }
+            }
+        }
+        dump
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        self.write_dot(&mut dot).unwrap();
+        dot
+    }
+
+    fn write_dot(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "digraph tasks {{")?;
+        for node in self.nodes.iter() {
+            write!(out, "  t{} [label={:?}", node.id, node.name)?;
+            if node.dirty {
+                write!(out, ", color=red")?;
+            }
+            writeln!(out, "];")?;
+        }
+        for edge in self.edges.iter() {
+            writeln!(
+                out,
+                "  t{} -> t{} [style={}];",
+                edge.from,
+                edge.to,
+                edge.kind.dot_style()
+            )?;
+        }
+        writeln!(out, "}}")
+    }
+}
+
+fn edges(task: &TaskInfo) -> impl Iterator<Item = (TaskId, GraphEdgeKind)> + '_ {
+    task.children
+        .iter()
+        .map(|&task| (task, GraphEdgeKind::Child))
+        .chain(
+            task.output_dependencies
+                .iter()
+                .map(|&task| (task, GraphEdgeKind::OutputDependency)),
+        )
+        .chain(
+            task.cell_dependencies
+                .iter()
+                .map(|&(task, _)| (task, GraphEdgeKind::CellDependency)),
+        )
+        .chain(
+            task.collectibles_dependencies
+                .iter()
+                .map(|&(task, _)| (task, GraphEdgeKind::CollectiblesDependency)),
+        )
+        .chain(task.uppers.iter().map(|&task| (task, GraphEdgeKind::Upper)))
+}
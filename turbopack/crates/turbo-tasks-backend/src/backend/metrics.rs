@@ -0,0 +1,53 @@
+use serde::Serialize;
+
+use super::TurboTasksBackend;
+use crate::{
+    backing_storage::BackingStorage,
+    data::{CachedDataItemKey, CachedDataItemValue, InProgressState},
+};
+
+/// Counts of the tasks in memory, see [`TurboTasksBackend::task_metrics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TaskMetrics {
+    pub persistent_tasks: usize,
+    pub transient_tasks: usize,
+    /// Tasks that are currently executing.
+    pub in_progress_tasks: usize,
+    /// Tasks that are waiting to be executed.
+    pub scheduled_tasks: usize,
+    /// Cells of all tasks, including the cells that have been removed from memory after they
+    /// were persisted.
+    pub cells: usize,
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Counts the tasks in memory and their cells. The tasks are visited one after another
+    /// without suspending the operations, so the counts are only approximate while tasks are
+    /// executing. Tasks that are only stored in the backing storage are not included.
+    pub fn task_metrics(&self) -> TaskMetrics {
+        let mut metrics = TaskMetrics::default();
+        self.0.storage.for_each(|task_id, task| {
+            if task_id.is_transient() {
+                metrics.transient_tasks += 1;
+            } else {
+                metrics.persistent_tasks += 1;
+            }
+            for (key, value) in task.iter_all() {
+                match (key, value) {
+                    (
+                        CachedDataItemKey::CellData { .. }
+                        | CachedDataItemKey::SerializedCellData { .. }
+                        | CachedDataItemKey::SpilledCell { .. },
+                        _,
+                    ) => metrics.cells += 1,
+                    (_, CachedDataItemValue::InProgress { value }) => match value {
+                        InProgressState::Scheduled { .. } => metrics.scheduled_tasks += 1,
+                        InProgressState::InProgress { .. } => metrics.in_progress_tasks += 1,
+                    },
+                    _ => {}
+                }
+            }
+        });
+        metrics
+    }
+}
@@ -1,67 +1,147 @@
+mod cache_profiling;
+mod consistency;
+mod dependency_diff;
+mod events;
+mod execution_history;
+mod hanging_detection;
 pub mod indexed;
+pub mod introspection;
+mod metrics;
 mod operation;
+mod operation_log;
+mod operation_statistics;
+mod progress;
+mod read_view;
+pub mod replay;
+mod scheduler;
+mod snapshot_policy;
 mod storage;
+mod task_cache_limit;
+mod task_descriptions;
+mod turn_transaction;
 
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    cell::Cell,
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
     future::Future,
     hash::BuildHasherDefault,
     mem::take,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering},
         Arc,
     },
+    task::{Context, Poll},
     thread::available_parallelism,
+    time::SystemTime,
 };
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use auto_hash_map::{AutoMap, AutoSet};
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use parking_lot::{Condvar, Mutex};
-use rustc_hash::FxHasher;
+use rustc_hash::{FxHashSet, FxHasher};
 use smallvec::smallvec;
-use tokio::time::{Duration, Instant};
+use tokio::{
+    sync::broadcast,
+    time::{Duration, Instant},
+};
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CachedTaskType, CellContent, TaskExecutionSpec, TransientTaskRoot,
-        TransientTaskType, TypedCellContent,
+        Backend, BackendJobId, CachedTaskType, CellContent, EmittedCollectible, TaskExecutionSpec,
+        TaskExecutionStatistics, TransientTaskRoot, TransientTaskType, TypedCellContent,
     },
     event::{Event, EventListener},
     registry,
-    util::IdFactoryWithReuse,
-    CellId, FunctionId, RawVc, ReadConsistency, SessionId, TaskId, TraitTypeId,
-    TurboTasksBackendApi, ValueTypeId, TRANSIENT_TASK_BIT,
+    util::{IdFactoryWithReuse, StaticOrArc},
+    CellId, FunctionId, InvalidationReason, RawVc, ReadConsistency, SessionId, TaskId,
+    TaskPriority, TraitTypeId, TurboTasksBackendApi, TypedSharedReference, ValueTypeId,
+    MAX_TRANSIENT_TASK_ID, TRANSIENT_TASK_BIT, TRANSIENT_TASK_GENERATION_BITS,
 };
 
-pub use self::{operation::AnyOperation, storage::TaskDataCategory};
+pub use self::{
+    cache_profiling::{CacheEffectiveness, RecomputeReason},
+    dependency_diff::{DependencyDiff, TaskDependency},
+    events::{LifecycleEvent, TaskEvent},
+    execution_history::{ExecutionOutcome, TaskExecution},
+    hanging_detection::{
+        HangingChain, HangingChainEntry, WaitCycleEntry, WaitCycleError, WaitKind,
+    },
+    metrics::TaskMetrics,
+    operation::{AnyOperation, TaskDirtyCause},
+    operation_statistics::{DurationHistogram, OperationStatistics},
+    progress::TaskProgress,
+    read_view::ReadView,
+    scheduler::FairSchedulingOptions,
+    snapshot_policy::{
+        DefaultSnapshotPolicy, IdleSnapshotPolicy, ShutdownSnapshotPolicy, SnapshotDecision,
+        SnapshotPolicy, SnapshotPolicyState,
+    },
+    storage::{HeapSize, MemoryCategorized, MemoryCategory, TaskDataCategory, TaskMemoryUsage},
+    task_cache_limit::TaskCacheStatistics,
+};
 use crate::{
     backend::{
+        cache_profiling::CacheProfiler,
+        dependency_diff::DependencyTracker,
+        events::{LifecycleEvents, TaskEvents},
+        execution_history::ExecutionHistory,
+        hanging_detection::WaitingTasks,
         operation::{
-            get_aggregation_number, is_root_node, AggregatedDataUpdate, AggregationUpdateJob,
-            AggregationUpdateQueue, CleanupOldEdgesOperation, ConnectChildOperation,
-            ExecuteContext, ExecuteContextImpl, Operation, OutdatedEdge, TaskDirtyCause, TaskGuard,
+            get_aggregation_number, is_aggregating_node, is_root_node, AggregatedDataUpdate,
+            AggregationUpdateJob, AggregationUpdateQueue, CleanupOldEdgesOperation,
+            ConnectChildOperation, ExecuteContext, ExecuteContextImpl, Operation, OutdatedEdge,
+            TaskGuard, LEAF_NUMBER,
         },
-        storage::{get, get_many, get_mut, iter_many, remove, Storage},
+        operation_log::OperationRecorder,
+        operation_statistics::OperationStatisticsCollector,
+        scheduler::FairScheduler,
+        storage::{get, get_many, get_mut, iter_many, remove, InnerStorage, Storage},
+        task_cache_limit::TaskCacheLimit,
+        task_descriptions::TaskDescriptions,
+        turn_transaction::WithTurnTransaction,
     },
-    backing_storage::BackingStorage,
+    backing_storage::{BackingStorage, SavedSnapshot, SessionInfo},
     data::{
         ActiveType, AggregationNumber, CachedDataItem, CachedDataItemIndex, CachedDataItemKey,
         CachedDataItemValue, CachedDataUpdate, CellRef, CollectibleRef, CollectiblesRef,
-        DirtyState, InProgressCellState, InProgressState, OutputValue, RootState,
+        DirtyContainerCount, DirtyState, InProgressCellState, InProgressState, OutputValue,
+        RootState, TaskUsage,
     },
     utils::{bi_map::BiMap, chunked_vec::ChunkedVec, ptr_eq_arc::PtrEqArc, sharded::Sharded},
 };
 
 const BACKEND_JOB_INITIAL_SNAPSHOT: BackendJobId = unsafe { BackendJobId::new_unchecked(1) };
 const BACKEND_JOB_FOLLOW_UP_SNAPSHOT: BackendJobId = unsafe { BackendJobId::new_unchecked(2) };
+const BACKEND_JOB_HANGING_DETECTION: BackendJobId = unsafe { BackendJobId::new_unchecked(3) };
+const BACKEND_JOB_INVALIDATION_BATCH: BackendJobId = unsafe { BackendJobId::new_unchecked(4) };
 
 const SNAPSHOT_REQUESTED_BIT: usize = 1 << (usize::BITS - 1);
 
+thread_local! {
+    /// The log epoch of the operation that is running on this thread, see
+    /// [`TurboTasksBackendInner::push_persisted_log`].
+    static OPERATION_LOG_EPOCH: Cell<Option<usize>> = const { Cell::new(None) };
+    /// Whether the operation running on this thread has been started from within another
+    /// operation.
+    static NESTED_OPERATION: Cell<bool> = const { Cell::new(false) };
+}
+
 struct SnapshotRequest {
     snapshot_requested: bool,
     suspended_operations: HashSet<PtrEqArc<AnyOperation>>,
+    /// When all operations were completed or suspended for the current request.
+    suspended_since: Option<Instant>,
+    /// Operations of a frozen log epoch that have reached a suspend point. They are persisted as
+    /// uncompleted operations together with the frozen logs.
+    frozen_operations: Vec<Arc<AnyOperation>>,
+    /// Set while [`TurboTasksBackendInner::freeze_logs`] waits for the operations of the frozen
+    /// epoch. Operations of the next epoch wait until it's cleared.
+    freezing_logs: bool,
 }
 
 impl SnapshotRequest {
@@ -69,6 +149,36 @@ impl SnapshotRequest {
         Self {
             snapshot_requested: false,
             suspended_operations: HashSet::new(),
+            suspended_since: None,
+            frozen_operations: Vec::new(),
+            freezing_logs: false,
+        }
+    }
+}
+
+/// Changes of persistent tasks that are written to the backing storage with the next snapshot.
+struct PersistedLogs {
+    task_cache: Sharded<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+    task_cache_removals: Sharded<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+    storage_data: Sharded<ChunkedVec<CachedDataUpdate>>,
+    storage_meta: Sharded<ChunkedVec<CachedDataUpdate>>,
+}
+
+impl PersistedLogs {
+    fn new(shard_amount: usize) -> Self {
+        Self {
+            task_cache: Sharded::new(shard_amount),
+            task_cache_removals: Sharded::new(shard_amount),
+            storage_data: Sharded::new(shard_amount),
+            storage_meta: Sharded::new(shard_amount),
+        }
+    }
+
+    fn storage(&self, category: TaskDataCategory) -> &Sharded<ChunkedVec<CachedDataUpdate>> {
+        match category {
+            TaskDataCategory::Data => &self.storage_data,
+            TaskDataCategory::Meta => &self.storage_meta,
+            TaskDataCategory::All => unreachable!(),
         }
     }
 }
@@ -84,97 +194,629 @@ pub enum TransientTask {
     /// Always active. Automatically scheduled.
     Root(TransientTaskRoot),
 
-    // TODO implement these strongly consistency
     /// A single root task execution. It won't track dependencies.
     /// Task will definitely include all invalidations that happened before the
     /// start of the task. It may or may not include invalidations that
     /// happened after that. It may see these invalidations partially
-    /// applied.
+    /// applied, unless [`BackendOptions::strongly_consistent_once_tasks`] is
+    /// enabled.
     ///
     /// Active until done. Automatically scheduled.
     Once(TransientTaskOnce),
 }
 
+/// Counters for unloading tasks from memory. A high number of reloaded tasks means that the
+/// memory limit is too low for the working set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStatistics {
+    /// Number of tasks unloaded from memory.
+    pub unloaded_tasks: usize,
+    /// Number of times task data was restored from the backing storage.
+    pub restored_tasks: usize,
+    /// Number of restores of tasks that had been unloaded before.
+    pub reloaded_tasks: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackendOptions {
+    /// After this many consecutive failures to save a snapshot, persistence is disabled for the
+    /// rest of the session and further changes are no longer tracked for persisting.
+    pub max_consecutive_snapshot_failures: usize,
+    /// When the approximate memory used by task data (see
+    /// [`TurboTasksBackend::memory_usage`]) exceeds this limit, tasks that are persisted and
+    /// haven't been used for a while are unloaded from memory, least recently used first. They
+    /// are restored from the backing storage when accessed again, so this must only be used with
+    /// a backing storage that actually persists data.
+    pub memory_limit: Option<usize>,
+    /// When enabled, all task outputs read by a Once task are read with
+    /// [`ReadConsistency::Strong`]. The Once task then waits for all pending invalidations below
+    /// the read tasks to settle and never sees them partially applied.
+    pub strongly_consistent_once_tasks: bool,
+    /// When set, strongly consistent reads that are still waiting for dirty tasks after this
+    /// timeout fail with an error listing the dirty tasks below the read task.
+    pub strongly_consistent_read_timeout: Option<Duration>,
+    /// When set, a background job reports tasks that wait for another task longer than this
+    /// timeout, together with the chain of tasks they are waiting for, as [`TaskEvent::Hanging`]
+    /// and as a warning. Waits are only tracked when this or [`Self::detect_wait_cycles`] is set.
+    pub hanging_detection_timeout: Option<Duration>,
+    /// When enabled, strongly consistent reads that would wait for the reading task itself fail
+    /// with a [`WaitCycleError`] instead of never settling. This tracks all waits between tasks.
+    pub detect_wait_cycles: bool,
+    /// Decides when the background job persists snapshots.
+    pub snapshot_policy: Arc<dyn SnapshotPolicy>,
+    /// When the number of changes waiting to be persisted exceeds this limit, a snapshot is taken
+    /// right away, independent of the snapshot policy. This bounds the memory used by the
+    /// pending changes during large builds.
+    pub max_pending_log_items: Option<usize>,
+    /// When set, tasks that panicked are retried according to this policy instead of keeping the
+    /// panic as output until they are invalidated.
+    pub panic_retry_policy: Option<PanicRetryPolicy>,
+    /// When set, tasks that execute longer than the configured timeout are reported.
+    pub execution_timeout: Option<ExecutionTimeout>,
+    /// Restores tasks from the backing storage, but never writes to it. Changes are not logged
+    /// and no snapshots are taken, so the cache can be consumed from a read-only location.
+    pub read_only: bool,
+    /// Records per function how often tasks were served from the cache and why they were
+    /// recomputed. A summary is printed when the backend is stopping.
+    pub cache_profiling: bool,
+    /// Records which dependencies of a task changed, were added or were removed between its
+    /// executions. Available via [`TurboTasksBackend::dependency_diff`].
+    pub dependency_tracking: bool,
+    /// When set, cells whose serialized data is larger than this number of bytes are removed from
+    /// memory after they have been persisted. They are read from the backing storage again when
+    /// accessed.
+    pub large_cell_threshold: Option<usize>,
+    /// When enabled, updating a cell with content that serializes to the same data as before
+    /// doesn't invalidate the tasks that read the cell. This costs serializing both values on
+    /// every cell update.
+    pub cell_content_cutoff: bool,
+    /// When set, single task invalidations are collected for this duration and applied together.
+    /// This reduces the aggregation work for bursts of invalidations, e.g. from the file watcher
+    /// during a `git checkout`. Strongly consistent reads apply the pending invalidations first,
+    /// so they never see an outdated output.
+    pub invalidation_batch_window: Option<Duration>,
+    /// When set, every operation transition and aggregation update job is appended as JSON line
+    /// to this file. Meant for debugging the operations, as it slows down the backend a lot.
+    pub operation_log: Option<PathBuf>,
+    /// When set, the last this many executions of every task are kept in memory with their
+    /// duration, outcome and dirty cause. Available via [`TurboTasksBackend::execution_history`].
+    pub execution_history: Option<usize>,
+    /// When set, dirty tasks that are recomputed without being read only get a weighted share of
+    /// the task executions while tasks that are read are executing. This keeps the revalidation
+    /// of restored dirty tasks after a warm start from delaying the first request.
+    pub fair_scheduling: Option<FairSchedulingOptions>,
+    /// When set, the in-memory cache that maps task types to task ids is bounded to about this
+    /// many entries. Entries that are in the backing storage are evicted first in first out and
+    /// fetched again when needed. This must only be used with a backing storage that actually
+    /// persists data.
+    pub task_cache_limit: Option<usize>,
+    /// Whether the aggregation structure of the task graph is written to the backing storage.
+    pub aggregation_persistence: AggregationPersistence,
+    /// When set, tasks whose serialized data exceeds this number of bytes when they are written
+    /// with a snapshot are reported with a warning and a [`TaskEvent::OversizedTask`]. The data
+    /// of a task is serialized as a whole whenever any part of it changes, so large outputs or
+    /// cells are costly when the task is recomputed often.
+    pub oversized_task_threshold: Option<usize>,
+    /// When set, aggregation updates that update more upper tasks at once are reported with a
+    /// warning and a [`TaskEvent::AggregationFanOut`]. This usually means that a single change
+    /// affects a large part of the task graph.
+    pub aggregation_fan_out_threshold: Option<usize>,
+    /// When set, aggregation updates that queue more jobs are reported like for
+    /// [`Self::aggregation_fan_out_threshold`].
+    pub aggregation_queue_threshold: Option<usize>,
+}
+
+impl Default for BackendOptions {
+    fn default() -> Self {
+        Self {
+            max_consecutive_snapshot_failures: 3,
+            memory_limit: None,
+            strongly_consistent_once_tasks: false,
+            strongly_consistent_read_timeout: None,
+            hanging_detection_timeout: None,
+            detect_wait_cycles: false,
+            snapshot_policy: Arc::new(DefaultSnapshotPolicy::default()),
+            max_pending_log_items: Some(1_000_000),
+            panic_retry_policy: None,
+            execution_timeout: None,
+            read_only: false,
+            cache_profiling: false,
+            dependency_tracking: false,
+            cell_content_cutoff: false,
+            invalidation_batch_window: None,
+            large_cell_threshold: None,
+            operation_log: None,
+            execution_history: None,
+            fair_scheduling: None,
+            task_cache_limit: None,
+            aggregation_persistence: AggregationPersistence::default(),
+            oversized_task_threshold: None,
+            aggregation_fan_out_threshold: None,
+            aggregation_queue_threshold: None,
+        }
+    }
+}
+
+/// Whether the aggregation structure of the task graph, i.e. the aggregation numbers, the
+/// `Upper` and `Follower` edges and the aggregated dirty containers and collectibles, is written
+/// to the backing storage. It usually makes up most of the task metadata in the database.
+///
+/// Switching from a mode that doesn't persist it back to [`AggregationPersistence::Persisted`]
+/// requires a fresh database, as the structure that was persisted before is outdated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AggregationPersistence {
+    /// The aggregation structure is restored together with the tasks.
+    #[default]
+    Persisted,
+    /// The aggregation structure is not persisted. Restored tasks start as leaf nodes without
+    /// uppers, which is a valid structure on its own. It's rebuilt from the children of the
+    /// tasks when they get an upper again, i.e. when they are connected to a task of this session
+    /// or become the root of a strongly consistent read. This makes the database much smaller,
+    /// at the cost of restoring and connecting every reachable task after a warm start.
+    Rebuilt,
+    /// [`AggregationPersistence::Rebuilt`] when the backing storage contains more than this many
+    /// tasks when the backend is created, otherwise [`AggregationPersistence::Persisted`].
+    RebuiltAbove(usize),
+}
+
+/// Retries tasks that panicked, e.g. because of a flaky file system or the process running out
+/// of memory.
+#[derive(Debug, Clone)]
+pub struct PanicRetryPolicy {
+    /// How often a task is retried before the panic is kept as output. The count is reset when
+    /// the task completes without panicking.
+    pub max_retries: u32,
+    /// The delay before the first retry. It's doubled for every following retry.
+    pub backoff: Duration,
+}
+
+/// Limits how long a single execution of a task may take. Only the time spent polling the task
+/// counts, waiting for other tasks doesn't. Exceeding the limit emits
+/// [`TaskEvent::ExecutionTimeout`] and a warning with the task description.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionTimeout {
+    /// The timeout for all persistent tasks.
+    pub timeout: Option<Duration>,
+    /// Timeouts for specific functions by their name, overriding `timeout`.
+    pub per_function: HashMap<String, Duration>,
+    /// Fails the task with an error when the timeout is exceeded instead of letting it continue.
+    pub strict: bool,
+}
+
+impl ExecutionTimeout {
+    fn for_task(&self, task_type: &CachedTaskType) -> Option<Duration> {
+        if let CachedTaskType::Native { fn_type, .. } = task_type {
+            let name = &registry::get_function(*fn_type).name;
+            if let Some(timeout) = self.per_function.get(name) {
+                return Some(*timeout);
+            }
+        }
+        self.timeout
+    }
+}
+
+/// Applies the [`ExecutionTimeout`] to the time spent polling the execution of a task.
+struct WithExecutionTimeout<'a, D: Fn() -> String + Unpin> {
+    future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'a>>,
+    task_id: TaskId,
+    timeout: Duration,
+    strict: bool,
+    description: D,
+    task_events: &'a TaskEvents,
+    polling_time: Duration,
+    reported: bool,
+}
+
+impl<D: Fn() -> String + Unpin> Future for WithExecutionTimeout<'_, D> {
+    type Output = Result<RawVc>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let start = Instant::now();
+        let result = this.future.as_mut().poll(cx);
+        this.polling_time += start.elapsed();
+        if this.reported || this.polling_time <= this.timeout {
+            return result;
+        }
+        this.reported = true;
+        let description = (this.description)();
+        tracing::warn!(
+            task = %description,
+            timeout = ?this.timeout,
+            polling_time = ?this.polling_time,
+            strict = this.strict,
+            "task execution exceeded the timeout"
+        );
+        this.task_events.emit(|| TaskEvent::ExecutionTimeout {
+            task_id: this.task_id,
+            description: description.clone(),
+            polling_time: this.polling_time,
+            strict: this.strict,
+        });
+        if this.strict {
+            return Poll::Ready(Err(anyhow!(
+                "Execution of {description} exceeded the timeout of {:?}",
+                this.timeout
+            )));
+        }
+        result
+    }
+}
+
+impl PanicRetryPolicy {
+    fn backoff(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(1 << retry.min(16))
+    }
+}
+
 pub struct TurboTasksBackend<B: BackingStorage>(Arc<TurboTasksBackendInner<B>>);
 
 struct TurboTasksBackendInner<B: BackingStorage> {
+    options: BackendOptions,
+
     start_time: Instant,
     session_id: SessionId,
 
     persisted_task_id_factory: IdFactoryWithReuse<TaskId>,
     transient_task_id_factory: IdFactoryWithReuse<TaskId>,
 
-    persisted_task_cache_log: Sharded<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
     task_cache: BiMap<Arc<CachedTaskType>, TaskId>,
+    task_cache_limit: TaskCacheLimit,
+    /// Formatted descriptions of tasks, used for listener notes and hanging detection.
+    task_descriptions: Arc<TaskDescriptions>,
+    /// Resolved from [`BackendOptions::aggregation_persistence`] when the backend is created.
+    persist_aggregation: bool,
     transient_tasks: DashMap<TaskId, Arc<TransientTask>, BuildHasherDefault<FxHasher>>,
 
-    persisted_storage_data_log: Sharded<ChunkedVec<CachedDataUpdate>>,
-    persisted_storage_meta_log: Sharded<ChunkedVec<CachedDataUpdate>>,
+    /// The persisted logs are double buffered. A snapshot freezes the logs of the current epoch
+    /// and waits for the operations of that epoch. While the snapshot is written, later operations
+    /// already write into the logs of the next epoch.
+    persisted_logs: [PersistedLogs; 2],
+    /// The current log epoch, its logs are `persisted_logs[log_epoch & 1]`.
+    log_epoch: AtomicUsize,
+    /// Set while the operations of a frozen epoch are completing, see [`Self::freeze_logs`].
+    freezing_logs: AtomicBool,
+    /// Number of running operations per log epoch parity.
+    epoch_operations: [AtomicUsize; 2],
+    /// Number of items in the persisted logs.
+    pending_log_items: AtomicUsize,
+    /// Triggered when `pending_log_items` exceeds [`BackendOptions::max_pending_log_items`].
+    log_pressure_event: Event,
     storage: Storage<TaskId, CachedDataItem>,
 
-    /// Number of executing operations + Highest bit is set when snapshot is
-    /// requested. When that bit is set, operations should pause until the
-    /// snapshot is completed. When the bit is set and in progress counter
-    /// reaches zero, `operations_completed_when_snapshot_requested` is
-    /// triggered.
+    /// Number of executing operations + Highest bit is set when all operations are requested to
+    /// suspend, e.g. for an introspection. When that bit is set, operations should pause until
+    /// they are resumed. When the bit is set and in progress counter reaches zero,
+    /// `operations_suspended` is triggered.
     in_progress_operations: AtomicUsize,
 
     snapshot_request: Mutex<SnapshotRequest>,
+    /// Held while operations are suspended for a snapshot or an introspection, so these don't
+    /// run concurrently.
+    snapshot_lock: Mutex<()>,
+    /// How operations are held up by snapshots, see [`TurboTasksBackend::operation_statistics`].
+    operation_statistics: OperationStatisticsCollector,
     /// Condition Variable that is triggered when `in_progress_operations`
     /// reaches zero while snapshot is requested. All operations are either
-    /// completed or suspended.
+    /// completed or suspended. Also triggered when the last operation of a frozen log epoch
+    /// completes or suspends.
     operations_suspended: Condvar,
     /// Condition Variable that is triggered when a snapshot is completed and
     /// operations can continue.
     snapshot_completed: Condvar,
     /// The timestamp of the last started snapshot since [`Self::start_time`].
     last_snapshot: AtomicU64,
+    /// Number of snapshots that failed to save in a row.
+    consecutive_snapshot_failures: AtomicUsize,
+    /// Set when persisting failed too often. Snapshots will only discard the logs afterwards.
+    persistence_disabled: AtomicBool,
+    /// The storage generation up to which all changes have been persisted successfully.
+    persisted_generation: AtomicU32,
+    /// Number of snapshots that were saved successfully. Increased before persisted data is
+    /// removed from memory.
+    committed_snapshots: AtomicU32,
+    /// Set when any snapshot failed. Changes from that snapshot are lost, so tasks can no longer
+    /// be restored safely from the backing storage and garbage collection is disabled.
+    gc_disabled: AtomicBool,
 
     stopping: AtomicBool,
     stopping_event: Event,
+    /// Set by [`TurboTasksBackend::shutdown`]. Creating root tasks afterwards panics.
+    shut_down: AtomicBool,
     idle_start_event: Event,
     idle_end_event: Event,
 
-    backing_storage: B,
+    /// Tasks that have been unloaded and not restored since.
+    unloaded_tasks: DashSet<TaskId, BuildHasherDefault<FxHasher>>,
+    unloaded_tasks_count: AtomicUsize,
+    restored_tasks_count: AtomicUsize,
+    reloaded_tasks_count: AtomicUsize,
+    /// Tasks that are never unloaded, with the number of times they have been pinned.
+    pinned_tasks: DashMap<TaskId, usize, BuildHasherDefault<FxHasher>>,
+
+    /// Tasks whose current execution reads without keeping the read tasks in memory, see
+    /// [`turbo_tasks::mark_weak_reader`].
+    weak_readers: DashSet<TaskId, BuildHasherDefault<FxHasher>>,
+    /// Persistent tasks that depend on transient tasks, see
+    /// [`turbo_tasks::mark_non_persistable`]. They are treated like transient tasks for the
+    /// rest of the session.
+    non_persistable_tasks: DashSet<TaskId, BuildHasherDefault<FxHasher>>,
+    has_non_persistable_tasks: AtomicBool,
+    /// Tasks that became non-persistable in previous sessions. Their task cache entries have been
+    /// removed, and persisted items of other tasks that refer to them are dropped on restore.
+    removed_tasks: FxHashSet<TaskId>,
+    /// Weak dependents of transient readers of unloaded tasks. They can't be persisted and are
+    /// added back when the task is restored.
+    weak_dependents: DashMap<TaskId, Vec<CachedDataItem>, BuildHasherDefault<FxHasher>>,
+
+    /// Statistics per function of all completed task executions and created persistent tasks.
+    task_statistics: DashMap<FunctionId, TaskExecutionStatistics, BuildHasherDefault<FxHasher>>,
+    /// The pending reads of executing tasks, used for hanging detection.
+    waiting_tasks: WaitingTasks,
+    task_events: TaskEvents,
+    lifecycle_events: LifecycleEvents,
+    /// Only set when [`BackendOptions::cache_profiling`] is enabled.
+    cache_profiler: Option<CacheProfiler>,
+    /// Only set when [`BackendOptions::dependency_tracking`] is enabled.
+    dependency_tracker: Option<DependencyTracker>,
+    /// Only set when [`BackendOptions::execution_history`] is set.
+    execution_history: Option<ExecutionHistory>,
+    /// Only set when [`BackendOptions::fair_scheduling`] is set.
+    scheduler: Option<FairScheduler>,
+    /// Only set when [`BackendOptions::operation_log`] is set.
+    operation_recorder: Option<OperationRecorder>,
+    /// Set while [`TurboTasksBackend::replay_operations`] runs. Tasks are not scheduled then.
+    replaying_operations: AtomicBool,
+    /// Invalidations waiting for the [`BackendOptions::invalidation_batch_window`] to pass.
+    pending_invalidations: Mutex<Vec<(TaskId, TaskDirtyCause)>>,
+    /// When the tasks read with [`ReadConsistency::Strong`] started to wait for dirty tasks. Only
+    /// used when [`BackendOptions::strongly_consistent_read_timeout`] is set.
+    strongly_consistent_reads: DashMap<TaskId, Instant, BuildHasherDefault<FxHasher>>,
+
+    backing_storage: Arc<B>,
 }
 
 impl<B: BackingStorage> TurboTasksBackend<B> {
-    pub fn new(backing_storage: B) -> Self {
-        Self(Arc::new(TurboTasksBackendInner::new(backing_storage)))
+    pub fn new(options: BackendOptions, backing_storage: B) -> Self {
+        Self(Arc::new(TurboTasksBackendInner::new(
+            options,
+            backing_storage,
+        )))
+    }
+
+    /// Suspends all operations and persists all changes made so far, like the background snapshot
+    /// job does. Resolves when the data has been written to the backing storage, so the process
+    /// can be terminated afterwards without losing cache data. Like after a background snapshot,
+    /// tasks are unloaded afterwards when [`BackendOptions::memory_limit`] is exceeded.
+    pub async fn flush(&self) -> Result<()> {
+        let inner = self.0.clone();
+        tokio::task::spawn_blocking(move || {
+            inner.flush()?;
+            inner.run_gc();
+            Ok(())
+        })
+        .await?
+    }
+
+    /// Returns the approximate memory used by the items of all tasks in memory.
+    pub fn memory_usage(&self) -> TaskMemoryUsage {
+        let mut total = TaskMemoryUsage::default();
+        self.0
+            .storage
+            .for_each(|_, task| total += *task.memory_usage());
+        total
+    }
+
+    pub fn gc_statistics(&self) -> GcStatistics {
+        self.0.gc_statistics()
+    }
+
+    /// Prevents the garbage collection from unloading `task_id` until [`Self::unpin_task`] is
+    /// called the same number of times.
+    pub fn pin_task(&self, task_id: TaskId) {
+        *self.0.pinned_tasks.entry(task_id).or_default() += 1;
+    }
+
+    /// Reverts one call to [`Self::pin_task`].
+    pub fn unpin_task(&self, task_id: TaskId) {
+        self.0.pinned_tasks.remove_if_mut(&task_id, |_, count| {
+            *count -= 1;
+            *count == 0
+        });
+    }
+
+    /// Returns the size of the in-memory task cache and how often entries were evicted from it or
+    /// fetched from the backing storage.
+    pub fn task_cache_statistics(&self) -> TaskCacheStatistics {
+        self.0.task_cache_limit.statistics(&self.0.task_cache)
+    }
+
+    /// Returns how often and how long operations were held up by snapshots.
+    pub fn operation_statistics(&self) -> OperationStatistics {
+        self.0.operation_statistics.get()
+    }
+
+    /// Returns the `count` tasks that use the most memory, largest first.
+    pub fn largest_tasks(&self, count: usize) -> Vec<(TaskId, TaskMemoryUsage)> {
+        if count == 0 {
+            return Vec::new();
+        }
+        let mut tasks = Vec::new();
+        self.0.storage.for_each(|&task_id, task| {
+            tasks.push((task_id, *task.memory_usage()));
+            // Keep the candidates bounded by discarding the smaller half from time to time
+            if tasks.len() >= count * 2 {
+                tasks.select_nth_unstable_by_key(count - 1, |(_, usage)| Reverse(usage.total()));
+                tasks.truncate(count);
+            }
+        });
+        tasks.sort_by_key(|(_, usage)| Reverse(usage.total()));
+        tasks.truncate(count);
+        tasks
+    }
+
+    /// Shuts the backend down: In-flight operations are completed, a final snapshot is persisted
+    /// and the backing storage is closed. Changes made after this are no longer persisted and
+    /// tasks that haven't been restored from the backing storage until then are treated as new.
+    /// Creating root or once tasks afterwards panics.
+    ///
+    /// Call this after [`TurboTasks::stop_and_wait`][turbo_tasks::TurboTasks::stop_and_wait] to
+    /// also include the results of tasks that were executing at that time.
+    pub async fn shutdown(&self) -> Result<()> {
+        let inner = self.0.clone();
+        tokio::task::spawn_blocking(move || inner.shutdown()).await?
+    }
+
+    /// Subscribes to lifecycle events of all tasks. Events are only collected while there are
+    /// subscribers. Subscribe before passing the backend to `TurboTasks::new` to receive the
+    /// [`TaskEvent::StartupProgress`] events.
+    pub fn subscribe_task_events(&self) -> broadcast::Receiver<TaskEvent> {
+        self.0.task_events.subscribe()
+    }
+
+    /// Subscribes to the idle and stopping events of the backend, e.g. to do background work while
+    /// no tasks are executing. They are emitted when the background jobs of the backend are
+    /// notified, so e.g. the snapshot written on idle might run concurrently.
+    pub fn subscribe_lifecycle_events(&self) -> broadcast::Receiver<LifecycleEvent> {
+        self.0.lifecycle_events.subscribe()
+    }
+
+    /// Returns the cache effectiveness per function, most recomputed first. Empty unless
+    /// [`BackendOptions::cache_profiling`] is enabled.
+    pub fn cache_effectiveness(&self) -> Vec<(FunctionId, CacheEffectiveness)> {
+        self.0
+            .cache_profiler
+            .as_ref()
+            .map(|profiler| profiler.summary())
+            .unwrap_or_default()
+    }
+
+    /// Returns how the dependencies of the task changed for its last execution, i.e. why it was
+    /// executed again. `None` unless [`BackendOptions::dependency_tracking`] is enabled and the
+    /// task has been executed since.
+    pub fn dependency_diff(&self, task_id: TaskId) -> Option<DependencyDiff> {
+        self.0.dependency_tracker.as_ref()?.get(task_id)
+    }
+
+    /// Returns the most recent sessions that persisted snapshots to the backing storage.
+    pub fn session_history(&self) -> Vec<SessionInfo> {
+        self.0.backing_storage.session_history()
+    }
+
+    /// Merges the session dependent dirty state of all sessions except the current one and the
+    /// `keep_sessions` before it into the session independent state. Aggregated dirty counts that
+    /// become zero are removed. The changes are persisted with the next snapshot. Returns the
+    /// number of compacted items.
+    ///
+    /// Only tasks that are in memory are compacted. Tasks that are only in the backing storage
+    /// keep their session dependent state until they are restored and compacted again.
+    pub fn compact_sessions(&self, keep_sessions: u32) -> usize {
+        self.0.compact_sessions(keep_sessions)
     }
 }
 
 impl<B: BackingStorage> TurboTasksBackendInner<B> {
-    pub fn new(backing_storage: B) -> Self {
+    pub fn new(options: BackendOptions, backing_storage: B) -> Self {
         let shard_amount =
             (available_parallelism().map_or(4, |v| v.get()) * 64).next_power_of_two();
+        let read_only = options.read_only;
+        let cache_profiler = options.cache_profiling.then(CacheProfiler::default);
+        let dependency_tracker = options.dependency_tracking.then(DependencyTracker::default);
+        let execution_history = options
+            .execution_history
+            .filter(|&executions| executions > 0)
+            .map(ExecutionHistory::new);
+        let scheduler = options.fair_scheduling.clone().map(FairScheduler::new);
+        let task_cache_limit = TaskCacheLimit::new(options.task_cache_limit);
+        let waiting_tasks = WaitingTasks::new(
+            options.hanging_detection_timeout.is_some() || options.detect_wait_cycles,
+        );
+        let next_free_task_id = backing_storage.next_free_task_id();
+        let removed_tasks = backing_storage.removed_tasks().into_iter().collect();
+        let persist_aggregation = match options.aggregation_persistence {
+            AggregationPersistence::Persisted => true,
+            AggregationPersistence::Rebuilt => false,
+            AggregationPersistence::RebuiltAbove(max_tasks) => {
+                *next_free_task_id as usize <= max_tasks
+            }
+        };
+        let operation_recorder = options.operation_log.as_deref().and_then(|path| {
+            OperationRecorder::new(path)
+                .inspect_err(|err| {
+                    println!(
+                        "Creating the operation log at {} failed: {err:?}",
+                        path.display()
+                    )
+                })
+                .ok()
+        });
         Self {
+            options,
             start_time: Instant::now(),
             session_id: backing_storage.next_session_id(),
             persisted_task_id_factory: IdFactoryWithReuse::new(
-                *backing_storage.next_free_task_id() as u64,
+                *next_free_task_id as u64,
                 (TRANSIENT_TASK_BIT - 1) as u64,
             ),
             transient_task_id_factory: IdFactoryWithReuse::new(
                 TRANSIENT_TASK_BIT as u64,
-                u32::MAX as u64,
+                MAX_TRANSIENT_TASK_ID as u64,
             ),
-            persisted_task_cache_log: Sharded::new(shard_amount),
             task_cache: BiMap::new(),
+            task_cache_limit,
+            task_descriptions: Default::default(),
+            persist_aggregation,
             transient_tasks: DashMap::default(),
-            persisted_storage_data_log: Sharded::new(shard_amount),
-            persisted_storage_meta_log: Sharded::new(shard_amount),
+            persisted_logs: [
+                PersistedLogs::new(shard_amount),
+                PersistedLogs::new(shard_amount),
+            ],
+            log_epoch: AtomicUsize::new(0),
+            freezing_logs: AtomicBool::new(false),
+            epoch_operations: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            pending_log_items: AtomicUsize::new(0),
+            log_pressure_event: Event::new(|| "TurboTasksBackend::log_pressure_event".to_string()),
             storage: Storage::new(),
             in_progress_operations: AtomicUsize::new(0),
             snapshot_request: Mutex::new(SnapshotRequest::new()),
+            snapshot_lock: Mutex::new(()),
+            operation_statistics: OperationStatisticsCollector::default(),
             operations_suspended: Condvar::new(),
             snapshot_completed: Condvar::new(),
             last_snapshot: AtomicU64::new(0),
+            consecutive_snapshot_failures: AtomicUsize::new(0),
+            persistence_disabled: AtomicBool::new(read_only),
+            persisted_generation: AtomicU32::new(0),
+            committed_snapshots: AtomicU32::new(0),
+            gc_disabled: AtomicBool::new(false),
             stopping: AtomicBool::new(false),
             stopping_event: Event::new(|| "TurboTasksBackend::stopping_event".to_string()),
+            shut_down: AtomicBool::new(false),
             idle_start_event: Event::new(|| "TurboTasksBackend::idle_start_event".to_string()),
             idle_end_event: Event::new(|| "TurboTasksBackend::idle_end_event".to_string()),
-            backing_storage,
+            unloaded_tasks: DashSet::default(),
+            unloaded_tasks_count: AtomicUsize::new(0),
+            restored_tasks_count: AtomicUsize::new(0),
+            reloaded_tasks_count: AtomicUsize::new(0),
+            pinned_tasks: DashMap::default(),
+            weak_readers: DashSet::default(),
+            non_persistable_tasks: DashSet::default(),
+            has_non_persistable_tasks: AtomicBool::new(false),
+            removed_tasks,
+            weak_dependents: DashMap::default(),
+            task_statistics: DashMap::default(),
+            waiting_tasks,
+            task_events: TaskEvents::default(),
+            lifecycle_events: LifecycleEvents::default(),
+            cache_profiler,
+            dependency_tracker,
+            execution_history,
+            scheduler,
+            operation_recorder,
+            replaying_operations: AtomicBool::new(false),
+            pending_invalidations: Mutex::new(Vec::new()),
+            strongly_consistent_reads: DashMap::default(),
+            backing_storage: Arc::new(backing_storage),
         }
     }
 
@@ -207,6 +849,19 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     fn suspending_requested(&self) -> bool {
         (self.in_progress_operations.load(Ordering::Relaxed) & SNAPSHOT_REQUESTED_BIT) != 0
+            || self.frozen_operation_epoch().is_some()
+    }
+
+    /// The log epoch of the operation running on this thread, if the logs of that epoch have been
+    /// frozen by a snapshot. Nested operations don't leave the frozen epoch, as the operation they
+    /// have been started from still belongs to it.
+    fn frozen_operation_epoch(&self) -> Option<usize> {
+        if NESTED_OPERATION.get() {
+            return None;
+        }
+        OPERATION_LOG_EPOCH
+            .get()
+            .filter(|&epoch| epoch != self.log_epoch.load(Ordering::SeqCst))
     }
 
     fn operation_suspend_point(&self, suspend: impl FnOnce() -> AnyOperation) {
@@ -216,8 +871,21 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             suspend: impl FnOnce() -> AnyOperation,
         ) {
             let operation = Arc::new(suspend());
+            if let Some(frozen_epoch) = this.frozen_operation_epoch() {
+                // The state at this point is persisted with the frozen logs. The operation
+                // continues in the current epoch once the frozen epoch is complete.
+                this.operation_statistics.operation_suspended();
+                this.snapshot_request
+                    .lock()
+                    .frozen_operations
+                    .push(operation);
+                this.end_epoch_operation(frozen_epoch);
+                OPERATION_LOG_EPOCH.set(Some(this.start_epoch_operation()));
+                return;
+            }
             let mut snapshot_request = this.snapshot_request.lock();
             if snapshot_request.snapshot_requested {
+                this.operation_statistics.operation_suspended();
                 snapshot_request
                     .suspended_operations
                     .insert(operation.clone().into());
@@ -244,9 +912,12 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     pub(crate) fn start_operation(&self) -> OperationGuard<'_, B> {
         let fetch_add = self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
+        self.operation_statistics
+            .operation_started((fetch_add & !SNAPSHOT_REQUESTED_BIT) + 1);
         if (fetch_add & SNAPSHOT_REQUESTED_BIT) != 0 {
             let mut snapshot_request = self.snapshot_request.lock();
             if snapshot_request.snapshot_requested {
+                self.operation_statistics.operation_delayed();
                 let value = self.in_progress_operations.fetch_sub(1, Ordering::AcqRel) - 1;
                 if value == SNAPSHOT_REQUESTED_BIT {
                     self.operations_suspended.notify_all();
@@ -258,23 +929,324 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
             }
         }
-        OperationGuard { backend: self }
+        let previous_log_epoch = OPERATION_LOG_EPOCH.get();
+        let log_epoch = if let Some(epoch) = previous_log_epoch {
+            // Operations started from within another operation belong to the same epoch
+            self.epoch_operations[epoch & 1].fetch_add(1, Ordering::SeqCst);
+            epoch
+        } else {
+            self.start_epoch_operation()
+        };
+        OPERATION_LOG_EPOCH.set(Some(log_epoch));
+        let previous_nested = NESTED_OPERATION.replace(previous_log_epoch.is_some());
+        OperationGuard {
+            backend: self,
+            previous_log_epoch,
+            previous_nested,
+        }
     }
 
-    fn persisted_storage_log(
+    /// Registers an operation in the current log epoch and returns the epoch. While the
+    /// operations of a frozen epoch are completing, this waits until they are done, so all changes
+    /// persisted with the frozen logs are made before any change of the current epoch.
+    fn start_epoch_operation(&self) -> usize {
+        loop {
+            if self.freezing_logs.load(Ordering::SeqCst) {
+                let mut snapshot_request = self.snapshot_request.lock();
+                if snapshot_request.freezing_logs {
+                    self.operation_statistics.operation_delayed();
+                    self.snapshot_completed
+                        .wait_while(&mut snapshot_request, |snapshot_request| {
+                            snapshot_request.freezing_logs
+                        });
+                }
+            }
+            let epoch = self.log_epoch.load(Ordering::SeqCst);
+            self.epoch_operations[epoch & 1].fetch_add(1, Ordering::SeqCst);
+            if self.log_epoch.load(Ordering::SeqCst) == epoch
+                && !self.freezing_logs.load(Ordering::SeqCst)
+            {
+                return epoch;
+            }
+            // The logs are being frozen in the meantime
+            self.end_epoch_operation(epoch);
+        }
+    }
+
+    fn end_epoch_operation(&self, epoch: usize) {
+        let remaining = self.epoch_operations[epoch & 1].fetch_sub(1, Ordering::SeqCst) - 1;
+        if remaining == 0 && self.log_epoch.load(Ordering::SeqCst) != epoch {
+            // Locking avoids notifying between the check and the wait of `freeze_logs`
+            let _snapshot_request = self.snapshot_request.lock();
+            self.operations_suspended.notify_all();
+        }
+    }
+
+    fn pending_log_items(&self) -> usize {
+        self.pending_log_items.load(Ordering::Relaxed)
+    }
+
+    fn track_pending_log_items(&self, count: usize) {
+        let before = self.pending_log_items.fetch_add(count, Ordering::Relaxed);
+        if let Some(max) = self.options.max_pending_log_items {
+            if before < max && before + count >= max {
+                self.log_pressure_event.notify(usize::MAX);
+            }
+        }
+    }
+
+    fn log_pressure(&self) -> bool {
+        self.options
+            .max_pending_log_items
+            .is_some_and(|max| self.pending_log_items() >= max)
+    }
+
+    /// Changes of persistent tasks are logged to be written with the next snapshot, unless the
+    /// session is read-only or persistence has been disabled.
+    fn should_persist(&self, task_id: TaskId) -> bool {
+        !task_id.is_transient()
+            && !self.persistence_disabled.load(Ordering::Relaxed)
+            && !self.is_non_persistable(task_id)
+    }
+
+    /// Panics when `task_id` is a stale reference to a transient task id that has been reused with
+    /// another generation. Accessing it would otherwise silently create an empty task. Only checked
+    /// when task ids have a generation, i.e. with debug assertions.
+    fn validate_task_id_generation(&self, task_id: TaskId) {
+        if TRANSIENT_TASK_GENERATION_BITS == 0
+            || !task_id.is_transient()
+            || self.storage.access(&task_id).is_some()
+        {
+            return;
+        }
+        let mut other = task_id.next_generation();
+        while other != task_id {
+            assert!(
+                self.storage.access(&other).is_none(),
+                "Stale reference to task {task_id}, the id has been reused as {other}"
+            );
+            other = other.next_generation();
+        }
+    }
+
+    fn is_non_persistable(&self, task_id: TaskId) -> bool {
+        self.has_non_persistable_tasks.load(Ordering::Acquire)
+            && self.non_persistable_tasks.contains(&task_id)
+    }
+
+    /// Stops persisting `task_id` and all persistent tasks that read from it, so that they can
+    /// depend on transient tasks. Their persisted data and task cache entries are removed, so they
+    /// are new tasks in the next session. Items of other tasks that refer to them are dropped when
+    /// these tasks are restored, see [`Self::discard_references_to_removed_tasks`].
+    fn mark_non_persistable(&self, task_id: TaskId, ctx: &mut impl ExecuteContext) {
+        let mut queue = vec![task_id];
+        while let Some(task_id) = queue.pop() {
+            if task_id.is_transient() || self.non_persistable_tasks.contains(&task_id) {
+                continue;
+            }
+            let task = ctx.task(task_id, TaskDataCategory::All);
+            let mut removals = Vec::new();
+            for (key, value) in task.iter_all() {
+                match *key {
+                    CachedDataItemKey::OutputDependent { task }
+                    | CachedDataItemKey::CellDependent { task, .. }
+                    | CachedDataItemKey::CollectiblesDependent { task, .. }
+                    | CachedDataItemKey::WeakOutputDependent { task }
+                    | CachedDataItemKey::WeakCellDependent { task, .. } => queue.push(task),
+                    _ => {}
+                }
+                if self.should_persist_key(task_id, key) && value.is_persistent() {
+                    removals.push(CachedDataUpdate {
+                        key: key.clone(),
+                        task: task_id,
+                        value: None,
+                        old_value: Some(value.clone()),
+                    });
+                }
+            }
+            // Insert while holding the task lock, so no later change of the task is persisted
+            if !self.non_persistable_tasks.insert(task_id) {
+                continue;
+            }
+            self.has_non_persistable_tasks
+                .store(true, Ordering::Release);
+            drop(task);
+            if self.persistence_disabled.load(Ordering::Relaxed) {
+                continue;
+            }
+            self.track_pending_log_items(removals.len());
+            for update in removals {
+                self.push_persisted_storage_update(update.key.category(), update);
+            }
+            if let Some(task_type) = self.lookup_task_type(task_id) {
+                self.push_persisted_log(
+                    |logs| &logs.task_cache_removals,
+                    task_id,
+                    (task_type, task_id),
+                );
+                self.track_pending_log_items(1);
+            }
+        }
+    }
+
+    /// Whether changes of items with this key are written to the backing storage.
+    fn should_persist_key(&self, task_id: TaskId, key: &CachedDataItemKey) -> bool {
+        self.should_persist(task_id)
+            && key.is_persistent()
+            && (self.persist_aggregation || !key.is_aggregation())
+    }
+
+    /// The categories that are restored when a task is accessed with `category`. When the
+    /// aggregation structure is rebuilt, the children of a leaf node are its followers, so they
+    /// need to be restored together with the aggregation data.
+    fn restore_category(&self, category: TaskDataCategory) -> TaskDataCategory {
+        if self.persist_aggregation || category != TaskDataCategory::Meta {
+            category
+        } else {
+            TaskDataCategory::All
+        }
+    }
+
+    /// Removes the aggregation structure from restored items when it's rebuilt instead of
+    /// persisted, e.g. because the database was written with
+    /// [`AggregationPersistence::Persisted`]. The removal is persisted, so the outdated structure
+    /// doesn't take up space in the database.
+    fn discard_restored_aggregation(&self, task_id: TaskId, items: &mut Vec<CachedDataItem>) {
+        if self.persist_aggregation {
+            return;
+        }
+        let persist = self.should_persist(task_id);
+        items.retain(|item| {
+            let key = item.key();
+            if !key.is_aggregation() {
+                return true;
+            }
+            if persist {
+                self.push_persisted_storage_update(
+                    key.category(),
+                    CachedDataUpdate {
+                        key,
+                        task: task_id,
+                        value: None,
+                        old_value: Some(item.value()),
+                    },
+                );
+                self.track_pending_log_items(1);
+            }
+            false
+        });
+    }
+
+    /// Removes restored items that refer to tasks which became non-persistable in a previous
+    /// session, since these tasks are never restored again. The aggregated dirty count is adjusted
+    /// to the removed dirty containers. The removals are persisted.
+    fn discard_references_to_removed_tasks(
         &self,
-        category: TaskDataCategory,
-    ) -> &Sharded<ChunkedVec<CachedDataUpdate>> {
-        match category {
-            TaskDataCategory::Data => &self.persisted_storage_data_log,
-            TaskDataCategory::Meta => &self.persisted_storage_meta_log,
-            TaskDataCategory::All => unreachable!(),
+        task_id: TaskId,
+        items: &mut Vec<CachedDataItem>,
+    ) {
+        if self.removed_tasks.is_empty() {
+            return;
+        }
+        let persist = self.should_persist(task_id);
+        let mut removed_dirty_count = DirtyContainerCount::default();
+        items.retain(|item| {
+            let key = item.key();
+            let Some(referenced_task) = key.referenced_task() else {
+                return true;
+            };
+            if !self.removed_tasks.contains(&referenced_task) {
+                return true;
+            }
+            if let CachedDataItem::AggregatedDirtyContainer { value, .. } = item {
+                removed_dirty_count
+                    .update_count(&DirtyContainerCount::default().update_count(value));
+            }
+            if persist {
+                self.push_persisted_storage_update(
+                    key.category(),
+                    CachedDataUpdate {
+                        key,
+                        task: task_id,
+                        value: None,
+                        old_value: Some(item.value()),
+                    },
+                );
+                self.track_pending_log_items(1);
+            }
+            false
+        });
+        if removed_dirty_count.is_zero() {
+            return;
+        }
+        let Some(index) = items
+            .iter()
+            .position(|item| matches!(item, CachedDataItem::AggregatedDirtyContainerCount { .. }))
+        else {
+            return;
+        };
+        let old_value = items[index].value();
+        let CachedDataItem::AggregatedDirtyContainerCount { value } = &mut items[index] else {
+            unreachable!();
+        };
+        value.update_count(&removed_dirty_count.negate());
+        let new_value = if value.is_zero() {
+            items.swap_remove(index);
+            None
+        } else {
+            Some(items[index].value())
+        };
+        if persist {
+            self.push_persisted_storage_update(
+                TaskDataCategory::Meta,
+                CachedDataUpdate {
+                    key: CachedDataItemKey::AggregatedDirtyContainerCount {},
+                    task: task_id,
+                    value: new_value,
+                    old_value: Some(old_value),
+                },
+            );
+            self.track_pending_log_items(1);
+        }
+    }
+
+    fn push_persisted_storage_update(&self, category: TaskDataCategory, update: CachedDataUpdate) {
+        self.push_persisted_log(|logs| logs.storage(category), update.task, update);
+    }
+
+    /// Adds an item to the logs of the epoch of the running operation. The logs of an epoch are
+    /// only taken after all its operations are completed or suspended, and operations of the next
+    /// epoch only start afterwards, so the frozen logs are a consistent cut. Changes outside of
+    /// operations are added to the logs of the current epoch.
+    fn push_persisted_log<T>(
+        &self,
+        log: impl Fn(&PersistedLogs) -> &Sharded<ChunkedVec<T>>,
+        task_id: TaskId,
+        item: T,
+    ) {
+        if let Some(epoch) = OPERATION_LOG_EPOCH.get() {
+            log(&self.persisted_logs[epoch & 1])
+                .lock(task_id)
+                .push(item);
+            return;
+        }
+        loop {
+            let epoch = self.log_epoch.load(Ordering::SeqCst);
+            let mut shard = log(&self.persisted_logs[epoch & 1]).lock(task_id);
+            // The logs might have been frozen and taken before the shard was locked
+            if self.log_epoch.load(Ordering::SeqCst) != epoch {
+                continue;
+            }
+            shard.push(item);
+            return;
         }
     }
 }
 
 pub(crate) struct OperationGuard<'a, B: BackingStorage> {
     backend: &'a TurboTasksBackendInner<B>,
+    previous_log_epoch: Option<usize>,
+    previous_nested: bool,
 }
 
 impl<B: BackingStorage> Drop for OperationGuard<'_, B> {
@@ -286,6 +1258,14 @@ impl<B: BackingStorage> Drop for OperationGuard<'_, B> {
         if fetch_sub - 1 == SNAPSHOT_REQUESTED_BIT {
             self.backend.operations_suspended.notify_all();
         }
+        NESTED_OPERATION.set(self.previous_nested);
+        if let Some(epoch) = OPERATION_LOG_EPOCH.replace(self.previous_log_epoch) {
+            self.backend.end_epoch_operation(epoch);
+        }
+        #[cfg(feature = "verify_consistency")]
+        if self.previous_log_epoch.is_none() && fetch_sub - 1 == 0 {
+            self.backend.verify_consistency_after_operation();
+        }
     }
 }
 
@@ -326,7 +1306,24 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         consistency: ReadConsistency,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> Result<Result<RawVc, EventListener>> {
+        let consistency = match reader {
+            Some(reader)
+                if self.options.strongly_consistent_once_tasks && self.is_once_task(reader) =>
+            {
+                ReadConsistency::Strong
+            }
+            _ => consistency,
+        };
+
+        if consistency.waits_for_settled() {
+            // The read must see the tasks that are invalidated, but not marked as dirty yet
+            self.apply_pending_invalidations(turbo_tasks);
+        } else if let Some(output) = self.try_read_clean_task_output(task_id, reader) {
+            return Ok(Ok(output));
+        }
+
         let mut ctx = self.execute_context(turbo_tasks);
+
         let mut task = ctx.task(task_id, TaskDataCategory::All);
 
         if let Some(in_progress) = get!(task, InProgress) {
@@ -341,39 +1338,89 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                             "try_read_task_output (untracked)".to_string()
                         }
                     });
+                    drop(task);
+                    self.promote_scheduled_task(task_id, turbo_tasks);
+                    if let Some(reader) = reader {
+                        self.waiting_tasks
+                            .start_waiting(reader, task_id, WaitKind::Output);
+                    }
                     return Ok(Err(listener));
                 }
             }
         }
 
-        if matches!(consistency, ReadConsistency::Strong) {
-            // Ensure it's an root node
+        if consistency.waits_for_settled() {
+            // Somebody is waiting for the dirty tasks found below
+            ctx.schedule_urgently();
+            // Ensure it's an aggregating node. It's only a temporary root for the read: promoting
+            // it to a root node would flatten the aggregation tree below it for good.
             loop {
                 let aggregation_number = get_aggregation_number(&task);
-                if is_root_node(aggregation_number) {
+                if is_aggregating_node(aggregation_number) {
                     break;
                 }
                 drop(task);
                 AggregationUpdateQueue::run(
                     AggregationUpdateJob::UpdateAggregationNumber {
                         task_id,
-                        base_aggregation_number: u32::MAX,
+                        base_aggregation_number: LEAF_NUMBER,
                         distance: None,
                     },
                     &mut ctx,
                 );
                 task = ctx.task(task_id, TaskDataCategory::All);
             }
-
-            let is_dirty =
-                get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id));
-
-            // Check the dirty count of the root node
-            let dirty_tasks = get!(task, AggregatedDirtyContainerCount)
-                .cloned()
-                .unwrap_or_default()
-                .get(self.session_id);
-            if dirty_tasks > 0 || is_dirty {
+            drop(task);
+
+            while let Some(unclean_task_id) = self.find_unclean_task(task_id, &mut ctx) {
+                let mut task = ctx.task(unclean_task_id, TaskDataCategory::Meta);
+                let is_dirty =
+                    get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id));
+                let dirty_tasks = get!(task, AggregatedDirtyContainerCount)
+                    .cloned()
+                    .unwrap_or_default()
+                    .get(self.session_id);
+                if dirty_tasks <= 0 && !is_dirty {
+                    // It became clean in the meantime
+                    continue;
+                }
+                if let Some(timeout) = self.options.strongly_consistent_read_timeout {
+                    let started = *self
+                        .strongly_consistent_reads
+                        .entry(task_id)
+                        .or_insert_with(|| {
+                            let turbo_tasks = turbo_tasks.pin();
+                            tokio::spawn(async move {
+                                tokio::time::sleep(timeout).await;
+                                turbo_tasks
+                                    .backend()
+                                    .0
+                                    .strongly_consistent_read_timed_out(unclean_task_id);
+                            });
+                            Instant::now()
+                        });
+                    if started.elapsed() >= timeout {
+                        self.strongly_consistent_reads.remove(&task_id);
+                        drop(task);
+                        const MAX_REPORTED_DIRTY_TASKS: usize = 20;
+                        let pending = self
+                            .pending_dirty_tasks(
+                                unclean_task_id,
+                                MAX_REPORTED_DIRTY_TASKS,
+                                &mut ctx,
+                            )
+                            .into_iter()
+                            .map(|task_id| format!("\n  - {}", ctx.get_task_description(task_id)))
+                            .collect::<String>();
+                        bail!(
+                            "Strongly consistent read of {} did not settle within {:?}. Dirty \
+                             tasks still pending:{}",
+                            ctx.get_task_description(task_id),
+                            timeout,
+                            pending
+                        );
+                    }
+                }
                 let root = get!(task, AggregateRoot);
                 let mut task_ids_to_schedule: Vec<_> = Vec::new();
                 // When there are dirty task, subscribe to the all_clean_event
@@ -384,7 +1431,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     // active and this task won't stale. CachedActiveUntilClean
                     // is automatically removed when this task is clean.
                     task.add_new(CachedDataItem::AggregateRoot {
-                        value: RootState::new(ActiveType::CachedActiveUntilClean, task_id),
+                        value: RootState::new(ActiveType::CachedActiveUntilClean, unclean_task_id),
                     });
                     // A newly added AggregateRoot need to make sure to schedule the tasks
                     task_ids_to_schedule = get_many!(
@@ -396,7 +1443,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         }
                     );
                     if is_dirty {
-                        task_ids_to_schedule.push(task_id);
+                        task_ids_to_schedule.push(unclean_task_id);
                     }
                     get!(task, AggregateRoot).unwrap()
                 };
@@ -412,9 +1459,25 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     queue.extend_find_and_schedule_dirty(task_ids_to_schedule);
                     queue.execute(&mut ctx);
                 }
+                if let Some(reader) = reader {
+                    self.waiting_tasks
+                        .start_waiting(reader, task_id, WaitKind::StronglyConsistent);
+                    // The read would never settle when the reader itself, or a task it is waiting
+                    // for, is one of the dirty tasks
+                    if self.options.detect_wait_cycles {
+                        if let Some(cycle) = self.find_wait_cycle(reader, &mut ctx) {
+                            self.waiting_tasks.stop_waiting(reader);
+                            return Err(cycle.into());
+                        }
+                    }
+                }
 
                 return Ok(Err(listener));
             }
+            if self.options.strongly_consistent_read_timeout.is_some() {
+                self.strongly_consistent_reads.remove(&task_id);
+            }
+            task = ctx.task(task_id, TaskDataCategory::All);
         }
 
         if let Some(output) = get!(task, Output) {
@@ -426,22 +1489,47 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 }
             };
             if let Some(result) = result {
-                if let Some(reader) = reader {
-                    let _ = task.add(CachedDataItem::OutputDependent {
-                        task: reader,
-                        value: (),
-                    });
+                let is_dirty =
+                    get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id));
+                if !is_dirty {
+                    if let Some(profiler) = &self.cache_profiler {
+                        if let Some(fn_type) = self.try_get_function_id(task_id) {
+                            profiler.hit(task_id, fn_type);
+                        }
+                    }
+                    self.record_task_hit(&mut task);
+                }
+                // Immutable tasks never change their output, so there is no need to track readers
+                if let Some(reader) = reader.filter(|_| !self.is_immutable_task(task_id)) {
+                    if self.weak_readers.contains(&reader) {
+                        let _ = task.add(CachedDataItem::WeakOutputDependent {
+                            task: reader,
+                            value: (),
+                        });
+                    } else {
+                        let _ = task.add(CachedDataItem::OutputDependent {
+                            task: reader,
+                            value: (),
+                        });
+                    }
                     drop(task);
 
                     let mut reader_task = ctx.task(reader, TaskDataCategory::Data);
                     if reader_task
                         .remove(&CachedDataItemKey::OutdatedOutputDependency { target: task_id })
                         .is_none()
-                    {
-                        let _ = reader_task.add(CachedDataItem::OutputDependency {
+                        && reader_task.add(CachedDataItem::OutputDependency {
                             target: task_id,
                             value: (),
-                        });
+                        })
+                    {
+                        if let Some(tracker) = &self.dependency_tracker {
+                            tracker.dependency_added(reader, TaskDependency::Output(task_id));
+                        }
+                    }
+                    drop(reader_task);
+                    if self.is_non_persistable(task_id) {
+                        self.mark_non_persistable(reader, &mut ctx);
                     }
                 }
 
@@ -462,11 +1550,139 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         let (item, listener) =
             CachedDataItem::new_scheduled_with_listener(self.get_task_desc_fn(task_id), note);
         task.add_new(item);
-        turbo_tasks.schedule(task_id);
+        drop(task);
+        self.task_events.emit(|| TaskEvent::Scheduled { task_id });
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.schedule_demand(task_id);
+        }
+        // A strongly consistent read is usually waited on by the user
+        if consistency.waits_for_settled() {
+            turbo_tasks.schedule_with_priority(task_id, TaskPriority::Urgent);
+        } else {
+            turbo_tasks.schedule(task_id);
+        }
 
         Ok(Err(listener))
     }
 
+    /// Starts a task that is read while the [`FairScheduler`] still holds it back.
+    fn promote_scheduled_task(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if let Some(scheduler) = &self.scheduler {
+            if scheduler.promote(task_id) {
+                turbo_tasks.schedule(task_id);
+            }
+        }
+    }
+
+    /// Called when a scheduled task has finished executing, or turned out not to need an
+    /// execution. Starts the tasks the [`FairScheduler`] has held back.
+    fn execution_finished(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if let Some(scheduler) = &self.scheduler {
+            for task_id in scheduler.execution_finished(task_id) {
+                turbo_tasks.schedule(task_id);
+            }
+        }
+    }
+
+    /// Wakes up the strongly consistent readers of `task_id`, so they notice that the
+    /// [`BackendOptions::strongly_consistent_read_timeout`] has passed.
+    fn strongly_consistent_read_timed_out(&self, task_id: TaskId) {
+        let task = self.storage.access_mut(task_id);
+        if let Some(root_state) = get!(task, AggregateRoot) {
+            root_state.all_clean_event.notify(usize::MAX);
+        }
+    }
+
+    /// Finds a task that keeps `task_id` from being clean for a strongly consistent read. Besides
+    /// the task itself, these are the aggregating nodes following it, as their state is not
+    /// aggregated into the task. When there are too many followers to visit, the task is promoted
+    /// to a root node instead, which aggregates the state of all tasks below it.
+    fn find_unclean_task(&self, task_id: TaskId, ctx: &mut impl ExecuteContext) -> Option<TaskId> {
+        const MAX_FOLLOWER_VISITS: usize = 1000;
+
+        let mut visited = FxHashSet::default();
+        let mut queue = VecDeque::new();
+        visited.insert(task_id);
+        queue.push_back(task_id);
+        while let Some(current_id) = queue.pop_front() {
+            let task = ctx.task(current_id, TaskDataCategory::Meta);
+            let is_dirty =
+                get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id));
+            let dirty_tasks = get!(task, AggregatedDirtyContainerCount)
+                .map_or(0, |count| count.get(self.session_id));
+            if is_dirty || dirty_tasks > 0 {
+                return Some(current_id);
+            }
+            if is_root_node(get_aggregation_number(&task)) {
+                continue;
+            }
+            for follower_id in iter_many!(task, Follower { task } count if *count > 0 => *task) {
+                if visited.insert(follower_id) {
+                    queue.push_back(follower_id);
+                }
+            }
+            if visited.len() > MAX_FOLLOWER_VISITS {
+                drop(task);
+                AggregationUpdateQueue::run(
+                    AggregationUpdateJob::UpdateAggregationNumber {
+                        task_id,
+                        base_aggregation_number: u32::MAX,
+                        distance: None,
+                    },
+                    ctx,
+                );
+                return self.find_unclean_task(task_id, ctx);
+            }
+        }
+        None
+    }
+
+    /// Collects up to `MAX_PENDING_DIRTY_TASKS` tasks below `root` that are dirty in the current
+    /// session, following the aggregated dirty containers and the followers that are not
+    /// aggregated into their upper.
+    fn pending_dirty_tasks(
+        &self,
+        root: TaskId,
+        limit: usize,
+        ctx: &mut impl ExecuteContext,
+    ) -> Vec<TaskId> {
+        let mut pending = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack = vec![root];
+        while let Some(task_id) = stack.pop() {
+            if !visited.insert(task_id) {
+                continue;
+            }
+            let task = ctx.task(task_id, TaskDataCategory::Meta);
+            if get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id)) {
+                pending.push(task_id);
+                if pending.len() >= limit {
+                    break;
+                }
+            }
+            stack.extend(iter_many!(
+                task,
+                AggregatedDirtyContainer {
+                    task
+                } count if count.get(self.session_id) > 0 => {
+                    *task
+                }
+            ));
+            if !is_root_node(get_aggregation_number(&task)) {
+                stack.extend(iter_many!(task, Follower { task } count if *count > 0 => *task));
+            }
+        }
+        pending
+    }
+
     fn try_read_task_cell(
         &self,
         task_id: TaskId,
@@ -476,14 +1692,23 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     ) -> Result<Result<TypedCellContent, EventListener>> {
         let mut ctx = self.execute_context(turbo_tasks);
         let mut task = ctx.task(task_id, TaskDataCategory::Data);
+        task.restore_cell(cell);
         if let Some(content) = get!(task, CellData { cell }) {
             let content = content.clone();
-            if let Some(reader) = reader {
-                let _ = task.add(CachedDataItem::CellDependent {
-                    cell,
-                    task: reader,
-                    value: (),
-                });
+            if let Some(reader) = reader.filter(|_| !self.is_immutable_task(task_id)) {
+                if self.weak_readers.contains(&reader) {
+                    let _ = task.add(CachedDataItem::WeakCellDependent {
+                        cell,
+                        task: reader,
+                        value: (),
+                    });
+                } else {
+                    let _ = task.add(CachedDataItem::CellDependent {
+                        cell,
+                        task: reader,
+                        value: (),
+                    });
+                }
                 drop(task);
 
                 let mut reader_task = ctx.task(reader, TaskDataCategory::Data);
@@ -494,8 +1719,15 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 if reader_task
                     .remove(&CachedDataItemKey::OutdatedCellDependency { target })
                     .is_none()
+                    && reader_task.add(CachedDataItem::CellDependency { target, value: () })
                 {
-                    let _ = reader_task.add(CachedDataItem::CellDependency { target, value: () });
+                    if let Some(tracker) = &self.dependency_tracker {
+                        tracker.dependency_added(reader, TaskDependency::Cell(task_id, cell));
+                    }
+                }
+                drop(reader_task);
+                if self.is_non_persistable(task_id) {
+                    self.mark_non_persistable(reader, &mut ctx);
                 }
             }
             return Ok(Ok(TypedCellContent(
@@ -535,6 +1767,10 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         if let Some(in_progress) = get!(task, InProgressCell { cell }) {
             // Someone else is already computing the cell
             let listener = in_progress.event.listen_with_note(note);
+            if let Some(reader) = reader {
+                self.waiting_tasks
+                    .start_waiting(reader, task_id, WaitKind::Cell(cell));
+            }
             return Ok(Err(listener));
         }
 
@@ -548,15 +1784,54 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         });
 
         // Schedule the task, if not already scheduled
-        if task.add(CachedDataItem::new_scheduled(
+        let should_schedule = task.add(CachedDataItem::new_scheduled(
             self.get_task_desc_fn(task_id),
-        )) {
+        ));
+        drop(task);
+        if should_schedule {
+            self.task_events.emit(|| TaskEvent::Scheduled { task_id });
+            if let Some(scheduler) = &self.scheduler {
+                scheduler.schedule_demand(task_id);
+            }
             turbo_tasks.schedule(task_id);
+        } else {
+            self.promote_scheduled_task(task_id, turbo_tasks);
+        }
+        if let Some(reader) = reader {
+            self.waiting_tasks
+                .start_waiting(reader, task_id, WaitKind::Cell(cell));
         }
 
         Ok(Err(listener))
     }
 
+    /// Counts the first read of the output in this session as hit, unless the task has been
+    /// executed in this session.
+    fn record_task_hit(&self, task: &mut impl TaskGuard) {
+        let usage = get!(task, Usage).copied();
+        if usage.is_some_and(|usage| usage.last_session == self.session_id) {
+            return;
+        }
+        task.insert(CachedDataItem::Usage {
+            value: TaskUsage {
+                executions: usage.map_or(0, |usage| usage.executions),
+                hits: usage.map_or(0, |usage| usage.hits) + 1,
+                last_session: self.session_id,
+            },
+        });
+    }
+
+    fn record_task_execution(&self, task: &mut impl TaskGuard) {
+        let usage = get!(task, Usage).copied();
+        task.insert(CachedDataItem::Usage {
+            value: TaskUsage {
+                executions: usage.map_or(0, |usage| usage.executions) + 1,
+                hits: usage.map_or(0, |usage| usage.hits),
+                last_session: self.session_id,
+            },
+        });
+    }
+
     fn lookup_task_type(&self, task_id: TaskId) -> Option<Arc<CachedTaskType>> {
         if let Some(task_type) = self.task_cache.lookup_reverse(&task_id) {
             return Some(task_type);
@@ -567,24 +1842,47 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     .reverse_lookup_task_cache(None, task_id)
             } {
                 let _ = self.task_cache.try_insert(task_type.clone(), task_id);
+                self.task_cache_limit
+                    .fetched(&self.task_cache, task_id, |task_id| {
+                        !self.is_non_persistable(task_id)
+                    });
                 return Some(task_type);
             }
         }
         None
     }
 
-    // TODO feature flag that for hanging detection only
+    /// Returns a function that describes the task. The description is only formatted when the
+    /// function is called, and a lookup in the backing storage is deferred until then as well.
     fn get_task_desc_fn(&self, task_id: TaskId) -> impl Fn() -> String + Send + Sync + 'static {
-        let task_type = self.lookup_task_type(task_id);
+        let task_descriptions = self.task_descriptions.clone();
+        let task_type = self.task_cache.lookup_reverse(&task_id);
+        let backing_storage =
+            (task_type.is_none() && !task_id.is_transient()).then(|| self.backing_storage.clone());
         move || {
-            task_type.as_ref().map_or_else(
+            if let Some(description) = task_descriptions.get(task_id) {
+                return description.to_string();
+            }
+            let task_type = task_type.clone().or_else(|| {
+                // Safety: No transaction is passed.
+                backing_storage.as_ref().and_then(|backing_storage| unsafe {
+                    backing_storage.reverse_lookup_task_cache(None, task_id)
+                })
+            });
+            let description = task_type.as_ref().map_or_else(
                 || format!("{task_id:?} transient"),
                 |task_type| format!("{task_id:?} {task_type}"),
-            )
+            );
+            task_descriptions.insert(task_id, description.as_str().into());
+            description
         }
     }
 
-    fn snapshot(&self) -> Option<(Instant, bool)> {
+    /// Requests all operations to suspend and waits until they are completed or suspended.
+    /// Returns the suspended operations. Must be followed by [`Self::resume_operations`]. Unlike
+    /// [`Self::freeze_logs`], this blocks all operations until they are resumed.
+    fn suspend_operations(&self) -> Vec<Arc<AnyOperation>> {
+        let start = Instant::now();
         let mut snapshot_request = self.snapshot_request.lock();
         snapshot_request.snapshot_requested = true;
         let active_operations = self
@@ -596,22 +1894,78 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     self.in_progress_operations.load(Ordering::Relaxed) != SNAPSHOT_REQUESTED_BIT
                 });
         }
-        let suspended_operations = snapshot_request
+        self.operation_statistics
+            .operations_suspended(start.elapsed());
+        snapshot_request.suspended_since = Some(Instant::now());
+        snapshot_request
             .suspended_operations
             .iter()
             .map(|op| op.arc().clone())
-            .collect::<Vec<_>>();
-        drop(snapshot_request);
-        let persisted_storage_meta_log = self.persisted_storage_meta_log.take();
-        let persisted_storage_data_log = self.persisted_storage_data_log.take();
-        let persisted_task_cache_log = self.persisted_task_cache_log.take();
+            .collect::<Vec<_>>()
+    }
+
+    fn resume_operations(&self) {
         let mut snapshot_request = self.snapshot_request.lock();
         snapshot_request.snapshot_requested = false;
+        if let Some(suspended_since) = snapshot_request.suspended_since.take() {
+            self.operation_statistics
+                .operations_resumed(suspended_since.elapsed());
+        }
         self.in_progress_operations
             .fetch_sub(SNAPSHOT_REQUESTED_BIT, Ordering::Relaxed);
         self.snapshot_completed.notify_all();
+    }
+
+    /// Freezes the logs of the current epoch and waits until all operations of that epoch are
+    /// completed or have reached a suspend point. Operations of the next epoch wait until then, but
+    /// not for the snapshot to be written. Returns the frozen epoch, the generation whose changes
+    /// are all in its logs and the states of the operations that were suspended.
+    fn freeze_logs(&self) -> (usize, u32, Vec<Arc<AnyOperation>>) {
+        let start = Instant::now();
+        // Tasks changed after the logs are frozen must be marked with a newer generation, so the
+        // generation is incremented first.
+        let generation = self.storage.next_generation();
+        let mut snapshot_request = self.snapshot_request.lock();
+        snapshot_request.freezing_logs = true;
+        self.freezing_logs.store(true, Ordering::SeqCst);
+        let epoch = self.log_epoch.fetch_add(1, Ordering::SeqCst);
+        self.operations_suspended
+            .wait_while(&mut snapshot_request, |_| {
+                self.epoch_operations[epoch & 1].load(Ordering::SeqCst) != 0
+            });
+        self.freezing_logs.store(false, Ordering::SeqCst);
+        snapshot_request.freezing_logs = false;
+        self.snapshot_completed.notify_all();
+        self.operation_statistics
+            .operations_suspended(start.elapsed());
+        let frozen_operations = take(&mut snapshot_request.frozen_operations);
+        (epoch, generation, frozen_operations)
+    }
+
+    fn snapshot(&self) -> Option<(Instant, bool)> {
+        let _snapshot_lock = self.snapshot_lock.lock();
+        let (epoch, generation, suspended_operations) = self.freeze_logs();
+        let logs = &self.persisted_logs[epoch & 1];
+        let persisted_storage_meta_log = logs.storage_meta.take();
+        let persisted_storage_data_log = logs.storage_data.take();
+        let persisted_task_cache_log = logs.task_cache.take();
+        let persisted_task_cache_removals_log = logs.task_cache_removals.take();
+        let items = persisted_storage_meta_log
+            .iter()
+            .chain(persisted_storage_data_log.iter())
+            .map(|shard| shard.len())
+            .sum::<usize>()
+            + persisted_task_cache_log
+                .iter()
+                .chain(persisted_task_cache_removals_log.iter())
+                .map(|shard| shard.len())
+                .sum::<usize>();
+        self.pending_log_items
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |pending| {
+                Some(pending.saturating_sub(items))
+            })
+            .ok();
         let snapshot_time = Instant::now();
-        drop(snapshot_request);
 
         // TODO track which items are persisting
         // TODO This is very inefficient, maybe the BackingStorage could compute that since it need
@@ -632,22 +1986,72 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             shards.iter().all(|shard| shard.is_empty())
         }
 
+        if self.persistence_disabled.load(Ordering::Relaxed) {
+            // The logs are dropped here, which keeps memory bounded while persistence is off.
+            return Some((snapshot_time, false));
+        }
+
+        let large_cells = self
+            .options
+            .large_cell_threshold
+            .map(|threshold| large_cells(&persisted_storage_data_log, threshold))
+            .unwrap_or_default();
+
         if !shards_empty(&persisted_task_cache_log)
+            || !shards_empty(&persisted_task_cache_removals_log)
             || !shards_empty(&persisted_storage_meta_log)
             || !shards_empty(&persisted_storage_data_log)
         {
             new_items = true;
-            if let Err(err) = self.backing_storage.save_snapshot(
+            // The entries can only be evicted once they are in the backing storage
+            let persisted_task_cache_entries = self.task_cache_limit.is_enabled().then(|| {
+                persisted_task_cache_log
+                    .iter()
+                    .flat_map(|shard| shard.iter().map(|(_, task_id)| *task_id))
+                    .collect::<Vec<_>>()
+            });
+            let saved_snapshot = match self.backing_storage.save_snapshot(
                 self.session_id,
                 suspended_operations,
                 persisted_task_cache_log,
+                persisted_task_cache_removals_log,
                 persisted_storage_meta_log,
                 persisted_storage_data_log,
             ) {
-                println!("Persising failed: {:#?}", err);
-                return None;
+                Ok(saved_snapshot) => saved_snapshot,
+                Err(err) => {
+                    println!("Persising failed: {:#?}", err);
+                    self.gc_disabled.store(true, Ordering::Relaxed);
+                    let failures = self
+                        .consecutive_snapshot_failures
+                        .fetch_add(1, Ordering::Relaxed)
+                        + 1;
+                    if failures >= self.options.max_consecutive_snapshot_failures {
+                        self.disable_persistence(failures, &err);
+                    }
+                    return None;
+                }
+            };
+            self.consecutive_snapshot_failures
+                .store(0, Ordering::Relaxed);
+            self.committed_snapshots.fetch_add(1, Ordering::Release);
+            if let Some(entries) = persisted_task_cache_entries {
+                self.task_cache_limit
+                    .persisted(&self.task_cache, entries, |task_id| {
+                        !self.is_non_persistable(task_id)
+                    });
+            }
+            self.task_events.emit(|| TaskEvent::Persisted {
+                items,
+                duration: snapshot_time.elapsed(),
+            });
+            self.spill_cells(large_cells);
+            if let Some(threshold) = self.options.oversized_task_threshold {
+                self.report_oversized_tasks(&saved_snapshot, threshold);
             }
         }
+        self.persisted_generation
+            .store(generation, Ordering::Release);
 
         // TODO add when we need to track persisted items
         // for (task_id, count) in counts {
@@ -660,33 +2064,385 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         Some((snapshot_time, new_items))
     }
 
+    fn report_oversized_tasks(&self, saved_snapshot: &SavedSnapshot, threshold: usize) {
+        for (&task_id, &size) in saved_snapshot.task_sizes.iter() {
+            if size <= threshold {
+                continue;
+            }
+            let function = self
+                .lookup_task_type(task_id)
+                .map(|task_type| task_type.get_name().into_owned());
+            tracing::warn!(
+                task = %self.get_task_description(task_id),
+                size,
+                threshold,
+                "task data exceeds the size threshold and is serialized again with every change"
+            );
+            self.task_events.emit(|| TaskEvent::OversizedTask {
+                task_id,
+                function,
+                size,
+            });
+        }
+    }
+
+    /// Removes the persisted `cells` from memory, unless they have changed in the meantime.
+    fn spill_cells(&self, cells: Vec<(TaskId, CellId, TypedSharedReference)>) {
+        for (task_id, cell, value) in cells {
+            let mut task = self.storage.access_mut(task_id);
+            let unchanged = matches!(
+                task.get(&CachedDataItemKey::CellData { cell }),
+                Some(CachedDataItemValue::CellData { value: current }) if *current == value
+            );
+            if unchanged {
+                // The persisted data is kept, so the change is not logged
+                task.remove(&CachedDataItemKey::CellData { cell });
+                task.add(CachedDataItem::SpilledCell { cell, value: () });
+            }
+        }
+    }
+
+    fn flush(&self) -> Result<()> {
+        if self.options.read_only {
+            bail!("The backend is read-only");
+        }
+        if self.persistence_disabled.load(Ordering::Relaxed) {
+            bail!("Persistence has been disabled after repeated snapshot failures");
+        }
+        if self.snapshot().is_none() {
+            bail!("Persisting the snapshot failed");
+        }
+        Ok(())
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        if self.shut_down.swap(true, Ordering::AcqRel) {
+            return Ok(());
+        }
+        self.stopping();
+        // The snapshot waits for all in-flight operations to complete or suspend.
+        let result = if self.options.read_only {
+            Ok(())
+        } else {
+            self.flush()
+        };
+        // Further snapshots must not touch the closed backing storage. This also drops all logs
+        // collected from now on.
+        self.persistence_disabled.store(true, Ordering::Relaxed);
+        self.backing_storage.shutdown()?;
+        result
+    }
+
+    /// Unloads tasks that are fully persisted and haven't been accessed for a few generations
+    /// when the memory limit is exceeded.
+    fn run_gc(&self) {
+        /// Number of snapshots a task must be unused before it's considered for unloading.
+        const GC_MIN_UNUSED_GENERATIONS: u32 = 2;
+
+        let Some(memory_limit) = self.options.memory_limit else {
+            return;
+        };
+        if self.gc_disabled.load(Ordering::Relaxed)
+            || self.persistence_disabled.load(Ordering::Relaxed)
+        {
+            return;
+        }
+        let mut memory_usage = 0;
+        self.storage
+            .for_each(|_, task| memory_usage += task.memory_usage().total());
+        if memory_usage < memory_limit {
+            return;
+        }
+        let persisted_generation = self.persisted_generation.load(Ordering::Acquire);
+        let current_generation = self.storage.generation();
+        let span = tracing::trace_span!(
+            "garbage collection",
+            candidates = tracing::field::Empty,
+            unloaded = tracing::field::Empty
+        )
+        .entered();
+
+        let is_weak_dependent = |key: &CachedDataItemKey| {
+            matches!(
+                key,
+                CachedDataItemKey::WeakOutputDependent { .. }
+                    | CachedDataItemKey::WeakCellDependent { .. }
+            )
+        };
+        let is_unloadable = |task_id: TaskId, task: &InnerStorage<CachedDataItem>| {
+            if task_id.is_transient()
+                || self.pinned_tasks.contains_key(&task_id)
+                || self.is_non_persistable(task_id)
+            {
+                return false;
+            }
+            let last_access = task.persistance_state().last_access();
+            if last_access > persisted_generation
+                || last_access + GC_MIN_UNUSED_GENERATIONS > current_generation
+            {
+                return false;
+            }
+            // Only tasks that are clean and only contain persisted data can be restored without
+            // loss. Weak dependents of transient readers are kept aside until the task is restored.
+            task.iter_all().all(|(key, value)| {
+                (is_weak_dependent(key)
+                    || (self.should_persist_key(task_id, key) && value.is_persistent()))
+                    && !matches!(key, CachedDataItemKey::Dirty { .. })
+            })
+        };
+
+        let mut candidates = Vec::new();
+        self.storage.for_each(|&task_id, task| {
+            if is_unloadable(task_id, task) {
+                let hits = get!(task, Usage).map_or(0, |usage| usage.hits);
+                candidates.push((task.persistance_state().last_access(), hits, task_id));
+            }
+        });
+        span.record("candidates", candidates.len());
+        // Unload the least recently used tasks first, and of those the ones that are rarely reused
+        // across sessions
+        candidates.sort_unstable();
+        let mut unloaded = 0;
+        for (_, _, task_id) in candidates {
+            if memory_usage < memory_limit {
+                break;
+            }
+            let mut task_memory_usage = 0;
+            let mut weak_dependents = Vec::new();
+            if self.storage.remove_if(&task_id, |task| {
+                task_memory_usage = task.memory_usage().total();
+                if !is_unloadable(task_id, task) {
+                    return false;
+                }
+                weak_dependents.extend(task.iter_all().filter_map(|(key, value)| {
+                    (is_weak_dependent(key) && !key.is_persistent())
+                        .then(|| CachedDataItem::from_key_and_value(key.clone(), value.clone()))
+                }));
+                true
+            }) {
+                if !weak_dependents.is_empty() {
+                    self.weak_dependents
+                        .entry(task_id)
+                        .or_default()
+                        .extend(weak_dependents);
+                }
+                memory_usage -= task_memory_usage;
+                self.unloaded_tasks.insert(task_id);
+                unloaded += 1;
+            }
+        }
+        self.unloaded_tasks_count
+            .fetch_add(unloaded, Ordering::Relaxed);
+        span.record("unloaded", unloaded);
+    }
+
+    /// Called when task data has been restored from the backing storage.
+    pub(crate) fn track_restored_task(&self, task_id: TaskId) {
+        self.restored_tasks_count.fetch_add(1, Ordering::Relaxed);
+        if self.unloaded_tasks.remove(&task_id).is_some() {
+            self.reloaded_tasks_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn compact_sessions(&self, keep_sessions: u32) -> usize {
+        let is_old =
+            |session: SessionId| (*session).saturating_add(keep_sessions) < *self.session_id;
+        // Returns `None` when the count is unchanged and `Some(None)` when it can be removed
+        let compact_count = |count: &DirtyContainerCount| {
+            let compacted = match count.count_in_session {
+                Some((session, _)) if is_old(session) => DirtyContainerCount {
+                    count: count.count,
+                    count_in_session: None,
+                },
+                _ if count.is_zero() => return Some(None),
+                _ => return None,
+            };
+            Some((!compacted.is_zero()).then_some(compacted))
+        };
+
+        let mut task_ids = Vec::new();
+        self.storage.for_each(|&task_id, _| task_ids.push(task_id));
+        let mut compacted = 0;
+        for task_id in task_ids {
+            // Snapshots must not see the changes of a task partially applied
+            let _operation_guard = self.start_operation();
+            let mut task = self.storage.access_mut(task_id);
+            let updates = task
+                .iter_all()
+                .filter_map(|(key, value)| {
+                    let new_value = match value {
+                        CachedDataItemValue::Dirty {
+                            value:
+                                DirtyState {
+                                    clean_in_session: Some(session),
+                                },
+                        } if is_old(*session) => Some(CachedDataItemValue::Dirty {
+                            value: DirtyState {
+                                clean_in_session: None,
+                            },
+                        }),
+                        CachedDataItemValue::AggregatedDirtyContainerCount { value } => {
+                            compact_count(value)?.map(|value| {
+                                CachedDataItemValue::AggregatedDirtyContainerCount { value }
+                            })
+                        }
+                        CachedDataItemValue::AggregatedDirtyContainer { value } => {
+                            compact_count(value)?.map(|value| {
+                                CachedDataItemValue::AggregatedDirtyContainer { value }
+                            })
+                        }
+                        _ => return None,
+                    };
+                    Some((key.clone(), value.clone(), new_value))
+                })
+                .collect::<Vec<_>>();
+            for (key, old_value, value) in updates {
+                if let Some(value) = &value {
+                    task.insert(CachedDataItem::from_key_and_value(
+                        key.clone(),
+                        value.clone(),
+                    ));
+                } else {
+                    task.remove(&key);
+                }
+                if self.should_persist_key(task_id, &key) {
+                    task.persistance_state_mut().add_persisting_item();
+                    self.push_persisted_storage_update(
+                        key.category(),
+                        CachedDataUpdate {
+                            key,
+                            task: task_id,
+                            value,
+                            old_value: Some(old_value),
+                        },
+                    );
+                    self.track_pending_log_items(1);
+                }
+                compacted += 1;
+            }
+        }
+        compacted
+    }
+
+    fn gc_statistics(&self) -> GcStatistics {
+        GcStatistics {
+            unloaded_tasks: self.unloaded_tasks_count.load(Ordering::Relaxed),
+            restored_tasks: self.restored_tasks_count.load(Ordering::Relaxed),
+            reloaded_tasks: self.reloaded_tasks_count.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cold]
+    fn disable_persistence(&self, failures: usize, err: &anyhow::Error) {
+        if self.persistence_disabled.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        tracing::warn!(
+            "Persisting the cache failed {failures} times in a row, Persistent Caching is \
+             disabled for the rest of this session: {err:#}"
+        );
+        self.task_events.emit(|| TaskEvent::PersistenceDisabled {
+            failures,
+            error: format!("{err:#}"),
+        });
+        // Free everything that has been collected since the last failed snapshot.
+        for logs in &self.persisted_logs {
+            drop(logs.storage_meta.take());
+            drop(logs.storage_data.take());
+            drop(logs.task_cache.take());
+            drop(logs.task_cache_removals.take());
+        }
+        self.pending_log_items.store(0, Ordering::Relaxed);
+    }
+
     fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>) {
         // Continue all uncompleted operations
         // They can't be interrupted by a snapshot since the snapshotting job has not been scheduled
         // yet.
         let uncompleted_operations = self.backing_storage.uncompleted_operations();
         if !uncompleted_operations.is_empty() {
-            let mut ctx = self.execute_context(turbo_tasks);
-            for op in uncompleted_operations {
-                op.execute(&mut ctx);
+            let total_operations = uncompleted_operations.len();
+            let mut failed_operations = 0;
+            let emit_progress = |replayed_operations, failed_operations| {
+                self.task_events.emit(|| TaskEvent::StartupProgress {
+                    replayed_operations,
+                    total_operations,
+                    failed_operations,
+                    restored_tasks: self.restored_tasks_count.load(Ordering::Relaxed),
+                });
+            };
+            emit_progress(0, 0);
+            for (i, op) in uncompleted_operations.into_iter().enumerate() {
+                // A broken operation must not prevent the backend from starting. It's dropped
+                // and the tasks it would have modified are recomputed instead.
+                let mut task_ids = FxHashSet::default();
+                op.collect_task_ids(&mut task_ids);
+                let result = catch_unwind(AssertUnwindSafe(|| {
+                    op.execute(&mut self.execute_context(turbo_tasks));
+                }));
+                if let Err(err) = result {
+                    failed_operations += 1;
+                    let message = err
+                        .downcast_ref::<String>()
+                        .map(|s| s.as_str())
+                        .or_else(|| err.downcast_ref::<&'static str>().copied())
+                        .unwrap_or("unknown panic");
+                    println!(
+                        "Replaying an uncompleted operation failed, invalidating {} affected \
+                         tasks: {message}",
+                        task_ids.len()
+                    );
+                    let _ = catch_unwind(AssertUnwindSafe(|| {
+                        operation::InvalidateOperation::run(
+                            task_ids.into_iter().collect(),
+                            TaskDirtyCause::OperationReplayFailed,
+                            self.execute_context(turbo_tasks),
+                        );
+                    }));
+                }
+                emit_progress(i + 1, failed_operations);
             }
         }
 
+        // Session dependent tasks are dirty in every new session. Invalidating them right away
+        // schedules their recomputation, instead of waiting until they are read.
+        let session_dependent_tasks = self.backing_storage.session_dependent_tasks();
+        if !session_dependent_tasks.is_empty() {
+            operation::InvalidateOperation::run(
+                session_dependent_tasks.into_iter().collect(),
+                TaskDirtyCause::SessionStart,
+                self.execute_context(turbo_tasks),
+            );
+        }
+
         // Schedule the snapshot job
-        turbo_tasks.schedule_backend_background_job(BACKEND_JOB_INITIAL_SNAPSHOT);
+        if !self.options.read_only {
+            turbo_tasks.schedule_backend_background_job(BACKEND_JOB_INITIAL_SNAPSHOT);
+        }
+
+        if self.options.hanging_detection_timeout.is_some() {
+            turbo_tasks.schedule_backend_background_job(BACKEND_JOB_HANGING_DETECTION);
+        }
     }
 
     fn stopping(&self) {
-        self.stopping.store(true, Ordering::Release);
+        if !self.stopping.swap(true, Ordering::AcqRel) {
+            if let Some(profiler) = &self.cache_profiler {
+                println!("Cache effectiveness:\n{}", profiler.format_summary());
+            }
+            self.lifecycle_events.emit(LifecycleEvent::Stopping);
+        }
         self.stopping_event.notify(usize::MAX);
     }
 
     fn idle_start(&self) {
         self.idle_start_event.notify(usize::MAX);
+        self.lifecycle_events.emit(LifecycleEvent::IdleStart);
     }
 
     fn idle_end(&self) {
         self.idle_end_event.notify(usize::MAX);
+        self.lifecycle_events.emit(LifecycleEvent::IdleEnd);
     }
 
     fn get_or_create_persistent_task(
@@ -700,38 +2456,70 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             return task_id;
         }
 
-        let tx = self.backing_storage.start_read_transaction();
-        let task_id = {
+        let owned_tx;
+        let retry_tx;
+        // Safety: The transaction is only used during this call.
+        let turn_tx = unsafe { self.turn_transaction() };
+        let mut tx = match turn_tx {
+            Some(tx) => Some(tx),
+            None => {
+                owned_tx = self.backing_storage.start_read_transaction();
+                owned_tx.as_ref()
+            }
+        };
+        // Safety: `tx` is a valid transaction from `self.backend.backing_storage`.
+        let mut persisted_task_id = unsafe {
+            self.backing_storage
+                .forward_lookup_task_cache(tx, &task_type)
+        };
+        if persisted_task_id.is_none() && turn_tx.is_some() && self.is_turn_transaction_outdated() {
+            // The entry might have been persisted and evicted from memory after the turn
+            // transaction was started. Creating a new task id would duplicate the task.
+            retry_tx = self.backing_storage.start_read_transaction();
+            tx = retry_tx.as_ref();
             // Safety: `tx` is a valid transaction from `self.backend.backing_storage`.
-            if let Some(task_id) = unsafe {
+            persisted_task_id = unsafe {
                 self.backing_storage
-                    .forward_lookup_task_cache(tx.as_ref(), &task_type)
-            } {
-                let _ = self.task_cache.try_insert(Arc::new(task_type), task_id);
-                task_id
+                    .forward_lookup_task_cache(tx, &task_type)
+            };
+        }
+        let task_id = if let Some(task_id) = persisted_task_id {
+            let task_type = Arc::new(task_type);
+            let _ = self.task_cache.try_insert(task_type.clone(), task_id);
+            self.task_cache_limit
+                .fetched(&self.task_cache, task_id, |task_id| {
+                    !self.is_non_persistable(task_id)
+                });
+            task_id
+        } else {
+            let task_type = Arc::new(task_type);
+            let task_id = self.persisted_task_id_factory.get();
+            let task_id = if let Err(existing_task_id) =
+                self.task_cache.try_insert(task_type.clone(), task_id)
+            {
+                // Safety: We just created the id and failed to insert it.
+                unsafe {
+                    self.persisted_task_id_factory.reuse(task_id);
+                }
+                existing_task_id
             } else {
-                let task_type = Arc::new(task_type);
-                let task_id = self.persisted_task_id_factory.get();
-                let task_id = if let Err(existing_task_id) =
-                    self.task_cache.try_insert(task_type.clone(), task_id)
-                {
-                    // Safety: We just created the id and failed to insert it.
-                    unsafe {
-                        self.persisted_task_id_factory.reuse(task_id);
-                    }
-                    existing_task_id
-                } else {
-                    task_id
-                };
-                self.persisted_task_cache_log
-                    .lock(task_id)
-                    .push((task_type, task_id));
+                if let CachedTaskType::Native { fn_type, .. } = &*task_type {
+                    self.task_statistics
+                        .entry(*fn_type)
+                        .or_default()
+                        .created_persistent_tasks += 1;
+                }
                 task_id
+            };
+            if !self.persistence_disabled.load(Ordering::Relaxed) {
+                self.push_persisted_log(|logs| &logs.task_cache, task_id, (task_type, task_id));
+                self.track_pending_log_items(1);
             }
+            task_id
         };
 
         // Safety: `tx` is a valid transaction from `self.backend.backing_storage`.
-        unsafe { self.connect_child_with_tx(tx.as_ref(), parent_task, task_id, turbo_tasks) };
+        unsafe { self.connect_child_with_tx(tx, parent_task, task_id, turbo_tasks) };
 
         task_id
     }
@@ -742,10 +2530,11 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         parent_task: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> TaskId {
-        if !parent_task.is_transient() {
+        if !parent_task.is_transient() && !self.is_non_persistable(parent_task) {
             let parent_task_type = self.lookup_task_type(parent_task);
             panic!(
-                "Calling transient function {} from persistent function function {} is not allowed",
+                "Calling transient function {} from persistent function function {} is not \
+                 allowed, unless the caller opts in via turbo_tasks::mark_non_persistable()",
                 task_type.get_name(),
                 parent_task_type.map_or_else(|| "unknown".into(), |t| t.get_name())
             );
@@ -761,7 +2550,8 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         if let Err(existing_task_id) = self.task_cache.try_insert(task_type, task_id) {
             // Safety: We just created the id and failed to insert it.
             unsafe {
-                self.transient_task_id_factory.reuse(task_id);
+                self.transient_task_id_factory
+                    .reuse(task_id.next_generation());
             }
             // Safety: `tx` is a valid transaction from `self.backend.backing_storage`.
             self.connect_child(parent_task, existing_task_id, turbo_tasks);
@@ -777,15 +2567,44 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     fn invalidate_task(
         &self,
         task_id: TaskId,
+        cause: TaskDirtyCause,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        if self.options.invalidation_batch_window.is_some()
+            && !self.stopping.load(Ordering::Acquire)
+        {
+            let mut pending_invalidations = self.pending_invalidations.lock();
+            pending_invalidations.push((task_id, cause));
+            if pending_invalidations.len() == 1 {
+                turbo_tasks.schedule_backend_background_job(BACKEND_JOB_INVALIDATION_BATCH);
+            }
+            return;
+        }
         operation::InvalidateOperation::run(
             smallvec![task_id],
-            TaskDirtyCause::Unknown,
+            cause,
             self.execute_context(turbo_tasks),
         );
     }
 
+    /// Marks the tasks that are waiting for the [`BackendOptions::invalidation_batch_window`] as
+    /// dirty right away.
+    fn apply_pending_invalidations(
+        &self,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if self.options.invalidation_batch_window.is_none() {
+            return;
+        }
+        let tasks = take(&mut *self.pending_invalidations.lock());
+        if !tasks.is_empty() {
+            operation::InvalidateOperation::run_with_causes(
+                tasks.into_iter().collect(),
+                self.execute_context(turbo_tasks),
+            );
+        }
+    }
+
     fn invalidate_tasks(
         &self,
         tasks: &[TaskId],
@@ -838,6 +2657,86 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             })
     }
 
+    /// Reads the output of a finished, clean task through shared storage locks instead of write
+    /// locks, so concurrent readers of the same task don't serialize. This isn't lock-free: the
+    /// shard locks are still taken for reading, once for the task and, for tracked reads, once
+    /// more for the reader and for the version check. This only works when the read doesn't need
+    /// to change anything: the read is untracked, the task is immutable or the reader is already
+    /// registered as dependent. Returns `None` when the read has to take the regular path.
+    ///
+    /// The task and the reader are read one after another, so the version of the task is compared
+    /// afterwards to make sure it didn't change in between.
+    fn try_read_clean_task_output(&self, task_id: TaskId, reader: Option<TaskId>) -> Option<RawVc> {
+        let reader = reader.filter(|_| !self.is_immutable_task(task_id));
+        let (output, version) = {
+            let task = self.storage.access(&task_id)?;
+            if !task.persistance_state().is_restored(TaskDataCategory::All)
+                || get!(task, InProgress).is_some()
+                || get!(task, Dirty).is_some_and(|dirty_state| dirty_state.get(self.session_id))
+                // The first read in a session is counted as hit, which needs a write lock
+                || get!(task, Usage).map_or(true, |usage| usage.last_session != self.session_id)
+            {
+                return None;
+            }
+            let output = match get!(task, Output)? {
+                OutputValue::Cell(cell) => RawVc::TaskCell(cell.task, cell.cell),
+                OutputValue::Output(task) => RawVc::TaskOutput(*task),
+                OutputValue::Error | OutputValue::Panic => return None,
+            };
+            if let Some(reader) = reader {
+                let is_dependent = if self.weak_readers.contains(&reader) {
+                    task.has_key(&CachedDataItemKey::WeakOutputDependent { task: reader })
+                } else {
+                    task.has_key(&CachedDataItemKey::OutputDependent { task: reader })
+                };
+                if !is_dependent {
+                    return None;
+                }
+            }
+            // Keeps hot tasks from being unloaded by the garbage collection
+            task.persistance_state()
+                .mark_accessed(self.storage.generation());
+            (output, task.version())
+        };
+        if let Some(reader) = reader {
+            let reader_task = self.storage.access(&reader)?;
+            if !reader_task
+                .persistance_state()
+                .is_restored(TaskDataCategory::Data)
+                || !reader_task.has_key(&CachedDataItemKey::OutputDependency { target: task_id })
+                || reader_task
+                    .has_key(&CachedDataItemKey::OutdatedOutputDependency { target: task_id })
+            {
+                return None;
+            }
+            drop(reader_task);
+            if self.storage.access(&task_id)?.version() != version {
+                return None;
+            }
+        }
+        if let Some(profiler) = &self.cache_profiler {
+            if let Some(fn_type) = self.try_get_function_id(task_id) {
+                profiler.hit(task_id, fn_type);
+            }
+        }
+        Some(output)
+    }
+
+    fn is_once_task(&self, task_id: TaskId) -> bool {
+        task_id.is_transient()
+            && self
+                .transient_tasks
+                .get(&task_id)
+                .is_some_and(|task| matches!(**task, TransientTask::Once(_)))
+    }
+
+    /// Returns true when the task is a call of a function that is declared as `immutable`.
+    fn is_immutable_task(&self, task_id: TaskId) -> bool {
+        self.try_get_function_id(task_id).map_or(false, |fn_type| {
+            registry::get_function(fn_type).function_meta.immutable
+        })
+    }
+
     fn try_start_task_execution(
         &self,
         task_id: TaskId,
@@ -871,8 +2770,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     once_task,
                     done_event,
                     session_dependent: false,
+                    cancelled: false,
                 },
             });
+            self.task_events.emit(|| TaskEvent::Started { task_id });
+            if let Some(tracker) = &self.dependency_tracker {
+                tracker.execution_started(task_id);
+            }
 
             // Make all current children outdated (remove left-over outdated children)
             enum Child {
@@ -990,6 +2894,37 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             }
         }
 
+        if let Some(profiler) = &self.cache_profiler {
+            if let Some(fn_type) = self.try_get_function_id(task_id) {
+                let mut ctx = self.execute_context(turbo_tasks);
+                let task = ctx.task(task_id, TaskDataCategory::All);
+                let reason = if !task.has_key(&CachedDataItemKey::Output {}) {
+                    RecomputeReason::NewTask
+                } else {
+                    match get!(task, Dirty) {
+                        Some(DirtyState {
+                            clean_in_session: Some(_),
+                        }) => RecomputeReason::SessionDependent,
+                        Some(_) if profiler.was_invalidated(task_id) => {
+                            RecomputeReason::DependencyDirty
+                        }
+                        // Dirty state or missing cells that were restored from the backing storage
+                        _ => RecomputeReason::RestoredInvalid,
+                    }
+                };
+                drop(task);
+                profiler.recompute(task_id, fn_type, reason);
+            }
+        }
+
+        let execution_timeout = match &task_type {
+            TaskType::Cached(task_type) => self
+                .options
+                .execution_timeout
+                .as_ref()
+                .and_then(|execution_timeout| execution_timeout.for_task(task_type)),
+            TaskType::Transient(_) => None,
+        };
         let (span, future) = match task_type {
             TaskType::Cached(task_type) => match &*task_type {
                 CachedTaskType::Native { fn_type, this, arg } => (
@@ -1059,6 +2994,26 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 (span, future)
             }
         };
+        let future = if let Some(timeout) = execution_timeout {
+            let strict = self
+                .options
+                .execution_timeout
+                .as_ref()
+                .is_some_and(|execution_timeout| execution_timeout.strict);
+            Box::pin(WithExecutionTimeout {
+                future,
+                task_id,
+                timeout,
+                strict,
+                description: self.get_task_desc_fn(task_id),
+                task_events: &self.task_events,
+                polling_time: Duration::ZERO,
+                reported: false,
+            }) as Pin<Box<dyn Future<Output = _> + Send + '_>>
+        } else {
+            future
+        };
+        let future = Box::pin(WithTurnTransaction::new(self, future));
         Some(TaskExecutionSpec { future, span })
     }
 
@@ -1068,29 +3023,138 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        let panicked = result.is_err();
         operation::UpdateOutputOperation::run(task_id, result, self.execute_context(turbo_tasks));
+        self.update_panic_retry(task_id, panicked, turbo_tasks);
+    }
+
+    fn update_panic_retry(
+        &self,
+        task_id: TaskId,
+        panicked: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        // Without a policy there are no retry counts to update
+        let Some(policy) = &self.options.panic_retry_policy else {
+            return;
+        };
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut task = ctx.task(task_id, TaskDataCategory::Data);
+        if !panicked {
+            task.remove(&CachedDataItemKey::PanicRetryCount {});
+            return;
+        }
+        let retry = get!(task, PanicRetryCount).copied().unwrap_or_default();
+        if retry >= policy.max_retries {
+            return;
+        }
+        task.insert(CachedDataItem::PanicRetryCount { value: retry + 1 });
+        drop(task);
+
+        let backoff = policy.backoff(retry);
+        let turbo_tasks = turbo_tasks.pin();
+        tokio::spawn(async move {
+            tokio::time::sleep(backoff).await;
+            turbo_tasks
+                .backend()
+                .invalidate_task(task_id, &*turbo_tasks);
+        });
+    }
+
+    fn track_task_execution(&self, task_id: TaskId, duration: Duration, memory_usage: usize) {
+        // Resolve tasks execute the native function as separate task, so only native tasks are
+        // counted to avoid attributing the time twice.
+        let Some(task_type) = self.task_cache.lookup_reverse(&task_id) else {
+            return;
+        };
+        if let CachedTaskType::Native { fn_type, .. } = &*task_type {
+            self.task_statistics
+                .entry(*fn_type)
+                .or_default()
+                .record(duration, memory_usage);
+        }
+    }
+
+    fn task_statistics(&self) -> Vec<(FunctionId, TaskExecutionStatistics)> {
+        self.task_statistics
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
     }
 
     fn task_execution_completed(
         &self,
         task_id: TaskId,
-        _duration: Duration,
-        _memory_usage: usize,
+        duration: Duration,
+        memory_usage: usize,
         cell_counters: &AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>,
         stateful: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> bool {
+        self.track_task_execution(task_id, duration, memory_usage);
+        self.waiting_tasks.stop_waiting(task_id);
+        self.weak_readers.remove(&task_id);
+        self.task_events
+            .emit(|| TaskEvent::Finished { task_id, duration });
+
         let mut ctx = self.execute_context(turbo_tasks);
         let mut task = ctx.task(task_id, TaskDataCategory::All);
         let Some(in_progress) = get!(task, InProgress) else {
             panic!("Task execution completed, but task is not in progress: {task:#?}");
         };
-        let &InProgressState::InProgress { stale, .. } = in_progress else {
+        let &InProgressState::InProgress {
+            stale, cancelled, ..
+        } = in_progress
+        else {
             panic!("Task execution completed, but task is not in progress: {task:#?}");
         };
+        if let Some(history) = &self.execution_history {
+            let outcome = if cancelled {
+                ExecutionOutcome::Cancelled
+            } else if stale {
+                ExecutionOutcome::Stale
+            } else {
+                match get!(task, Output) {
+                    Some(OutputValue::Error) => ExecutionOutcome::Error,
+                    Some(OutputValue::Panic) => ExecutionOutcome::Panic,
+                    _ => ExecutionOutcome::Output,
+                }
+            };
+            history.record(
+                task_id,
+                TaskExecution {
+                    finished_at: SystemTime::now(),
+                    duration,
+                    outcome,
+                    dirty_cause: get!(task, DirtyCause).cloned(),
+                },
+            );
+        }
 
-        // If the task is stale, reschedule it
-        if stale {
+        // If the task was cancelled, discard the execution. The task keeps its previous output and
+        // edges. It's still dirty and might be waited for, e.g. by a strongly consistent read, so
+        // it's rescheduled like a stale task.
+        if cancelled {
+            // The edges of the previous execution are still there, so only the outdated markers
+            // need to be removed. The next execution marks them outdated again.
+            let outdated_markers = task
+                .iter_all()
+                .filter_map(|(key, _)| match key {
+                    CachedDataItemKey::OutdatedChild { .. }
+                    | CachedDataItemKey::OutdatedCollectible { .. }
+                    | CachedDataItemKey::OutdatedCellDependency { .. }
+                    | CachedDataItemKey::OutdatedOutputDependency { .. }
+                    | CachedDataItemKey::OutdatedCollectiblesDependency { .. } => Some(key.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            for key in outdated_markers {
+                task.remove(&key);
+            }
+        }
+
+        // If the task is stale or cancelled, reschedule it
+        if stale || cancelled {
             let Some(InProgressState::InProgress { done_event, .. }) = remove!(task, InProgress)
             else {
                 unreachable!();
@@ -1133,14 +3197,15 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         let mut removed_data = Vec::new();
         for (&cell_type, range) in removed_cells.iter() {
             for index in range.clone() {
+                let cell = CellId {
+                    type_id: cell_type,
+                    index,
+                };
+                // Cells that are not in memory are restored first, so their removal is persisted
+                task.restore_cell(cell);
                 removed_data.extend(
-                    task.remove(&CachedDataItemKey::CellData {
-                        cell: CellId {
-                            type_id: cell_type,
-                            index,
-                        },
-                    })
-                    .into_iter(),
+                    task.remove(&CachedDataItemKey::CellData { cell })
+                        .into_iter(),
                 );
             }
         }
@@ -1167,6 +3232,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                     task.iter(CachedDataItemIndex::CellDependent).filter_map(
                         |(key, _)| match *key {
                             CachedDataItemKey::CellDependent { cell, task }
+                            | CachedDataItemKey::WeakCellDependent { cell, task }
                                 if removed_cells
                                     .get(&cell.type_id)
                                     .map_or(false, |range| range.contains(&cell.index)) =>
@@ -1198,6 +3264,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         Some(OutdatedEdge::CollectiblesDependency(target))
                     }
                     CachedDataItemKey::CellDependent { cell, task }
+                    | CachedDataItemKey::WeakCellDependent { cell, task }
                         if removed_cells
                             .get(&cell.type_id)
                             .map_or(false, |range| range.contains(&cell.index)) =>
@@ -1208,12 +3275,28 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 })
                 .collect::<Vec<_>>()
         };
+        if let Some(tracker) = &self.dependency_tracker {
+            let removed = old_edges
+                .iter()
+                .filter_map(|edge| match *edge {
+                    OutdatedEdge::OutputDependency(target) => Some(TaskDependency::Output(target)),
+                    OutdatedEdge::CellDependency(target) => {
+                        Some(TaskDependency::Cell(target.task, target.cell))
+                    }
+                    OutdatedEdge::CollectiblesDependency(target) => Some(
+                        TaskDependency::Collectibles(target.task, target.collectible_type),
+                    ),
+                    _ => None,
+                })
+                .collect();
+            tracker.execution_completed(task_id, get!(task, DirtyCause).cloned(), removed);
+        }
         drop(task);
 
         // Remove outdated edges first, before removing in_progress+dirty flag.
         // We need to make sure all outdated edges are removed before the task can potentially be
         // scheduled and executed again
-        CleanupOldEdgesOperation::run(task_id, old_edges, &mut ctx);
+        let cleanup_queue = CleanupOldEdgesOperation::run(task_id, old_edges, &mut ctx);
 
         // When restoring from persistent caching the following might not be executed (since we can
         // suspend in `CleanupOldEdgesOperation`), but that's ok as the task is still dirty and
@@ -1228,6 +3311,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             once_task: _,
             stale: _,
             session_dependent,
+            cancelled: _,
         } = in_progress
         else {
             panic!("Task execution completed, but task is not in progress: {task:#?}");
@@ -1238,9 +3322,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             task.add_new(CachedDataItem::InProgress {
                 value: InProgressState::Scheduled { done_event },
             });
+            drop(task);
+            cleanup_queue.execute(&mut ctx);
             return true;
         }
 
+        self.record_task_execution(&mut task);
+
         // Update the dirty state
         let new_dirty_state = if session_dependent {
             Some(DirtyState {
@@ -1257,6 +3345,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         } else {
             task.remove(&CachedDataItemKey::Dirty {})
         };
+        task.remove(&CachedDataItemKey::DirtyCause {});
 
         let old_dirty_state = old_dirty.map(|old_dirty| match old_dirty {
             CachedDataItemValue::Dirty { value } => value,
@@ -1300,6 +3389,8 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
         done_event.notify(usize::MAX);
 
+        // The uppers lose the removed children and collectibles before they see the task as clean
+        cleanup_queue.execute(&mut ctx);
         if let Some(data_update) = data_update {
             AggregationUpdateQueue::run(data_update, &mut ctx);
         }
@@ -1319,70 +3410,103 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 let last_snapshot = self.last_snapshot.load(Ordering::Relaxed);
                 let mut last_snapshot = self.start_time + Duration::from_millis(last_snapshot);
                 loop {
-                    const FIRST_SNAPSHOT_WAIT: Duration = Duration::from_secs(30);
-                    const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(15);
-                    const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
-
-                    let time = if id == BACKEND_JOB_INITIAL_SNAPSHOT {
-                        FIRST_SNAPSHOT_WAIT
-                    } else {
-                        SNAPSHOT_INTERVAL
-                    };
-
-                    let until = last_snapshot + time;
-                    if until > Instant::now() {
-                        let mut stop_listener = self.stopping_event.listen();
-                        if !self.stopping.load(Ordering::Acquire) {
-                            let mut idle_start_listener = self.idle_start_event.listen();
-                            let mut idle_end_listener = self.idle_end_event.listen();
-                            let mut idle_time = if turbo_tasks.is_idle() {
-                                Instant::now() + IDLE_TIMEOUT
-                            } else {
-                                far_future()
+                    let mut stop_listener = self.stopping_event.listen();
+                    if !self.stopping.load(Ordering::Acquire) {
+                        let mut idle_start_listener = self.idle_start_event.listen();
+                        let mut idle_end_listener = self.idle_end_event.listen();
+                        let mut idle_since = turbo_tasks.is_idle().then(Instant::now);
+                        let mut log_pressure_listener = self.log_pressure_event.listen();
+                        loop {
+                            if self.log_pressure() {
+                                break;
+                            }
+                            let now = Instant::now();
+                            let state = SnapshotPolicyState {
+                                first_snapshot: id == BACKEND_JOB_INITIAL_SNAPSHOT,
+                                since_last_snapshot: now.duration_since(last_snapshot),
+                                pending_log_items: self.pending_log_items(),
+                                idle_for: idle_since
+                                    .filter(|_| turbo_tasks.is_idle())
+                                    .map(|idle_since| now.duration_since(idle_since)),
+                            };
+                            let until = match self.options.snapshot_policy.next_snapshot(&state) {
+                                SnapshotDecision::Now => break,
+                                SnapshotDecision::After(duration) => now + duration,
+                                SnapshotDecision::OnIdleChange => far_future(),
                             };
-                            loop {
-                                tokio::select! {
-                                    _ = &mut stop_listener => {
-                                        break;
-                                    },
-                                    _ = &mut idle_start_listener => {
-                                        idle_time = Instant::now() + IDLE_TIMEOUT;
-                                        idle_start_listener = self.idle_start_event.listen()
-                                    },
-                                    _ = &mut idle_end_listener => {
-                                        idle_time = until + IDLE_TIMEOUT;
-                                        idle_end_listener = self.idle_end_event.listen()
-                                    },
-                                    _ = tokio::time::sleep_until(until) => {
-                                        break;
-                                    },
-                                    _ = tokio::time::sleep_until(idle_time) => {
-                                        if turbo_tasks.is_idle() {
-                                            break;
-                                        }
-                                    },
-                                }
+                            tokio::select! {
+                                _ = &mut stop_listener => {
+                                    break;
+                                },
+                                _ = &mut log_pressure_listener => {
+                                    log_pressure_listener = self.log_pressure_event.listen()
+                                },
+                                _ = &mut idle_start_listener => {
+                                    idle_since = Some(Instant::now());
+                                    idle_start_listener = self.idle_start_event.listen()
+                                },
+                                _ = &mut idle_end_listener => {
+                                    idle_since = None;
+                                    idle_end_listener = self.idle_end_event.listen()
+                                },
+                                _ = tokio::time::sleep_until(until) => {},
                             }
                         }
                     }
 
                     let this = self.clone();
+                    let snapshot_attempt = Instant::now();
                     let snapshot = turbo_tasks::spawn_blocking(move || this.snapshot()).await;
-                    if let Some((snapshot_start, new_data)) = snapshot {
-                        last_snapshot = snapshot_start;
-                        if new_data {
-                            continue;
-                        }
-                        let last_snapshot = last_snapshot.duration_since(self.start_time);
-                        self.last_snapshot.store(
-                            last_snapshot.as_millis().try_into().unwrap(),
-                            Ordering::Relaxed,
-                        );
+                    let Some((snapshot_start, new_data)) = snapshot else {
+                        // Wait for the next interval before retrying
+                        last_snapshot = snapshot_attempt;
+                        continue;
+                    };
+                    last_snapshot = snapshot_start;
+                    if self.options.memory_limit.is_some() {
+                        let this = self.clone();
+                        turbo_tasks::spawn_blocking(move || this.run_gc()).await;
+                    }
+                    if new_data {
+                        continue;
+                    }
+                    let last_snapshot = last_snapshot.duration_since(self.start_time);
+                    self.last_snapshot.store(
+                        last_snapshot.as_millis().try_into().unwrap(),
+                        Ordering::Relaxed,
+                    );
 
-                        turbo_tasks.schedule_backend_background_job(BACKEND_JOB_FOLLOW_UP_SNAPSHOT);
-                        return;
+                    turbo_tasks.schedule_backend_background_job(BACKEND_JOB_FOLLOW_UP_SNAPSHOT);
+                    return;
+                }
+            } else if id == BACKEND_JOB_HANGING_DETECTION {
+                let Some(timeout) = self.options.hanging_detection_timeout else {
+                    return;
+                };
+                let mut stop_listener = self.stopping_event.listen();
+                while !self.stopping.load(Ordering::Acquire) {
+                    tokio::select! {
+                        _ = &mut stop_listener => {
+                            return;
+                        },
+                        _ = tokio::time::sleep(timeout / 2) => {},
+                    }
+                    for chain in self.detect_hanging_tasks(timeout, turbo_tasks) {
+                        tracing::warn!("{chain}");
+                        self.task_events.emit(|| TaskEvent::Hanging { chain });
                     }
                 }
+            } else if id == BACKEND_JOB_INVALIDATION_BATCH {
+                if let Some(window) = self.options.invalidation_batch_window {
+                    let stop_listener = self.stopping_event.listen();
+                    if !self.stopping.load(Ordering::Acquire) {
+                        tokio::select! {
+                            _ = stop_listener => {},
+                            _ = tokio::time::sleep(window) => {},
+                        }
+                    }
+                }
+                self.apply_pending_invalidations(turbo_tasks);
             }
         })
     }
@@ -1394,7 +3518,8 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> Result<TypedCellContent> {
         let mut ctx = self.execute_context(turbo_tasks);
-        let task = ctx.task(task_id, TaskDataCategory::Data);
+        let mut task = ctx.task(task_id, TaskDataCategory::Data);
+        task.restore_cell(cell);
         if let Some(content) = get!(task, CellData { cell }) {
             Ok(CellContent(Some(content.1.clone())).into_typed(cell.type_id))
         } else {
@@ -1468,11 +3593,81 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             };
             if reader.add(CachedDataItem::CollectiblesDependency { target, value: () }) {
                 reader.remove(&CachedDataItemKey::OutdatedCollectiblesDependency { target });
+                if let Some(tracker) = &self.dependency_tracker {
+                    tracker.dependency_added(
+                        reader_id,
+                        TaskDependency::Collectibles(task_id, collectible_type),
+                    );
+                }
             }
         }
         collectibles
     }
 
+    fn read_task_collectibles_with_emitters(
+        &self,
+        task_id: TaskId,
+        collectible_type: TraitTypeId,
+        reader_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<EmittedCollectible> {
+        let mut remaining: FxHashSet<RawVc> = self
+            .read_task_collectibles(task_id, collectible_type, reader_id, turbo_tasks)
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(collectible, _)| collectible)
+            .collect();
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut emitted = Vec::new();
+        // The aggregated collectibles don't know their emitter, so find the emitting tasks by
+        // walking the children until all collectibles are attributed
+        let mut visited = FxHashSet::default();
+        visited.insert(task_id);
+        let mut stack = vec![task_id];
+        while let Some(current_id) = stack.pop() {
+            if remaining.is_empty() {
+                break;
+            }
+            let task = ctx.task(current_id, TaskDataCategory::All);
+            let own_collectibles: Vec<_> = iter_many!(
+                task,
+                Collectible {
+                    collectible
+                } count if collectible.collectible_type == collectible_type && *count > 0 => {
+                    RawVc::TaskCell(collectible.cell.task, collectible.cell.cell)
+                }
+            )
+            .filter(|collectible| remaining.remove(collectible))
+            .collect();
+            for child_id in iter_many!(task, Child { task } => *task) {
+                if visited.insert(child_id) {
+                    stack.push(child_id);
+                }
+            }
+            drop(task);
+            if !own_collectibles.is_empty() {
+                let emitter_description = ctx.get_task_description(current_id);
+                emitted.extend(own_collectibles.into_iter().map(|collectible| {
+                    EmittedCollectible {
+                        collectible,
+                        emitter: current_id,
+                        emitter_description: emitter_description.clone(),
+                    }
+                }));
+            }
+        }
+        // Fall back to the task owning the cell for collectibles that were not found
+        for collectible in remaining {
+            let emitter = collectible.get_task_id();
+            emitted.push(EmittedCollectible {
+                collectible,
+                emitter,
+                emitter_description: ctx.get_task_description(emitter),
+            });
+        }
+        emitted
+    }
+
     fn emit_collectible(
         &self,
         collectible_type: TraitTypeId,
@@ -1535,10 +3730,52 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             task_id,
             cell,
             content,
+            self.options.cell_content_cutoff,
             self.execute_context(turbo_tasks),
         );
     }
 
+    fn cancel_task(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut task = ctx.task(task_id, TaskDataCategory::Data);
+        if let Some(InProgressState::InProgress { cancelled, .. }) = get_mut!(task, InProgress) {
+            *cancelled = true;
+        }
+    }
+
+    fn is_own_task_cancelled(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> bool {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let task = ctx.task(task_id, TaskDataCategory::Data);
+        matches!(
+            get!(task, InProgress),
+            Some(InProgressState::InProgress {
+                cancelled: true,
+                ..
+            })
+        )
+    }
+
+    fn mark_own_task_as_weak_reader(&self, task: TaskId) {
+        self.weak_readers.insert(task);
+    }
+
+    fn mark_own_task_as_non_persistable(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        let mut ctx = self.execute_context(turbo_tasks);
+        self.mark_non_persistable(task, &mut ctx);
+    }
+
     fn mark_own_task_as_session_dependent(
         &self,
         task: TaskId,
@@ -1564,6 +3801,10 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     }
 
     fn create_transient_task(&self, task_type: TransientTaskType) -> TaskId {
+        assert!(
+            !self.shut_down.load(Ordering::Acquire),
+            "Root and once tasks can't be created after TurboTasksBackend::shutdown"
+        );
         let task_id = self.transient_task_id_factory.get();
         let root_type = match task_type {
             TransientTaskType::Root(_) => ActiveType::RootTask,
@@ -1636,7 +3877,23 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     }
 
     fn invalidate_task(&self, task_id: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
-        self.0.invalidate_task(task_id, turbo_tasks);
+        self.0
+            .invalidate_task(task_id, TaskDirtyCause::Unknown, turbo_tasks);
+    }
+
+    fn invalidate_task_with_reason(
+        &self,
+        task_id: TaskId,
+        reason: StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.invalidate_task(
+            task_id,
+            TaskDirtyCause::Invalidation {
+                reason: reason.to_string(),
+            },
+            turbo_tasks,
+        );
     }
 
     fn invalidate_tasks(&self, tasks: &[TaskId], turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
@@ -1675,7 +3932,11 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         task_id: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> Option<TaskExecutionSpec<'_>> {
-        self.0.try_start_task_execution(task_id, turbo_tasks)
+        let spec = self.0.try_start_task_execution(task_id, turbo_tasks);
+        if spec.is_none() {
+            self.0.execution_finished(task_id, turbo_tasks);
+        }
+        spec
     }
 
     fn task_execution_result(
@@ -1690,20 +3951,24 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     fn task_execution_completed(
         &self,
         task_id: TaskId,
-        _duration: Duration,
-        _memory_usage: usize,
+        duration: Duration,
+        memory_usage: usize,
         cell_counters: &AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>,
         stateful: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> bool {
-        self.0.task_execution_completed(
+        let schedule_again = self.0.task_execution_completed(
             task_id,
-            _duration,
-            _memory_usage,
+            duration,
+            memory_usage,
             cell_counters,
             stateful,
             turbo_tasks,
-        )
+        );
+        if !schedule_again {
+            self.0.execution_finished(task_id, turbo_tasks);
+        }
+        schedule_again
     }
 
     fn run_backend_job<'a>(
@@ -1776,6 +4041,17 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
             .read_task_collectibles(task_id, collectible_type, reader, turbo_tasks)
     }
 
+    fn read_task_collectibles_with_emitters(
+        &self,
+        task_id: TaskId,
+        collectible_type: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<EmittedCollectible> {
+        self.0
+            .read_task_collectibles_with_emitters(task_id, collectible_type, reader, turbo_tasks)
+    }
+
     fn emit_collectible(
         &self,
         collectible_type: TraitTypeId,
@@ -1817,6 +4093,34 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.mark_own_task_as_session_dependent(task, turbo_tasks);
     }
 
+    fn mark_own_task_as_weak_reader(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.mark_own_task_as_weak_reader(task);
+    }
+
+    fn mark_own_task_as_non_persistable(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.mark_own_task_as_non_persistable(task, turbo_tasks);
+    }
+
+    fn cancel_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
+        self.0.cancel_task(task, turbo_tasks);
+    }
+
+    fn is_own_task_cancelled(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> bool {
+        self.0.is_own_task_cancelled(task, turbo_tasks)
+    }
+
     fn connect_task(
         &self,
         task: TaskId,
@@ -1837,6 +4141,10 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     fn dispose_root_task(&self, _: TaskId, _: &dyn TurboTasksBackendApi<Self>) {
         // TODO implement
     }
+
+    fn task_statistics(&self) -> Vec<(FunctionId, TaskExecutionStatistics)> {
+        self.0.task_statistics()
+    }
 }
 
 // from https://github.com/tokio-rs/tokio/blob/29cd6ec1ec6f90a7ee1ad641c03e0e00badbcb0e/tokio/src/time/instant.rs#L57-L63
@@ -1847,3 +4155,26 @@ fn far_future() -> Instant {
     // 1000 years overflows on macOS, 100 years overflows on FreeBSD.
     Instant::now() + Duration::from_secs(86400 * 365 * 30)
 }
+
+/// Finds the cells in the data log whose serialized data is larger than `threshold` bytes.
+fn large_cells(
+    log: &[ChunkedVec<CachedDataUpdate>],
+    threshold: usize,
+) -> Vec<(TaskId, CellId, TypedSharedReference)> {
+    log.iter()
+        .flat_map(|shard| shard.iter())
+        .filter_map(|update| match update {
+            CachedDataUpdate {
+                task,
+                key: CachedDataItemKey::CellData { cell },
+                value: Some(CachedDataItemValue::CellData { value }),
+                ..
+            } => {
+                // Values that can't be serialized are not persisted and must stay in memory
+                let size = pot::to_vec(value).ok()?.len();
+                (size > threshold).then(|| (*task, *cell, value.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
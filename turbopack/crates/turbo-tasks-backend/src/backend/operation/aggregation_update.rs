@@ -16,8 +16,9 @@ use crate::{
             invalidate::{make_task_dirty, TaskDirtyCause},
             ExecuteContext, Operation, TaskGuard,
         },
+        operation_log::OperationLogEntry,
         storage::{get, get_many, iter_many, remove, update, update_count},
-        TaskDataCategory,
+        TaskDataCategory, TaskDependency,
     },
     data::{
         ActiveType, AggregationNumber, CachedDataItem, CachedDataItemKey, CollectibleRef,
@@ -105,6 +106,8 @@ pub enum AggregationUpdateJob {
         update: AggregatedDataUpdate,
     },
     InvalidateDueToCollectiblesChange {
+        /// The task whose aggregated collectibles have changed.
+        task_id: TaskId,
         task_ids: SmallVec<[TaskId; 4]>,
         collectible_type: TraitTypeId,
     },
@@ -115,6 +118,121 @@ pub enum AggregationUpdateJob {
 }
 
 impl AggregationUpdateJob {
+    /// The task whose change caused the job, used to describe the job in warnings.
+    fn cause(&self) -> Option<TaskId> {
+        match self {
+            AggregationUpdateJob::UpdateAggregationNumber { task_id, .. }
+            | AggregationUpdateJob::InvalidateDueToCollectiblesChange { task_id, .. }
+            | AggregationUpdateJob::BalanceEdge { task_id, .. } => Some(*task_id),
+            AggregationUpdateJob::InnerOfUpperHasNewFollower {
+                new_follower_id, ..
+            }
+            | AggregationUpdateJob::InnerOfUppersHasNewFollower {
+                new_follower_id, ..
+            } => Some(*new_follower_id),
+            AggregationUpdateJob::InnerOfUppersLostFollower {
+                lost_follower_id, ..
+            } => Some(*lost_follower_id),
+            AggregationUpdateJob::InnerOfUpperHasNewFollowers {
+                new_follower_ids, ..
+            }
+            | AggregationUpdateJob::InnerOfUppersHasNewFollowers {
+                new_follower_ids, ..
+            } => new_follower_ids.first().copied(),
+            AggregationUpdateJob::InnerOfUppersLostFollowers {
+                lost_follower_ids, ..
+            }
+            | AggregationUpdateJob::InnerOfUpperLostFollowers {
+                lost_follower_ids, ..
+            } => lost_follower_ids.first().copied(),
+            AggregationUpdateJob::AggregatedDataUpdate { upper_ids, update } => update
+                .dirty_container_update
+                .as_ref()
+                .map(|(task_id, _)| *task_id)
+                .or_else(|| upper_ids.first().copied()),
+        }
+    }
+
+    /// The number of upper tasks the job updates.
+    fn uppers(&self) -> usize {
+        match self {
+            AggregationUpdateJob::InnerOfUppersHasNewFollower { upper_ids, .. }
+            | AggregationUpdateJob::InnerOfUppersHasNewFollowers { upper_ids, .. }
+            | AggregationUpdateJob::InnerOfUppersLostFollower { upper_ids, .. }
+            | AggregationUpdateJob::InnerOfUppersLostFollowers { upper_ids, .. }
+            | AggregationUpdateJob::AggregatedDataUpdate { upper_ids, .. } => upper_ids.len(),
+            _ => 1,
+        }
+    }
+
+    fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            AggregationUpdateJob::UpdateAggregationNumber { task_id, .. } => {
+                task_ids.insert(*task_id);
+            }
+            AggregationUpdateJob::InnerOfUpperHasNewFollower {
+                upper_id,
+                new_follower_id,
+            } => {
+                task_ids.extend([*upper_id, *new_follower_id]);
+            }
+            AggregationUpdateJob::InnerOfUppersHasNewFollower {
+                upper_ids,
+                new_follower_id,
+            } => {
+                task_ids.extend(upper_ids.iter().copied());
+                task_ids.insert(*new_follower_id);
+            }
+            AggregationUpdateJob::InnerOfUpperHasNewFollowers {
+                upper_id,
+                new_follower_ids,
+            } => {
+                task_ids.insert(*upper_id);
+                task_ids.extend(new_follower_ids.iter().copied());
+            }
+            AggregationUpdateJob::InnerOfUppersHasNewFollowers {
+                upper_ids,
+                new_follower_ids,
+            } => {
+                task_ids.extend(upper_ids.iter().chain(new_follower_ids.iter()).copied());
+            }
+            AggregationUpdateJob::InnerOfUppersLostFollower {
+                upper_ids,
+                lost_follower_id,
+            } => {
+                task_ids.extend(upper_ids.iter().copied());
+                task_ids.insert(*lost_follower_id);
+            }
+            AggregationUpdateJob::InnerOfUppersLostFollowers {
+                upper_ids,
+                lost_follower_ids,
+            } => {
+                task_ids.extend(upper_ids.iter().chain(lost_follower_ids.iter()).copied());
+            }
+            AggregationUpdateJob::InnerOfUpperLostFollowers {
+                upper_id,
+                lost_follower_ids,
+            } => {
+                task_ids.insert(*upper_id);
+                task_ids.extend(lost_follower_ids.iter().copied());
+            }
+            AggregationUpdateJob::AggregatedDataUpdate { upper_ids, .. } => {
+                task_ids.extend(upper_ids.iter().copied());
+            }
+            AggregationUpdateJob::InvalidateDueToCollectiblesChange {
+                task_id,
+                task_ids: dependent_task_ids,
+                ..
+            } => {
+                task_ids.insert(*task_id);
+                task_ids.extend(dependent_task_ids.iter().copied());
+            }
+            AggregationUpdateJob::BalanceEdge { upper_id, task_id } => {
+                task_ids.extend([*upper_id, *task_id]);
+            }
+        }
+    }
+
     pub fn data_update(
         task: &mut impl TaskGuard,
         update: AggregatedDataUpdate,
@@ -289,6 +407,7 @@ impl AggregatedDataUpdate {
                 );
                 if !dependent.is_empty() {
                     queue.push(AggregationUpdateJob::InvalidateDueToCollectiblesChange {
+                        task_id: task.id(),
                         task_ids: dependent,
                         collectible_type: ty,
                     })
@@ -343,6 +462,9 @@ pub struct AggregationUpdateQueue {
     find_and_schedule: FxIndexSet<TaskId>,
     done_find_and_schedule: FxHashSet<TaskId>,
     balance_queue: FxIndexSet<(TaskId, TaskId)>,
+    /// The fan-out of the queue has been reported, so it's not reported again for every job.
+    #[serde(skip)]
+    fan_out_reported: bool,
 }
 
 impl AggregationUpdateQueue {
@@ -354,6 +476,7 @@ impl AggregationUpdateQueue {
             find_and_schedule: FxIndexSet::default(),
             done_find_and_schedule: FxHashSet::default(),
             balance_queue: FxIndexSet::default(),
+            fan_out_reported: false,
         }
     }
 
@@ -361,6 +484,18 @@ impl AggregationUpdateQueue {
         self.jobs.is_empty()
     }
 
+    /// Collects the tasks that the remaining jobs of the queue would modify.
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        for job in self.jobs.iter() {
+            job.collect_task_ids(task_ids);
+        }
+        task_ids.extend(self.number_updates.keys().copied());
+        task_ids.extend(self.find_and_schedule.iter().copied());
+        for &(upper_id, task_id) in self.balance_queue.iter() {
+            task_ids.extend([upper_id, task_id]);
+        }
+    }
+
     pub fn push(&mut self, job: AggregationUpdateJob) {
         match job {
             AggregationUpdateJob::UpdateAggregationNumber {
@@ -444,6 +579,13 @@ impl AggregationUpdateQueue {
 
     pub fn process(&mut self, ctx: &mut impl ExecuteContext) -> bool {
         if let Some(job) = self.jobs.pop_front() {
+            ctx.record_operation(|| OperationLogEntry::AggregationUpdateJob(job.clone()));
+            if !self.fan_out_reported {
+                if let Some(task_id) = job.cause() {
+                    self.fan_out_reported =
+                        ctx.report_aggregation_fan_out(task_id, job.uppers(), self.jobs.len());
+                }
+            }
             match job {
                 AggregationUpdateJob::UpdateAggregationNumber { .. }
                 | AggregationUpdateJob::BalanceEdge { .. } => {
@@ -562,9 +704,14 @@ impl AggregationUpdateQueue {
                     self.aggregated_data_update(upper_ids, ctx, update);
                 }
                 AggregationUpdateJob::InvalidateDueToCollectiblesChange {
+                    task_id,
                     task_ids,
                     collectible_type,
                 } => {
+                    ctx.dependency_changed(
+                        &task_ids,
+                        TaskDependency::Collectibles(task_id, collectible_type),
+                    );
                     for task_id in task_ids {
                         make_task_dirty(
                             task_id,
@@ -1283,6 +1430,7 @@ impl AggregationUpdateQueue {
 
 impl Operation for AggregationUpdateQueue {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        ctx.operation_started(&self);
         loop {
             ctx.operation_suspend_point(&self);
             if self.process(ctx) {
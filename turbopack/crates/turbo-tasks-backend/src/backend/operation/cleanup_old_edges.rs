@@ -1,5 +1,6 @@
 use std::mem::take;
 
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{TaskId, ValueTypeId};
 
@@ -19,6 +20,10 @@ use crate::{
     data::{CachedDataItemKey, CellRef, CollectibleRef, CollectiblesRef},
 };
 
+/// The maximum number of children or collectibles removed from the task at once. Tasks with many
+/// edges are cleaned up in multiple steps, so other operations can interleave.
+const MAX_EDGES_PER_STEP: usize = 1000;
+
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub enum CleanupOldEdgesOperation {
     RemoveEdges {
@@ -45,19 +50,200 @@ pub enum OutdatedEdge {
 }
 
 impl CleanupOldEdgesOperation {
-    pub fn run(task_id: TaskId, outdated: Vec<OutdatedEdge>, ctx: &mut impl ExecuteContext) {
-        let queue = AggregationUpdateQueue::new();
-        CleanupOldEdgesOperation::RemoveEdges {
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            CleanupOldEdgesOperation::RemoveEdges {
+                task_id,
+                outdated,
+                queue,
+            } => {
+                task_ids.insert(*task_id);
+                for edge in outdated {
+                    match edge {
+                        OutdatedEdge::Child(task)
+                        | OutdatedEdge::OutputDependency(task)
+                        | OutdatedEdge::CellDependency(CellRef { task, .. })
+                        | OutdatedEdge::CollectiblesDependency(CollectiblesRef { task, .. })
+                        | OutdatedEdge::RemovedCellDependent(task, _) => {
+                            task_ids.insert(*task);
+                        }
+                        OutdatedEdge::Collectible(..) => {}
+                    }
+                }
+                queue.collect_task_ids(task_ids);
+            }
+            CleanupOldEdgesOperation::AggregationUpdate { queue } => {
+                queue.collect_task_ids(task_ids)
+            }
+            CleanupOldEdgesOperation::Done => {}
+        }
+    }
+
+    /// Removes the outdated edges of a task. The aggregation updates caused by the removal are
+    /// returned instead of processed, so they can be processed after the task completion has been
+    /// notified.
+    pub fn run(
+        task_id: TaskId,
+        outdated: Vec<OutdatedEdge>,
+        ctx: &mut impl ExecuteContext,
+    ) -> AggregationUpdateQueue {
+        let mut op = CleanupOldEdgesOperation::RemoveEdges {
             task_id,
             outdated,
-            queue,
+            queue: AggregationUpdateQueue::new(),
+        };
+        ctx.operation_started(&op);
+        loop {
+            ctx.operation_suspend_point(&op);
+            let CleanupOldEdgesOperation::RemoveEdges {
+                task_id,
+                ref mut outdated,
+                ref mut queue,
+            } = op
+            else {
+                unreachable!();
+            };
+            let Some(edge) = outdated.pop() else {
+                return take(queue);
+            };
+            remove_edge(task_id, edge, outdated, queue, ctx);
+        }
+    }
+}
+
+/// Removes the edge and all edges of the same kind up to [`MAX_EDGES_PER_STEP`].
+fn remove_edge(
+    task_id: TaskId,
+    edge: OutdatedEdge,
+    outdated: &mut Vec<OutdatedEdge>,
+    queue: &mut AggregationUpdateQueue,
+    ctx: &mut impl ExecuteContext,
+) {
+    match edge {
+        OutdatedEdge::Child(child_id) => {
+            let mut children = Vec::new();
+            children.push(child_id);
+            outdated.retain(|e| match e {
+                OutdatedEdge::Child(id) if children.len() < MAX_EDGES_PER_STEP => {
+                    children.push(*id);
+                    false
+                }
+                _ => true,
+            });
+            let mut task = ctx.task(task_id, TaskDataCategory::All);
+            for &child_id in children.iter() {
+                task.remove(&CachedDataItemKey::Child { task: child_id });
+            }
+            let remove_children_count = u32::try_from(children.len()).unwrap();
+            update!(task, ChildrenCount, |count: Option<u32>| {
+                // If this underflows, we messed up counting somewhere
+                let count = count.unwrap_or_default() - remove_children_count;
+                (count != 0).then_some(count)
+            });
+            if is_aggregating_node(get_aggregation_number(&task)) {
+                queue.push(AggregationUpdateJob::InnerOfUpperLostFollowers {
+                    upper_id: task_id,
+                    lost_follower_ids: children,
+                });
+            } else {
+                let upper_ids = get_uppers(&task);
+                queue.push(AggregationUpdateJob::InnerOfUppersLostFollowers {
+                    upper_ids,
+                    lost_follower_ids: children,
+                });
+            }
+        }
+        OutdatedEdge::Collectible(collectible, count) => {
+            let mut collectibles = Vec::new();
+            collectibles.push((collectible, count));
+            outdated.retain(|e| match e {
+                OutdatedEdge::Collectible(collectible, count)
+                    if collectibles.len() < MAX_EDGES_PER_STEP =>
+                {
+                    collectibles.push((*collectible, -*count));
+                    false
+                }
+                _ => true,
+            });
+            let mut task = ctx.task(task_id, TaskDataCategory::All);
+            for &(collectible, count) in collectibles.iter() {
+                update_count!(task, Collectible { collectible }, -count);
+            }
+            queue.extend(AggregationUpdateJob::data_update(
+                &mut task,
+                AggregatedDataUpdate::new().collectibles_update(collectibles),
+            ));
+        }
+        OutdatedEdge::CellDependency(CellRef {
+            task: cell_task_id,
+            cell,
+        }) => {
+            {
+                let mut task = ctx.task(cell_task_id, TaskDataCategory::Data);
+                task.remove(&CachedDataItemKey::CellDependent {
+                    cell,
+                    task: task_id,
+                });
+                task.remove(&CachedDataItemKey::WeakCellDependent {
+                    cell,
+                    task: task_id,
+                });
+            }
+            {
+                let mut task = ctx.task(task_id, TaskDataCategory::Data);
+                task.remove(&CachedDataItemKey::CellDependency {
+                    target: CellRef {
+                        task: cell_task_id,
+                        cell,
+                    },
+                });
+            }
+        }
+        OutdatedEdge::OutputDependency(output_task_id) => {
+            // A task never depends on its own output, as reading it would wait for itself
+            let [mut task, mut output_task] =
+                ctx.tasks([task_id, output_task_id], TaskDataCategory::Data);
+            output_task.remove(&CachedDataItemKey::OutputDependent { task: task_id });
+            output_task.remove(&CachedDataItemKey::WeakOutputDependent { task: task_id });
+            task.remove(&CachedDataItemKey::OutputDependency {
+                target: output_task_id,
+            });
+        }
+        OutdatedEdge::CollectiblesDependency(CollectiblesRef {
+            collectible_type,
+            task: dependent_task_id,
+        }) => {
+            {
+                let mut task = ctx.task(dependent_task_id, TaskDataCategory::Data);
+                task.remove(&CachedDataItemKey::CollectiblesDependent {
+                    collectible_type,
+                    task: task_id,
+                });
+            }
+            {
+                let mut task = ctx.task(task_id, TaskDataCategory::Data);
+                task.remove(&CachedDataItemKey::CollectiblesDependency {
+                    target: CollectiblesRef {
+                        collectible_type,
+                        task: dependent_task_id,
+                    },
+                });
+            }
+        }
+        OutdatedEdge::RemovedCellDependent(task_id, value_type) => {
+            make_task_dirty(
+                task_id,
+                TaskDirtyCause::CellRemoved { value_type },
+                queue,
+                ctx,
+            );
         }
-        .execute(ctx);
     }
 }
 
 impl Operation for CleanupOldEdgesOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        ctx.operation_started(&self);
         loop {
             ctx.operation_suspend_point(&self);
             match self {
@@ -67,125 +253,7 @@ impl Operation for CleanupOldEdgesOperation {
                     ref mut queue,
                 } => {
                     if let Some(edge) = outdated.pop() {
-                        match edge {
-                            OutdatedEdge::Child(child_id) => {
-                                let mut children = Vec::new();
-                                children.push(child_id);
-                                outdated.retain(|e| match e {
-                                    OutdatedEdge::Child(id) => {
-                                        children.push(*id);
-                                        false
-                                    }
-                                    _ => true,
-                                });
-                                let mut task = ctx.task(task_id, TaskDataCategory::All);
-                                for &child_id in children.iter() {
-                                    task.remove(&CachedDataItemKey::Child { task: child_id });
-                                }
-                                let remove_children_count = u32::try_from(children.len()).unwrap();
-                                update!(task, ChildrenCount, |count: Option<u32>| {
-                                    // If this underflows, we messed up counting somewhere
-                                    let count = count.unwrap_or_default() - remove_children_count;
-                                    (count != 0).then_some(count)
-                                });
-                                if is_aggregating_node(get_aggregation_number(&task)) {
-                                    queue.push(AggregationUpdateJob::InnerOfUpperLostFollowers {
-                                        upper_id: task_id,
-                                        lost_follower_ids: children,
-                                    });
-                                } else {
-                                    let upper_ids = get_uppers(&task);
-                                    queue.push(AggregationUpdateJob::InnerOfUppersLostFollowers {
-                                        upper_ids,
-                                        lost_follower_ids: children,
-                                    });
-                                }
-                            }
-                            OutdatedEdge::Collectible(collectible, count) => {
-                                let mut collectibles = Vec::new();
-                                collectibles.push((collectible, count));
-                                outdated.retain(|e| match e {
-                                    OutdatedEdge::Collectible(collectible, count) => {
-                                        collectibles.push((*collectible, -*count));
-                                        false
-                                    }
-                                    _ => true,
-                                });
-                                let mut task = ctx.task(task_id, TaskDataCategory::All);
-                                for &(collectible, count) in collectibles.iter() {
-                                    update_count!(task, Collectible { collectible }, -count);
-                                }
-                                queue.extend(AggregationUpdateJob::data_update(
-                                    &mut task,
-                                    AggregatedDataUpdate::new().collectibles_update(collectibles),
-                                ));
-                            }
-                            OutdatedEdge::CellDependency(CellRef {
-                                task: cell_task_id,
-                                cell,
-                            }) => {
-                                {
-                                    let mut task = ctx.task(cell_task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::CellDependent {
-                                        cell,
-                                        task: task_id,
-                                    });
-                                }
-                                {
-                                    let mut task = ctx.task(task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::CellDependency {
-                                        target: CellRef {
-                                            task: cell_task_id,
-                                            cell,
-                                        },
-                                    });
-                                }
-                            }
-                            OutdatedEdge::OutputDependency(output_task_id) => {
-                                {
-                                    let mut task = ctx.task(output_task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::OutputDependent {
-                                        task: task_id,
-                                    });
-                                }
-                                {
-                                    let mut task = ctx.task(task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::OutputDependency {
-                                        target: output_task_id,
-                                    });
-                                }
-                            }
-                            OutdatedEdge::CollectiblesDependency(CollectiblesRef {
-                                collectible_type,
-                                task: dependent_task_id,
-                            }) => {
-                                {
-                                    let mut task =
-                                        ctx.task(dependent_task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::CollectiblesDependent {
-                                        collectible_type,
-                                        task: task_id,
-                                    });
-                                }
-                                {
-                                    let mut task = ctx.task(task_id, TaskDataCategory::Data);
-                                    task.remove(&CachedDataItemKey::CollectiblesDependency {
-                                        target: CollectiblesRef {
-                                            collectible_type,
-                                            task: dependent_task_id,
-                                        },
-                                    });
-                                }
-                            }
-                            OutdatedEdge::RemovedCellDependent(task_id, value_type) => {
-                                make_task_dirty(
-                                    task_id,
-                                    TaskDirtyCause::CellRemoved { value_type },
-                                    queue,
-                                    ctx,
-                                );
-                            }
-                        }
+                        remove_edge(task_id, edge, outdated, queue, ctx);
                     }
 
                     if outdated.is_empty() {
@@ -1,5 +1,6 @@
 use std::{cmp::max, num::NonZeroU32};
 
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::TaskId;
 
@@ -10,6 +11,7 @@ use crate::{
                 get_uppers, is_aggregating_node, AggregationUpdateJob, AggregationUpdateQueue,
                 LEAF_NUMBER,
             },
+            invalidate::{make_task_dirty_internal, TaskDirtyCause},
             is_root_node, ExecuteContext, Operation, TaskGuard,
         },
         storage::{get, update},
@@ -31,6 +33,15 @@ pub enum ConnectChildOperation {
 }
 
 impl ConnectChildOperation {
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            ConnectChildOperation::UpdateAggregation { aggregation_update } => {
+                aggregation_update.collect_task_ids(task_ids)
+            }
+            ConnectChildOperation::Done => {}
+        }
+    }
+
     pub fn run(parent_task_id: TaskId, child_task_id: TaskId, mut ctx: impl ExecuteContext) {
         let mut parent_task = ctx.task(parent_task_id, TaskDataCategory::All);
         // Quick skip if the child was already connected before
@@ -118,17 +129,22 @@ impl ConnectChildOperation {
             }
             drop(parent_task);
 
-            {
-                let mut task = ctx.task(child_task_id, TaskDataCategory::Data);
-                if !task.has_key(&CachedDataItemKey::Output {}) {
-                    let description = ctx.get_task_desc_fn(child_task_id);
-                    let should_schedule = task.add(CachedDataItem::new_scheduled(description));
-                    drop(task);
-                    if should_schedule {
-                        ctx.schedule(child_task_id);
-                    }
-                }
+            // A child that has never been computed is marked as dirty instead of being scheduled.
+            // Its dirty state is aggregated into the new uppers, which schedules it when one of
+            // them is an active `AggregateRoot`. Otherwise it's scheduled when its output is read
+            // or when one of the uppers becomes active.
+            let mut task = ctx.task(child_task_id, TaskDataCategory::All);
+            if !task.has_key(&CachedDataItemKey::Output {}) {
+                make_task_dirty_internal(
+                    &mut task,
+                    child_task_id,
+                    false,
+                    TaskDirtyCause::InitialDirty,
+                    &mut queue,
+                    &ctx,
+                );
             }
+            drop(task);
 
             ConnectChildOperation::UpdateAggregation {
                 aggregation_update: queue,
@@ -140,6 +156,7 @@ impl ConnectChildOperation {
 
 impl Operation for ConnectChildOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        ctx.operation_started(&self);
         loop {
             ctx.operation_suspend_point(&self);
             match self {
@@ -1,5 +1,6 @@
 use std::fmt::Display;
 
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use turbo_tasks::{registry, TaskId, TraitTypeId, ValueTypeId};
@@ -23,8 +24,7 @@ use crate::{
 pub enum InvalidateOperation {
     // TODO DetermineActiveness
     MakeDirty {
-        task_ids: SmallVec<[TaskId; 4]>,
-        cause: TaskDirtyCause,
+        task_ids: SmallVec<[(TaskId, TaskDirtyCause); 4]>,
     },
     AggregationUpdate {
         queue: AggregationUpdateQueue,
@@ -34,23 +34,46 @@ pub enum InvalidateOperation {
 }
 
 impl InvalidateOperation {
-    pub fn run(
-        task_ids: SmallVec<[TaskId; 4]>,
-        cause: TaskDirtyCause,
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            InvalidateOperation::MakeDirty {
+                task_ids: dirty_task_ids,
+            } => {
+                task_ids.extend(dirty_task_ids.iter().map(|(task_id, _)| *task_id));
+            }
+            InvalidateOperation::AggregationUpdate { queue } => queue.collect_task_ids(task_ids),
+            InvalidateOperation::Done => {}
+        }
+    }
+
+    pub fn run(task_ids: SmallVec<[TaskId; 4]>, cause: TaskDirtyCause, ctx: impl ExecuteContext) {
+        Self::run_with_causes(
+            task_ids
+                .into_iter()
+                .map(|task_id| (task_id, cause.clone()))
+                .collect(),
+            ctx,
+        )
+    }
+
+    /// Like [`InvalidateOperation::run`], but with a separate cause for every task.
+    pub fn run_with_causes(
+        task_ids: SmallVec<[(TaskId, TaskDirtyCause); 4]>,
         mut ctx: impl ExecuteContext,
     ) {
-        InvalidateOperation::MakeDirty { task_ids, cause }.execute(&mut ctx)
+        InvalidateOperation::MakeDirty { task_ids }.execute(&mut ctx)
     }
 }
 
 impl Operation for InvalidateOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        ctx.operation_started(&self);
         loop {
             ctx.operation_suspend_point(&self);
             match self {
-                InvalidateOperation::MakeDirty { task_ids, cause } => {
+                InvalidateOperation::MakeDirty { task_ids } => {
                     let mut queue = AggregationUpdateQueue::new();
-                    for task_id in task_ids {
+                    for (task_id, cause) in task_ids {
                         make_task_dirty(task_id, cause, &mut queue, ctx);
                     }
                     if queue.is_empty() {
@@ -73,13 +96,33 @@ impl Operation for InvalidateOperation {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+/// Why a task has been made dirty. The cause is kept in memory until the task is clean again, see
+/// [`TurboTasksBackend::task_dirty_cause`][crate::TurboTasksBackend::task_dirty_cause].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum TaskDirtyCause {
     InitialDirty,
-    CellChange { value_type: ValueTypeId },
-    CellRemoved { value_type: ValueTypeId },
+    CellChange {
+        value_type: ValueTypeId,
+    },
+    CellRemoved {
+        value_type: ValueTypeId,
+    },
     OutputChange,
-    CollectiblesChange { collectible_type: TraitTypeId },
+    CollectiblesChange {
+        collectible_type: TraitTypeId,
+    },
+    /// The task was invalidated from outside of the task graph, e.g. because a watched file has
+    /// changed.
+    Invalidation {
+        reason: String,
+    },
+    /// The task depends on session state and was clean in a previous session only.
+    SessionStart,
+    /// Replaying an uncompleted operation of a previous session failed and the task might have
+    /// been left in an inconsistent state.
+    OperationReplayFailed,
+    /// The task was invalidated without a reason, or was already dirty when restored from the
+    /// backing storage.
     Unknown,
 }
 
@@ -109,6 +152,9 @@ impl Display for TaskDirtyCause {
                     registry::get_trait(*collectible_type).name
                 )
             }
+            TaskDirtyCause::Invalidation { reason } => write!(f, "{reason}"),
+            TaskDirtyCause::SessionStart => write!(f, "new session"),
+            TaskDirtyCause::OperationReplayFailed => write!(f, "operation replay failed"),
             TaskDirtyCause::Unknown => write!(f, "unknown"),
         }
     }
@@ -176,12 +222,14 @@ pub fn make_task_dirty_internal(
         }
         _ => unreachable!(),
     };
+    ctx.task_invalidated(task_id, &cause);
     let _span = tracing::trace_span!(
         "make task dirty",
         name = ctx.get_task_description(task_id),
         cause = cause.to_string()
     )
     .entered();
+    task.insert(CachedDataItem::DirtyCause { value: cause });
     let aggregated_update = dirty_container.update_with_dirty_state(&DirtyState {
         clean_in_session: None,
     });
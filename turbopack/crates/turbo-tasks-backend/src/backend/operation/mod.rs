@@ -6,18 +6,23 @@ mod update_cell;
 mod update_collectible;
 mod update_output;
 
+#[cfg(test)]
+mod tests;
+
 use std::{
     fmt::{Debug, Formatter},
     mem::{take, transmute},
+    sync::atomic::Ordering,
 };
 
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use turbo_tasks::{KeyValuePair, SessionId, TaskId, TurboTasksBackendApi};
+use turbo_tasks::{CellId, KeyValuePair, SessionId, TaskId, TaskPriority, TurboTasksBackendApi};
 
 use crate::{
     backend::{
-        storage::StorageWriteGuard, OperationGuard, TaskDataCategory, TransientTask,
-        TurboTasksBackend, TurboTasksBackendInner,
+        operation_log::OperationLogEntry, storage::StorageWriteGuard, OperationGuard,
+        TaskDataCategory, TaskDependency, TaskEvent, TurboTasksBackend, TurboTasksBackendInner,
     },
     backing_storage::BackingStorage,
     data::{
@@ -72,7 +77,21 @@ pub trait ExecuteContext<'e>: Sized {
         task_id2: TaskId,
         category: TaskDataCategory,
     ) -> (impl TaskGuard + 'e, impl TaskGuard + 'e);
+    /// Locks multiple distinct tasks at once without risking a deadlock, e.g. to update both
+    /// sides of an edge atomically.
+    fn tasks<const N: usize>(
+        &mut self,
+        task_ids: [TaskId; N],
+        category: TaskDataCategory,
+    ) -> [impl TaskGuard + 'e; N];
     fn schedule(&self, task_id: TaskId);
+    /// Schedules tasks with [`TaskPriority::Urgent`] from now on, bypassing the fair scheduler.
+    /// Used when somebody is waiting for the scheduled work.
+    fn schedule_urgently(&mut self);
+    /// Called when an operation starts executing, before its first suspend point.
+    fn operation_started<T>(&self, op: &T)
+    where
+        T: Clone + Into<AnyOperation>;
     fn operation_suspend_point<T>(&mut self, op: &T)
     where
         T: Clone + Into<AnyOperation>;
@@ -85,6 +104,27 @@ pub trait ExecuteContext<'e>: Sized {
     );
     fn get_task_desc_fn(&self, task_id: TaskId) -> impl Fn() -> String + Send + Sync + 'static;
     fn get_task_description(&self, task_id: TaskId) -> String;
+    fn emit_task_event(&self, event: impl FnOnce() -> TaskEvent);
+    /// Reports an aggregation update that exceeds
+    /// [`BackendOptions::aggregation_fan_out_threshold`] or
+    /// [`BackendOptions::aggregation_queue_threshold`]. Returns whether it has been reported.
+    ///
+    /// [`BackendOptions::aggregation_fan_out_threshold`]: crate::BackendOptions::aggregation_fan_out_threshold
+    /// [`BackendOptions::aggregation_queue_threshold`]: crate::BackendOptions::aggregation_queue_threshold
+    fn report_aggregation_fan_out(
+        &self,
+        task_id: TaskId,
+        uppers: usize,
+        queued_jobs: usize,
+    ) -> bool;
+    /// Called when a task is made dirty.
+    fn task_invalidated(&self, task_id: TaskId, cause: &TaskDirtyCause);
+    /// Called when `dependency` has changed and the tasks that read it are made dirty.
+    fn dependency_changed(&self, task_ids: &[TaskId], dependency: TaskDependency);
+    /// Appends an entry to the operation log, when [`BackendOptions::operation_log`] is set.
+    ///
+    /// [`BackendOptions::operation_log`]: crate::BackendOptions::operation_log
+    fn record_operation(&self, entry: impl FnOnce() -> OperationLogEntry);
 }
 
 pub struct ParentRef<'a> {
@@ -102,6 +142,8 @@ where
     turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     _operation_guard: Option<OperationGuard<'e, B>>,
     transaction: TransactionState<'e, 'tx, B>,
+    /// The priority of tasks scheduled by this context.
+    priority: TaskPriority,
 }
 
 impl<'e, 'tx, B: BackingStorage> ExecuteContextImpl<'e, 'tx, B>
@@ -118,6 +160,7 @@ where
             _operation_guard: Some(backend.start_operation()),
             parent: None,
             transaction: TransactionState::None,
+            priority: TaskPriority::Normal,
         }
     }
 
@@ -132,6 +175,7 @@ where
             _operation_guard: Some(backend.start_operation()),
             parent: None,
             transaction: TransactionState::Borrowed(transaction),
+            priority: TaskPriority::Normal,
         }
     }
 
@@ -139,6 +183,16 @@ where
     where
         'e: 'l,
     {
+        if matches!(self.transaction, TransactionState::None) {
+            // Safety: The context doesn't outlive the current call into the backend.
+            if let Some(tx) = unsafe { self.backend.turn_transaction() } {
+                // Safety: The backend is valid for 'e, and the transaction for the current call.
+                let tx = unsafe {
+                    transmute::<&B::ReadTransaction<'_>, &'e B::ReadTransaction<'tx>>(tx)
+                };
+                self.transaction = TransactionState::Borrowed(Some(tx));
+            }
+        }
         if matches!(self.transaction, TransactionState::None) {
             let tx = self.backend.backing_storage.start_read_transaction();
             let tx = tx.map(|tx| {
@@ -160,11 +214,21 @@ where
         category: TaskDataCategory,
     ) -> Vec<CachedDataItem> {
         // Safety: `transaction` is a valid transaction from `self.backend.backing_storage`.
-        unsafe {
+        let mut items = unsafe {
             self.backend
                 .backing_storage
                 .lookup_data(self.transaction(), task_id, category)
+        };
+        self.backend
+            .discard_restored_aggregation(task_id, &mut items);
+        self.backend
+            .discard_references_to_removed_tasks(task_id, &mut items);
+        if category == TaskDataCategory::Data {
+            if let Some((_, weak_dependents)) = self.backend.weak_dependents.remove(&task_id) {
+                items.extend(weak_dependents);
+            }
         }
+        items
     }
 }
 
@@ -177,6 +241,8 @@ where
     }
 
     fn task(&mut self, task_id: TaskId, category: TaskDataCategory) -> impl TaskGuard + 'e {
+        self.backend.validate_task_id_generation(task_id);
+        let category = self.backend.restore_category(category);
         let mut task = self.backend.storage.access_mut(task_id);
         if !task.persistance_state().is_restored(category) {
             if task_id.is_transient() {
@@ -195,6 +261,7 @@ where
                                 task.add(item);
                             }
                             task.persistance_state_mut().set_restored(category);
+                            self.backend.track_restored_task(task_id);
                         }
                     }
                 }
@@ -208,14 +275,7 @@ where
     }
 
     fn is_once_task(&self, task_id: TaskId) -> bool {
-        if !task_id.is_transient() {
-            return false;
-        }
-        if let Some(ty) = self.backend.transient_tasks.get(&task_id) {
-            matches!(**ty, TransientTask::Once(_))
-        } else {
-            false
-        }
+        self.backend.is_once_task(task_id)
     }
 
     fn task_pair(
@@ -224,6 +284,9 @@ where
         task_id2: TaskId,
         category: TaskDataCategory,
     ) -> (impl TaskGuard + 'e, impl TaskGuard + 'e) {
+        self.backend.validate_task_id_generation(task_id1);
+        self.backend.validate_task_id_generation(task_id2);
+        let category = self.backend.restore_category(category);
         let (mut task1, mut task2) = self.backend.storage.access_pair_mut(task_id1, task_id2);
         let is_restored1 = task1.persistance_state().is_restored(category);
         let is_restored2 = task2.persistance_state().is_restored(category);
@@ -244,12 +307,14 @@ where
                         task1.add(item);
                     }
                     task1.persistance_state_mut().set_restored(category);
+                    self.backend.track_restored_task(task_id1);
                 }
                 if !task2.persistance_state().is_restored(category) {
                     for item in items2.unwrap() {
                         task2.add(item);
                     }
                     task2.persistance_state_mut().set_restored(category);
+                    self.backend.track_restored_task(task_id2);
                 }
             }
         }
@@ -267,27 +332,106 @@ where
         )
     }
 
+    fn tasks<const N: usize>(
+        &mut self,
+        task_ids: [TaskId; N],
+        category: TaskDataCategory,
+    ) -> [impl TaskGuard + 'e; N] {
+        for task_id in task_ids {
+            self.backend.validate_task_id_generation(task_id);
+        }
+        let category = self.backend.restore_category(category);
+        let mut tasks = self.backend.storage.access_many_mut(task_ids);
+        for category in category {
+            let is_restored = tasks
+                .each_ref()
+                .map(|task| task.persistance_state().is_restored(category));
+            if is_restored.iter().all(|&is_restored| is_restored) {
+                continue;
+            }
+            // Avoid holding the locks too long since this can also affect other tasks
+            drop(tasks);
+
+            let mut items = std::array::from_fn::<_, N, _>(|i| {
+                (!is_restored[i] && !task_ids[i].is_transient())
+                    .then(|| self.restore_task_data(task_ids[i], category))
+            });
+
+            tasks = self.backend.storage.access_many_mut(task_ids);
+            for (i, task) in tasks.iter_mut().enumerate() {
+                let task_id = task_ids[i];
+                if !task.persistance_state().is_restored(category) {
+                    if task_id.is_transient() {
+                        task.persistance_state_mut()
+                            .set_restored(TaskDataCategory::All);
+                        continue;
+                    }
+                    for item in items[i].take().unwrap() {
+                        task.add(item);
+                    }
+                    task.persistance_state_mut().set_restored(category);
+                    self.backend.track_restored_task(task_id);
+                }
+            }
+        }
+        let mut task_ids = task_ids.into_iter();
+        tasks.map(|task| TaskGuardImpl {
+            task,
+            task_id: task_ids.next().unwrap(),
+            backend: self.backend,
+        })
+    }
+
     fn schedule(&self, task_id: TaskId) {
+        if self.backend.replaying_operations.load(Ordering::Acquire) {
+            return;
+        }
+        self.emit_task_event(|| TaskEvent::Scheduled { task_id });
+        if self.priority == TaskPriority::Urgent {
+            self.turbo_tasks
+                .schedule_with_priority(task_id, TaskPriority::Urgent);
+            return;
+        }
+        if let Some(scheduler) = &self.backend.scheduler {
+            if !scheduler.schedule_revalidation(task_id) {
+                // Started by the scheduler when a share is free or the task is read
+                return;
+            }
+        }
         self.turbo_tasks.schedule(task_id);
     }
 
-    fn operation_suspend_point<T: Clone + Into<AnyOperation>>(&mut self, op: &T) {
-        if self.parent.is_some() {
-            self.backend.operation_suspend_point(|| {
-                let mut nested = Vec::new();
-                nested.push(op.clone().into());
-                let mut cur = self.parent.as_ref();
-                while let Some(ParentRef { op, parent }) = cur {
-                    nested.push((*op).clone());
-                    cur = parent.as_ref();
-                }
-                AnyOperation::Nested(nested)
-            });
-        } else {
-            self.backend.operation_suspend_point(|| op.clone().into());
+    fn schedule_urgently(&mut self) {
+        self.priority = TaskPriority::Urgent;
+    }
+
+    fn operation_started<T: Clone + Into<AnyOperation>>(&self, op: &T) {
+        // Nested operations are started again when their parent is replayed
+        if self.parent.is_none() {
+            self.record_operation(|| OperationLogEntry::OperationStarted(op.clone().into()));
         }
     }
 
+    fn operation_suspend_point<T: Clone + Into<AnyOperation>>(&mut self, op: &T) {
+        self.record_operation(|| OperationLogEntry::Operation(op.clone().into()));
+        let suspend = || {
+            if self.parent.is_none() {
+                return op.clone().into();
+            }
+            let mut nested = Vec::new();
+            nested.push(op.clone().into());
+            let mut cur = self.parent.as_ref();
+            while let Some(ParentRef { op, parent }) = cur {
+                nested.push((*op).clone());
+                cur = parent.as_ref();
+            }
+            AnyOperation::Nested(nested)
+        };
+        #[cfg(test)]
+        tests::interrupt_at_suspend_point(&suspend);
+        self.backend.operation_suspend_point(suspend);
+    }
+
     fn suspending_requested(&self) -> bool {
         self.backend.suspending_requested()
     }
@@ -307,6 +451,7 @@ where
             turbo_tasks: &'a dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
             parent: ParentRef<'a>,
             transaction: TransactionState<'a, '_, B>,
+            priority: TaskPriority,
             run: impl FnOnce(&mut ExecuteContextImpl<'_, '_, B>),
         ) {
             let mut inner_ctx: ExecuteContextImpl<'_, '_, B> = ExecuteContextImpl {
@@ -315,6 +460,7 @@ where
                 _operation_guard: None,
                 parent: Some(parent),
                 transaction,
+                priority,
             };
             run(&mut inner_ctx);
         }
@@ -326,6 +472,7 @@ where
                 parent: &this.parent,
             },
             self.transaction.borrow(),
+            self.priority,
             run,
         );
         *parent_op_ref = parent_op.try_into().unwrap();
@@ -338,6 +485,59 @@ where
     fn get_task_description(&self, task_id: TaskId) -> String {
         self.backend.get_task_description(task_id)
     }
+
+    fn emit_task_event(&self, event: impl FnOnce() -> TaskEvent) {
+        self.backend.task_events.emit(event);
+    }
+
+    fn report_aggregation_fan_out(
+        &self,
+        task_id: TaskId,
+        uppers: usize,
+        queued_jobs: usize,
+    ) -> bool {
+        let options = &self.backend.options;
+        let exceeds = |threshold: Option<usize>, value: usize| threshold.is_some_and(|t| value > t);
+        if !exceeds(options.aggregation_fan_out_threshold, uppers)
+            && !exceeds(options.aggregation_queue_threshold, queued_jobs)
+        {
+            return false;
+        }
+        tracing::warn!(
+            task = %self.get_task_description(task_id),
+            uppers,
+            queued_jobs,
+            "aggregation update reaches a large part of the task graph"
+        );
+        self.emit_task_event(|| TaskEvent::AggregationFanOut {
+            task_id,
+            uppers,
+            queued_jobs,
+        });
+        true
+    }
+
+    fn task_invalidated(&self, task_id: TaskId, cause: &TaskDirtyCause) {
+        if let Some(profiler) = &self.backend.cache_profiler {
+            profiler.invalidated(task_id);
+        }
+        self.emit_task_event(|| TaskEvent::Invalidated {
+            task_id,
+            reason: cause.to_string(),
+        });
+    }
+
+    fn dependency_changed(&self, task_ids: &[TaskId], dependency: TaskDependency) {
+        if let Some(tracker) = &self.backend.dependency_tracker {
+            tracker.dependency_changed(task_ids, dependency);
+        }
+    }
+
+    fn record_operation(&self, entry: impl FnOnce() -> OperationLogEntry) {
+        if let Some(recorder) = &self.backend.operation_recorder {
+            recorder.record(entry());
+        }
+    }
 }
 
 pub trait TaskGuard: Debug {
@@ -361,6 +561,10 @@ pub trait TaskGuard: Debug {
     ) -> impl Iterator<Item = (&CachedDataItemKey, &CachedDataItemValue)>;
     fn iter_all(&self) -> impl Iterator<Item = (&CachedDataItemKey, &CachedDataItemValue)>;
     fn invalidate_serialization(&mut self);
+    /// Makes the data of `cell` available as [`CachedDataItem::CellData`]. Restores it from the
+    /// backing storage when it has been spilled and deserializes it when it hasn't been read since
+    /// it was restored.
+    fn restore_cell(&mut self, cell: CellId);
 }
 
 struct TaskGuardImpl<'a, B: BackingStorage> {
@@ -390,20 +594,21 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
 
     #[must_use]
     fn add(&mut self, item: CachedDataItem) -> bool {
-        if self.task_id.is_transient() || !item.is_persistent() {
+        if !self.backend.should_persist_key(self.task_id, &item.key()) || !item.is_persistent() {
             self.task.add(item)
         } else if self.task.add(item.clone()) {
             let (key, value) = item.into_key_and_value();
             self.task.persistance_state_mut().add_persisting_item();
-            self.backend
-                .persisted_storage_log(key.category())
-                .lock(self.task_id)
-                .push(CachedDataUpdate {
+            self.backend.push_persisted_storage_update(
+                key.category(),
+                CachedDataUpdate {
                     key,
                     task: self.task_id,
                     value: Some(value),
                     old_value: None,
-                });
+                },
+            );
+            self.backend.track_pending_log_items(1);
             true
         } else {
             false
@@ -417,7 +622,7 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
 
     fn insert(&mut self, item: CachedDataItem) -> Option<CachedDataItemValue> {
         let (key, value) = item.into_key_and_value();
-        if self.task_id.is_transient() || !key.is_persistent() {
+        if !self.backend.should_persist_key(self.task_id, &key) {
             self.task
                 .insert(CachedDataItem::from_key_and_value(key, value))
         } else if value.is_persistent() {
@@ -426,32 +631,34 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
                 value.clone(),
             ));
             self.task.persistance_state_mut().add_persisting_item();
-            self.backend
-                .persisted_storage_log(key.category())
-                .lock(self.task_id)
-                .push(CachedDataUpdate {
+            self.backend.push_persisted_storage_update(
+                key.category(),
+                CachedDataUpdate {
                     key,
                     task: self.task_id,
                     value: Some(value),
                     old_value: old
                         .as_ref()
                         .and_then(|old| old.is_persistent().then(|| old.clone())),
-                });
+                },
+            );
+            self.backend.track_pending_log_items(1);
             old
         } else {
             let item = CachedDataItem::from_key_and_value(key.clone(), value);
             if let Some(old) = self.task.insert(item) {
                 if old.is_persistent() {
                     self.task.persistance_state_mut().add_persisting_item();
-                    self.backend
-                        .persisted_storage_log(key.category())
-                        .lock(self.task_id)
-                        .push(CachedDataUpdate {
+                    self.backend.push_persisted_storage_update(
+                        key.category(),
+                        CachedDataUpdate {
                             key,
                             task: self.task_id,
                             value: None,
                             old_value: Some(old.clone()),
-                        });
+                        },
+                    );
+                    self.backend.track_pending_log_items(1);
                 }
                 Some(old)
             } else {
@@ -465,7 +672,7 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
         key: &CachedDataItemKey,
         update: impl FnOnce(Option<CachedDataItemValue>) -> Option<CachedDataItemValue>,
     ) {
-        if self.task_id.is_transient() || !key.is_persistent() {
+        if !self.backend.should_persist_key(self.task_id, key) {
             self.task.update(key, update);
             return;
         }
@@ -486,27 +693,29 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
                 (None, false) => {}
                 (Some(old_value), false) => {
                     add_persisting_item = true;
-                    backend
-                        .persisted_storage_log(key.category())
-                        .lock(*task_id)
-                        .push(CachedDataUpdate {
+                    backend.push_persisted_storage_update(
+                        key.category(),
+                        CachedDataUpdate {
                             key: key.clone(),
                             task: *task_id,
                             value: None,
                             old_value: Some(old_value),
-                        });
+                        },
+                    );
+                    backend.track_pending_log_items(1);
                 }
                 (old_value, true) => {
                     add_persisting_item = true;
-                    backend
-                        .persisted_storage_log(key.category())
-                        .lock(*task_id)
-                        .push(CachedDataUpdate {
+                    backend.push_persisted_storage_update(
+                        key.category(),
+                        CachedDataUpdate {
                             key: key.clone(),
                             task: *task_id,
                             value: new.clone(),
                             old_value,
-                        });
+                        },
+                    );
+                    backend.track_pending_log_items(1);
                 }
             }
 
@@ -520,18 +729,19 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
     fn remove(&mut self, key: &CachedDataItemKey) -> Option<CachedDataItemValue> {
         let old_value = self.task.remove(key);
         if let Some(value) = old_value {
-            if !self.task_id.is_transient() && key.is_persistent() && value.is_persistent() {
+            if self.backend.should_persist_key(self.task_id, key) && value.is_persistent() {
                 let key = key.clone();
                 self.task.persistance_state_mut().add_persisting_item();
-                self.backend
-                    .persisted_storage_log(key.category())
-                    .lock(self.task_id)
-                    .push(CachedDataUpdate {
+                self.backend.push_persisted_storage_update(
+                    key.category(),
+                    CachedDataUpdate {
                         key,
                         task: self.task_id,
                         value: None,
                         old_value: value.is_persistent().then(|| value.clone()),
-                    });
+                    },
+                );
+                self.backend.track_pending_log_items(1);
             }
             Some(value)
         } else {
@@ -567,6 +777,19 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
     }
 
     fn invalidate_serialization(&mut self) {
+        if self.backend.options.read_only {
+            return;
+        }
+        let spilled_cells = self
+            .iter_all()
+            .filter_map(|(key, _)| match key {
+                CachedDataItemKey::SpilledCell { cell } => Some(*cell),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for cell in spilled_cells {
+            self.restore_cell(cell);
+        }
         let mut count = 0;
         let cell_data = self
             .iter(CachedDataItemIndex::CellData)
@@ -584,15 +807,55 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
                 }
                 _ => None,
             });
+        for update in cell_data {
+            self.backend
+                .push_persisted_storage_update(TaskDataCategory::Data, update);
+        }
+        self.task
+            .persistance_state_mut()
+            .add_persisting_items(count);
+        self.backend.track_pending_log_items(count as usize);
+    }
+
+    fn restore_cell(&mut self, cell: CellId) {
+        // The cell is unchanged since it was persisted, so nothing here is logged.
+        if self
+            .task
+            .remove(&CachedDataItemKey::SpilledCell { cell })
+            .is_some()
         {
-            let mut guard = self
-                .backend
-                .persisted_storage_log(TaskDataCategory::Data)
-                .lock(self.task_id);
-            guard.extend(cell_data);
-            self.task
-                .persistance_state_mut()
-                .add_persisting_items(count);
+            // Safety: Passing no transaction is always valid.
+            let restored = unsafe {
+                self.backend
+                    .backing_storage
+                    .lookup_cell(None, self.task_id, cell)
+            };
+            if let Some(value) = restored {
+                self.task
+                    .insert(CachedDataItem::SerializedCellData { cell, value });
+            }
+        }
+        if let Some(CachedDataItemValue::SerializedCellData { value }) = self
+            .task
+            .remove(&CachedDataItemKey::SerializedCellData { cell })
+        {
+            match value.deserialize() {
+                Ok(value) => {
+                    self.task.insert(CachedDataItem::CellData { cell, value });
+                }
+                Err(err) => {
+                    // The cell is treated as missing, so the task is recomputed
+                    let task_id = self.task_id;
+                    tracing::warn!("Deserializing cell {cell:?} of {task_id} failed: {err:?}");
+                    self.backend
+                        .task_events
+                        .emit(|| TaskEvent::CellDeserializationFailed {
+                            task_id,
+                            cell,
+                            error: format!("{err:?}"),
+                        });
+                }
+            }
         }
     }
 }
@@ -645,6 +908,22 @@ impl AnyOperation {
             }
         }
     }
+
+    /// Collects the tasks that the operation would modify when executed.
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            AnyOperation::ConnectChild(op) => op.collect_task_ids(task_ids),
+            AnyOperation::Invalidate(op) => op.collect_task_ids(task_ids),
+            AnyOperation::UpdateOutput(op) => op.collect_task_ids(task_ids),
+            AnyOperation::CleanupOldEdges(op) => op.collect_task_ids(task_ids),
+            AnyOperation::AggregationUpdate(op) => op.collect_task_ids(task_ids),
+            AnyOperation::Nested(ops) => {
+                for op in ops {
+                    op.collect_task_ids(task_ids);
+                }
+            }
+        }
+    }
 }
 
 impl_operation!(ConnectChild connect_child::ConnectChildOperation);
@@ -655,7 +934,8 @@ impl_operation!(AggregationUpdate aggregation_update::AggregationUpdateQueue);
 
 pub use self::{
     aggregation_update::{
-        get_aggregation_number, is_root_node, AggregatedDataUpdate, AggregationUpdateJob,
+        get_aggregation_number, is_aggregating_node, is_root_node, AggregatedDataUpdate,
+        AggregationUpdateJob, LEAF_NUMBER,
     },
     cleanup_old_edges::OutdatedEdge,
     invalidate::TaskDirtyCause,
@@ -0,0 +1,240 @@
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::scope,
+};
+
+use proptest::prelude::*;
+use smallvec::smallvec;
+use turbo_tasks::{TaskId, TaskIdValue, TurboTasks};
+
+use super::{
+    connect_child::ConnectChildOperation, invalidate::InvalidateOperation, AnyOperation,
+    ExecuteContext, TaskDirtyCause, TaskGuard,
+};
+use crate::{
+    backend::{
+        storage::{get, iter_many},
+        TaskDataCategory, TurboTasksBackend,
+    },
+    backing_storage::BackingStorage,
+    data::{CachedDataItem, OutputValue},
+    noop_backing_storage,
+    test_helpers::persistent_turbo_tasks,
+    BackendOptions, NoopBackingStorage,
+};
+
+const TASKS: u32 = 12;
+
+type TestTurboTasks = TurboTasks<TurboTasksBackend<NoopBackingStorage>>;
+
+thread_local! {
+    /// The number of suspend points to pass before the running operation is interrupted.
+    static INTERRUPT_AFTER: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// The serialized state of an interrupted operation.
+struct Interrupted(Vec<u8>);
+
+/// Aborts the running operation at the suspend point selected by [`INTERRUPT_AFTER`], like a
+/// crash right after the state of the operation has been persisted.
+pub(super) fn interrupt_at_suspend_point(suspend: impl FnOnce() -> AnyOperation) {
+    let Some(remaining) = INTERRUPT_AFTER.get() else {
+        return;
+    };
+    if remaining > 0 {
+        INTERRUPT_AFTER.set(Some(remaining - 1));
+        return;
+    }
+    INTERRUPT_AFTER.set(None);
+    // Unwinding without a panic skips the panic hook
+    resume_unwind(Box::new(Interrupted(pot::to_vec(&suspend()).unwrap())));
+}
+
+#[derive(Debug, Clone)]
+enum TestOperation {
+    ConnectChild(u32, u32),
+    Invalidate(u32),
+}
+
+fn test_operation() -> impl Strategy<Value = TestOperation> {
+    prop_oneof![
+        3 => (1..TASKS, 1..TASKS).prop_filter_map("the task graph must be acyclic", |(a, b)| {
+            (a < b).then_some(TestOperation::ConnectChild(a, b))
+        }),
+        1 => (1..TASKS).prop_map(TestOperation::Invalidate),
+    ]
+}
+
+fn task_id(id: u32) -> TaskId {
+    TaskId::from(TaskIdValue::from(id))
+}
+
+fn new_turbo_tasks() -> Arc<TestTurboTasks> {
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        noop_backing_storage(Path::new("")).unwrap(),
+    ));
+    add_outputs(&tt);
+    tt
+}
+
+/// The tasks have no task type and can't be executed. Giving them an output prevents scheduling
+/// them when they are connected as child. Invalidating only schedules aggregate roots.
+fn add_outputs<B: BackingStorage>(tt: &TurboTasks<TurboTasksBackend<B>>) {
+    let mut ctx = tt.backend().0.execute_context(tt);
+    for id in 1..TASKS {
+        ctx.task(task_id(id), TaskDataCategory::All)
+            .insert(CachedDataItem::Output {
+                value: OutputValue::Error,
+            });
+    }
+}
+
+fn run_operation<B: BackingStorage>(
+    tt: &TurboTasks<TurboTasksBackend<B>>,
+    operation: &TestOperation,
+) {
+    let ctx = tt.backend().0.execute_context(tt);
+    match *operation {
+        TestOperation::ConnectChild(parent, child) => {
+            ConnectChildOperation::run(task_id(parent), task_id(child), ctx)
+        }
+        TestOperation::Invalidate(task) => {
+            InvalidateOperation::run(smallvec![task_id(task)], TaskDirtyCause::Unknown, ctx)
+        }
+    }
+}
+
+/// The children and the dirty flag of every task. Unlike the aggregation state, these don't
+/// depend on the order in which the operations were executed.
+fn graph_state(tt: &TestTurboTasks) -> BTreeMap<TaskId, (BTreeSet<TaskId>, bool)> {
+    let inner = &tt.backend().0;
+    (1..TASKS)
+        .map(|id| {
+            let task = inner.storage.access_mut(task_id(id));
+            let children = iter_many!(task, Child { task } => *task).collect();
+            let dirty = get!(task, Dirty).map_or(false, |dirty| dirty.get(inner.session_id));
+            (task_id(id), (children, dirty))
+        })
+        .collect()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn operations_are_resumable(
+        operations in prop::collection::vec(test_operation(), 1..40),
+        interrupt_after in 0usize..200,
+        threads in 1usize..4,
+    ) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+
+        let tt = new_turbo_tasks();
+        for operation in operations.iter() {
+            run_operation(&tt, operation);
+        }
+        let expected_storage = tt.backend().storage_dump();
+        let expected_graph = graph_state(&tt);
+        let inconsistencies = tt.backend().verify_consistency();
+        prop_assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+
+        // Interrupt one operation at a suspend point and resume it from its serialized state
+        let resumed = new_turbo_tasks();
+        INTERRUPT_AFTER.set(Some(interrupt_after));
+        for operation in operations.iter() {
+            let Err(payload) = catch_unwind(AssertUnwindSafe(|| run_operation(&resumed, operation)))
+            else {
+                continue;
+            };
+            let state = match payload.downcast::<Interrupted>() {
+                Ok(interrupted) => interrupted.0,
+                Err(payload) => resume_unwind(payload),
+            };
+            let operation: AnyOperation = pot::from_slice(&state).unwrap();
+            operation.execute(&mut resumed.backend().0.execute_context(&*resumed));
+        }
+        INTERRUPT_AFTER.set(None);
+        prop_assert_eq!(
+            resumed.backend().storage_dump().differences(&expected_storage),
+            Vec::<String>::new()
+        );
+        let inconsistencies = resumed.backend().verify_consistency();
+        prop_assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+
+        // Interleaving the operations must not affect the task graph
+        let concurrent = new_turbo_tasks();
+        scope(|s| {
+            for thread in 0..threads {
+                let concurrent = &concurrent;
+                let operations = &operations;
+                s.spawn(move || {
+                    for operation in operations.iter().skip(thread).step_by(threads) {
+                        run_operation(concurrent, operation);
+                    }
+                });
+            }
+        });
+        prop_assert_eq!(graph_state(&concurrent), expected_graph);
+        let inconsistencies = concurrent.backend().verify_consistency();
+        prop_assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn snapshots_are_consistent(
+        operations in prop::collection::vec(test_operation(), 1..80),
+        threads in 1usize..4,
+    ) {
+        let db_dir = tempfile::tempdir().unwrap();
+
+        // Snapshot while operations are running and stop without persisting the remaining changes
+        {
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            let _guard = runtime.enter();
+            let tt = persistent_turbo_tasks(db_dir.path(), BackendOptions::default());
+            add_outputs(&tt);
+            let completed = AtomicUsize::new(0);
+            scope(|s| {
+                for thread in 0..threads {
+                    let tt = &tt;
+                    let operations = &operations;
+                    let completed = &completed;
+                    s.spawn(move || {
+                        for operation in operations.iter().skip(thread).step_by(threads) {
+                            run_operation(tt, operation);
+                            completed.fetch_add(1, Ordering::Relaxed);
+                        }
+                    });
+                }
+                while completed.load(Ordering::Relaxed) < operations.len() / 2 {
+                    std::thread::yield_now();
+                }
+                tt.backend().0.snapshot();
+            });
+        }
+
+        // The uncompleted operations are continued on startup
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+        let restored = persistent_turbo_tasks(db_dir.path(), BackendOptions::default());
+        let mut ctx = restored.backend().0.execute_context(&*restored);
+        for id in 1..TASKS {
+            drop(ctx.task(task_id(id), TaskDataCategory::All));
+        }
+        drop(ctx);
+        let inconsistencies = restored.backend().verify_consistency();
+        prop_assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+    }
+}
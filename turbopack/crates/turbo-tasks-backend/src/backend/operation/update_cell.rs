@@ -1,19 +1,30 @@
-use turbo_tasks::{backend::CellContent, CellId, TaskId};
+use smallvec::SmallVec;
+use turbo_tasks::{backend::CellContent, CellId, TaskId, TypedSharedReference};
 
 use crate::{
     backend::{
         operation::{invalidate::TaskDirtyCause, ExecuteContext, InvalidateOperation, TaskGuard},
-        storage::{get_many, remove},
-        TaskDataCategory,
+        storage::{get, iter_many, remove},
+        TaskDataCategory, TaskDependency,
     },
-    data::{CachedDataItem, CachedDataItemKey},
+    data::{CachedDataItem, CachedDataItemKey, CachedDataItemValue},
 };
 
 pub struct UpdateCellOperation;
 
 impl UpdateCellOperation {
-    pub fn run(task_id: TaskId, cell: CellId, content: CellContent, mut ctx: impl ExecuteContext) {
+    /// When `compare_contents` is set, dependent tasks are not invalidated when the new content
+    /// serializes to the same data as the old content.
+    pub fn run(
+        task_id: TaskId,
+        cell: CellId,
+        content: CellContent,
+        compare_contents: bool,
+        mut ctx: impl ExecuteContext,
+    ) {
         let mut task = ctx.task(task_id, TaskDataCategory::All);
+        // The old content is needed to decide if dependent tasks need to be invalidated
+        task.restore_cell(cell);
         let old_content = if let CellContent(Some(new_content)) = content {
             task.insert(CachedDataItem::CellData {
                 cell,
@@ -38,16 +49,42 @@ impl UpdateCellOperation {
             return;
         }
 
-        let dependent = get_many!(
+        // The contents are compared after the task lock is released, as serializing them can be
+        // expensive
+        let new_content = if compare_contents {
+            get!(task, CellData { cell }).cloned()
+        } else {
+            None
+        };
+
+        let dependent = iter_many!(
             task,
             CellDependent { cell: dependent_cell, task }
             if *dependent_cell == cell
             => *task
-        );
+        )
+        .chain(iter_many!(
+            task,
+            WeakCellDependent { cell: dependent_cell, task }
+            if *dependent_cell == cell
+            => *task
+        ))
+        .collect::<SmallVec<[_; 4]>>();
 
         drop(task);
+
+        let unchanged = match (&old_content, &new_content) {
+            (Some(CachedDataItemValue::CellData { value: old }), Some(new)) => {
+                same_serialization(old, new)
+            }
+            _ => false,
+        };
         drop(old_content);
+        if unchanged {
+            return;
+        }
 
+        ctx.dependency_changed(&dependent, TaskDependency::Cell(task_id, cell));
         InvalidateOperation::run(
             dependent,
             TaskDirtyCause::CellChange {
@@ -57,3 +94,16 @@ impl UpdateCellOperation {
         );
     }
 }
+
+/// Value types don't provide a generic equality, so the serialized data is compared instead.
+/// Values that can't be serialized, or that serialize nondeterministically, are considered
+/// different.
+fn same_serialization(old: &TypedSharedReference, new: &TypedSharedReference) -> bool {
+    if old == new {
+        return true;
+    }
+    match (pot::to_vec(old), pot::to_vec(new)) {
+        (Ok(old), Ok(new)) => old == new,
+        _ => false,
+    }
+}
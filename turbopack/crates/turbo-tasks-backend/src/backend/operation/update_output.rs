@@ -1,8 +1,9 @@
 use std::{borrow::Cow, mem::take};
 
 use anyhow::{anyhow, Result};
+use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
-use turbo_tasks::{util::SharedError, RawVc, TaskId};
+use turbo_tasks::{RawVc, TaskId};
 
 use crate::{
     backend::{
@@ -10,12 +11,12 @@ use crate::{
             invalidate::{make_task_dirty, make_task_dirty_internal, TaskDirtyCause},
             AggregationUpdateQueue, ExecuteContext, Operation, TaskGuard,
         },
-        storage::{get, get_many},
-        TaskDataCategory,
+        storage::{get, get_many, iter_many},
+        TaskDataCategory, TaskDependency,
     },
     data::{
         CachedDataItem, CachedDataItemKey, CachedDataItemValue, CellRef, InProgressState,
-        OutputValue,
+        OutputValue, TaskExecutionError,
     },
 };
 
@@ -38,15 +39,39 @@ pub enum UpdateOutputOperation {
 }
 
 impl UpdateOutputOperation {
+    pub fn collect_task_ids(&self, task_ids: &mut FxHashSet<TaskId>) {
+        match self {
+            UpdateOutputOperation::MakeDependentTasksDirty {
+                dependent_tasks,
+                children,
+                queue,
+            } => {
+                task_ids.extend(dependent_tasks.iter().chain(children.iter()).copied());
+                queue.collect_task_ids(task_ids);
+            }
+            UpdateOutputOperation::EnsureUnfinishedChildrenDirty { children, queue } => {
+                task_ids.extend(children.iter().copied());
+                queue.collect_task_ids(task_ids);
+            }
+            UpdateOutputOperation::AggregationUpdate { queue } => queue.collect_task_ids(task_ids),
+            UpdateOutputOperation::Done => {}
+        }
+    }
+
     pub fn run(
         task_id: TaskId,
         output: Result<Result<RawVc>, Option<Cow<'static, str>>>,
         mut ctx: impl ExecuteContext,
     ) {
         let mut task = ctx.task(task_id, TaskDataCategory::Data);
-        if let Some(InProgressState::InProgress { stale: true, .. }) = get!(task, InProgress) {
-            // Skip updating the output when the task is stale
-            return;
+        if let Some(InProgressState::InProgress {
+            stale, cancelled, ..
+        }) = get!(task, InProgress)
+        {
+            if *stale || *cancelled {
+                // Skip updating the output when the task is stale or cancelled
+                return;
+            }
         }
         let old_error = task.remove(&CachedDataItemKey::Error {});
         let current_output = task.get(&CachedDataItemKey::Output {});
@@ -80,21 +105,31 @@ impl UpdateOutputOperation {
                     cell,
                 })
             }
-            Ok(Ok(RawVc::LocalCell(_, _))) => {
-                panic!("LocalCell must not be output of a task");
-            }
-            Ok(Ok(RawVc::LocalOutput(_, _))) => {
-                panic!("LocalOutput must not be output of a task");
+            Ok(Ok(RawVc::LocalCell(_, _) | RawVc::LocalOutput(_, _))) => {
+                // The manager resolves local Vcs before passing the result to the backend. They
+                // can't be stored as they are only valid during the execution.
+                task.insert(CachedDataItem::Error {
+                    value: TaskExecutionError::new(
+                        anyhow!(
+                            "Task output must be resolved to a persistent cell, but was a local Vc"
+                        ),
+                        ctx.get_task_description(task_id),
+                    ),
+                });
+                OutputValue::Error
             }
             Ok(Err(err)) => {
                 task.insert(CachedDataItem::Error {
-                    value: SharedError::new(err),
+                    value: TaskExecutionError::new(err, ctx.get_task_description(task_id)),
                 });
                 OutputValue::Error
             }
             Err(panic) => {
                 task.insert(CachedDataItem::Error {
-                    value: SharedError::new(anyhow!("Panic: {:?}", panic)),
+                    value: TaskExecutionError::new(
+                        anyhow!("Panic: {:?}", panic),
+                        ctx.get_task_description(task_id),
+                    ),
                 });
                 OutputValue::Panic
             }
@@ -103,9 +138,13 @@ impl UpdateOutputOperation {
             value: output_value,
         });
 
-        let dependent_tasks = get_many!(task, OutputDependent { task } => *task);
+        let dependent_tasks = iter_many!(task, OutputDependent { task } => *task)
+            .chain(iter_many!(task, WeakOutputDependent { task } => *task))
+            .collect::<Vec<_>>();
         let children = get_many!(task, Child { task } => *task);
 
+        ctx.dependency_changed(&dependent_tasks, TaskDependency::Output(task_id));
+
         let mut queue = AggregationUpdateQueue::new();
 
         make_task_dirty_internal(
@@ -132,6 +171,7 @@ impl UpdateOutputOperation {
 
 impl Operation for UpdateOutputOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        ctx.operation_started(&self);
         loop {
             ctx.operation_suspend_point(&self);
             match self {
@@ -0,0 +1,81 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::operation::{AggregationUpdateJob, AnyOperation};
+
+/// An entry of the operation log, see [`super::BackendOptions::operation_log`].
+#[derive(Serialize, Deserialize)]
+pub enum OperationLogEntry {
+    /// A top-level operation started executing. Replaying these in order repeats all operations.
+    OperationStarted(AnyOperation),
+    /// An operation reached a suspend point, i.e. it's about to transition to its next state.
+    Operation(AnyOperation),
+    /// An aggregation update job is about to be processed.
+    AggregationUpdateJob(AggregationUpdateJob),
+}
+
+#[derive(Serialize, Deserialize)]
+struct OperationLogLine {
+    seq: u64,
+    #[serde(flatten)]
+    entry: OperationLogEntry,
+}
+
+/// Appends [`OperationLogEntry`]s as JSON lines to a file. Every line is written with a single
+/// write, so the log is complete up to the last operation even when the process crashes.
+pub struct OperationRecorder {
+    file: Mutex<File>,
+    next_seq: AtomicU64,
+}
+
+impl OperationRecorder {
+    pub fn new(path: &Path) -> Result<Self> {
+        Ok(Self {
+            file: Mutex::new(File::create(path)?),
+            next_seq: AtomicU64::new(0),
+        })
+    }
+
+    pub fn record(&self, entry: OperationLogEntry) {
+        let mut file = self.file.lock();
+        // The sequence number is taken while holding the lock to keep the lines ordered
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        let mut line = match serde_json::to_vec(&OperationLogLine { seq, entry }) {
+            Ok(line) => line,
+            Err(err) => {
+                println!("Serializing operation log entry {seq} failed: {err:?}");
+                return;
+            }
+        };
+        line.push(b'\n');
+        if let Err(err) = file.write_all(&line) {
+            println!("Writing operation log entry {seq} failed: {err:?}");
+        }
+    }
+}
+
+/// Reads the top-level operations from an operation log, in the order they were started.
+pub fn read_started_operations(path: &Path) -> Result<Vec<AnyOperation>> {
+    let file = File::open(path)?;
+    let mut operations = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let OperationLogLine { entry, .. } = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid operation log entry in line {}", index + 1))?;
+        if let OperationLogEntry::OperationStarted(operation) = entry {
+            operations.push(operation);
+        }
+    }
+    Ok(operations)
+}
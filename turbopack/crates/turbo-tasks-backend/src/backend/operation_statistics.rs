@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::time::Duration;
+
+/// Number of buckets of a [`DurationHistogram`].
+const HISTOGRAM_BUCKETS: usize = 16;
+
+/// Counts durations in buckets of powers of two milliseconds. Bucket `i` contains the durations
+/// below `2^i` ms that don't fit in a lower bucket, the last bucket also contains all longer
+/// durations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DurationHistogram {
+    pub counts: [usize; HISTOGRAM_BUCKETS],
+}
+
+impl DurationHistogram {
+    /// The exclusive upper limit of the bucket, `None` for the last bucket.
+    pub fn bucket_limit(index: usize) -> Option<Duration> {
+        (index + 1 < HISTOGRAM_BUCKETS).then(|| Duration::from_millis(1 << index))
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+#[derive(Default)]
+struct AtomicDurationHistogram {
+    counts: [AtomicUsize; HISTOGRAM_BUCKETS],
+}
+
+impl AtomicDurationHistogram {
+    fn record(&self, duration: Duration) {
+        let millis = duration.as_millis();
+        let index = if millis == 0 {
+            0
+        } else {
+            ((u128::BITS - millis.leading_zeros()) as usize).min(HISTOGRAM_BUCKETS - 1)
+        };
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> DurationHistogram {
+        DurationHistogram {
+            counts: std::array::from_fn(|i| self.counts[i].load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// How operations were affected by snapshots, see
+/// [`super::TurboTasksBackend::operation_statistics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationStatistics {
+    /// Number of times a running operation was suspended at a suspend point for a snapshot or an
+    /// introspection. Snapshots only record the state of the operation and don't block it.
+    pub suspended_operations: usize,
+    /// Number of times starting an operation had to wait for an introspection to complete.
+    pub delayed_operations: usize,
+    /// How long it took until all operations of the frozen logs, or all running operations for an
+    /// introspection, were completed or suspended.
+    pub suspend_durations: DurationHistogram,
+    /// How long operations were suspended for introspections.
+    pub suspension_durations: DurationHistogram,
+    /// The highest number of concurrently running operations seen.
+    pub max_in_progress_operations: usize,
+}
+
+#[derive(Default)]
+pub struct OperationStatisticsCollector {
+    suspended_operations: AtomicUsize,
+    delayed_operations: AtomicUsize,
+    suspend_durations: AtomicDurationHistogram,
+    suspension_durations: AtomicDurationHistogram,
+    max_in_progress_operations: AtomicUsize,
+}
+
+impl OperationStatisticsCollector {
+    pub fn operation_suspended(&self) {
+        self.suspended_operations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn operation_delayed(&self) {
+        self.delayed_operations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn operations_suspended(&self, duration: Duration) {
+        self.suspend_durations.record(duration);
+    }
+
+    pub fn operations_resumed(&self, duration: Duration) {
+        self.suspension_durations.record(duration);
+    }
+
+    pub fn operation_started(&self, in_progress_operations: usize) {
+        // Avoid contention on the shared counter when the maximum doesn't change
+        if in_progress_operations > self.max_in_progress_operations.load(Ordering::Relaxed) {
+            self.max_in_progress_operations
+                .fetch_max(in_progress_operations, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get(&self) -> OperationStatistics {
+        OperationStatistics {
+            suspended_operations: self.suspended_operations.load(Ordering::Relaxed),
+            delayed_operations: self.delayed_operations.load(Ordering::Relaxed),
+            suspend_durations: self.suspend_durations.get(),
+            suspension_durations: self.suspension_durations.get(),
+            max_in_progress_operations: self.max_in_progress_operations.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+
+use tokio::{sync::watch, time::Duration};
+use turbo_tasks::TaskId;
+
+use super::{TurboTasksBackend, TurboTasksBackendInner};
+use crate::{
+    backend::storage::{get, iter_many},
+    backing_storage::BackingStorage,
+};
+
+/// How far the computation below a root task has progressed, see
+/// [`TurboTasksBackend::progress`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskProgress {
+    /// Number of tasks below the root, including the root, that are dirty in the current session.
+    pub dirty: usize,
+    /// Number of tasks in memory below the root, including the root.
+    pub total: usize,
+}
+
+impl TaskProgress {
+    pub fn is_done(&self) -> bool {
+        self.dirty == 0
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Returns the progress of `root_task`. The dirty count is only complete for root tasks, as
+    /// only those aggregate the dirty state of all tasks below them.
+    pub fn progress(&self, root_task: TaskId) -> TaskProgress {
+        self.0.progress(root_task)
+    }
+
+    /// Returns a receiver for the progress of `root_task`. It's recomputed every `interval` and
+    /// the receiver is notified when it has changed. Stops when all receivers are dropped.
+    pub fn watch_progress(
+        &self,
+        root_task: TaskId,
+        interval: Duration,
+    ) -> watch::Receiver<TaskProgress> {
+        let (sender, receiver) = watch::channel(self.progress(root_task));
+        let inner = self.0.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = sender.closed() => return,
+                    _ = tokio::time::sleep(interval) => {},
+                }
+                let progress = inner.progress(root_task);
+                sender.send_if_modified(|current| {
+                    if *current == progress {
+                        return false;
+                    }
+                    *current = progress;
+                    true
+                });
+            }
+        });
+        receiver
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    fn progress(&self, root_task: TaskId) -> TaskProgress {
+        let dirty = {
+            let task = self.storage.access_mut(root_task);
+            let is_dirty =
+                get!(task, Dirty).map_or(false, |dirty_state| dirty_state.get(self.session_id));
+            let dirty_tasks = get!(task, AggregatedDirtyContainerCount)
+                .map_or(0, |count| count.get(self.session_id));
+            dirty_tasks.max(0) as usize + usize::from(is_dirty)
+        };
+
+        let mut visited = HashSet::new();
+        visited.insert(root_task);
+        let mut stack = vec![root_task];
+        while let Some(task_id) = stack.pop() {
+            let task = self.storage.access_mut(task_id);
+            for child in iter_many!(task, Child { task } => *task) {
+                if visited.insert(child) {
+                    stack.push(child);
+                }
+            }
+        }
+
+        TaskProgress {
+            dirty,
+            total: visited.len(),
+        }
+    }
+}
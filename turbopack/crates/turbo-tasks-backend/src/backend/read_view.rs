@@ -0,0 +1,103 @@
+use anyhow::Result;
+use turbo_tasks::{backend::CachedTaskType, CellId, RawVc, TaskId, TypedSharedReference};
+
+use super::{TaskDataCategory, TurboTasksBackend};
+use crate::{
+    backing_storage::BackingStorage,
+    data::{CachedDataItem, OutputValue},
+};
+
+/// A point-in-time view of the persisted state of the backend, see
+/// [`TurboTasksBackend::read_view`].
+///
+/// All reads go through a single read transaction of the backing storage, so they return the
+/// state of the last snapshot that was written before the view was created, even when snapshots
+/// are written while the view is used. Transient tasks and changes that haven't been persisted yet
+/// are not visible.
+pub struct ReadView<'a, B: BackingStorage> {
+    backing_storage: &'a B,
+    tx: Option<B::ReadTransaction<'a>>,
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Creates a [`ReadView`] of the state persisted so far. Call [`Self::flush`] before to
+    /// include all changes made until then.
+    ///
+    /// The view is only isolated from concurrent snapshots when the backing storage supports read
+    /// transactions.
+    pub fn read_view(&self) -> ReadView<'_, B> {
+        let backing_storage = &*self.0.backing_storage;
+        ReadView {
+            backing_storage,
+            tx: backing_storage.start_read_transaction(),
+        }
+    }
+}
+
+impl<B: BackingStorage> ReadView<'_, B> {
+    /// Looks up the persistent task for the given task type.
+    pub fn lookup_task(&self, task_type: &CachedTaskType) -> Option<TaskId> {
+        // Safety: `tx` is a transaction from this BackingStorage instance.
+        unsafe {
+            self.backing_storage
+                .forward_lookup_task_cache(self.tx.as_ref(), task_type)
+        }
+    }
+
+    /// Returns the output of the task, `None` when the task has no persisted output.
+    pub fn task_output(&self, task_id: TaskId) -> Option<Result<RawVc>> {
+        let output = self
+            .lookup(task_id, TaskDataCategory::Meta)
+            .find_map(|item| match item {
+                CachedDataItem::Output { value } => Some(value),
+                _ => None,
+            })?;
+        Some(match output {
+            OutputValue::Cell(cell) => Ok(RawVc::TaskCell(cell.task, cell.cell)),
+            OutputValue::Output(task) => Ok(RawVc::TaskOutput(task)),
+            OutputValue::Error | OutputValue::Panic => {
+                let error =
+                    self.lookup(task_id, TaskDataCategory::Data)
+                        .find_map(|item| match item {
+                            CachedDataItem::Error { value } => Some(value),
+                            _ => None,
+                        })?;
+                Err(error.into())
+            }
+        })
+    }
+
+    /// Returns the content of the cell, `None` when the cell isn't persisted.
+    pub fn task_cell(&self, task_id: TaskId, cell: CellId) -> Option<TypedSharedReference> {
+        self.lookup(task_id, TaskDataCategory::Data)
+            .find_map(|item| match item {
+                CachedDataItem::CellData {
+                    cell: item_cell,
+                    value,
+                } if item_cell == cell => Some(Ok(value)),
+                CachedDataItem::SerializedCellData {
+                    cell: item_cell,
+                    value,
+                } if item_cell == cell => Some(value.deserialize()),
+                _ => None,
+            })?
+            .ok()
+    }
+
+    fn lookup(
+        &self,
+        task_id: TaskId,
+        category: TaskDataCategory,
+    ) -> impl Iterator<Item = CachedDataItem> {
+        let items = if task_id.is_transient() {
+            Vec::new()
+        } else {
+            // Safety: `tx` is a transaction from this BackingStorage instance.
+            unsafe {
+                self.backing_storage
+                    .lookup_data(self.tx.as_ref(), task_id, category)
+            }
+        };
+        items.into_iter()
+    }
+}
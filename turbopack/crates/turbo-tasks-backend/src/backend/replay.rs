@@ -0,0 +1,159 @@
+//! Replays recorded operations against a backend, to turn caches that were corrupted by a bug in
+//! the operation state machines into regression tests.
+//!
+//! A test opens a copy of the database the recording started with, replays the operation log
+//! recorded with [`BackendOptions::operation_log`] and compares the resulting storage with the
+//! expected state, e.g. the database the recording ended with:
+//!
+//! ```ignore
+//! let operations = RecordedOperations::read(&log_path)?;
+//! let tt = TurboTasks::new(TurboTasksBackend::new(options, lmdb_backing_storage(&db_path)?));
+//! tt.backend().replay_operations(operations, &*tt);
+//! let expected = CacheInspector::open(&expected_db_path)?.storage_dump()?;
+//! assert_eq!(tt.backend().storage_dump().differences(&expected), Vec::<String>::new());
+//! ```
+//!
+//! [`BackendOptions::operation_log`]: crate::BackendOptions::operation_log
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::atomic::Ordering,
+};
+
+use anyhow::Result;
+use turbo_tasks::{TaskId, TurboTasksBackendApi};
+
+use super::{
+    operation::AnyOperation, operation_log::read_started_operations, TaskDataCategory,
+    TurboTasksBackend,
+};
+use crate::{
+    backing_storage::BackingStorage,
+    data::{CachedDataItemKey, CachedDataItemValue},
+};
+
+/// The top-level operations of an operation log, in the order they were started.
+pub struct RecordedOperations {
+    operations: Vec<AnyOperation>,
+}
+
+impl RecordedOperations {
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(Self {
+            operations: read_started_operations(path)?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+/// The persistent items of a task, formatted for comparison. A category is `None` when it wasn't
+/// restored into memory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskDump {
+    pub meta: Option<BTreeSet<String>>,
+    pub data: Option<BTreeSet<String>>,
+}
+
+/// The persistent items of multiple tasks, see [`TurboTasksBackend::storage_dump`] and
+/// [`CacheInspector::storage_dump`][crate::inspect::CacheInspector::storage_dump].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageDump {
+    pub tasks: BTreeMap<TaskId, TaskDump>,
+}
+
+impl StorageDump {
+    /// Describes how the tasks of this dump differ from the same tasks in `expected`. Only
+    /// categories that are present in both dumps are compared.
+    pub fn differences(&self, expected: &StorageDump) -> Vec<String> {
+        let mut differences = Vec::new();
+        let empty = TaskDump::default();
+        for (task_id, task) in self.tasks.iter() {
+            let expected_task = expected.tasks.get(task_id).unwrap_or(&empty);
+            for (category, items, expected_items) in [
+                ("meta", &task.meta, &expected_task.meta),
+                ("data", &task.data, &expected_task.data),
+            ] {
+                let (Some(items), Some(expected_items)) = (items, expected_items) else {
+                    continue;
+                };
+                for item in items.difference(expected_items) {
+                    differences.push(format!("{task_id} {category} unexpected: {item}"));
+                }
+                for item in expected_items.difference(items) {
+                    differences.push(format!("{task_id} {category} missing: {item}"));
+                }
+            }
+        }
+        differences
+    }
+}
+
+/// Formats a persistent item for a [`StorageDump`]. Cell contents are not compared, as they are
+/// only stored serialized.
+pub(crate) fn format_item(key: &CachedDataItemKey, value: &CachedDataItemValue) -> String {
+    match *key {
+        CachedDataItemKey::CellData { cell }
+        | CachedDataItemKey::SerializedCellData { cell }
+        | CachedDataItemKey::SpilledCell { cell } => {
+            format!("{:?}", CachedDataItemKey::CellData { cell })
+        }
+        _ => format!("{key:?} => {value:?}"),
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackend<B> {
+    /// Executes the recorded operations in order. Tasks scheduled by the operations are not
+    /// executed, so the resulting storage only depends on the operations and the initial state.
+    pub fn replay_operations(
+        &self,
+        operations: RecordedOperations,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.replaying_operations.store(true, Ordering::Release);
+        for operation in operations.operations {
+            operation.execute(&mut self.0.execute_context(turbo_tasks));
+        }
+        self.0.replaying_operations.store(false, Ordering::Release);
+    }
+
+    /// Returns the persistent items of all tasks in memory.
+    pub fn storage_dump(&self) -> StorageDump {
+        let mut dump = StorageDump::default();
+        self.0.storage.for_each(|&task_id, task| {
+            if task_id.is_transient() {
+                return;
+            }
+            let state = task.persistance_state();
+            let mut task_dump = TaskDump {
+                meta: state
+                    .is_restored(TaskDataCategory::Meta)
+                    .then(BTreeSet::new),
+                data: state
+                    .is_restored(TaskDataCategory::Data)
+                    .then(BTreeSet::new),
+            };
+            for (key, value) in task.iter_all() {
+                if !key.is_persistent() || !value.is_persistent() {
+                    continue;
+                }
+                let items = match key.category() {
+                    TaskDataCategory::Meta => &mut task_dump.meta,
+                    _ => &mut task_dump.data,
+                };
+                if let Some(items) = items {
+                    items.insert(format_item(key, value));
+                }
+            }
+            dump.tasks.insert(task_id, task_dump);
+        });
+        dump
+    }
+}
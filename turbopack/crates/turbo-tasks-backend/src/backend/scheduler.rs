@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+use parking_lot::Mutex;
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_tasks::TaskId;
+
+/// Shares the task executions between tasks that are read by somebody and tasks that are only
+/// recomputed because they are dirty, e.g. the dirty tasks restored after a warm start. See
+/// [`super::BackendOptions::fair_scheduling`].
+///
+/// Demand-driven tasks are always started right away. Revalidation tasks are queued when they
+/// would exceed their share of `concurrency`. As long as no demand-driven task is running, they
+/// can use all of it.
+#[derive(Debug, Clone)]
+pub struct FairSchedulingOptions {
+    /// The number of concurrent task executions that is shared by the weights, usually the
+    /// number of CPU cores.
+    pub concurrency: usize,
+    /// The weight of tasks whose output or cells are read.
+    pub demand_weight: usize,
+    /// The weight of dirty tasks that are recomputed because they are part of an active
+    /// aggregation tree, without being read.
+    pub revalidation_weight: usize,
+}
+
+impl Default for FairSchedulingOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: std::thread::available_parallelism().map_or(4, |v| v.get()),
+            demand_weight: 3,
+            revalidation_weight: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SchedulingClass {
+    Demand,
+    Revalidation,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    /// Revalidation tasks that wait for a free share, in the order they were scheduled. Tasks
+    /// that are no longer in `queued` have been promoted and are skipped.
+    queue: VecDeque<TaskId>,
+    queued: FxHashSet<TaskId>,
+    running: FxHashMap<TaskId, SchedulingClass>,
+    running_demand: usize,
+    running_revalidation: usize,
+}
+
+impl SchedulerState {
+    fn start(&mut self, task_id: TaskId, class: SchedulingClass) {
+        if let Some(previous) = self.running.insert(task_id, class) {
+            self.count(previous, false);
+        }
+        self.count(class, true);
+    }
+
+    fn count(&mut self, class: SchedulingClass, started: bool) {
+        let counter = match class {
+            SchedulingClass::Demand => &mut self.running_demand,
+            SchedulingClass::Revalidation => &mut self.running_revalidation,
+        };
+        if started {
+            *counter += 1;
+        } else {
+            *counter -= 1;
+        }
+    }
+}
+
+pub struct FairScheduler {
+    options: FairSchedulingOptions,
+    state: Mutex<SchedulerState>,
+}
+
+impl FairScheduler {
+    pub fn new(options: FairSchedulingOptions) -> Self {
+        Self {
+            options,
+            state: Mutex::new(SchedulerState::default()),
+        }
+    }
+
+    fn revalidation_share(&self, state: &SchedulerState) -> usize {
+        let FairSchedulingOptions {
+            concurrency,
+            demand_weight,
+            revalidation_weight,
+        } = self.options;
+        if state.running_demand == 0 {
+            return concurrency.max(1);
+        }
+        let total_weight = (demand_weight + revalidation_weight).max(1);
+        (concurrency * revalidation_weight / total_weight).max(1)
+    }
+
+    /// Schedules a task that somebody reads. It's started right away.
+    pub fn schedule_demand(&self, task_id: TaskId) {
+        self.state.lock().start(task_id, SchedulingClass::Demand);
+    }
+
+    /// Schedules a dirty task that is recomputed without being read. Returns true when the task
+    /// has to be started now, otherwise it's started by [`Self::execution_finished`] later.
+    pub fn schedule_revalidation(&self, task_id: TaskId) -> bool {
+        let mut state = self.state.lock();
+        if state.queued.is_empty() && state.running_revalidation < self.revalidation_share(&state) {
+            state.start(task_id, SchedulingClass::Revalidation);
+            true
+        } else {
+            state.queue.push_back(task_id);
+            state.queued.insert(task_id);
+            false
+        }
+    }
+
+    /// A scheduled task is read by somebody. Returns true when it was still queued and has to be
+    /// started now.
+    pub fn promote(&self, task_id: TaskId) -> bool {
+        let mut state = self.state.lock();
+        if state.queued.remove(&task_id) {
+            state.start(task_id, SchedulingClass::Demand);
+            return true;
+        }
+        if state.running.get(&task_id) == Some(&SchedulingClass::Revalidation) {
+            // Count it as demand-driven, so the revalidation tasks get their share back
+            state.start(task_id, SchedulingClass::Demand);
+        }
+        false
+    }
+
+    /// The execution of a task has finished. Returns the queued tasks that have to be started
+    /// now.
+    pub fn execution_finished(&self, task_id: TaskId) -> Vec<TaskId> {
+        let mut state = self.state.lock();
+        let Some(class) = state.running.remove(&task_id) else {
+            return Vec::new();
+        };
+        state.count(class, false);
+        let mut tasks_to_start = Vec::new();
+        while state.running_revalidation < self.revalidation_share(&state) {
+            let Some(task_id) = state.queue.pop_front() else {
+                break;
+            };
+            if !state.queued.remove(&task_id) {
+                continue;
+            }
+            state.start(task_id, SchedulingClass::Revalidation);
+            tasks_to_start.push(task_id);
+        }
+        tasks_to_start
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt::Debug;
+
+use tokio::time::Duration;
+
+/// The state the snapshot job passes to a [`SnapshotPolicy`] to decide when the next snapshot
+/// should be taken.
+#[derive(Debug, Clone)]
+pub struct SnapshotPolicyState {
+    /// No snapshot has been taken in this session yet.
+    pub first_snapshot: bool,
+    /// Time since the last snapshot, or since startup for the first snapshot.
+    pub since_last_snapshot: Duration,
+    /// Number of changes collected since the last snapshot.
+    pub pending_log_items: usize,
+    /// How long the system has been idle, or `None` when tasks are executing.
+    pub idle_for: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotDecision {
+    /// Take a snapshot right now.
+    Now,
+    /// Ask the policy again after this duration. The policy is also asked again when the idle
+    /// state changes.
+    After(Duration),
+    /// Only ask the policy again when the idle state changes.
+    OnIdleChange,
+}
+
+/// Decides when the background job persists a snapshot. A snapshot is always taken when the
+/// backend is stopping or [`BackendOptions::max_pending_log_items`] is exceeded, independent of
+/// the policy.
+///
+/// [`BackendOptions::max_pending_log_items`]: crate::BackendOptions::max_pending_log_items
+pub trait SnapshotPolicy: Debug + Send + Sync {
+    fn next_snapshot(&self, state: &SnapshotPolicyState) -> SnapshotDecision;
+}
+
+/// Snapshots periodically and additionally whenever the system becomes idle.
+#[derive(Debug, Clone)]
+pub struct DefaultSnapshotPolicy {
+    pub first_snapshot_wait: Duration,
+    pub snapshot_interval: Duration,
+    pub idle_timeout: Duration,
+}
+
+impl Default for DefaultSnapshotPolicy {
+    fn default() -> Self {
+        Self {
+            first_snapshot_wait: Duration::from_secs(30),
+            snapshot_interval: Duration::from_secs(15),
+            idle_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl SnapshotPolicy for DefaultSnapshotPolicy {
+    fn next_snapshot(&self, state: &SnapshotPolicyState) -> SnapshotDecision {
+        let interval = if state.first_snapshot {
+            self.first_snapshot_wait
+        } else {
+            self.snapshot_interval
+        };
+        let remaining = interval.saturating_sub(state.since_last_snapshot);
+        if remaining.is_zero() {
+            return SnapshotDecision::Now;
+        }
+        let Some(idle_for) = state.idle_for else {
+            return SnapshotDecision::After(remaining);
+        };
+        let idle_remaining = self.idle_timeout.saturating_sub(idle_for);
+        if idle_remaining.is_zero() {
+            SnapshotDecision::Now
+        } else {
+            SnapshotDecision::After(remaining.min(idle_remaining))
+        }
+    }
+}
+
+/// Only snapshots when the system has been idle for `idle_timeout` and there are changes to
+/// persist. Useful for builds that don't need the cache to be persisted while they are busy.
+#[derive(Debug, Clone)]
+pub struct IdleSnapshotPolicy {
+    pub idle_timeout: Duration,
+}
+
+impl SnapshotPolicy for IdleSnapshotPolicy {
+    fn next_snapshot(&self, state: &SnapshotPolicyState) -> SnapshotDecision {
+        let Some(idle_for) = state.idle_for else {
+            return SnapshotDecision::OnIdleChange;
+        };
+        if state.pending_log_items == 0 {
+            return SnapshotDecision::OnIdleChange;
+        }
+        let idle_remaining = self.idle_timeout.saturating_sub(idle_for);
+        if idle_remaining.is_zero() {
+            SnapshotDecision::Now
+        } else {
+            SnapshotDecision::After(idle_remaining)
+        }
+    }
+}
+
+/// Never snapshots while running. The cache is only persisted when the backend is stopping.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSnapshotPolicy;
+
+impl SnapshotPolicy for ShutdownSnapshotPolicy {
+    fn next_snapshot(&self, _state: &SnapshotPolicyState) -> SnapshotDecision {
+        SnapshotDecision::OnIdleChange
+    }
+}
@@ -1,19 +1,20 @@
 use std::{
     hash::{BuildHasherDefault, Hash},
     mem::take,
-    ops::{Deref, DerefMut},
+    ops::{AddAssign, Deref, DerefMut},
+    sync::atomic::{AtomicU32, Ordering},
     thread::available_parallelism,
 };
 
 use auto_hash_map::{map::Entry, AutoMap};
-use dashmap::DashMap;
+use dashmap::{mapref::one::Ref, DashMap};
 use either::Either;
 use rustc_hash::FxHasher;
 use turbo_tasks::KeyValuePair;
 
 use crate::{
     backend::indexed::Indexed,
-    utils::dash_map_multi::{get_multiple_mut, RefMut},
+    utils::dash_map_multi::{get_many_mut, get_multiple_mut, RefMut},
 };
 
 const META_UNRESTORED: u32 = 1 << 31;
@@ -81,17 +82,29 @@ impl Iterator for TaskDataCategoryIterator {
 
 pub struct PersistanceState {
     value: u32,
+    /// The storage generation in which the task was accessed the last time. Atomic, so that
+    /// reads through a shared lock can update it too.
+    last_access: AtomicU32,
 }
 
 impl Default for PersistanceState {
     fn default() -> Self {
         Self {
             value: META_UNRESTORED | DATA_UNRESTORED,
+            last_access: AtomicU32::new(0),
         }
     }
 }
 
 impl PersistanceState {
+    pub fn mark_accessed(&self, generation: u32) {
+        self.last_access.store(generation, Ordering::Relaxed);
+    }
+
+    pub fn last_access(&self) -> u32 {
+        self.last_access.load(Ordering::Relaxed)
+    }
+
     pub fn set_restored(&mut self, category: TaskDataCategory) {
         self.value &= !category.flag();
     }
@@ -118,6 +131,55 @@ impl PersistanceState {
     }
 }
 
+/// What a stored item is used for, for the memory accounting of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCategory {
+    Cell,
+    Edge,
+    Other,
+}
+
+pub trait MemoryCategorized {
+    fn memory_category(&self) -> MemoryCategory;
+}
+
+/// Estimates the heap memory owned by a stored value, for the memory accounting of a task.
+pub trait HeapSize {
+    fn heap_size(&self) -> usize;
+}
+
+/// Approximate number of bytes used by the items of a task. Every item is counted with the size
+/// of its key and value plus the heap memory owned by the value, see [`HeapSize`]. Values that are
+/// changed in place keep the size they had when they were stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskMemoryUsage {
+    pub cells: usize,
+    pub edges: usize,
+    pub other: usize,
+}
+
+impl TaskMemoryUsage {
+    pub fn total(&self) -> usize {
+        self.cells + self.edges + self.other
+    }
+
+    fn category_mut(&mut self, category: MemoryCategory) -> &mut usize {
+        match category {
+            MemoryCategory::Cell => &mut self.cells,
+            MemoryCategory::Edge => &mut self.edges,
+            MemoryCategory::Other => &mut self.other,
+        }
+    }
+}
+
+impl AddAssign for TaskMemoryUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.cells += other.cells;
+        self.edges += other.edges;
+        self.other += other.other;
+    }
+}
+
 const INDEX_THRESHOLD: usize = 1024;
 
 type IndexedMap<T> = AutoMap<
@@ -127,26 +189,75 @@ type IndexedMap<T> = AutoMap<
 
 pub enum InnerStorage<T: KeyValuePair>
 where
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
 {
     Plain {
         map: AutoMap<T::Key, T::Value>,
         persistance_state: PersistanceState,
+        memory_usage: TaskMemoryUsage,
+        version: u32,
     },
     Indexed {
         map: IndexedMap<T>,
         persistance_state: PersistanceState,
+        memory_usage: TaskMemoryUsage,
+        version: u32,
     },
 }
 
 impl<T: KeyValuePair> InnerStorage<T>
 where
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
 {
     fn new() -> Self {
         Self::Plain {
             map: AutoMap::new(),
             persistance_state: PersistanceState::default(),
+            memory_usage: TaskMemoryUsage::default(),
+            version: 0,
+        }
+    }
+
+    pub fn memory_usage(&self) -> &TaskMemoryUsage {
+        match self {
+            InnerStorage::Plain { memory_usage, .. } => memory_usage,
+            InnerStorage::Indexed { memory_usage, .. } => memory_usage,
+        }
+    }
+
+    /// Increased on every change of the items, so a reader can check that the task hasn't changed
+    /// between two reads without holding the lock in between.
+    pub fn version(&self) -> u32 {
+        match self {
+            InnerStorage::Plain { version, .. } => *version,
+            InnerStorage::Indexed { version, .. } => *version,
+        }
+    }
+
+    fn bump_version(&mut self) {
+        let version = match self {
+            InnerStorage::Plain { version, .. } => version,
+            InnerStorage::Indexed { version, .. } => version,
+        };
+        *version = version.wrapping_add(1);
+    }
+
+    fn item_size(value: &T::Value) -> usize {
+        size_of::<T::Key>() + size_of::<T::Value>() + value.heap_size()
+    }
+
+    fn track_memory(&mut self, category: MemoryCategory, size: usize, added: bool) {
+        let memory_usage = match self {
+            InnerStorage::Plain { memory_usage, .. } => memory_usage,
+            InnerStorage::Indexed { memory_usage, .. } => memory_usage,
+        };
+        let bytes = memory_usage.category_mut(category);
+        if added {
+            *bytes += size;
+        } else {
+            *bytes -= size;
         }
     }
 
@@ -176,6 +287,8 @@ where
         let InnerStorage::Plain {
             map: plain_map,
             persistance_state,
+            memory_usage,
+            version,
         } = self
         else {
             return;
@@ -189,6 +302,8 @@ where
             *self = InnerStorage::Indexed {
                 map,
                 persistance_state: take(persistance_state),
+                memory_usage: *memory_usage,
+                version: *version,
             };
         }
     }
@@ -225,22 +340,42 @@ where
 
     pub fn add(&mut self, item: T) -> bool {
         let (key, value) = item.into_key_and_value();
-        match self.get_or_create_map_mut(&key).entry(key) {
+        let category = key.memory_category();
+        let size = Self::item_size(&value);
+        let added = match self.get_or_create_map_mut(&key).entry(key) {
             Entry::Occupied(_) => false,
             Entry::Vacant(e) => {
                 e.insert(value);
                 true
             }
+        };
+        if added {
+            self.track_memory(category, size, true);
+            self.bump_version();
         }
+        added
     }
 
     pub fn insert(&mut self, item: T) -> Option<T::Value> {
         let (key, value) = item.into_key_and_value();
-        self.get_or_create_map_mut(&key).insert(key, value)
+        let category = key.memory_category();
+        let size = Self::item_size(&value);
+        let old = self.get_or_create_map_mut(&key).insert(key, value);
+        if let Some(old) = &old {
+            self.track_memory(category, Self::item_size(old), false);
+        }
+        self.track_memory(category, size, true);
+        self.bump_version();
+        old
     }
 
     pub fn remove(&mut self, key: &T::Key) -> Option<T::Value> {
-        self.get_map_mut(key).and_then(|m| m.remove(key))
+        let old = self.get_map_mut(key).and_then(|m| m.remove(key));
+        if let Some(old) = &old {
+            self.track_memory(key.memory_category(), Self::item_size(old), false);
+            self.bump_version();
+        }
+        old
     }
 
     pub fn get(&self, key: &T::Key) -> Option<&T::Value> {
@@ -248,6 +383,8 @@ where
     }
 
     pub fn get_mut(&mut self, key: &T::Key) -> Option<&mut T::Value> {
+        // The caller might change the value
+        self.bump_version();
         self.get_map_mut(key).and_then(|m| m.get_mut(key))
     }
 
@@ -283,7 +420,8 @@ where
 
 impl<T: KeyValuePair> InnerStorage<T>
 where
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
     T::Value: Default,
     T::Key: Clone,
 {
@@ -292,31 +430,53 @@ where
         key: &T::Key,
         update: impl FnOnce(Option<T::Value>) -> Option<T::Value>,
     ) {
+        self.bump_version();
         let map = self.get_or_create_map_mut(key);
-        if let Some(value) = map.get_mut(key) {
+        let (old_size, new_size) = if let Some(value) = map.get_mut(key) {
             let v = take(value);
+            let old_size = Self::item_size(&v);
             if let Some(v) = update(Some(v)) {
+                let new_size = Self::item_size(&v);
                 *value = v;
+                (Some(old_size), Some(new_size))
             } else {
                 map.remove(key);
+                (Some(old_size), None)
             }
         } else if let Some(v) = update(None) {
+            let new_size = Self::item_size(&v);
             map.insert(key.clone(), v);
+            (None, Some(new_size))
+        } else {
+            (None, None)
+        };
+        let category = key.memory_category();
+        if let Some(size) = old_size {
+            self.track_memory(category, size, false);
+        }
+        if let Some(size) = new_size {
+            self.track_memory(category, size, true);
         }
     }
 }
 
 pub struct Storage<K, T: KeyValuePair>
 where
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
 {
     map: DashMap<K, InnerStorage<T>, BuildHasherDefault<FxHasher>>,
+    /// The current generation. Every access marks the item with the current generation. It's
+    /// increased with every snapshot, so it can be used to find items that are unchanged since
+    /// the last snapshot.
+    generation: AtomicU32,
 }
 
 impl<K, T> Storage<K, T>
 where
     T: KeyValuePair,
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
     K: Eq + std::hash::Hash + Clone,
 {
     pub fn new() -> Self {
@@ -328,36 +488,119 @@ where
                 Default::default(),
                 shard_amount,
             ),
+            generation: AtomicU32::new(1),
         }
     }
 
+    pub fn generation(&self) -> u32 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Starts a new generation and returns the previous one.
+    pub fn next_generation(&self) -> u32 {
+        self.generation.fetch_add(1, Ordering::AcqRel)
+    }
+
     pub fn access_mut(&self, key: K) -> StorageWriteGuard<'_, K, T> {
-        let inner = match self.map.entry(key) {
+        let mut inner = match self.map.entry(key) {
             dashmap::mapref::entry::Entry::Occupied(e) => e.into_ref(),
             dashmap::mapref::entry::Entry::Vacant(e) => e.insert(InnerStorage::new()),
         };
+        inner
+            .persistance_state_mut()
+            .mark_accessed(self.generation());
         StorageWriteGuard {
             inner: inner.into(),
         }
     }
 
+    /// Returns the item for reading, when it exists. Unlike [`Self::access_mut`], this only takes
+    /// the shard lock for reading, so concurrent readers don't block each other. The item is not
+    /// marked as accessed, callers that use the item need to call
+    /// [`PersistanceState::mark_accessed`] themselves.
+    pub fn access(&self, key: &K) -> Option<StorageReadGuard<'_, K, T>> {
+        self.map.get(key).map(|inner| StorageReadGuard { inner })
+    }
+
     pub fn access_pair_mut(
         &self,
         key1: K,
         key2: K,
     ) -> (StorageWriteGuard<'_, K, T>, StorageWriteGuard<'_, K, T>) {
-        let (a, b) = get_multiple_mut(&self.map, key1, key2, || InnerStorage::new());
+        let (mut a, mut b) = get_multiple_mut(&self.map, key1, key2, || InnerStorage::new());
+        let generation = self.generation();
+        a.persistance_state_mut().mark_accessed(generation);
+        b.persistance_state_mut().mark_accessed(generation);
         (
             StorageWriteGuard { inner: a },
             StorageWriteGuard { inner: b },
         )
     }
+
+    /// Locks multiple distinct items at once, see [`get_many_mut`].
+    pub fn access_many_mut<const N: usize>(
+        &self,
+        keys: [K; N],
+    ) -> [StorageWriteGuard<'_, K, T>; N] {
+        let generation = self.generation();
+        get_many_mut(&self.map, keys, || InnerStorage::new()).map(|mut inner| {
+            inner.persistance_state_mut().mark_accessed(generation);
+            StorageWriteGuard { inner }
+        })
+    }
+}
+
+impl<K, T> Storage<K, T>
+where
+    T: KeyValuePair,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
+    K: Eq + std::hash::Hash + Clone,
+{
+    /// Calls `f` for every item. Items are not marked as accessed.
+    pub fn for_each(&self, mut f: impl FnMut(&K, &InnerStorage<T>)) {
+        for entry in self.map.iter() {
+            f(entry.key(), entry.value());
+        }
+    }
+
+    /// Removes the item when `predicate` returns true. The predicate is evaluated while the item
+    /// is locked.
+    pub fn remove_if(&self, key: &K, predicate: impl FnOnce(&InnerStorage<T>) -> bool) -> bool {
+        self.map
+            .remove_if(key, |_, value| predicate(value))
+            .is_some()
+    }
+}
+
+pub struct StorageReadGuard<'a, K, T>
+where
+    T: KeyValuePair,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
+{
+    inner: Ref<'a, K, InnerStorage<T>, BuildHasherDefault<FxHasher>>,
+}
+
+impl<K, T> Deref for StorageReadGuard<'_, K, T>
+where
+    T: KeyValuePair,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
+    K: Eq + Hash,
+{
+    type Target = InnerStorage<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
 }
 
 pub struct StorageWriteGuard<'a, K, T>
 where
     T: KeyValuePair,
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
 {
     inner: RefMut<'a, K, InnerStorage<T>, BuildHasherDefault<FxHasher>>,
 }
@@ -365,7 +608,8 @@ where
 impl<K, T> Deref for StorageWriteGuard<'_, K, T>
 where
     T: KeyValuePair,
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
     K: Eq + Hash,
 {
     type Target = InnerStorage<T>;
@@ -378,7 +622,8 @@ where
 impl<K, T> DerefMut for StorageWriteGuard<'_, K, T>
 where
     T: KeyValuePair,
-    T::Key: Indexed,
+    T::Key: Indexed + MemoryCategorized,
+    T::Value: HeapSize,
     K: Eq + Hash,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashSet;
+use turbo_tasks::{backend::CachedTaskType, TaskId};
+
+use crate::utils::bi_map::BiMap;
+
+/// The state of the in-memory task cache, see [`super::TurboTasksBackend::task_cache_statistics`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaskCacheStatistics {
+    /// Number of task types currently in memory.
+    pub entries: usize,
+    /// Number of entries removed from memory to stay below
+    /// [`super::BackendOptions::task_cache_limit`].
+    pub evictions: usize,
+    /// Number of entries read from the backing storage, either because they were not used yet in
+    /// this session or because they had been evicted.
+    pub fetches: usize,
+}
+
+/// The tasks whose task cache entry can be evicted, in the order they became evictable. Each task
+/// is queued at most once. Only the ids are kept, so evicted task types are freed.
+#[derive(Default)]
+struct EvictionQueue {
+    order: VecDeque<TaskId>,
+    queued: FxHashSet<TaskId>,
+}
+
+/// Bounds the number of entries of the task cache. Only entries that are in the backing storage
+/// are evicted, as they can always be fetched again. Evicts the entries in the order they became
+/// evictable. Entries that are removed from the backing storage later, e.g. of non-persistable
+/// tasks, are skipped when they are evicted.
+pub struct TaskCacheLimit {
+    max_entries: Option<usize>,
+    evictable: Mutex<EvictionQueue>,
+    evictions: AtomicUsize,
+    fetches: AtomicUsize,
+}
+
+impl TaskCacheLimit {
+    pub fn new(max_entries: Option<usize>) -> Self {
+        Self {
+            max_entries,
+            evictable: Mutex::new(EvictionQueue::default()),
+            evictions: AtomicUsize::new(0),
+            fetches: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_entries.is_some()
+    }
+
+    /// An entry has been read from the backing storage.
+    pub fn fetched(
+        &self,
+        task_cache: &BiMap<Arc<CachedTaskType>, TaskId>,
+        task_id: TaskId,
+        is_evictable: impl Fn(TaskId) -> bool,
+    ) {
+        self.fetches.fetch_add(1, Ordering::Relaxed);
+        self.persisted(task_cache, [task_id], is_evictable);
+    }
+
+    /// The entries of these tasks have been written to the backing storage.
+    pub fn persisted(
+        &self,
+        task_cache: &BiMap<Arc<CachedTaskType>, TaskId>,
+        task_ids: impl IntoIterator<Item = TaskId>,
+        is_evictable: impl Fn(TaskId) -> bool,
+    ) {
+        let Some(max_entries) = self.max_entries else {
+            return;
+        };
+        let mut evictable = self.evictable.lock();
+        let EvictionQueue { order, queued } = &mut *evictable;
+        order.extend(
+            task_ids
+                .into_iter()
+                .filter(|&task_id| queued.insert(task_id)),
+        );
+        while task_cache.len() > max_entries {
+            let Some(task_id) = order.pop_front() else {
+                break;
+            };
+            queued.remove(&task_id);
+            if !is_evictable(task_id) {
+                continue;
+            }
+            let Some(task_type) = task_cache.lookup_reverse(&task_id) else {
+                continue;
+            };
+            if task_cache.remove(&task_type, &task_id) {
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn statistics(
+        &self,
+        task_cache: &BiMap<Arc<CachedTaskType>, TaskId>,
+    ) -> TaskCacheStatistics {
+        TaskCacheStatistics {
+            entries: task_cache.len(),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            fetches: self.fetches.load(Ordering::Relaxed),
+        }
+    }
+}
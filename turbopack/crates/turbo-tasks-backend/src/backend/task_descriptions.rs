@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use turbo_tasks::TaskId;
+
+/// The maximum number of formatted task descriptions kept in memory.
+const MAX_ENTRIES: usize = 10_000;
+
+/// A bounded cache of formatted task descriptions. Descriptions of persistent tasks might need a
+/// lookup in the backing storage, so they are only formatted when they are actually rendered and
+/// kept for later renders. When full, the least recently used half of the entries is evicted.
+#[derive(Default)]
+pub struct TaskDescriptions {
+    state: Mutex<TaskDescriptionsState>,
+}
+
+#[derive(Default)]
+struct TaskDescriptionsState {
+    /// The description and the last time it was used.
+    entries: FxHashMap<TaskId, (Arc<str>, u64)>,
+    clock: u64,
+}
+
+impl TaskDescriptions {
+    pub fn get(&self, task_id: TaskId) -> Option<Arc<str>> {
+        let mut state = self.state.lock();
+        state.clock += 1;
+        let clock = state.clock;
+        let (description, last_used) = state.entries.get_mut(&task_id)?;
+        *last_used = clock;
+        Some(description.clone())
+    }
+
+    pub fn insert(&self, task_id: TaskId, description: Arc<str>) {
+        let mut state = self.state.lock();
+        state.clock += 1;
+        let clock = state.clock;
+        state.entries.insert(task_id, (description, clock));
+        if state.entries.len() > MAX_ENTRIES {
+            let mut last_used = state
+                .entries
+                .values()
+                .map(|&(_, last_used)| last_used)
+                .collect::<Vec<_>>();
+            let (_, &mut median, _) = last_used.select_nth_unstable(MAX_ENTRIES / 2);
+            state
+                .entries
+                .retain(|_, &mut (_, last_used)| last_used > median);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Shares one read transaction of the backing storage between all lookups during a single poll
+//! ("turn") of a task execution, instead of starting a transaction for every cache miss. The
+//! transaction can't be kept across polls, since the execution might continue on another thread.
+//!
+//! The transaction is kept open until the poll returns. A read transaction of LMDB prevents the
+//! database from reusing pages that have been freed since it was started, so a long running
+//! poll grows the database file while snapshots are committed. Task executions should yield
+//! regularly during long synchronous work anyway, which also bounds the lifetime of the
+//! transaction.
+
+use std::{
+    cell::{Cell, OnceCell},
+    future::Future,
+    mem::transmute,
+    pin::Pin,
+    ptr,
+    sync::atomic::Ordering,
+    task::{Context, Poll},
+};
+
+use anyhow::Result;
+use turbo_tasks::RawVc;
+
+use super::TurboTasksBackendInner;
+use crate::backing_storage::BackingStorage;
+
+thread_local! {
+    /// The backend and the [`TurnTransaction`] of the task execution that is polled on this
+    /// thread.
+    static CURRENT_TURN: Cell<Option<(*const (), *const ())>> = const { Cell::new(None) };
+}
+
+struct TurnTransaction<B: BackingStorage> {
+    /// The number of committed snapshots when the transaction was started, and the transaction.
+    tx: OnceCell<(u32, Option<B::ReadTransaction<'static>>)>,
+}
+
+struct CurrentTurnGuard {
+    previous: Option<(*const (), *const ())>,
+}
+
+impl Drop for CurrentTurnGuard {
+    fn drop(&mut self) {
+        CURRENT_TURN.set(self.previous);
+    }
+}
+
+/// Wraps the future of a task execution, so every poll of it gets its own turn transaction.
+pub struct WithTurnTransaction<'a, B: BackingStorage> {
+    backend: &'a TurboTasksBackendInner<B>,
+    future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'a>>,
+}
+
+impl<'a, B: BackingStorage> WithTurnTransaction<'a, B> {
+    pub fn new(
+        backend: &'a TurboTasksBackendInner<B>,
+        future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'a>>,
+    ) -> Self {
+        Self { backend, future }
+    }
+}
+
+impl<B: BackingStorage> Future for WithTurnTransaction<'_, B> {
+    type Output = Result<RawVc>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let turn = TurnTransaction::<B> {
+            tx: OnceCell::new(),
+        };
+        // Declared after `turn`, so it's dropped before the transaction is ended, even when the
+        // poll panics
+        let _guard = CurrentTurnGuard {
+            previous: CURRENT_TURN.replace(Some((
+                ptr::from_ref(this.backend).cast(),
+                ptr::from_ref(&turn).cast(),
+            ))),
+        };
+        this.future.as_mut().poll(cx)
+    }
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    /// Returns the read transaction of the task execution that is polled on this thread, starting
+    /// it on first use.
+    ///
+    /// Returns `None` outside of a task execution and when a snapshot has been committed since
+    /// the transaction was started. After a commit, persisted data is removed from memory, which
+    /// an older transaction might not see yet.
+    ///
+    /// # Safety
+    ///
+    /// The transaction must not be used after the current call into the backend has returned.
+    pub(super) unsafe fn turn_transaction(&self) -> Option<&B::ReadTransaction<'_>> {
+        let turn = self.current_turn()?;
+        let committed_snapshots = self.committed_snapshots.load(Ordering::Acquire);
+        let (started_at, tx) = turn.tx.get_or_init(|| {
+            let tx = self.backing_storage.start_read_transaction().map(|tx| {
+                // Safety: The turn ends before the backend and its backing storage are dropped.
+                unsafe { transmute::<B::ReadTransaction<'_>, B::ReadTransaction<'static>>(tx) }
+            });
+            (committed_snapshots, tx)
+        });
+        if *started_at != committed_snapshots {
+            return None;
+        }
+        tx.as_ref().map(B::lower_read_transaction)
+    }
+
+    /// Returns whether a snapshot has been committed since the read transaction of the current
+    /// turn was started. A lookup that missed in that transaction needs to be repeated in a new
+    /// transaction, since the entry might have been persisted and evicted from memory meanwhile.
+    pub(super) fn is_turn_transaction_outdated(&self) -> bool {
+        let Some(turn) = self.current_turn() else {
+            return false;
+        };
+        turn.tx.get().is_some_and(|(started_at, _)| {
+            *started_at != self.committed_snapshots.load(Ordering::Acquire)
+        })
+    }
+
+    fn current_turn(&self) -> Option<&TurnTransaction<B>> {
+        let (backend, turn) = CURRENT_TURN.get()?;
+        if !ptr::eq(backend, ptr::from_ref(self).cast()) {
+            return None;
+        }
+        // Safety: The pointer is only set while `WithTurnTransaction::poll` of this backend is on
+        // the stack of this thread, and the backend was checked above.
+        Some(unsafe { &*turn.cast::<TurnTransaction<B>>() })
+    }
+}
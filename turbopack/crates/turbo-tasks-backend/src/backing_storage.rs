@@ -1,14 +1,32 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use turbo_tasks::{backend::CachedTaskType, SessionId, TaskId};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{backend::CachedTaskType, CellId, SessionId, TaskId};
 
 use crate::{
     backend::{AnyOperation, TaskDataCategory},
-    data::{CachedDataItem, CachedDataUpdate},
+    data::{CachedDataItem, CachedDataUpdate, SerializedCellData},
     utils::chunked_vec::ChunkedVec,
 };
 
+/// A session that persisted at least one snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_id: SessionId,
+    /// Time of the first snapshot of the session, in seconds since the unix epoch.
+    pub first_snapshot: u64,
+    pub snapshots: u32,
+}
+
+/// Information about a snapshot that has been written by [`BackingStorage::save_snapshot`].
+#[derive(Debug, Default)]
+pub struct SavedSnapshot {
+    /// The serialized size in bytes of every task that was written, meta and data combined.
+    pub task_sizes: FxHashMap<TaskId, usize>,
+}
+
 pub trait BackingStorage: 'static + Send + Sync {
     type ReadTransaction<'l>;
     fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
@@ -22,9 +40,10 @@ pub trait BackingStorage: 'static + Send + Sync {
         session_id: SessionId,
         operations: Vec<Arc<AnyOperation>>,
         task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+        task_cache_removals: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
         meta_updates: Vec<ChunkedVec<CachedDataUpdate>>,
         data_updates: Vec<ChunkedVec<CachedDataUpdate>>,
-    ) -> Result<()>;
+    ) -> Result<SavedSnapshot>;
     fn start_read_transaction(&self) -> Option<Self::ReadTransaction<'_>>;
     /// # Safety
     ///
@@ -51,4 +70,48 @@ pub trait BackingStorage: 'static + Send + Sync {
         task_id: TaskId,
         category: TaskDataCategory,
     ) -> Vec<CachedDataItem>;
+    /// Looks up a single persisted cell of a task without restoring the remaining task data.
+    ///
+    /// # Safety
+    ///
+    /// `tx` must be a transaction from this BackingStorage instance.
+    unsafe fn lookup_cell(
+        &self,
+        tx: Option<&Self::ReadTransaction<'_>>,
+        task_id: TaskId,
+        cell: CellId,
+    ) -> Option<SerializedCellData> {
+        self.lookup_data(tx, task_id, TaskDataCategory::Data)
+            .into_iter()
+            .find_map(|item| match item {
+                CachedDataItem::SerializedCellData {
+                    cell: item_cell,
+                    value,
+                } if item_cell == cell => Some(value),
+                _ => None,
+            })
+    }
+
+    /// Flushes and releases the storage. Called once when the backend shuts down, after the final
+    /// snapshot has been saved. Lookups afterwards don't read the storage and find nothing.
+    fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns the most recent sessions that persisted snapshots, oldest first.
+    fn session_history(&self) -> Vec<SessionInfo> {
+        Vec::new()
+    }
+
+    /// Returns the persistent tasks that were only clean in the session they were computed in.
+    /// They are dirty in every later session.
+    fn session_dependent_tasks(&self) -> Vec<TaskId> {
+        Vec::new()
+    }
+
+    /// Returns the tasks whose task cache entries have been removed because they became
+    /// non-persistable. Persisted items of other tasks may still refer to them.
+    fn removed_tasks(&self) -> Vec<TaskId> {
+        Vec::new()
+    }
 }
@@ -0,0 +1,20 @@
+//! Small command line tool to look into a persistent cache database.
+//!
+//! Usage:
+//!   inspect_cache <db-path> stats
+//!   inspect_cache <db-path> tasks
+//!   inspect_cache <db-path> task <task-id>
+//!
+//! Only the turbo-tasks types are registered in this binary. Task types and cells of other crates
+//! show up as deserialization errors, use [`turbo_tasks_backend::inspect::run_cli`] from a binary
+//! that registers them to inspect those.
+
+use std::env;
+
+use anyhow::Result;
+
+fn main() -> Result<()> {
+    turbo_tasks_backend::register();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    turbo_tasks_backend::inspect::run_cli(&args)
+}
@@ -1,4 +1,9 @@
-use std::cmp::Ordering;
+use std::{
+    backtrace::BacktraceStatus,
+    cmp::Ordering,
+    error::Error,
+    fmt::{self, Display},
+};
 
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
@@ -8,7 +13,9 @@ use turbo_tasks::{
     CellId, KeyValuePair, SessionId, TaskId, TraitTypeId, TypedSharedReference, ValueTypeId,
 };
 
-use crate::backend::{indexed::Indexed, TaskDataCategory};
+use crate::backend::{
+    indexed::Indexed, HeapSize, MemoryCategorized, MemoryCategory, TaskDataCategory, TaskDirtyCause,
+};
 
 // this traits are needed for the transient variants of `CachedDataItem`
 // transient variants are never cloned or compared
@@ -48,6 +55,61 @@ pub struct CollectiblesRef {
     pub collectible_type: TraitTypeId,
 }
 
+/// The serialized data of a cell. Cells are persisted in this form, so restoring a task doesn't
+/// deserialize cells that are never read.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SerializedCellData(Box<[u8]>);
+
+impl SerializedCellData {
+    pub fn new(value: &TypedSharedReference) -> anyhow::Result<Self> {
+        Ok(Self(pot::to_vec(value)?.into_boxed_slice()))
+    }
+
+    pub fn deserialize(&self) -> anyhow::Result<TypedSharedReference> {
+        Ok(pot::from_slice(&self.0)?)
+    }
+
+    pub fn size_in_bytes(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl fmt::Debug for SerializedCellData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SerializedCellData({} bytes)", self.0.len())
+    }
+}
+
+impl Serialize for SerializedCellData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerializedCellData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = SerializedCellData;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("serialized cell data")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                Ok(SerializedCellData(bytes.into()))
+            }
+
+            fn visit_byte_buf<E: serde::de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+                Ok(SerializedCellData(bytes.into_boxed_slice()))
+            }
+        }
+
+        deserializer.deserialize_bytes(Visitor)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OutputValue {
     Cell(CellRef),
@@ -66,6 +128,64 @@ impl OutputValue {
     }
 }
 
+/// The error of a failed task execution. It's returned from reading the output of the task and
+/// can be found with [`anyhow::Error::downcast_ref`] or by walking [`anyhow::Error::chain`].
+///
+/// It's persisted with the task, so a failed task doesn't need to be executed again after a
+/// restart. Only the messages of the error chain survive that.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskExecutionError {
+    pub error: SharedError,
+    /// Descriptions of the tasks the error was propagated through, starting with the task that
+    /// stored this error and ending with the task that originally failed.
+    pub task_chain: Vec<String>,
+    /// The backtrace of the original error, if backtraces are enabled.
+    pub backtrace: Option<String>,
+}
+
+impl TaskExecutionError {
+    /// Creates the error for `task_description`. When the error was caused by reading the output
+    /// of another failed task, the chain of that task is continued.
+    pub fn new(error: anyhow::Error, task_description: String) -> Self {
+        let cause = error
+            .chain()
+            .find_map(|source| source.downcast_ref::<TaskExecutionError>());
+        let (task_chain, backtrace) = if let Some(cause) = cause {
+            let mut task_chain = Vec::with_capacity(cause.task_chain.len() + 1);
+            task_chain.push(task_description);
+            task_chain.extend(cause.task_chain.iter().cloned());
+            (task_chain, cause.backtrace.clone())
+        } else {
+            let backtrace = error.backtrace();
+            let backtrace = matches!(backtrace.status(), BacktraceStatus::Captured)
+                .then(|| backtrace.to_string());
+            (vec![task_description], backtrace)
+        };
+        Self {
+            error: SharedError::new(error),
+            task_chain,
+            backtrace,
+        }
+    }
+
+    /// The description of the task that originally failed.
+    pub fn origin(&self) -> &str {
+        self.task_chain.last().map_or("", |task| task.as_str())
+    }
+}
+
+impl Display for TaskExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl Error for TaskExecutionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
 #[derive(Debug)]
 pub struct RootState {
     pub ty: ActiveType,
@@ -258,6 +378,8 @@ pub enum InProgressState {
         #[allow(dead_code)]
         once_task: bool,
         session_dependent: bool,
+        /// The task has been cancelled. Its result will be discarded.
+        cancelled: bool,
         done_event: Event,
     },
 }
@@ -285,6 +407,17 @@ impl InProgressCellState {
     }
 }
 
+/// How often a task has been used over all sessions. Guides which tasks are unloaded first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskUsage {
+    /// Number of completed executions.
+    pub executions: u32,
+    /// Number of sessions in which the output was read without executing the task.
+    pub hits: u32,
+    /// The last session in which the task was executed or counted as hit.
+    pub last_session: SessionId,
+}
+
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AggregationNumber {
     pub base: u32,
@@ -307,6 +440,9 @@ pub enum CachedDataItem {
     Dirty {
         value: DirtyState,
     },
+    Usage {
+        value: TaskUsage,
+    },
 
     // Children
     Child {
@@ -322,6 +458,12 @@ pub enum CachedDataItem {
         cell: CellId,
         value: TypedSharedReference,
     },
+    /// A cell restored from the backing storage that hasn't been read yet. It's deserialized into
+    /// [`CachedDataItem::CellData`] on access.
+    SerializedCellData {
+        cell: CellId,
+        value: SerializedCellData,
+    },
     CellTypeMaxIndex {
         cell_type: ValueTypeId,
         value: u32,
@@ -356,6 +498,17 @@ pub enum CachedDataItem {
         task: TaskId,
         value: (),
     },
+    /// Like `OutputDependent`, but the reader doesn't keep this task in memory.
+    WeakOutputDependent {
+        task: TaskId,
+        value: (),
+    },
+    /// Like `CellDependent`, but the reader doesn't keep this task in memory.
+    WeakCellDependent {
+        cell: CellId,
+        task: TaskId,
+        value: (),
+    },
 
     // Aggregation Graph
     AggregationNumber {
@@ -425,10 +578,28 @@ pub enum CachedDataItem {
         value: (),
     },
 
-    // Transient Error State
-    #[serde(skip)]
+    // Error State
     Error {
-        value: SharedError,
+        value: TaskExecutionError,
+    },
+    /// Number of times the task has been retried after panicking. Reset when the task completes
+    /// without panicking.
+    #[serde(skip)]
+    PanicRetryCount {
+        value: u32,
+    },
+    /// Why the task has been made dirty in this session.
+    #[serde(skip)]
+    DirtyCause {
+        value: TaskDirtyCause,
+    },
+    /// The cell data has been removed from memory after it was persisted, because it exceeded
+    /// [`BackendOptions::large_cell_threshold`][crate::BackendOptions::large_cell_threshold].
+    /// It's restored from the backing storage when accessed.
+    #[serde(skip)]
+    SpilledCell {
+        cell: CellId,
+        value: (),
     },
 }
 
@@ -440,15 +611,19 @@ impl CachedDataItem {
                 !collectible.cell.task.is_transient()
             }
             CachedDataItem::Dirty { .. } => true,
+            CachedDataItem::Usage { .. } => true,
             CachedDataItem::Child { task, .. } => !task.is_transient(),
             CachedDataItem::ChildrenCount { .. } => true,
             CachedDataItem::CellData { .. } => true,
+            CachedDataItem::SerializedCellData { .. } => true,
             CachedDataItem::CellTypeMaxIndex { .. } => true,
             CachedDataItem::OutputDependency { target, .. } => !target.is_transient(),
             CachedDataItem::CellDependency { target, .. } => !target.task.is_transient(),
             CachedDataItem::CollectiblesDependency { target, .. } => !target.task.is_transient(),
             CachedDataItem::OutputDependent { task, .. } => !task.is_transient(),
             CachedDataItem::CellDependent { task, .. } => !task.is_transient(),
+            CachedDataItem::WeakOutputDependent { task, .. } => !task.is_transient(),
+            CachedDataItem::WeakCellDependent { task, .. } => !task.is_transient(),
             CachedDataItem::CollectiblesDependent { task, .. } => !task.is_transient(),
             CachedDataItem::AggregationNumber { .. } => true,
             CachedDataItem::Follower { task, .. } => !task.is_transient(),
@@ -466,7 +641,10 @@ impl CachedDataItem {
             CachedDataItem::OutdatedCellDependency { .. } => false,
             CachedDataItem::OutdatedCollectiblesDependency { .. } => false,
             CachedDataItem::OutdatedChild { .. } => false,
-            CachedDataItem::Error { .. } => false,
+            CachedDataItem::Error { .. } => true,
+            CachedDataItem::PanicRetryCount { .. } => false,
+            CachedDataItem::DirtyCause { .. } => false,
+            CachedDataItem::SpilledCell { .. } => false,
         }
     }
 
@@ -505,15 +683,19 @@ impl CachedDataItemKey {
                 !collectible.cell.task.is_transient()
             }
             CachedDataItemKey::Dirty { .. } => true,
+            CachedDataItemKey::Usage { .. } => true,
             CachedDataItemKey::Child { task, .. } => !task.is_transient(),
             CachedDataItemKey::ChildrenCount {} => true,
             CachedDataItemKey::CellData { .. } => true,
+            CachedDataItemKey::SerializedCellData { .. } => true,
             CachedDataItemKey::CellTypeMaxIndex { .. } => true,
             CachedDataItemKey::OutputDependency { target, .. } => !target.is_transient(),
             CachedDataItemKey::CellDependency { target, .. } => !target.task.is_transient(),
             CachedDataItemKey::CollectiblesDependency { target, .. } => !target.task.is_transient(),
             CachedDataItemKey::OutputDependent { task, .. } => !task.is_transient(),
             CachedDataItemKey::CellDependent { task, .. } => !task.is_transient(),
+            CachedDataItemKey::WeakOutputDependent { task, .. } => !task.is_transient(),
+            CachedDataItemKey::WeakCellDependent { task, .. } => !task.is_transient(),
             CachedDataItemKey::CollectiblesDependent { task, .. } => !task.is_transient(),
             CachedDataItemKey::AggregationNumber { .. } => true,
             CachedDataItemKey::Follower { task, .. } => !task.is_transient(),
@@ -531,7 +713,43 @@ impl CachedDataItemKey {
             CachedDataItemKey::OutdatedCellDependency { .. } => false,
             CachedDataItemKey::OutdatedCollectiblesDependency { .. } => false,
             CachedDataItemKey::OutdatedChild { .. } => false,
-            CachedDataItemKey::Error { .. } => false,
+            CachedDataItemKey::Error { .. } => true,
+            CachedDataItemKey::PanicRetryCount { .. } => false,
+            CachedDataItemKey::DirtyCause { .. } => false,
+            CachedDataItemKey::SpilledCell { .. } => false,
+        }
+    }
+
+    /// Whether the item is part of the aggregation structure, which can be rebuilt from the
+    /// children of the tasks.
+    pub fn is_aggregation(&self) -> bool {
+        matches!(
+            self,
+            CachedDataItemKey::AggregationNumber { .. }
+                | CachedDataItemKey::Follower { .. }
+                | CachedDataItemKey::Upper { .. }
+                | CachedDataItemKey::AggregatedDirtyContainer { .. }
+                | CachedDataItemKey::AggregatedCollectible { .. }
+                | CachedDataItemKey::AggregatedDirtyContainerCount { .. }
+        )
+    }
+
+    /// The other task that the edge of the item points to.
+    pub fn referenced_task(&self) -> Option<TaskId> {
+        match *self {
+            CachedDataItemKey::Child { task }
+            | CachedDataItemKey::OutputDependent { task }
+            | CachedDataItemKey::CellDependent { task, .. }
+            | CachedDataItemKey::CollectiblesDependent { task, .. }
+            | CachedDataItemKey::WeakOutputDependent { task }
+            | CachedDataItemKey::WeakCellDependent { task, .. }
+            | CachedDataItemKey::Follower { task }
+            | CachedDataItemKey::Upper { task }
+            | CachedDataItemKey::AggregatedDirtyContainer { task } => Some(task),
+            CachedDataItemKey::OutputDependency { target } => Some(target),
+            CachedDataItemKey::CellDependency { target } => Some(target.task),
+            CachedDataItemKey::CollectiblesDependency { target } => Some(target.task),
+            _ => None,
         }
     }
 
@@ -541,6 +759,7 @@ impl CachedDataItemKey {
             | CachedDataItemKey::Child { .. }
             | CachedDataItemKey::ChildrenCount { .. }
             | CachedDataItemKey::CellData { .. }
+            | CachedDataItemKey::SerializedCellData { .. }
             | CachedDataItemKey::CellTypeMaxIndex { .. }
             | CachedDataItemKey::OutputDependency { .. }
             | CachedDataItemKey::CellDependency { .. }
@@ -548,6 +767,8 @@ impl CachedDataItemKey {
             | CachedDataItemKey::OutputDependent { .. }
             | CachedDataItemKey::CellDependent { .. }
             | CachedDataItemKey::CollectiblesDependent { .. }
+            | CachedDataItemKey::WeakOutputDependent { .. }
+            | CachedDataItemKey::WeakCellDependent { .. }
             | CachedDataItemKey::InProgress { .. }
             | CachedDataItemKey::InProgressCell { .. }
             | CachedDataItemKey::OutdatedCollectible { .. }
@@ -555,17 +776,21 @@ impl CachedDataItemKey {
             | CachedDataItemKey::OutdatedCellDependency { .. }
             | CachedDataItemKey::OutdatedCollectiblesDependency { .. }
             | CachedDataItemKey::OutdatedChild { .. }
-            | CachedDataItemKey::Error { .. } => TaskDataCategory::Data,
+            | CachedDataItemKey::Error { .. }
+            | CachedDataItemKey::PanicRetryCount { .. }
+            | CachedDataItemKey::SpilledCell { .. } => TaskDataCategory::Data,
 
             CachedDataItemKey::Output { .. }
             | CachedDataItemKey::AggregationNumber { .. }
             | CachedDataItemKey::Dirty { .. }
+            | CachedDataItemKey::Usage { .. }
             | CachedDataItemKey::Follower { .. }
             | CachedDataItemKey::Upper { .. }
             | CachedDataItemKey::AggregatedDirtyContainer { .. }
             | CachedDataItemKey::AggregatedCollectible { .. }
             | CachedDataItemKey::AggregatedDirtyContainerCount { .. }
-            | CachedDataItemKey::AggregateRoot { .. } => TaskDataCategory::Meta,
+            | CachedDataItemKey::AggregateRoot { .. }
+            | CachedDataItemKey::DirtyCause { .. } => TaskDataCategory::Meta,
         }
     }
 }
@@ -612,6 +837,8 @@ pub mod indicies {
     pub const CellTypeMaxIndex: CachedDataItemIndex = CachedDataItemIndex::CellTypeMaxIndex;
     pub const CellDependent: CachedDataItemIndex = CachedDataItemIndex::CellDependent;
     pub const OutputDependent: CachedDataItemIndex = CachedDataItemIndex::OutputDependent;
+    pub const WeakCellDependent: CachedDataItemIndex = CachedDataItemIndex::CellDependent;
+    pub const WeakOutputDependent: CachedDataItemIndex = CachedDataItemIndex::OutputDependent;
     pub const CollectiblesDependent: CachedDataItemIndex =
         CachedDataItemIndex::CollectiblesDependent;
     pub const OutputDependency: CachedDataItemIndex = CachedDataItemIndex::Dependencies;
@@ -644,12 +871,20 @@ impl Indexed for CachedDataItemKey {
             CachedDataItemKey::AggregatedCollectible { .. } => {
                 Some(CachedDataItemIndex::AggregatedCollectible)
             }
-            CachedDataItemKey::CellData { .. } => Some(CachedDataItemIndex::CellData),
+            CachedDataItemKey::CellData { .. } | CachedDataItemKey::SerializedCellData { .. } => {
+                Some(CachedDataItemIndex::CellData)
+            }
             CachedDataItemKey::CellTypeMaxIndex { .. } => {
                 Some(CachedDataItemIndex::CellTypeMaxIndex)
             }
-            CachedDataItemKey::CellDependent { .. } => Some(CachedDataItemIndex::CellDependent),
-            CachedDataItemKey::OutputDependent { .. } => Some(CachedDataItemIndex::OutputDependent),
+            CachedDataItemKey::CellDependent { .. }
+            | CachedDataItemKey::WeakCellDependent { .. } => {
+                Some(CachedDataItemIndex::CellDependent)
+            }
+            CachedDataItemKey::OutputDependent { .. }
+            | CachedDataItemKey::WeakOutputDependent { .. } => {
+                Some(CachedDataItemIndex::OutputDependent)
+            }
             CachedDataItemKey::OutputDependency { .. } => Some(CachedDataItemIndex::Dependencies),
             CachedDataItemKey::CellDependency { .. } => Some(CachedDataItemIndex::Dependencies),
             CachedDataItemKey::CollectiblesDependency { .. } => {
@@ -669,6 +904,44 @@ impl Indexed for CachedDataItemKey {
     }
 }
 
+impl HeapSize for CachedDataItemValue {
+    fn heap_size(&self) -> usize {
+        match self {
+            // The shallow size of the cell value. Heap memory owned by the value itself is not
+            // known here.
+            CachedDataItemValue::CellData { value } => size_of_val(&*value.1 .0),
+            CachedDataItemValue::SerializedCellData { value } => value.size_in_bytes(),
+            _ => 0,
+        }
+    }
+}
+
+impl MemoryCategorized for CachedDataItemKey {
+    fn memory_category(&self) -> MemoryCategory {
+        match self {
+            CachedDataItemKey::CellData { .. }
+            | CachedDataItemKey::SerializedCellData { .. }
+            | CachedDataItemKey::CellTypeMaxIndex { .. } => MemoryCategory::Cell,
+            CachedDataItemKey::Child { .. }
+            | CachedDataItemKey::OutdatedChild { .. }
+            | CachedDataItemKey::OutputDependency { .. }
+            | CachedDataItemKey::CellDependency { .. }
+            | CachedDataItemKey::CollectiblesDependency { .. }
+            | CachedDataItemKey::OutdatedOutputDependency { .. }
+            | CachedDataItemKey::OutdatedCellDependency { .. }
+            | CachedDataItemKey::OutdatedCollectiblesDependency { .. }
+            | CachedDataItemKey::OutputDependent { .. }
+            | CachedDataItemKey::CellDependent { .. }
+            | CachedDataItemKey::CollectiblesDependent { .. }
+            | CachedDataItemKey::WeakOutputDependent { .. }
+            | CachedDataItemKey::WeakCellDependent { .. }
+            | CachedDataItemKey::Follower { .. }
+            | CachedDataItemKey::Upper { .. } => MemoryCategory::Edge,
+            _ => MemoryCategory::Other,
+        }
+    }
+}
+
 impl CachedDataItemValue {
     pub fn is_persistent(&self) -> bool {
         match self {
@@ -681,7 +954,7 @@ impl CachedDataItemValue {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CachedDataUpdate {
     pub task: TaskId,
     // TODO generate CachedDataItemUpdate to avoid repeating the variant field 3 times
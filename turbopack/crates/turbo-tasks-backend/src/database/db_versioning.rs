@@ -1,18 +1,44 @@
 use std::{
     env,
-    fs::{metadata, read_dir, remove_dir_all},
+    fs::{metadata, read_dir, read_to_string, remove_dir_all, write},
     path::{Path, PathBuf},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 
-/// Specifies many databases that have a different version than the current one are retained.
-/// For example if MAX_OTHER_DB_VERSIONS is 2, there can be at most 3 databases in the directory,
-/// the current one and two older/newer ones.
-const MAX_OTHER_DB_VERSIONS: usize = 2;
+/// Name of the file inside of each database directory that stores the time the database was
+/// last opened, in seconds since the unix epoch.
+const LAST_USED_FILE: &str = "last_used";
 
-pub fn handle_db_versioning(base_path: &Path) -> Result<PathBuf> {
+/// Controls which databases with a different version than the current one are retained.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// Specifies how many databases that have a different version than the current one are
+    /// retained. For example if this is 2, there can be at most 3 databases in the directory,
+    /// the current one and two older/newer ones.
+    pub max_other_versions: usize,
+    /// The maximum total size in bytes of all databases with a different version than the
+    /// current one. The least recently used databases are removed first.
+    pub max_total_size: Option<u64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_other_versions: 2,
+            max_total_size: None,
+        }
+    }
+}
+
+/// Returns the path of the database for the current version. Unless `read_only` is set, old
+/// databases are removed according to the `retention` policy.
+pub fn handle_db_versioning(
+    base_path: &Path,
+    retention: &RetentionPolicy,
+    read_only: bool,
+) -> Result<PathBuf> {
     // Database versioning. Pass `TURBO_ENGINE_IGNORE_DIRTY` at runtime to ignore a
     // dirty git repository. Pass `TURBO_ENGINE_DISABLE_VERSIONING` at runtime to disable
     // versioning and always use the same database.
@@ -46,9 +72,20 @@ pub fn handle_db_versioning(base_path: &Path) -> Result<PathBuf> {
         );
         None
     };
+    // Task ids are encoded with a different width, so the databases are incompatible
+    let version = version.map(|version| {
+        if cfg!(feature = "wide_task_ids") {
+            format!("{version}-wide-task-ids")
+        } else {
+            version.to_string()
+        }
+    });
     let path;
     if let Some(version) = version {
-        path = base_path.join(version);
+        path = base_path.join(&version);
+        if read_only {
+            return Ok(path);
+        }
 
         // Remove old databases if needed
         if let Ok(read_dir) = read_dir(base_path) {
@@ -60,36 +97,95 @@ pub fn handle_db_versioning(base_path: &Path) -> Result<PathBuf> {
                     }
                     let name = entry.file_name();
                     let name = name.to_string_lossy();
-                    if name == version {
+                    if name == version.as_str() {
                         return None;
                     }
                     Some(entry.path())
                 })
                 .collect::<Vec<_>>();
-            if old_dbs.len() > MAX_OTHER_DB_VERSIONS {
-                let mut old_dbs = old_dbs
-                    .iter()
-                    .map(|p| {
-                        fn get_age(p: &Path) -> Result<Duration> {
-                            let m = metadata(p)?;
-                            Ok(m.accessed().or_else(|_| m.modified())?.elapsed()?)
-                        }
-                        (
-                            p,
-                            get_age(p).unwrap_or(Duration::from_secs(10 * 356 * 24 * 60 * 60)),
-                        )
-                    })
-                    .collect::<Vec<_>>();
-                old_dbs.sort_by_key(|(_, age)| *age);
-                for (p, _) in old_dbs.into_iter().skip(MAX_OTHER_DB_VERSIONS) {
-                    let _ = remove_dir_all(p);
-                }
-            }
+            remove_old_dbs(old_dbs, retention);
         }
     } else {
+        if read_only {
+            bail!("Persistent Caching is disabled, so there is no database to read from");
+        }
         let _ = remove_dir_all(base_path);
         path = base_path.join("temp");
     }
 
     Ok(path)
 }
+
+/// Records the current time as last used time of the database in `path`. This must be called
+/// after the database has been opened, as the directory doesn't exist before that for a fresh
+/// database, and creating it here would prevent detecting the database as fresh.
+pub fn mark_used(path: &Path) {
+    let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+        return;
+    };
+    let _ = write(path.join(LAST_USED_FILE), now.as_secs().to_string());
+}
+
+/// Returns how long ago the database in `path` was last used. Falls back to filesystem
+/// metadata for databases that were created before the last used time was recorded.
+fn get_age(path: &Path) -> Result<Duration> {
+    if let Ok(content) = read_to_string(path.join(LAST_USED_FILE)) {
+        if let Ok(secs) = content.trim().parse::<u64>() {
+            let last_used = UNIX_EPOCH + Duration::from_secs(secs);
+            return Ok(last_used.elapsed().unwrap_or_default());
+        }
+    }
+    let m = metadata(path)?;
+    Ok(m.accessed().or_else(|_| m.modified())?.elapsed()?)
+}
+
+/// Returns the number of bytes the directory occupies on disk. LMDB files are sparse, so the
+/// allocated size is used where available.
+fn get_size(path: &Path) -> u64 {
+    let Ok(read_dir) = read_dir(path) else {
+        return 0;
+    };
+    read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let m = entry.metadata().ok()?;
+            if m.is_dir() {
+                return Some(get_size(&entry.path()));
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                Some(m.blocks() * 512)
+            }
+            #[cfg(not(unix))]
+            {
+                Some(m.len())
+            }
+        })
+        .sum()
+}
+
+fn remove_old_dbs(old_dbs: Vec<PathBuf>, retention: &RetentionPolicy) {
+    if old_dbs.len() <= retention.max_other_versions && retention.max_total_size.is_none() {
+        return;
+    }
+    let mut old_dbs = old_dbs
+        .into_iter()
+        .map(|p| {
+            let age = get_age(&p).unwrap_or(Duration::from_secs(10 * 356 * 24 * 60 * 60));
+            (p, age)
+        })
+        .collect::<Vec<_>>();
+    old_dbs.sort_by_key(|(_, age)| *age);
+    let mut total_size = 0;
+    for (i, (p, _)) in old_dbs.into_iter().enumerate() {
+        let keep = i < retention.max_other_versions
+            && retention.max_total_size.is_none_or(|max_total_size| {
+                total_size += get_size(&p);
+                total_size <= max_total_size
+            });
+        if !keep {
+            let _ = remove_dir_all(p);
+        }
+    }
+}
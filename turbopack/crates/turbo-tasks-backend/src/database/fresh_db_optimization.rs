@@ -74,6 +74,10 @@ impl<T: KeyValueDatabase> KeyValueDatabase for FreshDbOptimization<T> {
             fresh_db: &self.fresh_db,
         })
     }
+
+    fn shutdown(&self) -> Result<()> {
+        self.database.shutdown()
+    }
 }
 
 pub struct FreshDbOptimizationWriteBatch<'a, T: KeyValueDatabase>
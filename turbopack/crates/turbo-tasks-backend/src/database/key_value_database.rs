@@ -51,4 +51,10 @@ pub trait KeyValueDatabase {
     where
         Self: 'l;
     fn write_batch(&self) -> Result<Self::WriteBatch<'_>>;
+
+    /// Makes sure all committed data is durable and releases resources that are held between
+    /// write batches. The database is not used afterwards.
+    fn shutdown(&self) -> Result<()> {
+        Ok(())
+    }
 }
@@ -2,8 +2,8 @@ use std::{borrow::Cow, fs::create_dir_all, path::Path, thread::available_paralle
 
 use anyhow::{Context, Result};
 use lmdb::{
-    Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
-    Transaction, WriteFlags,
+    Cursor, Database, DatabaseFlags, Environment, EnvironmentFlags, RoTransaction, RwTransaction,
+    Stat, Transaction, WriteFlags,
 };
 
 use crate::database::key_value_database::{KeySpace, KeyValueDatabase, WriteBatch};
@@ -17,17 +17,18 @@ pub struct LmbdKeyValueDatabase {
     meta_db: Database,
     forward_task_cache_db: Database,
     reverse_task_cache_db: Database,
+    read_only: bool,
 }
 
+#[cfg(target_arch = "x86")]
+const MAP_SIZE: usize = usize::MAX;
+#[cfg(not(target_arch = "x86"))]
+const MAP_SIZE: usize = 40 * 1024 * 1024 * 1024;
+
 impl LmbdKeyValueDatabase {
     pub fn new(path: &Path) -> Result<Self> {
         create_dir_all(path).context("Creating database directory failed")?;
 
-        #[cfg(target_arch = "x86")]
-        const MAP_SIZE: usize = usize::MAX;
-        #[cfg(not(target_arch = "x86"))]
-        const MAP_SIZE: usize = 40 * 1024 * 1024 * 1024;
-
         let env = Environment::new()
             .set_flags(
                 EnvironmentFlags::WRITE_MAP
@@ -52,9 +53,56 @@ impl LmbdKeyValueDatabase {
             meta_db,
             forward_task_cache_db,
             reverse_task_cache_db,
+            read_only: false,
         })
     }
 
+    /// Opens an existing database without write access. Used for inspecting a cache directory
+    /// while it might be in use by another process.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let env = Environment::new()
+            .set_flags(EnvironmentFlags::READ_ONLY | EnvironmentFlags::NO_TLS)
+            .set_max_dbs(5)
+            .set_map_size(MAP_SIZE)
+            .open(path)
+            .with_context(|| format!("Opening database at {} failed", path.display()))?;
+        let infra_db = env.open_db(Some("infra"))?;
+        let data_db = env.open_db(Some("data"))?;
+        let meta_db = env.open_db(Some("meta"))?;
+        let forward_task_cache_db = env.open_db(Some("forward_task_cache"))?;
+        let reverse_task_cache_db = env.open_db(Some("reverse_task_cache"))?;
+        Ok(LmbdKeyValueDatabase {
+            env,
+            infra_db,
+            data_db,
+            meta_db,
+            forward_task_cache_db,
+            reverse_task_cache_db,
+            read_only: true,
+        })
+    }
+
+    /// Returns the LMDB statistics of a key space.
+    pub fn stat(&self, tx: &RoTransaction<'_>, key_space: KeySpace) -> Result<Stat> {
+        Ok(tx.stat(self.db(key_space))?)
+    }
+
+    /// Calls `f` for every raw entry in a key space. Keys longer than the LMDB key limit are
+    /// reported in their hashed form.
+    pub fn for_each_entry(
+        &self,
+        tx: &RoTransaction<'_>,
+        key_space: KeySpace,
+        mut f: impl FnMut(&[u8], &[u8]) -> Result<()>,
+    ) -> Result<()> {
+        let mut cursor = tx.open_ro_cursor(self.db(key_space))?;
+        for entry in cursor.iter_start() {
+            let (key, value) = entry?;
+            f(key, value)?;
+        }
+        Ok(())
+    }
+
     fn db(&self, key_space: KeySpace) -> Database {
         match key_space {
             KeySpace::Infra => self.infra_db,
@@ -122,6 +170,14 @@ impl KeyValueDatabase for LmbdKeyValueDatabase {
             this: self,
         })
     }
+
+    fn shutdown(&self) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.env.sync(true)?;
+        Ok(())
+    }
 }
 
 pub struct LmbdWriteBatch<'l> {
@@ -7,7 +7,7 @@ pub mod noop_kv;
 pub mod read_transaction_cache;
 mod startup_cache;
 
-pub use db_versioning::handle_db_versioning;
+pub use db_versioning::{handle_db_versioning, mark_used};
 pub use fresh_db_optimization::{is_fresh, FreshDbOptimization};
 #[allow(unused_imports)]
 pub use noop_kv::NoopKvDb;
@@ -94,6 +94,13 @@ impl<T: KeyValueDatabase + 'static> KeyValueDatabase for ReadTransactionCache<T>
             this: self,
         })
     }
+
+    fn shutdown(&self) -> Result<()> {
+        // End all cached read transactions before closing the database.
+        self.read_transactions_cache
+            .store(Arc::new(ThreadLocal::new()));
+        self.database.shutdown()
+    }
 }
 
 pub struct CachedReadTransaction<'l, T: KeyValueDatabase + 'static> {
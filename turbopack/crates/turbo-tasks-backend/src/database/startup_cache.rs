@@ -166,6 +166,10 @@ impl<T: KeyValueDatabase> KeyValueDatabase for StartupCacheLayer<T> {
             this: self,
         })
     }
+
+    fn shutdown(&self) -> Result<()> {
+        self.database.shutdown()
+    }
 }
 
 pub struct StartupCacheWriteBatch<'a, T: KeyValueDatabase> {
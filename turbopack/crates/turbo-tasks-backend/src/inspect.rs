@@ -0,0 +1,335 @@
+//! Read-only access to a persistent cache database for debugging purposes.
+//!
+//! The inspector opens the database without write access, so it can be used on a cache
+//! directory that is currently used by a running process.
+//!
+//! Task types and cell contents are deserialized through the turbo-tasks registry. The
+//! `register()` functions of all crates that defined the cached functions and values must be
+//! called before inspecting a cache, otherwise those entries are reported as errors. Tools that
+//! know their crates can call [`run_cli`] after registering them.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use turbo_tasks::{backend::CachedTaskType, KeyValuePair, TaskId, TaskIdValue};
+
+use crate::{
+    backend::{
+        replay::{format_item, StorageDump, TaskDump},
+        AnyOperation,
+    },
+    backing_storage::SessionInfo,
+    data::CachedDataItem,
+    database::{
+        key_value_database::{KeySpace, KeyValueDatabase},
+        lmdb::LmbdKeyValueDatabase,
+    },
+    kv_backing_storage::{
+        META_KEY_NEXT_FREE_TASK_ID, META_KEY_OPERATIONS, META_KEY_SESSION_HISTORY,
+        META_KEY_SESSION_ID,
+    },
+};
+
+const ALL_KEY_SPACES: [(KeySpace, &str); 5] = [
+    (KeySpace::Infra, "infra"),
+    (KeySpace::TaskMeta, "meta"),
+    (KeySpace::TaskData, "data"),
+    (KeySpace::ForwardTaskCache, "forward_task_cache"),
+    (KeySpace::ReverseTaskCache, "reverse_task_cache"),
+];
+
+/// Size information about a single key space of the database.
+#[derive(Debug, Clone)]
+pub struct KeySpaceStats {
+    pub name: &'static str,
+    pub entries: usize,
+    pub key_bytes: usize,
+    pub value_bytes: usize,
+    pub pages: usize,
+}
+
+/// Session related information stored in the infra key space.
+#[derive(Debug, Clone)]
+pub struct InfraInfo {
+    pub session_id: Option<u32>,
+    pub next_free_task_id: Option<TaskIdValue>,
+    pub uncompleted_operations: usize,
+    /// The most recent sessions that persisted snapshots, oldest first.
+    pub session_history: Vec<SessionInfo>,
+}
+
+/// A single stored item of a task, formatted for display.
+#[derive(Debug, Clone)]
+pub struct InspectedItem {
+    pub key: String,
+    pub value: String,
+}
+
+/// The stored state of a task.
+#[derive(Debug, Clone)]
+pub struct InspectedTask {
+    pub task_id: TaskId,
+    pub task_type: Option<String>,
+    pub meta: Vec<InspectedItem>,
+    pub data: Vec<InspectedItem>,
+}
+
+pub struct CacheInspector {
+    database: LmbdKeyValueDatabase,
+}
+
+impl CacheInspector {
+    /// Opens the database in `path` read-only. `path` must point to a versioned database
+    /// directory (e.g. `.next/cache/turbopack/<version>`), not to the base cache directory.
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            database: LmbdKeyValueDatabase::open_read_only(path)?,
+        })
+    }
+
+    pub fn key_space_stats(&self) -> Result<Vec<KeySpaceStats>> {
+        let tx = self.database.begin_read_transaction()?;
+        ALL_KEY_SPACES
+            .iter()
+            .map(|&(key_space, name)| {
+                let stat = self.database.stat(&tx, key_space)?;
+                let mut key_bytes = 0;
+                let mut value_bytes = 0;
+                self.database.for_each_entry(&tx, key_space, |key, value| {
+                    key_bytes += key.len();
+                    value_bytes += value.len();
+                    Ok(())
+                })?;
+                Ok(KeySpaceStats {
+                    name,
+                    entries: stat.entries(),
+                    key_bytes,
+                    value_bytes,
+                    pages: stat.branch_pages() + stat.leaf_pages() + stat.overflow_pages(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn infra_info(&self) -> Result<InfraInfo> {
+        let tx = self.database.begin_read_transaction()?;
+        let get_u32 = |key: u32| -> Result<Option<u32>> {
+            let Some(bytes) = self
+                .database
+                .get(&tx, KeySpace::Infra, &key.to_be_bytes())?
+            else {
+                return Ok(None);
+            };
+            Ok(Some(u32::from_be_bytes(bytes.try_into()?)))
+        };
+        let session_id = get_u32(META_KEY_SESSION_ID)?;
+        let next_free_task_id = match self.database.get(
+            &tx,
+            KeySpace::Infra,
+            &META_KEY_NEXT_FREE_TASK_ID.to_be_bytes(),
+        )? {
+            Some(bytes) => Some(TaskIdValue::from_be_bytes(bytes.try_into()?)),
+            None => None,
+        };
+        let uncompleted_operations =
+            match self
+                .database
+                .get(&tx, KeySpace::Infra, &META_KEY_OPERATIONS.to_be_bytes())?
+            {
+                Some(bytes) => {
+                    let operations: Vec<AnyOperation> = pot::from_slice(bytes)
+                        .context("Unable to deserialize uncompleted operations")?;
+                    operations.len()
+                }
+                None => 0,
+            };
+        let session_history = match self.database.get(
+            &tx,
+            KeySpace::Infra,
+            &META_KEY_SESSION_HISTORY.to_be_bytes(),
+        )? {
+            Some(bytes) => {
+                pot::from_slice(bytes).context("Unable to deserialize session history")?
+            }
+            None => Vec::new(),
+        };
+        Ok(InfraInfo {
+            session_id,
+            next_free_task_id,
+            uncompleted_operations,
+            session_history,
+        })
+    }
+
+    /// Returns the ids of all tasks that have a task type or stored data.
+    pub fn task_ids(&self) -> Result<Vec<TaskId>> {
+        let tx = self.database.begin_read_transaction()?;
+        let mut ids = BTreeSet::new();
+        for key_space in [
+            KeySpace::ReverseTaskCache,
+            KeySpace::TaskMeta,
+            KeySpace::TaskData,
+        ] {
+            self.database.for_each_entry(&tx, key_space, |key, _| {
+                ids.insert(TaskIdValue::from_be_bytes(key.try_into()?));
+                Ok(())
+            })?;
+        }
+        Ok(ids.into_iter().map(TaskId::from).collect())
+    }
+
+    /// Resolves a task id to its task type. Deserialization requires the task's function to be
+    /// registered in the current process.
+    pub fn task_type(&self, task_id: TaskId) -> Result<Option<Arc<CachedTaskType>>> {
+        let tx = self.database.begin_read_transaction()?;
+        let Some(bytes) =
+            self.database
+                .get(&tx, KeySpace::ReverseTaskCache, &task_id.to_be_bytes())?
+        else {
+            return Ok(None);
+        };
+        let task_type = pot::from_slice(bytes)
+            .with_context(|| format!("Unable to deserialize task type of {task_id}"))?;
+        Ok(Some(task_type))
+    }
+
+    /// Reads all stored items of a task. Failures to deserialize the task type or data are
+    /// reported inline instead of failing the whole task.
+    pub fn task(&self, task_id: TaskId) -> Result<InspectedTask> {
+        let task_type = match self.task_type(task_id) {
+            Ok(task_type) => task_type.map(|t| t.to_string()),
+            Err(err) => Some(format!("<{err:#}>")),
+        };
+        let tx = self.database.begin_read_transaction()?;
+        let read_items = |key_space: KeySpace| -> Result<Vec<InspectedItem>> {
+            let Some(bytes) = self.database.get(&tx, key_space, &task_id.to_be_bytes())? else {
+                return Ok(Vec::new());
+            };
+            let items: Vec<CachedDataItem> = match pot::from_slice(bytes) {
+                Ok(items) => items,
+                Err(err) => {
+                    return Ok(vec![InspectedItem {
+                        key: "<error>".to_string(),
+                        value: format!("Unable to deserialize {} bytes: {err}", bytes.len()),
+                    }])
+                }
+            };
+            Ok(items
+                .into_iter()
+                .map(|item| {
+                    let (key, value) = item.into_key_and_value();
+                    InspectedItem {
+                        key: format!("{key:?}"),
+                        value: format!("{value:?}"),
+                    }
+                })
+                .collect())
+        };
+        Ok(InspectedTask {
+            task_id,
+            task_type,
+            meta: read_items(KeySpace::TaskMeta)?,
+            data: read_items(KeySpace::TaskData)?,
+        })
+    }
+
+    /// Reads the persistent items of all stored tasks for comparing them with the state of a
+    /// backend, see [`crate::replay`].
+    pub fn storage_dump(&self) -> Result<StorageDump> {
+        let tx = self.database.begin_read_transaction()?;
+        let read_items = |task_id: TaskId, key_space: KeySpace| -> Result<BTreeSet<String>> {
+            let Some(bytes) = self.database.get(&tx, key_space, &task_id.to_be_bytes())? else {
+                return Ok(BTreeSet::new());
+            };
+            let items: Vec<CachedDataItem> = pot::from_slice(bytes)
+                .with_context(|| format!("Unable to deserialize the data of {task_id}"))?;
+            Ok(items
+                .into_iter()
+                .map(|item| {
+                    let (key, value) = item.into_key_and_value();
+                    format_item(&key, &value)
+                })
+                .collect())
+        };
+        let mut tasks = BTreeMap::new();
+        for task_id in self.task_ids()? {
+            tasks.insert(
+                task_id,
+                TaskDump {
+                    meta: Some(read_items(task_id, KeySpace::TaskMeta)?),
+                    data: Some(read_items(task_id, KeySpace::TaskData)?),
+                },
+            );
+        }
+        Ok(StorageDump { tasks })
+    }
+}
+
+/// Runs the `inspect_cache` command line tool with the given arguments (without the program name):
+///
+/// ```text
+/// <db-path> stats
+/// <db-path> tasks
+/// <db-path> task <task-id>
+/// ```
+pub fn run_cli(args: &[String]) -> Result<()> {
+    let [path, command, rest @ ..] = args else {
+        bail!("Usage: inspect_cache <db-path> (stats | tasks | task <task-id>)");
+    };
+    let inspector = CacheInspector::open(Path::new(path))?;
+    match (command.as_str(), rest) {
+        ("stats", []) => {
+            let info = inspector.infra_info()?;
+            println!("session id: {:?}", info.session_id);
+            println!("next free task id: {:?}", info.next_free_task_id);
+            println!("uncompleted operations: {}", info.uncompleted_operations);
+            for session in info.session_history.iter() {
+                println!(
+                    "session {:?}: first snapshot at {}, {} snapshots",
+                    session.session_id, session.first_snapshot, session.snapshots
+                );
+            }
+            println!();
+            println!(
+                "{:<20} {:>10} {:>14} {:>14} {:>10}",
+                "key space", "entries", "key bytes", "value bytes", "pages"
+            );
+            for stats in inspector.key_space_stats()? {
+                println!(
+                    "{:<20} {:>10} {:>14} {:>14} {:>10}",
+                    stats.name, stats.entries, stats.key_bytes, stats.value_bytes, stats.pages
+                );
+            }
+        }
+        ("tasks", []) => {
+            for task_id in inspector.task_ids()? {
+                match inspector.task_type(task_id) {
+                    Ok(Some(task_type)) => println!("{task_id} {task_type}"),
+                    Ok(None) => println!("{task_id} <no task type>"),
+                    Err(err) => println!("{task_id} <{err:#}>"),
+                }
+            }
+        }
+        ("task", [task_id]) => {
+            let task_id: TaskIdValue = task_id.parse().context("Invalid task id")?;
+            let task = inspector.task(TaskId::from(task_id))?;
+            println!(
+                "{} {}",
+                task.task_id,
+                task.task_type.as_deref().unwrap_or("<no task type>")
+            );
+            for (category, items) in [("meta", &task.meta), ("data", &task.data)] {
+                println!("{category}:");
+                for item in items {
+                    println!("  {} = {}", item.key, item.value);
+                }
+            }
+        }
+        _ => bail!("Unknown command {command:?}"),
+    }
+    Ok(())
+}
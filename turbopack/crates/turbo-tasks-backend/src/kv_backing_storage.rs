@@ -1,26 +1,48 @@
 use std::{
     borrow::{Borrow, Cow},
     collections::hash_map::Entry,
-    sync::Arc,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{anyhow, Context, Result};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{
+    de::{DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor},
+    Deserializer,
+};
 use tracing::Span;
-use turbo_tasks::{backend::CachedTaskType, turbo_tasks_scope, KeyValuePair, SessionId, TaskId};
+use turbo_tasks::{
+    backend::CachedTaskType, turbo_tasks_scope, CellId, KeyValuePair, SessionId, TaskId,
+    TaskIdValue,
+};
 
 use crate::{
     backend::{AnyOperation, TaskDataCategory},
-    backing_storage::BackingStorage,
-    data::{CachedDataItem, CachedDataItemKey, CachedDataItemValue, CachedDataUpdate},
+    backing_storage::{BackingStorage, SavedSnapshot, SessionInfo},
+    data::{
+        CachedDataItem, CachedDataItemKey, CachedDataItemValue, CachedDataUpdate, DirtyState,
+        SerializedCellData,
+    },
     database::key_value_database::{KeySpace, KeyValueDatabase, WriteBatch},
     utils::chunked_vec::ChunkedVec,
 };
 
-const META_KEY_OPERATIONS: u32 = 0;
-const META_KEY_NEXT_FREE_TASK_ID: u32 = 1;
-const META_KEY_SESSION_ID: u32 = 2;
+// Keys of the infra key space. These are also read by `crate::inspect`.
+pub(crate) const META_KEY_OPERATIONS: u32 = 0;
+pub(crate) const META_KEY_NEXT_FREE_TASK_ID: u32 = 1;
+pub(crate) const META_KEY_SESSION_ID: u32 = 2;
+pub(crate) const META_KEY_SESSION_HISTORY: u32 = 3;
+pub(crate) const META_KEY_SESSION_DEPENDENT_TASKS: u32 = 4;
+pub(crate) const META_KEY_REMOVED_TASKS: u32 = 5;
+
+/// Number of sessions kept in the session history.
+const MAX_SESSION_HISTORY: usize = 100;
 
 struct IntKey([u8; 4]);
 
@@ -36,6 +58,26 @@ impl AsRef<[u8]> for IntKey {
     }
 }
 
+/// Key of a task in the task key spaces. Its width depends on [`TaskIdValue`].
+struct TaskIdKey([u8; size_of::<TaskIdValue>()]);
+
+impl TaskIdKey {
+    fn new(value: TaskIdValue) -> Self {
+        Self(value.to_be_bytes())
+    }
+}
+
+impl AsRef<[u8]> for TaskIdKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+fn as_task_id_value(bytes: impl Borrow<[u8]>) -> Result<TaskIdValue> {
+    let n = TaskIdValue::from_be_bytes(bytes.borrow().try_into()?);
+    Ok(n)
+}
+
 fn as_u32(bytes: impl Borrow<[u8]>) -> Result<u32> {
     let n = u32::from_be_bytes(bytes.borrow().try_into()?);
     Ok(n)
@@ -43,11 +85,20 @@ fn as_u32(bytes: impl Borrow<[u8]>) -> Result<u32> {
 
 pub struct KeyValueDatabaseBackingStorage<T: KeyValueDatabase> {
     database: T,
+    /// Set by [`BackingStorage::shutdown`]. The database must not be read afterwards.
+    shut_down: AtomicBool,
 }
 
 impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
     pub fn new(database: T) -> Self {
-        Self { database }
+        Self {
+            database,
+            shut_down: AtomicBool::new(false),
+        }
+    }
+
+    fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::Acquire)
     }
 
     fn with_tx<R>(
@@ -76,6 +127,16 @@ fn get_infra_u32(database: &impl KeyValueDatabase, key: u32) -> Option<u32> {
     Some(value)
 }
 
+fn get_infra_task_id_value(database: &impl KeyValueDatabase, key: u32) -> Option<TaskIdValue> {
+    let tx = database.begin_read_transaction().ok()?;
+    let value = database
+        .get(&tx, KeySpace::Infra, IntKey::new(key).as_ref())
+        .ok()?
+        .map(as_task_id_value)?
+        .ok()?;
+    Some(value)
+}
+
 impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     for KeyValueDatabaseBackingStorage<T>
 {
@@ -88,7 +149,9 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     }
 
     fn next_free_task_id(&self) -> TaskId {
-        TaskId::from(get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1))
+        TaskId::from(
+            get_infra_task_id_value(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1),
+        )
     }
 
     fn next_session_id(&self) -> SessionId {
@@ -117,14 +180,16 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
         session_id: SessionId,
         operations: Vec<Arc<AnyOperation>>,
         task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+        task_cache_removals: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
         meta_updates: Vec<ChunkedVec<CachedDataUpdate>>,
         data_updates: Vec<ChunkedVec<CachedDataUpdate>>,
-    ) -> Result<()> {
+    ) -> Result<SavedSnapshot> {
         let span = tracing::trace_span!("save snapshot", session_id = ?session_id, operations = operations.len(), db_operation_count = tracing::field::Empty);
         let mut op_count = 0;
         let mut batch = self.database.write_batch()?;
         let mut task_meta_items_result = Ok(Vec::new());
         let mut task_data_items_result = Ok(Vec::new());
+        let session_dependent_updates = session_dependent_updates(&meta_updates);
 
         turbo_tasks::scope(|s| {
             // Start organizing the updates in parallel
@@ -147,13 +212,81 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                         Cow::Borrowed(&session_id.to_be_bytes()),
                     )
                     .with_context(|| anyhow!("Unable to write next session id"))?;
+
+                let mut history: Vec<SessionInfo> = match batch.get(
+                    KeySpace::Infra,
+                    IntKey::new(META_KEY_SESSION_HISTORY).as_ref(),
+                )? {
+                    Some(bytes) => pot::from_slice(bytes.borrow()).unwrap_or_default(),
+                    None => Vec::new(),
+                };
+                match history.last_mut() {
+                    Some(last) if last.session_id == session_id => last.snapshots += 1,
+                    _ => history.push(SessionInfo {
+                        session_id,
+                        first_snapshot: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |time| time.as_secs()),
+                        snapshots: 1,
+                    }),
+                }
+                if history.len() > MAX_SESSION_HISTORY {
+                    history.drain(..history.len() - MAX_SESSION_HISTORY);
+                }
+                let history = pot::to_vec(&history)
+                    .with_context(|| anyhow!("Unable to serialize session history"))?;
+                batch
+                    .put(
+                        KeySpace::Infra,
+                        Cow::Borrowed(IntKey::new(META_KEY_SESSION_HISTORY).as_ref()),
+                        history.into(),
+                    )
+                    .with_context(|| anyhow!("Unable to write session history"))?;
+            }
+
+            if !session_dependent_updates.is_empty()
+                || task_cache_removals
+                    .iter()
+                    .any(|removals| !removals.is_empty())
+            {
+                let _span = tracing::trace_span!(
+                    "update session dependent tasks",
+                    updates = session_dependent_updates.len()
+                )
+                .entered();
+                let mut tasks: FxHashSet<TaskId> = match batch.get(
+                    KeySpace::Infra,
+                    IntKey::new(META_KEY_SESSION_DEPENDENT_TASKS).as_ref(),
+                )? {
+                    Some(bytes) => pot::from_slice(bytes.borrow()).unwrap_or_default(),
+                    None => FxHashSet::default(),
+                };
+                for (task_id, session_dependent) in session_dependent_updates {
+                    if session_dependent {
+                        tasks.insert(task_id);
+                    } else {
+                        tasks.remove(&task_id);
+                    }
+                }
+                for (_, task_id) in task_cache_removals.iter().flatten() {
+                    tasks.remove(task_id);
+                }
+                let tasks = pot::to_vec(&tasks)
+                    .with_context(|| anyhow!("Unable to serialize session dependent tasks"))?;
+                batch
+                    .put(
+                        KeySpace::Infra,
+                        Cow::Borrowed(IntKey::new(META_KEY_SESSION_DEPENDENT_TASKS).as_ref()),
+                        tasks.into(),
+                    )
+                    .with_context(|| anyhow!("Unable to write session dependent tasks"))?;
             }
 
             let mut next_task_id = match batch.get(
                 KeySpace::Infra,
                 IntKey::new(META_KEY_NEXT_FREE_TASK_ID).as_ref(),
             )? {
-                Some(bytes) => u32::from_be_bytes(bytes.borrow().try_into()?),
+                Some(bytes) => as_task_id_value(bytes)?,
                 None => 1,
             };
             {
@@ -195,7 +328,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                     batch
                         .put(
                             KeySpace::ReverseTaskCache,
-                            Cow::Borrowed(IntKey::new(task_id).as_ref()),
+                            Cow::Borrowed(TaskIdKey::new(task_id).as_ref()),
                             Cow::Borrowed(&task_type_bytes),
                         )
                         .with_context(|| {
@@ -212,6 +345,53 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                     )
                     .with_context(|| anyhow!("Unable to write next free task id"))?;
             }
+            if task_cache_removals
+                .iter()
+                .any(|removals| !removals.is_empty())
+            {
+                let _span = tracing::trace_span!(
+                    "remove task cache entries",
+                    items = task_cache_removals.iter().map(|m| m.len()).sum::<usize>()
+                )
+                .entered();
+                let mut removed_tasks: FxHashSet<TaskId> = match batch.get(
+                    KeySpace::Infra,
+                    IntKey::new(META_KEY_REMOVED_TASKS).as_ref(),
+                )? {
+                    Some(bytes) => pot::from_slice(bytes.borrow()).unwrap_or_default(),
+                    None => FxHashSet::default(),
+                };
+                for (task_type, task_id) in task_cache_removals.into_iter().flatten() {
+                    let task_id = *task_id;
+                    let task_type_bytes = pot::to_vec(&*task_type).with_context(|| {
+                        anyhow!("Unable to serialize task cache key {task_type:?}")
+                    })?;
+                    batch
+                        .delete(KeySpace::ForwardTaskCache, Cow::Borrowed(&task_type_bytes))
+                        .with_context(|| {
+                            anyhow!("Unable to remove task cache {task_type:?} => {task_id}")
+                        })?;
+                    batch
+                        .delete(
+                            KeySpace::ReverseTaskCache,
+                            Cow::Borrowed(TaskIdKey::new(task_id).as_ref()),
+                        )
+                        .with_context(|| {
+                            anyhow!("Unable to remove task cache {task_id} => {task_type:?}")
+                        })?;
+                    op_count += 2;
+                    removed_tasks.insert(task_id);
+                }
+                let removed_tasks = pot::to_vec(&removed_tasks)
+                    .with_context(|| anyhow!("Unable to serialize removed tasks"))?;
+                batch
+                    .put(
+                        KeySpace::Infra,
+                        Cow::Borrowed(IntKey::new(META_KEY_REMOVED_TASKS).as_ref()),
+                        removed_tasks.into(),
+                    )
+                    .with_context(|| anyhow!("Unable to write removed tasks"))?;
+            }
             {
                 let _span =
                     tracing::trace_span!("update operations", operations = operations.len())
@@ -231,6 +411,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             anyhow::Ok(())
         })?;
 
+        let mut saved_snapshot = SavedSnapshot::default();
         for (key_space, task_items) in [
             (KeySpace::TaskMeta, task_meta_items_result?),
             (KeySpace::TaskData, task_data_items_result?),
@@ -239,10 +420,11 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                 let _span =
                     tracing::trace_span!("update task data", tasks = task_items.len()).entered();
                 for (task_id, value) in task_items.into_iter().flatten() {
+                    *saved_snapshot.task_sizes.entry(task_id).or_default() += value.len();
                     batch
                         .put(
                             key_space,
-                            Cow::Borrowed(IntKey::new(*task_id).as_ref()),
+                            Cow::Borrowed(TaskIdKey::new(*task_id).as_ref()),
                             value.into(),
                         )
                         .with_context(|| anyhow!("Unable to write data items for {task_id}"))?;
@@ -257,10 +439,13 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                 .with_context(|| anyhow!("Unable to commit operations"))?;
         }
         span.record("db_operation_count", op_count);
-        Ok(())
+        Ok(saved_snapshot)
     }
 
     fn start_read_transaction(&self) -> Option<Self::ReadTransaction<'_>> {
+        if self.is_shut_down() {
+            return None;
+        }
         self.database.begin_read_transaction().ok()
     }
 
@@ -279,9 +464,12 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                 return Ok(None);
             };
             let bytes = bytes.borrow().try_into()?;
-            let id = TaskId::from(u32::from_be_bytes(bytes));
+            let id = TaskId::from(TaskIdValue::from_be_bytes(bytes));
             Ok(Some(id))
         }
+        if self.is_shut_down() {
+            return None;
+        }
         let id = self
             .with_tx(tx, |tx| lookup(&self.database, tx, task_type))
             .inspect_err(|err| println!("Looking up task id for {task_type:?} failed: {err:?}"))
@@ -302,13 +490,16 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             let Some(bytes) = database.get(
                 tx,
                 KeySpace::ReverseTaskCache,
-                IntKey::new(*task_id).as_ref(),
+                TaskIdKey::new(*task_id).as_ref(),
             )?
             else {
                 return Ok(None);
             };
             Ok(Some(pot::from_slice(bytes.borrow())?))
         }
+        if self.is_shut_down() {
+            return None;
+        }
         let result = self
             .with_tx(tx, |tx| lookup(&self.database, tx, task_id))
             .inspect_err(|err| println!("Looking up task type for {task_id} failed: {err:?}"))
@@ -335,7 +526,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                     TaskDataCategory::Data => KeySpace::TaskData,
                     TaskDataCategory::All => unreachable!(),
                 },
-                IntKey::new(*task_id).as_ref(),
+                TaskIdKey::new(*task_id).as_ref(),
             )?
             else {
                 return Ok(Vec::new());
@@ -343,10 +534,119 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             let result: Vec<CachedDataItem> = pot::from_slice(bytes.borrow())?;
             Ok(result)
         }
+        if self.is_shut_down() {
+            return Vec::new();
+        }
         self.with_tx(tx, |tx| lookup(&self.database, tx, task_id, category))
             .inspect_err(|err| println!("Looking up data for {task_id} failed: {err:?}"))
             .unwrap_or_default()
     }
+
+    unsafe fn lookup_cell(
+        &self,
+        tx: Option<&T::ReadTransaction<'_>>,
+        task_id: TaskId,
+        cell: CellId,
+    ) -> Option<SerializedCellData> {
+        fn lookup<D: KeyValueDatabase>(
+            database: &D,
+            tx: &D::ReadTransaction<'_>,
+            task_id: TaskId,
+            cell: CellId,
+        ) -> Result<Option<SerializedCellData>> {
+            let Some(bytes) =
+                database.get(tx, KeySpace::TaskData, TaskIdKey::new(*task_id).as_ref())?
+            else {
+                return Ok(None);
+            };
+            let mut deserializer =
+                pot::de::SymbolList::new().deserializer_for_slice(bytes.borrow())?;
+            Ok(FindSerializedCell(cell).deserialize(&mut deserializer)?)
+        }
+        if self.is_shut_down() {
+            return None;
+        }
+        self.with_tx(tx, |tx| lookup(&self.database, tx, task_id, cell))
+            .inspect_err(|err| println!("Looking up cell {cell:?} of {task_id} failed: {err:?}"))
+            .unwrap_or_default()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        self.shut_down.store(true, Ordering::Release);
+        self.database.shutdown()
+    }
+
+    fn session_history(&self) -> Vec<SessionInfo> {
+        fn get(database: &impl KeyValueDatabase) -> Result<Vec<SessionInfo>> {
+            let tx = database.begin_read_transaction()?;
+            let Some(history) = database.get(
+                &tx,
+                KeySpace::Infra,
+                IntKey::new(META_KEY_SESSION_HISTORY).as_ref(),
+            )?
+            else {
+                return Ok(Vec::new());
+            };
+            let history = pot::from_slice(history.borrow())?;
+            Ok(history)
+        }
+        get(&self.database).unwrap_or_default()
+    }
+
+    fn session_dependent_tasks(&self) -> Vec<TaskId> {
+        fn get(database: &impl KeyValueDatabase) -> Result<Vec<TaskId>> {
+            let tx = database.begin_read_transaction()?;
+            let Some(tasks) = database.get(
+                &tx,
+                KeySpace::Infra,
+                IntKey::new(META_KEY_SESSION_DEPENDENT_TASKS).as_ref(),
+            )?
+            else {
+                return Ok(Vec::new());
+            };
+            let tasks = pot::from_slice(tasks.borrow())?;
+            Ok(tasks)
+        }
+        get(&self.database).unwrap_or_default()
+    }
+
+    fn removed_tasks(&self) -> Vec<TaskId> {
+        fn get(database: &impl KeyValueDatabase) -> Result<Vec<TaskId>> {
+            let tx = database.begin_read_transaction()?;
+            let Some(tasks) = database.get(
+                &tx,
+                KeySpace::Infra,
+                IntKey::new(META_KEY_REMOVED_TASKS).as_ref(),
+            )?
+            else {
+                return Ok(Vec::new());
+            };
+            let tasks = pot::from_slice(tasks.borrow())?;
+            Ok(tasks)
+        }
+        get(&self.database).unwrap_or_default()
+    }
+}
+
+/// Returns the tasks whose dirty state is changed by the updates, and whether they are only clean
+/// in a single session afterwards.
+fn session_dependent_updates(meta_updates: &[ChunkedVec<CachedDataUpdate>]) -> Vec<(TaskId, bool)> {
+    meta_updates
+        .iter()
+        .flat_map(|shard| shard.iter())
+        .filter(|update| matches!(update.key, CachedDataItemKey::Dirty { .. }))
+        .map(|update| {
+            let session_dependent = matches!(
+                update.value,
+                Some(CachedDataItemValue::Dirty {
+                    value: DirtyState {
+                        clean_in_session: Some(_)
+                    }
+                })
+            );
+            (update.task, session_dependent)
+        })
+        .collect()
 }
 
 type SerializedTasks = Vec<Vec<(TaskId, Vec<u8>)>>;
@@ -439,7 +739,7 @@ fn process_task_data(
                 for (task, updates) in task_updates {
                     // Restore the old task data
                     if let Some(old_data) =
-                        database.get(&tx, key_space, IntKey::new(*task).as_ref())?
+                        database.get(&tx, key_space, TaskIdKey::new(*task).as_ref())?
                     {
                         let old_data: Vec<CachedDataItem> = match pot::from_slice(old_data.borrow())
                         {
@@ -459,6 +759,7 @@ fn process_task_data(
 
                     // Apply update
                     for (key, (_, value)) in updates {
+                        let (key, value) = serialize_cell_data(key, value);
                         if let Some(value) = value {
                             map.insert(key, value);
                         } else {
@@ -486,6 +787,148 @@ fn process_task_data(
         .collect::<Result<Vec<_>>>()
 }
 
+/// Cells are persisted as [`CachedDataItem::SerializedCellData`], so they are only deserialized
+/// when they are read after being restored. Cells that can't be serialized are not persisted.
+fn serialize_cell_data(
+    key: CachedDataItemKey,
+    value: Option<CachedDataItemValue>,
+) -> (CachedDataItemKey, Option<CachedDataItemValue>) {
+    let CachedDataItemKey::CellData { cell } = key else {
+        return (key, value);
+    };
+    let value = match value {
+        Some(CachedDataItemValue::CellData { value }) => SerializedCellData::new(&value)
+            .ok()
+            .map(|value| CachedDataItemValue::SerializedCellData { value }),
+        _ => None,
+    };
+    (CachedDataItemKey::SerializedCellData { cell }, value)
+}
+
+/// Finds a single [`CachedDataItem::SerializedCellData`] in the serialized data of a task. All
+/// other items are skipped without deserializing them.
+struct FindSerializedCell(CellId);
+
+impl<'de> DeserializeSeed<'de> for FindSerializedCell {
+    type Value = Option<SerializedCellData>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FindSerializedCell {
+    type Value = Option<SerializedCellData>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of task data items")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut found = None;
+        while let Some(item) = seq.next_element_seed(SerializedCellItem)? {
+            if let Some((cell, value)) = item {
+                if cell == self.0 {
+                    found = Some(value);
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// A single [`CachedDataItem`] that is only deserialized when it's a
+/// [`CachedDataItem::SerializedCellData`].
+struct SerializedCellItem;
+
+impl<'de> DeserializeSeed<'de> for SerializedCellItem {
+    type Value = Option<(CellId, SerializedCellData)>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_enum("CachedDataItem", &[], self)
+    }
+}
+
+impl<'de> Visitor<'de> for SerializedCellItem {
+    type Value = Option<(CellId, SerializedCellData)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a task data item")
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        let (is_cell, variant) = data.variant_seed(NameSeed("SerializedCellData"))?;
+        if !is_cell {
+            variant.struct_variant(&[], IgnoredAny)?;
+            return Ok(None);
+        }
+        variant.struct_variant(&["cell", "value"], SerializedCellFields)
+    }
+}
+
+/// The fields of a [`CachedDataItem::SerializedCellData`].
+struct SerializedCellFields;
+
+impl<'de> Visitor<'de> for SerializedCellFields {
+    type Value = Option<(CellId, SerializedCellData)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("the fields of a serialized cell")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let Some(cell) = seq.next_element()? else {
+            return Err(serde::de::Error::invalid_length(0, &self));
+        };
+        let Some(value) = seq.next_element()? else {
+            return Err(serde::de::Error::invalid_length(1, &self));
+        };
+        Ok(Some((cell, value)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut cell = None;
+        let mut value = None;
+        while let Some(is_cell) = map.next_key_seed(NameSeed("cell"))? {
+            if is_cell {
+                cell = Some(map.next_value()?);
+            } else {
+                value = Some(map.next_value()?);
+            }
+        }
+        let cell = cell.ok_or_else(|| serde::de::Error::missing_field("cell"))?;
+        let value = value.ok_or_else(|| serde::de::Error::missing_field("value"))?;
+        Ok(Some((cell, value)))
+    }
+}
+
+/// Deserializes an identifier and compares it with the expected name.
+struct NameSeed(&'static str);
+
+impl<'de> DeserializeSeed<'de> for NameSeed {
+    type Value = bool;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl Visitor<'_> for NameSeed {
+    type Value = bool;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an identifier")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Self::Value, E> {
+        Ok(name == self.0)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, name: &[u8]) -> Result<Self::Value, E> {
+        Ok(name == self.0.as_bytes())
+    }
+}
+
 fn serialize(task: TaskId, mut data: Vec<CachedDataItem>) -> Result<Vec<u8>> {
     Ok(match pot::to_vec(&data) {
         #[cfg(not(feature = "verify_serialization"))]
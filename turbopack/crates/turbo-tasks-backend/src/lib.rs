@@ -4,27 +4,72 @@ mod backend;
 mod backing_storage;
 mod data;
 mod database;
+pub mod inspect;
 mod kv_backing_storage;
+pub mod test_helpers;
 mod utils;
 
 use std::path::Path;
 
 use anyhow::Result;
 
-pub use self::{backend::TurboTasksBackend, kv_backing_storage::KeyValueDatabaseBackingStorage};
+pub use self::{
+    backend::{
+        introspection, replay, AggregationPersistence, BackendOptions, CacheEffectiveness,
+        DefaultSnapshotPolicy, DependencyDiff, DurationHistogram, ExecutionOutcome,
+        ExecutionTimeout, FairSchedulingOptions, GcStatistics, HangingChain, HangingChainEntry,
+        IdleSnapshotPolicy, LifecycleEvent, OperationStatistics, PanicRetryPolicy, ReadView,
+        RecomputeReason, ShutdownSnapshotPolicy, SnapshotDecision, SnapshotPolicy,
+        SnapshotPolicyState, TaskCacheStatistics, TaskDependency, TaskDirtyCause, TaskEvent,
+        TaskExecution, TaskMemoryUsage, TaskMetrics, TaskProgress, TurboTasksBackend,
+        WaitCycleEntry, WaitCycleError, WaitKind,
+    },
+    backing_storage::SessionInfo,
+    data::TaskExecutionError,
+    database::db_versioning::RetentionPolicy,
+    kv_backing_storage::KeyValueDatabaseBackingStorage,
+};
 use crate::database::{
-    handle_db_versioning, is_fresh, lmdb::LmbdKeyValueDatabase, FreshDbOptimization, NoopKvDb,
-    ReadTransactionCache, StartupCacheLayer,
+    handle_db_versioning, is_fresh, lmdb::LmbdKeyValueDatabase, mark_used, FreshDbOptimization,
+    NoopKvDb, ReadTransactionCache, StartupCacheLayer,
 };
 
+pub fn register() {
+    turbo_tasks::register();
+    include!(concat!(env!("OUT_DIR"), "/register.rs"));
+}
+
 pub type LmdbBackingStorage = KeyValueDatabaseBackingStorage<
     ReadTransactionCache<StartupCacheLayer<FreshDbOptimization<LmbdKeyValueDatabase>>>,
 >;
 
+/// Options for opening a persistent backing storage.
+#[derive(Debug, Clone, Default)]
+pub struct BackingStorageOptions {
+    /// Which databases of other versions are kept around in the cache directory.
+    pub retention: RetentionPolicy,
+    /// Opens the database without write access. Must be combined with
+    /// [`BackendOptions::read_only`], since the backend can't persist any changes.
+    pub read_only: bool,
+}
+
 pub fn lmdb_backing_storage(path: &Path) -> Result<LmdbBackingStorage> {
-    let path = handle_db_versioning(path)?;
+    lmdb_backing_storage_with_options(path, &BackingStorageOptions::default())
+}
+
+pub fn lmdb_backing_storage_with_options(
+    path: &Path,
+    options: &BackingStorageOptions,
+) -> Result<LmdbBackingStorage> {
+    let path = handle_db_versioning(path, &options.retention, options.read_only)?;
     let fresh_db = is_fresh(&path);
-    let database = LmbdKeyValueDatabase::new(&path)?;
+    let database = if options.read_only {
+        LmbdKeyValueDatabase::open_read_only(&path)?
+    } else {
+        let database = LmbdKeyValueDatabase::new(&path)?;
+        mark_used(&path);
+        database
+    };
     let database = FreshDbOptimization::new(database, fresh_db);
     let database = StartupCacheLayer::new(database, path.join("startup.cache"), fresh_db)?;
     let database = ReadTransactionCache::new(database);
@@ -42,3 +87,10 @@ pub type DefaultBackingStorage = LmdbBackingStorage;
 pub fn default_backing_storage(path: &Path) -> Result<DefaultBackingStorage> {
     lmdb_backing_storage(path)
 }
+
+pub fn default_backing_storage_with_options(
+    path: &Path,
+    options: &BackingStorageOptions,
+) -> Result<DefaultBackingStorage> {
+    lmdb_backing_storage_with_options(path, options)
+}
@@ -0,0 +1,28 @@
+//! Helpers for tests that persist their tasks and start again from the persisted cache.
+
+use std::{future::Future, path::Path, sync::Arc};
+
+use anyhow::Result;
+use turbo_tasks::{run_once, TurboTasks};
+
+use crate::{lmdb_backing_storage, BackendOptions, LmdbBackingStorage, TurboTasksBackend};
+
+pub type PersistentTurboTasks = TurboTasks<TurboTasksBackend<LmdbBackingStorage>>;
+
+/// Creates a [`TurboTasks`] instance that persists its tasks in the database at `path`. Creating
+/// another instance for the same path after stopping this one restores the persisted tasks, like
+/// restarting the process does.
+pub fn persistent_turbo_tasks(path: &Path, options: BackendOptions) -> Arc<PersistentTurboTasks> {
+    TurboTasks::new(TurboTasksBackend::new(
+        options,
+        lmdb_backing_storage(path).unwrap(),
+    ))
+}
+
+/// Runs `future` in a once task of `tt` and returns its result. Panics when it fails.
+pub async fn run_once_for_testing<T: Send + 'static>(
+    tt: &Arc<PersistentTurboTasks>,
+    future: impl Future<Output = Result<T>> + Send + 'static,
+) -> T {
+    run_once(tt.clone(), future).await.unwrap()
+}
@@ -43,6 +43,20 @@ where
         self.reverse.get(key).map(|v| v.value().clone())
     }
 
+    pub fn len(&self) -> usize {
+        self.forward.len()
+    }
+
+    /// Removes the entry, unless the key has been mapped to a different value in the meantime.
+    /// Returns true when the entry was removed.
+    pub fn remove(&self, key: &K, value: &V) -> bool {
+        if self.forward.remove_if(key, |_, v| v == value).is_none() {
+            return false;
+        }
+        self.reverse.remove_if(value, |_, k| k == key);
+        true
+    }
+
     pub fn try_insert(&self, key: K, value: V) -> Result<(), V> {
         match self.forward.entry(key) {
             Entry::Occupied(e) => Err(e.get().clone()),
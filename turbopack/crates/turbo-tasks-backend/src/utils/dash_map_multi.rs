@@ -171,6 +171,63 @@ where
     }
 }
 
+/// Like [`get_multiple_mut`], but for any number of distinct keys. Only the lock of one shard is
+/// awaited at a time, while the locks of the other shards are only tried, so this can't deadlock
+/// with other accesses to the map.
+pub fn get_many_mut<K, V, S, const N: usize>(
+    map: &DashMap<K, V, S>,
+    keys: [K; N],
+    insert_with: impl Fn() -> V,
+) -> [RefMut<'_, K, V, S>; N]
+where
+    K: Hash + Eq + Clone,
+    S: BuildHasher + Clone,
+{
+    for (i, key) in keys.iter().enumerate() {
+        assert!(!keys[..i].contains(key), "keys must be distinct");
+    }
+    let key_shards = keys.each_ref().map(|key| map.determine_map(key));
+    let mut shard_ids = key_shards.to_vec();
+    shard_ids.sort_unstable();
+    shard_ids.dedup();
+    let shards = map.shards();
+    let mut blocking = 0;
+    let mut guards = 'lock: loop {
+        let mut guards = shard_ids.iter().map(|_| None).collect::<Vec<_>>();
+        guards[blocking] = Some(shards[shard_ids[blocking]].write());
+        for (i, &shard) in shard_ids.iter().enumerate() {
+            if i == blocking {
+                continue;
+            }
+            let Some(guard) = shards[shard].try_write() else {
+                blocking = i;
+                continue 'lock;
+            };
+            guards[i] = Some(guard);
+        }
+        break guards.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+    };
+    let guard_indices = key_shards.map(|shard| shard_ids.binary_search(&shard).unwrap());
+    for (key, &index) in keys.iter().zip(guard_indices.iter()) {
+        guards[index]
+            .raw_entry_mut()
+            .from_key(key)
+            .or_insert_with(|| (key.clone(), SharedValue::new(insert_with())));
+    }
+    // Pointers are only taken after all entries are inserted, since inserting might move them
+    let pointers: [_; N] = std::array::from_fn(|i| {
+        let index = guard_indices[i];
+        let (key, value) = guards[index].get_key_value_mut(&keys[i]).unwrap();
+        (index, key as *const K, value.get_mut() as *mut V)
+    });
+    let guards = guards.into_iter().map(Arc::new).collect::<Vec<_>>();
+    pointers.map(|(index, key, value)| RefMut::Shared {
+        _guard: guards[index].clone(),
+        key,
+        value,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::thread::scope;
@@ -213,4 +270,36 @@ mod tests {
         let value = *map.get(&N).unwrap();
         assert_eq!(value, THREADS);
     }
+
+    #[test]
+    fn stress_deadlock_many() {
+        const N: usize = 100000;
+        const THREADS: usize = 20;
+
+        let map = DashMap::with_shard_amount(4);
+        let indicies = (0..THREADS)
+            .map(|_| {
+                let mut vec = (0..N).collect::<Vec<_>>();
+                vec.shuffle(&mut rand::thread_rng());
+                vec
+            })
+            .collect::<Vec<_>>();
+        let map = &map;
+        scope(|s| {
+            for indicies in indicies {
+                s.spawn(|| {
+                    for i in indicies {
+                        let [mut a, mut b, mut c] = get_many_mut(map, [i + 2, i, i + 1], || 0);
+                        *a += 1;
+                        *b += 1;
+                        *c += 1;
+                    }
+                });
+            }
+        });
+        for i in 2..N {
+            let value = *map.get(&i).unwrap();
+            assert_eq!(value, THREADS * 3);
+        }
+    }
 }
@@ -0,0 +1,81 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{test_helpers::current_task_for_testing, TaskId, TurboTasksApi, Vc};
+use turbo_tasks_backend::{
+    test_helpers::{persistent_turbo_tasks, run_once_for_testing, PersistentTurboTasks},
+    AggregationPersistence, BackendOptions,
+};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+static VALUE: AtomicU32 = AtomicU32::new(0);
+static LEAF_TASK: Mutex<Option<TaskId>> = Mutex::new(None);
+
+fn options() -> BackendOptions {
+    BackendOptions {
+        aggregation_persistence: AggregationPersistence::Rebuilt,
+        ..Default::default()
+    }
+}
+
+async fn read_outer(tt: &Arc<PersistentTurboTasks>) -> u32 {
+    run_once_for_testing(tt, async { Ok(*outer().strongly_consistent().await?) }).await
+}
+
+fn invalidate_leaf(tt: &PersistentTurboTasks, value: u32) {
+    VALUE.store(value, Ordering::SeqCst);
+    let leaf = LEAF_TASK
+        .lock()
+        .unwrap()
+        .expect("the leaf task has been executed");
+    tt.invalidate(leaf);
+}
+
+#[tokio::test]
+async fn aggregation_is_rebuilt_after_warm_start() {
+    REGISTRATION.ensure_registered();
+    let db_dir = tempfile::tempdir().unwrap();
+
+    let tt = persistent_turbo_tasks(db_dir.path(), options());
+    VALUE.store(1, Ordering::SeqCst);
+    assert_eq!(read_outer(&tt).await, 1);
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    // The restored leaf is invalidated before the structure above it has been rebuilt
+    let tt = persistent_turbo_tasks(db_dir.path(), options());
+    invalidate_leaf(&tt, 2);
+    assert_eq!(read_outer(&tt).await, 2);
+
+    // Invalidations of restored tasks reach the rebuilt root
+    invalidate_leaf(&tt, 3);
+    assert_eq!(read_outer(&tt).await, 3);
+    tt.stop_and_wait().await;
+}
+
+#[turbo_tasks::function]
+async fn outer() -> Result<Vc<u32>> {
+    let value = *middle().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn middle() -> Result<Vc<u32>> {
+    let value = *leaf().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+fn leaf() -> Vc<u32> {
+    *LEAF_TASK.lock().unwrap() = Some(current_task_for_testing());
+    Vc::cell(VALUE.load(Ordering::SeqCst))
+}
@@ -0,0 +1,89 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    is_cancelled, run_once, test_helpers::current_task_for_testing, State, TaskId, TurboTasks, Vc,
+};
+use turbo_tasks_backend::{noop_backing_storage, BackendOptions, TurboTasksBackend};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+static STALL: AtomicBool = AtomicBool::new(false);
+static STALLED_TASK: Mutex<Option<TaskId>> = Mutex::new(None);
+static OBSERVED_CANCELLATION: AtomicBool = AtomicBool::new(false);
+static EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn cancel_while_strongly_consistent_read_waits() {
+    REGISTRATION.ensure_registered();
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        noop_backing_storage(Path::new("")).unwrap(),
+    ));
+    let cancelling_tt = tt.clone();
+    run_once(tt, async move {
+        let input = ChangingInput {
+            state: State::new(1),
+        }
+        .cell();
+        let output = compute(input);
+        assert_eq!(*output.await?, 1);
+        assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+        // The next execution stalls until it's cancelled
+        STALL.store(true, Ordering::SeqCst);
+        input.await?.state.set(2);
+
+        let cancel = async {
+            let task_id = loop {
+                if let Some(task_id) = *STALLED_TASK.lock().unwrap() {
+                    break task_id;
+                }
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            };
+            cancelling_tt.cancel_task(task_id);
+        };
+        let read = tokio::time::timeout(Duration::from_secs(10), output.strongly_consistent());
+        let (read, ()) = tokio::join!(read, cancel);
+
+        // The cancelled execution is discarded and the task is executed again
+        assert_eq!(*read.expect("read should settle after cancellation")?, 2);
+        assert!(OBSERVED_CANCELLATION.load(Ordering::SeqCst));
+        assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 3);
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+#[turbo_tasks::value]
+struct ChangingInput {
+    state: State<u32>,
+}
+
+#[turbo_tasks::function]
+async fn compute(input: Vc<ChangingInput>) -> Result<Vc<u32>> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let value = *input.await?.state.get();
+    if STALL.swap(false, Ordering::SeqCst) {
+        *STALLED_TASK.lock().unwrap() = Some(current_task_for_testing());
+        while !is_cancelled() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        OBSERVED_CANCELLATION.store(true, Ordering::SeqCst);
+        return Ok(Vc::cell(0));
+    }
+    Ok(Vc::cell(value))
+}
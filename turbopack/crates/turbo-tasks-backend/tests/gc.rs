@@ -0,0 +1,96 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{test_helpers::current_task_for_testing, TaskId, TurboTasksApi, Vc};
+use turbo_tasks_backend::{
+    test_helpers::{persistent_turbo_tasks, run_once_for_testing, PersistentTurboTasks},
+    BackendOptions,
+};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+static VALUE: AtomicU32 = AtomicU32::new(0);
+static LEAF_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static MIDDLE_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static LEAF_TASK: Mutex<Option<TaskId>> = Mutex::new(None);
+
+async fn read_outer(tt: &Arc<PersistentTurboTasks>) -> u32 {
+    run_once_for_testing(tt, async { Ok(*outer().strongly_consistent().await?) }).await
+}
+
+async fn read_middle(tt: &Arc<PersistentTurboTasks>) -> u32 {
+    run_once_for_testing(tt, async { Ok(*middle().strongly_consistent().await?) }).await
+}
+
+#[tokio::test]
+async fn unloaded_tasks_are_restored_with_their_dependencies() {
+    REGISTRATION.ensure_registered();
+    let db_dir = tempfile::tempdir().unwrap();
+    let tt = persistent_turbo_tasks(
+        db_dir.path(),
+        BackendOptions {
+            // Always exceeded, so every unused task is unloaded
+            memory_limit: Some(1),
+            ..Default::default()
+        },
+    );
+
+    VALUE.store(1, Ordering::SeqCst);
+    assert_eq!(read_outer(&tt).await, 1);
+
+    // Tasks are only unloaded after they have been unused for a few snapshots
+    for _ in 0..3 {
+        tt.backend().flush().await.unwrap();
+    }
+    let statistics = tt.backend().gc_statistics();
+    assert!(statistics.unloaded_tasks >= 2, "{statistics:?}");
+
+    // The output is restored without executing the task again
+    assert_eq!(read_middle(&tt).await, 1);
+    assert_eq!(MIDDLE_EXECUTIONS.load(Ordering::SeqCst), 1);
+    assert_eq!(LEAF_EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // The restored tasks still invalidate the tasks that read them
+    VALUE.store(2, Ordering::SeqCst);
+    let leaf = LEAF_TASK
+        .lock()
+        .unwrap()
+        .expect("the leaf task has been executed");
+    tt.invalidate(leaf);
+    assert_eq!(read_outer(&tt).await, 2);
+    assert_eq!(MIDDLE_EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(LEAF_EXECUTIONS.load(Ordering::SeqCst), 2);
+
+    let reloaded = tt.backend().gc_statistics();
+    assert!(reloaded.reloaded_tasks >= 2, "{reloaded:?}");
+    assert!(reloaded.restored_tasks >= reloaded.reloaded_tasks);
+    tt.stop_and_wait().await;
+}
+
+#[turbo_tasks::function]
+async fn outer() -> Result<Vc<u32>> {
+    let value = *middle().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn middle() -> Result<Vc<u32>> {
+    MIDDLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let value = *leaf().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+fn leaf() -> Vc<u32> {
+    LEAF_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    *LEAF_TASK.lock().unwrap() = Some(current_task_for_testing());
+    Vc::cell(VALUE.load(Ordering::SeqCst))
+}
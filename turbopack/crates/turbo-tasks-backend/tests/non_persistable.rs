@@ -0,0 +1,82 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{test_helpers::current_task_for_testing, TaskId, Vc};
+use turbo_tasks_backend::{
+    test_helpers::{persistent_turbo_tasks, run_once_for_testing, PersistentTurboTasks},
+    BackendOptions,
+};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+static EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static OUTER_TASK: Mutex<Option<TaskId>> = Mutex::new(None);
+
+async fn read_root(tt: &Arc<PersistentTurboTasks>) -> u32 {
+    run_once_for_testing(tt, async {
+        root().strongly_consistent().await?;
+        Ok(*outer().strongly_consistent().await?)
+    })
+    .await
+}
+
+fn outer_task() -> TaskId {
+    OUTER_TASK
+        .lock()
+        .unwrap()
+        .expect("the outer task has been executed")
+}
+
+#[tokio::test]
+async fn non_persistable_tasks_are_removed_from_the_task_cache() {
+    REGISTRATION.ensure_registered();
+    let db_dir = tempfile::tempdir().unwrap();
+
+    let tt = persistent_turbo_tasks(db_dir.path(), BackendOptions::default());
+    assert_eq!(read_root(&tt).await, 1);
+    let first_outer_task = outer_task();
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    // The persisted root refers to the removed outer task, which is created again with a new id
+    let tt = persistent_turbo_tasks(db_dir.path(), BackendOptions::default());
+    assert_eq!(read_root(&tt).await, 2);
+    assert_ne!(outer_task(), first_outer_task);
+    let inconsistencies = tt.backend().verify_consistency();
+    assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+    tt.stop_and_wait().await;
+    drop(tt);
+
+    let tt = persistent_turbo_tasks(db_dir.path(), BackendOptions::default());
+    assert_eq!(read_root(&tt).await, 3);
+    let inconsistencies = tt.backend().verify_consistency();
+    assert!(inconsistencies.is_empty(), "{:?}", inconsistencies);
+    tt.stop_and_wait().await;
+}
+
+#[turbo_tasks::function]
+fn root() -> Vc<u32> {
+    let _ = outer();
+    Vc::cell(0)
+}
+
+#[turbo_tasks::function]
+async fn outer() -> Result<Vc<u32>> {
+    *OUTER_TASK.lock().unwrap() = Some(current_task_for_testing());
+    let value = *inner().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+fn inner() -> Vc<u32> {
+    turbo_tasks::mark_non_persistable();
+    Vc::cell(EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1)
+}
@@ -0,0 +1,101 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use std::{
+    path::Path,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use tokio::sync::watch;
+use turbo_tasks::{run_once, Completion, State, TransientInstance, TurboTasks, Vc};
+use turbo_tasks_backend::{noop_backing_storage, BackendOptions, TurboTasksBackend};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+static INPUT_READS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn normal_tasks_run_while_an_urgent_task_waits_to_settle() {
+    REGISTRATION.ensure_registered();
+    let tt = TurboTasks::new(TurboTasksBackend::new(
+        BackendOptions::default(),
+        noop_backing_storage(Path::new("")).unwrap(),
+    ));
+    run_once(tt, async move {
+        let (tx, _) = watch::channel(false);
+        let tx = TransientInstance::new(tx);
+        let input = ChangingInput {
+            state: State::new(1),
+        }
+        .cell();
+
+        // `outer` has no output yet, so it's scheduled urgently. It waits for `inner` to settle,
+        // which includes the `blocked` child.
+        let read = tokio::time::timeout(
+            Duration::from_secs(10),
+            outer(input, tx.clone()).strongly_consistent(),
+        );
+        let invalidate = async {
+            while INPUT_READS.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            // Give `outer` time to start waiting for `inner` to settle
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            // `read_input` is scheduled again with normal priority while the urgent `outer` is
+            // still waiting
+            input.await?.state.set(2);
+            tx.send(true)?;
+            anyhow::Ok(())
+        };
+        let (read, invalidate) = tokio::join!(read, invalidate);
+        invalidate?;
+
+        assert_eq!(*read.expect("read should settle")?, 2);
+        assert_eq!(INPUT_READS.load(Ordering::SeqCst), 2);
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap()
+}
+
+#[turbo_tasks::value]
+struct ChangingInput {
+    state: State<u32>,
+}
+
+#[turbo_tasks::function]
+async fn outer(
+    input: Vc<ChangingInput>,
+    sender: TransientInstance<watch::Sender<bool>>,
+) -> Result<Vc<u32>> {
+    let value = *inner(input, sender).strongly_consistent().await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn inner(
+    input: Vc<ChangingInput>,
+    sender: TransientInstance<watch::Sender<bool>>,
+) -> Result<Vc<u32>> {
+    let _ = blocked(sender);
+    let value = *read_input(input).await?;
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn read_input(input: Vc<ChangingInput>) -> Result<Vc<u32>> {
+    INPUT_READS.fetch_add(1, Ordering::SeqCst);
+    let value = *input.await?.state.get();
+    Ok(Vc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn blocked(sender: TransientInstance<watch::Sender<bool>>) -> Result<Vc<Completion>> {
+    sender.subscribe().wait_for(|&done| done).await?;
+    Ok(Completion::new())
+}
@@ -0,0 +1,64 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbo_tasks_backend::{
+    test_helpers::{persistent_turbo_tasks, run_once_for_testing},
+    BackendOptions,
+};
+use turbo_tasks_testing::{register, Registration};
+
+static REGISTRATION: Registration = register!();
+
+const TASKS: u32 = 20;
+const TASK_CACHE_LIMIT: usize = 5;
+
+fn options() -> BackendOptions {
+    BackendOptions {
+        task_cache_limit: Some(TASK_CACHE_LIMIT),
+        ..Default::default()
+    }
+}
+
+async fn compute_all() -> Result<()> {
+    for i in 0..TASKS {
+        assert_eq!(*double(i).await?, i * 2);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn evicted_entries_are_fetched_again() {
+    REGISTRATION.ensure_registered();
+    let db_dir = tempfile::tempdir().unwrap();
+
+    // Persist the tasks, so their task cache entries become evictable
+    let tt = persistent_turbo_tasks(db_dir.path(), options());
+    run_once_for_testing(&tt, compute_all()).await;
+    tt.stop_and_wait().await;
+    let statistics = tt.backend().task_cache_statistics();
+    assert!(statistics.entries <= TASK_CACHE_LIMIT);
+    assert!(statistics.evictions >= TASKS as usize - TASK_CACHE_LIMIT);
+    drop(tt);
+
+    // Every entry is fetched from the backing storage on the first call, and evicted entries are
+    // fetched again on the second call
+    let tt = persistent_turbo_tasks(db_dir.path(), options());
+    run_once_for_testing(&tt, compute_all()).await;
+    let statistics = tt.backend().task_cache_statistics();
+    assert!(statistics.entries <= TASK_CACHE_LIMIT);
+    assert!(statistics.fetches >= TASKS as usize);
+    run_once_for_testing(&tt, compute_all()).await;
+    let refetched = tt.backend().task_cache_statistics();
+    assert!(refetched.entries <= TASK_CACHE_LIMIT);
+    assert!(refetched.fetches >= statistics.fetches + TASKS as usize - TASK_CACHE_LIMIT);
+    assert!(refetched.evictions > statistics.evictions);
+    tt.stop_and_wait().await;
+}
+
+#[turbo_tasks::function]
+fn double(value: u32) -> Vc<u32> {
+    Vc::cell(value * 2)
+}
@@ -662,6 +662,9 @@ pub struct FunctionArguments {
     ///
     /// Setting this option will also set [`Self::resolved`] to the same span.
     pub local_cells: Option<Span>,
+    /// Declares that the output of the function never changes for the same inputs, e.g. because
+    /// it's computed from a content hash. Readers of such tasks are not tracked.
+    pub immutable: Option<Span>,
 }
 
 impl Parse for FunctionArguments {
@@ -691,11 +694,14 @@ impl Parse for FunctionArguments {
                     parsed_args.local_cells = span;
                     parsed_args.resolved = span;
                 }
+                ("immutable", Meta::Path(_)) => {
+                    parsed_args.immutable = Some(meta.span());
+                }
                 (_, meta) => {
                     return Err(syn::Error::new_spanned(
                         meta,
                         "unexpected token, expected one of: \"fs\", \"network\", \"resolved\", \
-                         \"local_cells\"",
+                         \"local_cells\", \"immutable\"",
                     ))
                 }
             }
@@ -961,6 +967,7 @@ pub struct NativeFn {
     function_path: ExprPath,
     is_method: bool,
     local_cells: bool,
+    immutable: bool,
 }
 
 impl NativeFn {
@@ -969,12 +976,14 @@ impl NativeFn {
         function_path: &ExprPath,
         is_method: bool,
         local_cells: bool,
+        immutable: bool,
     ) -> NativeFn {
         NativeFn {
             function_path_string: function_path_string.to_owned(),
             function_path: function_path.clone(),
             is_method,
             local_cells,
+            immutable,
         }
     }
 
@@ -988,6 +997,7 @@ impl NativeFn {
             function_path,
             is_method,
             local_cells,
+            immutable,
         } = self;
 
         let constructor = if *is_method {
@@ -1003,6 +1013,7 @@ impl NativeFn {
                     #function_path_string.to_owned(),
                     turbo_tasks::FunctionMeta {
                         local_cells: #local_cells,
+                        immutable: #immutable,
                     },
                     #function_path,
                 )
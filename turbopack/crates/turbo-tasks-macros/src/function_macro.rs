@@ -38,6 +38,7 @@ pub fn function(args: TokenStream, input: TokenStream) -> TokenStream {
         .inspect_err(|err| errors.push(err.to_compile_error()))
         .unwrap_or_default();
     let local_cells = args.local_cells.is_some();
+    let immutable = args.immutable.is_some();
 
     let Some(turbo_fn) = TurboFn::new(&sig, DefinitionContext::NakedFn, args) else {
         return quote! {
@@ -56,6 +57,7 @@ pub fn function(args: TokenStream, input: TokenStream) -> TokenStream {
         &parse_quote! { #inline_function_ident },
         turbo_fn.is_method(),
         local_cells,
+        immutable,
     );
     let native_function_ident = get_native_function_ident(ident);
     let native_function_ty = native_fn.ty();
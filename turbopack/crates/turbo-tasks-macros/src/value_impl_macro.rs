@@ -120,6 +120,7 @@ pub fn value_impl(args: TokenStream, input: TokenStream) -> TokenStream {
                     .inspect_err(|err| errors.push(err.to_compile_error()))
                     .unwrap_or_default();
                 let local_cells = func_args.local_cells.is_some();
+                let immutable = func_args.immutable.is_some();
 
                 let Some(turbo_fn) =
                     TurboFn::new(sig, DefinitionContext::ValueInherentImpl, func_args)
@@ -136,6 +137,7 @@ pub fn value_impl(args: TokenStream, input: TokenStream) -> TokenStream {
                     &parse_quote! { <#ty>::#inline_function_ident },
                     turbo_fn.is_method(),
                     local_cells,
+                    immutable,
                 );
 
                 let native_function_ident = get_inherent_impl_function_ident(ty_ident, ident);
@@ -221,6 +223,7 @@ pub fn value_impl(args: TokenStream, input: TokenStream) -> TokenStream {
                     .inspect_err(|err| errors.push(err.to_compile_error()))
                     .unwrap_or_default();
                 let local_cells = func_args.local_cells.is_some();
+                let immutable = func_args.immutable.is_some();
 
                 let Some(turbo_fn) =
                     TurboFn::new(sig, DefinitionContext::ValueTraitImpl, func_args)
@@ -248,6 +251,7 @@ pub fn value_impl(args: TokenStream, input: TokenStream) -> TokenStream {
                     },
                     turbo_fn.is_method(),
                     local_cells,
+                    immutable,
                 );
 
                 let native_function_ident =
@@ -122,6 +122,8 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
                 //   argument.
                 // - This only makes sense when a default implementation is present.
                 false,
+                // `immutable` is unsupported for the same reasons.
+                false,
             );
 
             let native_function_ident = get_trait_default_impl_function_ident(trait_ident, ident);
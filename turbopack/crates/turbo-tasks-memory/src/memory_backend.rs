@@ -24,7 +24,7 @@ use turbo_tasks::{
     },
     event::EventListener,
     util::{IdFactoryWithReuse, NoMoveVec},
-    CellId, FunctionId, RawVc, ReadConsistency, TaskId, TaskIdSet, TraitTypeId,
+    CellId, FunctionId, RawVc, ReadConsistency, TaskId, TaskIdSet, TaskIdValue, TraitTypeId,
     TurboTasksBackendApi, Unused, ValueTypeId, TRANSIENT_TASK_BIT,
 };
 
@@ -140,26 +140,22 @@ impl MemoryBackend {
 
     #[inline(always)]
     pub fn with_task<T>(&self, id: TaskId, func: impl FnOnce(&Task) -> T) -> T {
-        let value = *id;
-        let index = (value & !TRANSIENT_TASK_BIT) as usize;
-        let item = if value & TRANSIENT_TASK_BIT == 0 {
-            self.persistent_tasks.get(index)
-        } else {
-            self.transient_tasks.get(index)
-        };
-        func(item.unwrap())
+        func(self.task(id))
     }
 
     #[inline(always)]
     pub fn task(&self, id: TaskId) -> &Task {
-        let value = *id;
+        let value = id.without_generation();
         let index = (value & !TRANSIENT_TASK_BIT) as usize;
         let item = if value & TRANSIENT_TASK_BIT == 0 {
             self.persistent_tasks.get(index)
         } else {
             self.transient_tasks.get(index)
         };
-        item.unwrap()
+        let task = item.unwrap();
+        // A different generation means the id has been reused for another task since
+        debug_assert_eq!(task.id(), id, "Stale reference to a reused task id");
+        task
     }
 
     /// Runs the garbage collection until reaching the target memory. An `idle`
@@ -254,14 +250,14 @@ impl MemoryBackend {
         parent_task: TaskId,
         task_cache: &DashMap<K, TaskId, H>,
         task_storage: &NoMoveVec<Task, N>,
-        task_storage_offset: u32,
+        task_storage_offset: TaskIdValue,
         key: K,
         new_id: Unused<TaskId>,
         task: Task,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> TaskId {
         let new_id = new_id.into();
-        let index = (*new_id - task_storage_offset) as usize;
+        let index = (new_id.without_generation() - task_storage_offset) as usize;
         // Safety: We have a fresh task id that nobody knows about yet
         unsafe { task_storage.insert(index, task) };
         let result_task = match task_cache.entry(key) {
@@ -804,7 +800,7 @@ impl Backend for MemoryBackend {
     ) -> TaskId {
         let id = turbo_tasks.get_fresh_transient_task_id();
         let id = id.into();
-        let index = (*id - TRANSIENT_TASK_BIT) as usize;
+        let index = (id.without_generation() - TRANSIENT_TASK_BIT) as usize;
         match task_type {
             TransientTaskType::Root(f) => {
                 let task = Task::new_root(id, move || f() as _);
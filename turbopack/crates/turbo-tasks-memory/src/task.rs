@@ -610,6 +610,10 @@ impl Task {
         }
     }
 
+    pub(crate) fn id(&self) -> TaskId {
+        self.id
+    }
+
     pub(crate) fn get_description(&self) -> String {
         Self::format_description(&TaskTypeForDescription::from(&self.ty), self.id)
     }
@@ -1607,7 +1611,7 @@ impl Task {
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) -> Result<Result<T, EventListener>> {
         let mut aggregation_context = TaskAggregationContext::new(turbo_tasks, backend);
-        let mut state = if consistency == ReadConsistency::Strong {
+        let mut state = if consistency.waits_for_settled() {
             let mut aggregation = aggregation_data(&aggregation_context, &self.id);
             if aggregation.unfinished > 0 {
                 if aggregation.root_type.is_none() {
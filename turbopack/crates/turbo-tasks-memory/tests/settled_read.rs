@@ -0,0 +1 @@
+../../turbo-tasks-testing/tests/settled_read.rs
\ No newline at end of file
@@ -15,13 +15,13 @@ use std::{
 use anyhow::{anyhow, Result};
 use futures::FutureExt;
 use turbo_tasks::{
-    backend::{CellContent, TaskCollectiblesMap, TypedCellContent},
+    backend::{CellContent, EmittedCollectible, TaskCollectiblesMap, TypedCellContent},
     event::{Event, EventListener},
     registry,
     test_helpers::with_turbo_tasks_for_testing,
     util::{SharedError, StaticOrArc},
     CellId, ExecutionId, InvalidationReason, LocalTaskId, MagicAny, RawVc, ReadConsistency, TaskId,
-    TaskPersistence, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
+    TaskIdValue, TaskPersistence, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
 };
 
 pub use crate::run::{run, run_with_tt, run_without_cache_check, Registration};
@@ -56,7 +56,7 @@ impl VcStorage {
             })));
             i
         };
-        let task_id = TaskId::from(i as u32 + 1);
+        let task_id = TaskId::from(i as TaskIdValue + 1);
         let execution_id = ExecutionId::from(i as u64 + 1);
         handle.spawn(with_turbo_tasks_for_testing(
             this.clone(),
@@ -289,6 +289,14 @@ impl TurboTasksApi for VcStorage {
         unimplemented!()
     }
 
+    fn read_task_collectibles_with_emitters(
+        &self,
+        _task: TaskId,
+        _trait_id: TraitTypeId,
+    ) -> Vec<EmittedCollectible> {
+        unimplemented!()
+    }
+
     fn read_own_task_cell(&self, task: TaskId, index: CellId) -> Result<TypedCellContent> {
         let map = self.cells.lock().unwrap();
         Ok(if let Some(cell) = map.get(&(task, index)) {
@@ -336,7 +344,7 @@ impl VcStorage {
                 this: weak.clone(),
                 ..Default::default()
             }),
-            TaskId::from(u32::MAX),
+            TaskId::from(TaskIdValue::MAX),
             ExecutionId::from(u64::MAX),
             f,
         )
@@ -0,0 +1,59 @@
+#![allow(clippy::needless_return)] // clippy bug causes false positive
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+
+use anyhow::Result;
+use tokio::{
+    sync::watch,
+    time::{timeout, Duration},
+};
+use turbo_tasks::{Completion, TransientInstance, Vc};
+use turbo_tasks_testing::{register, run, Registration};
+
+static REGISTRATION: Registration = register!();
+
+#[tokio::test]
+async fn settled_read_stops_waiting_at_the_deadline() -> Result<()> {
+    run(&REGISTRATION, || async {
+        // timeout: prevent the test from hanging, and fail instead if this is broken
+        timeout(Duration::from_secs(5), async {
+            let (tx, _) = watch::channel(false);
+            let tx = TransientInstance::new(tx);
+            let out_vc = parent(tx.clone());
+
+            // the child task is still running, so a strongly consistent read waits for it
+            timeout(Duration::from_millis(100), out_vc.strongly_consistent())
+                .await
+                .expect_err("should wait on the child task");
+
+            // a settled read stops waiting for the child task at the deadline
+            let value = timeout(
+                Duration::from_secs(1),
+                out_vc.settled(Duration::from_millis(50)),
+            )
+            .await
+            .expect("should stop waiting at the deadline")?;
+            assert_eq!(*value, 42);
+
+            // once the child task completes, the strongly consistent read finishes as well
+            tx.send(true)?;
+            assert_eq!(*out_vc.strongly_consistent().await?, 42);
+
+            anyhow::Ok(())
+        })
+        .await?
+    })
+    .await
+}
+
+#[turbo_tasks::function]
+fn parent(sender: TransientInstance<watch::Sender<bool>>) -> Vc<u32> {
+    let _ = child(sender);
+    Vc::cell(42)
+}
+
+#[turbo_tasks::function]
+async fn child(sender: TransientInstance<watch::Sender<bool>>) -> Result<Vc<Completion>> {
+    sender.subscribe().wait_for(|&done| done).await?;
+    Ok(Completion::new())
+}
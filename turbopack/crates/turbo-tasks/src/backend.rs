@@ -23,8 +23,9 @@ use crate::{
     task::shared_reference::TypedSharedReference,
     trait_helpers::{get_trait_method, has_trait, traits},
     triomphe_utils::unchecked_sidecast_triomphe_arc,
-    FunctionId, RawVc, ReadRef, SharedReference, TaskId, TaskIdSet, TaskPersistence, TraitRef,
-    TraitTypeId, ValueTypeId, VcRead, VcValueTrait, VcValueType,
+    util::StaticOrArc,
+    FunctionId, InvalidationReason, RawVc, ReadRef, SharedReference, TaskId, TaskIdSet,
+    TaskPersistence, TraitRef, TraitTypeId, ValueTypeId, VcRead, VcValueTrait, VcValueType,
 };
 
 pub type TransientTaskRoot =
@@ -523,6 +524,38 @@ impl TryFrom<CellContent> for SharedReference {
 
 pub type TaskCollectiblesMap = AutoMap<RawVc, i32, BuildHasherDefault<FxHasher>, 1>;
 
+/// A collectible together with the task that emitted it, see
+/// [`Backend::read_task_collectibles_with_emitters`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedCollectible<T = RawVc> {
+    pub collectible: T,
+    pub emitter: TaskId,
+    pub emitter_description: String,
+}
+
+/// Aggregated execution and creation statistics of all tasks of a single function.
+#[derive(Debug, Clone, Default)]
+pub struct TaskExecutionStatistics {
+    pub executions: u64,
+    pub total_duration: Duration,
+    pub max_duration: Duration,
+    pub total_memory_usage: u64,
+    pub max_memory_usage: usize,
+    /// Number of persistent tasks created in this session, i. e. not found in the persistent
+    /// cache. A high number compared to the executions hints at unstable arguments.
+    pub created_persistent_tasks: u64,
+}
+
+impl TaskExecutionStatistics {
+    pub fn record(&mut self, duration: Duration, memory_usage: usize) {
+        self.executions += 1;
+        self.total_duration += duration;
+        self.max_duration = self.max_duration.max(duration);
+        self.total_memory_usage += memory_usage as u64;
+        self.max_memory_usage = self.max_memory_usage.max(memory_usage);
+    }
+}
+
 pub trait Backend: Sync + Send {
     #[allow(unused_variables)]
     fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {}
@@ -539,6 +572,17 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
+    /// Like [`Backend::invalidate_task`], but with the reason for the invalidation, e.g. the file
+    /// that has changed. Backends can keep it to explain why a task is dirty.
+    fn invalidate_task_with_reason(
+        &self,
+        task: TaskId,
+        _reason: StaticOrArc<dyn InvalidationReason>,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.invalidate_task(task, turbo_tasks);
+    }
+
     fn invalidate_tasks(&self, tasks: &[TaskId], turbo_tasks: &dyn TurboTasksBackendApi<Self>);
     fn invalidate_tasks_set(&self, tasks: &TaskIdSet, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
@@ -659,6 +703,31 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> TaskCollectiblesMap;
 
+    /// Like [`Backend::read_task_collectibles`], but also returns the task that emitted each
+    /// collectible. A collectible that is emitted by multiple tasks is attributed to one of them.
+    ///
+    /// The default implementation attributes each collectible to the task that owns its cell.
+    fn read_task_collectibles_with_emitters(
+        &self,
+        task: TaskId,
+        trait_id: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<EmittedCollectible> {
+        self.read_task_collectibles(task, trait_id, reader, turbo_tasks)
+            .into_iter()
+            .filter(|&(_, count)| count > 0)
+            .map(|(collectible, _)| {
+                let emitter = collectible.get_task_id();
+                EmittedCollectible {
+                    collectible,
+                    emitter,
+                    emitter_description: self.get_task_description(emitter),
+                }
+            })
+            .collect()
+    }
+
     fn emit_collectible(
         &self,
         trait_type: TraitTypeId,
@@ -725,6 +794,40 @@ pub trait Backend: Sync + Send {
         // Do nothing by default
     }
 
+    /// Marks the reads of the current execution of the task as weak. Readers are still
+    /// invalidated when the read data changes, but don't keep the read tasks in memory.
+    fn mark_own_task_as_weak_reader(
+        &self,
+        _task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        // Do nothing by default
+    }
+
+    /// Stops persisting the task and its persistent readers, so that it can call transient
+    /// functions.
+    fn mark_own_task_as_non_persistable(
+        &self,
+        _task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        // Do nothing by default
+    }
+
+    /// Marks an in-progress task as cancelled. The result of the current execution will be
+    /// discarded and the task is executed again. Tasks that are not in progress are not affected.
+    fn cancel_task(&self, _task: TaskId, _turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
+        // Do nothing by default
+    }
+
+    fn is_own_task_cancelled(
+        &self,
+        _task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> bool {
+        false
+    }
+
     fn create_transient_task(
         &self,
         task_type: TransientTaskType,
@@ -732,6 +835,12 @@ pub trait Backend: Sync + Send {
     ) -> TaskId;
 
     fn dispose_root_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
+
+    /// Returns the execution and task creation statistics per function. Backends that don't collect
+    /// statistics return an empty list.
+    fn task_statistics(&self) -> Vec<(FunctionId, TaskExecutionStatistics)> {
+        Vec::new()
+    }
 }
 
 impl CachedTaskType {
@@ -1,8 +1,13 @@
 use auto_hash_map::AutoSet;
 
-use crate::{Vc, VcValueTrait};
+use crate::{backend::EmittedCollectible, Vc, VcValueTrait};
 
 pub trait CollectiblesSource {
     fn take_collectibles<T: VcValueTrait + Send>(self) -> AutoSet<Vc<T>>;
     fn peek_collectibles<T: VcValueTrait + Send>(self) -> AutoSet<Vc<T>>;
+    /// Like [`CollectiblesSource::peek_collectibles`], but also returns the task that emitted
+    /// each collectible, e.g. to attribute issues to the route that caused them.
+    fn peek_collectibles_with_emitters<T: VcValueTrait + Send>(
+        self,
+    ) -> Vec<EmittedCollectible<Vc<T>>>;
 }
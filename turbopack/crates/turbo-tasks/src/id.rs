@@ -72,7 +72,16 @@ macro_rules! define_id {
     };
 }
 
-define_id!(TaskId: u32, derive(Serialize, Deserialize), serde(transparent));
+/// The integer type backing a [`TaskId`]. The `wide_task_ids` feature widens it to 64 bits for
+/// long running sessions that would exhaust the persistent task id space otherwise.
+#[cfg(not(feature = "wide_task_ids"))]
+pub type TaskIdValue = u32;
+/// The integer type backing a [`TaskId`]. The `wide_task_ids` feature widens it to 64 bits for
+/// long running sessions that would exhaust the persistent task id space otherwise.
+#[cfg(feature = "wide_task_ids")]
+pub type TaskIdValue = u64;
+
+define_id!(TaskId: TaskIdValue, derive(Serialize, Deserialize), serde(transparent));
 define_id!(FunctionId: u32);
 define_id!(ValueTypeId: u32);
 define_id!(TraitTypeId: u32);
@@ -98,12 +107,46 @@ impl Debug for TaskId {
     }
 }
 
-pub const TRANSIENT_TASK_BIT: u32 = 0x8000_0000;
+/// Task ids with this bit set belong to transient tasks. The ids below it are persistent.
+pub const TRANSIENT_TASK_BIT: TaskIdValue = 1 << (TaskIdValue::BITS - 1);
+
+/// Number of bits below [`TRANSIENT_TASK_BIT`] that hold the generation of a transient task id.
+/// The generation is incremented every time an id is reused, so a stale reference to a reused id
+/// no longer points to the new task. Only enabled with debug assertions, as it reduces the number
+/// of available transient task ids.
+pub const TRANSIENT_TASK_GENERATION_BITS: u32 = if cfg!(debug_assertions) { 4 } else { 0 };
+const TRANSIENT_TASK_GENERATION_SHIFT: u32 =
+    TRANSIENT_TASK_BIT.trailing_zeros() - TRANSIENT_TASK_GENERATION_BITS;
+const TRANSIENT_TASK_GENERATION_MASK: TaskIdValue =
+    (TRANSIENT_TASK_BIT - 1) & !((1 << TRANSIENT_TASK_GENERATION_SHIFT) - 1);
+/// The highest transient task id that can be handed out, not including a generation.
+pub const MAX_TRANSIENT_TASK_ID: TaskIdValue =
+    TRANSIENT_TASK_BIT | ((1 << TRANSIENT_TASK_GENERATION_SHIFT) - 1);
 
 impl TaskId {
     pub fn is_transient(&self) -> bool {
         **self & TRANSIENT_TASK_BIT != 0
     }
+    /// The generation of a transient task id. Always zero for persistent task ids.
+    pub fn generation(&self) -> u32 {
+        ((**self & TRANSIENT_TASK_GENERATION_MASK) >> TRANSIENT_TASK_GENERATION_SHIFT) as u32
+    }
+    /// The id without its generation. Backends use it to index their task storage.
+    pub fn without_generation(&self) -> TaskIdValue {
+        **self & !TRANSIENT_TASK_GENERATION_MASK
+    }
+    /// The id to use when a transient task id is reused. Persistent task ids have no generation
+    /// and are returned unchanged.
+    pub fn next_generation(&self) -> TaskId {
+        if !self.is_transient() {
+            return *self;
+        }
+        let generation = (self.generation() + 1) & ((1 << TRANSIENT_TASK_GENERATION_BITS) - 1);
+        TaskId::from(
+            self.without_generation()
+                | ((generation as TaskIdValue) << TRANSIENT_TASK_GENERATION_SHIFT),
+        )
+    }
     pub fn persistence(&self) -> TaskPersistence {
         // tasks with `TaskPersistence::LocalCells` have no `TaskId`, so we can ignore that case
         if self.is_transient() {
@@ -180,3 +223,23 @@ make_serializable!(
     registry::get_trait_type_id_by_global_name,
     TraitTypeVisitor
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transient_task_id_generation() {
+        let id = TaskId::from(TRANSIENT_TASK_BIT | 42);
+        let reused = id.next_generation();
+        assert!(reused.is_transient());
+        assert_eq!(reused.without_generation(), id.without_generation());
+        if TRANSIENT_TASK_GENERATION_BITS > 0 {
+            assert_ne!(reused, id);
+            assert_eq!(reused.generation(), 1);
+        }
+
+        let persistent = TaskId::from(42);
+        assert_eq!(persistent.next_generation(), persistent);
+    }
+}
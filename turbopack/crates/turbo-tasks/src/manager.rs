@@ -16,8 +16,9 @@ use std::{
 
 use anyhow::{anyhow, Result};
 use auto_hash_map::AutoMap;
+use dashmap::DashMap;
 use futures::FutureExt;
-use rustc_hash::FxHasher;
+use rustc_hash::{FxHashSet, FxHasher};
 use serde::{Deserialize, Serialize};
 use tokio::{runtime::Handle, select, task_local};
 use tokio_util::task::TaskTracker;
@@ -26,14 +27,14 @@ use turbo_tasks_malloc::TurboMalloc;
 
 use crate::{
     backend::{
-        Backend, CachedTaskType, CellContent, TaskCollectiblesMap, TaskExecutionSpec,
-        TransientTaskType, TypedCellContent,
+        Backend, CachedTaskType, CellContent, EmittedCollectible, TaskCollectiblesMap,
+        TaskExecutionSpec, TransientTaskType, TypedCellContent,
     },
     capture_future::{self, CaptureFuture},
     event::{Event, EventListener},
     id::{
         BackendJobId, ExecutionId, FunctionId, LocalCellId, LocalTaskId, TraitTypeId,
-        TRANSIENT_TASK_BIT,
+        MAX_TRANSIENT_TASK_ID, TRANSIENT_TASK_BIT,
     },
     id_factory::{IdFactory, IdFactoryWithReuse},
     magic_any::MagicAny,
@@ -167,6 +168,11 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     ) -> Result<Result<RawVc, EventListener>>;
 
     fn read_task_collectibles(&self, task: TaskId, trait_id: TraitTypeId) -> TaskCollectiblesMap;
+    fn read_task_collectibles_with_emitters(
+        &self,
+        task: TaskId,
+        trait_id: TraitTypeId,
+    ) -> Vec<EmittedCollectible>;
 
     fn emit_collectible(&self, trait_type: TraitTypeId, collectible: RawVc);
     fn unemit_collectible(&self, trait_type: TraitTypeId, collectible: RawVc, count: u32);
@@ -185,6 +191,14 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     fn mark_own_task_as_finished(&self, task: TaskId);
     fn mark_own_task_as_session_dependent(&self, task: TaskId);
 
+    fn mark_own_task_as_weak_reader(&self, _task: TaskId) {}
+
+    fn mark_own_task_as_non_persistable(&self, _task: TaskId) {}
+
+    fn is_own_task_cancelled(&self, _task: TaskId) -> bool {
+        false
+    }
+
     fn connect_task(&self, task: TaskId);
 
     /// Wraps the given future in the current task.
@@ -244,6 +258,12 @@ pub trait TurboTasksBackendApi<B: Backend + 'static>: TurboTasksCallApi + Sync +
     unsafe fn reuse_transient_task_id(&self, id: Unused<TaskId>);
 
     fn schedule(&self, task: TaskId);
+    /// Schedules a task with an explicit priority. [`Self::schedule`] uses the priority of the
+    /// currently executing task, or [`TaskPriority::Normal`] outside of tasks.
+    fn schedule_with_priority(&self, task: TaskId, priority: TaskPriority) {
+        let _ = priority;
+        self.schedule(task)
+    }
     fn schedule_backend_background_job(&self, id: BackendJobId);
     fn schedule_backend_foreground_job(&self, id: BackendJobId);
 
@@ -358,6 +378,72 @@ pub enum ReadConsistency {
     ///
     /// Top-level code that returns data to the user should use strongly consistent reads.
     Strong,
+    /// Waits like [`ReadConsistency::Strong`] until all dependencies are resolved, but for at most
+    /// the given duration. After that the read continues like [`ReadConsistency::Eventual`].
+    ///
+    /// This is useful for code that wants mostly consistent data, but must not stall for too long,
+    /// e.g. hot module replacement updates.
+    Settled(Duration),
+}
+
+impl ReadConsistency {
+    /// Whether the read waits for the task and its dependencies to settle, i.e. whether backends
+    /// need to handle it like a strongly consistent read.
+    pub fn waits_for_settled(&self) -> bool {
+        !matches!(self, ReadConsistency::Eventual)
+    }
+
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        match self {
+            ReadConsistency::Settled(max_wait) => Some(Instant::now() + *max_wait),
+            _ => None,
+        }
+    }
+}
+
+/// The priority of a scheduled task execution.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TaskPriority {
+    /// Background work, e.g. recomputations after invalidations.
+    #[default]
+    Normal,
+    /// Work that somebody is actively waiting for, e.g. a strongly consistent read or an update
+    /// subscription. Normal tasks hold back their start while urgent tasks are running, unless a
+    /// task waits for a strongly consistent read to settle. Tasks an urgent task waits for become
+    /// urgent too.
+    Urgent,
+}
+
+/// The priority of a scheduled task. It's raised to [`TaskPriority::Urgent`] when an urgent task
+/// waits for the task.
+struct ScheduledPriority {
+    urgent: AtomicBool,
+    /// Notified when the priority is raised, so that a held back task can start.
+    raised: Event,
+    /// The tasks this task has waited for. Raising the priority is propagated to them, as this
+    /// task can't finish before they do.
+    waited_for: Mutex<FxHashSet<TaskId>>,
+    /// Set while the task waits for a strongly consistent read to settle.
+    settling: AtomicBool,
+}
+
+impl ScheduledPriority {
+    fn new(priority: TaskPriority) -> Self {
+        Self {
+            urgent: AtomicBool::new(priority == TaskPriority::Urgent),
+            raised: Event::new(|| "ScheduledPriority::raised".to_string()),
+            waited_for: Default::default(),
+            settling: AtomicBool::new(false),
+        }
+    }
+
+    fn get(&self) -> TaskPriority {
+        if self.urgent.load(Ordering::Acquire) {
+            TaskPriority::Urgent
+        } else {
+            TaskPriority::Normal
+        }
+    }
 }
 
 pub struct TurboTasks<B: Backend + 'static> {
@@ -371,12 +457,23 @@ pub struct TurboTasks<B: Backend + 'static> {
     currently_scheduled_foreground_jobs: AtomicUsize,
     currently_scheduled_background_jobs: AtomicUsize,
     scheduled_tasks: AtomicUsize,
+    /// Number of scheduled tasks with [`TaskPriority::Urgent`] that haven't finished yet.
+    currently_scheduled_urgent_tasks: AtomicUsize,
+    /// Number of tasks waiting for a strongly consistent read to settle. The read waits for all
+    /// dirty tasks below the read task, including the ones that are scheduled later with
+    /// [`TaskPriority::Normal`], so no tasks are held back while there are any.
+    currently_settling_tasks: AtomicUsize,
+    /// The priorities of all scheduled tasks that haven't finished yet.
+    scheduled_priorities: DashMap<TaskId, Arc<ScheduledPriority>, BuildHasherDefault<FxHasher>>,
     start: Mutex<Option<Instant>>,
     aggregated_update: Mutex<(Option<(Duration, usize)>, InvalidationReasonSet)>,
     event: Event,
     event_start: Event,
     event_foreground: Event,
     event_background: Event,
+    /// Notified when normal tasks that are held back for urgent tasks can start: when no urgent
+    /// tasks are left, or when a task starts waiting for a strongly consistent read to settle.
+    event_release_held_back: Event,
     program_start: Instant,
 }
 
@@ -444,13 +541,22 @@ struct CurrentLocalTaskState {
     /// The function's metadata if this is a persistent task. Contains information about arguments
     /// passed to the `#[turbo_tasks::function(...)]` macro.
     function_meta: Option<&'static FunctionMeta>,
+
+    /// The priority of the task. Tasks scheduled from within this task inherit it, so work that
+    /// an urgent task waits for is urgent too.
+    priority: Arc<ScheduledPriority>,
 }
 
 impl CurrentLocalTaskState {
-    fn new(execution_id: ExecutionId, function_meta: Option<&'static FunctionMeta>) -> Self {
+    fn new(
+        execution_id: ExecutionId,
+        function_meta: Option<&'static FunctionMeta>,
+        priority: Arc<ScheduledPriority>,
+    ) -> Self {
         Self {
             execution_id,
             function_meta,
+            priority,
         }
     }
 }
@@ -473,7 +579,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
     pub fn new(backend: B) -> Arc<Self> {
         let task_id_factory = IdFactoryWithReuse::new(1, (TRANSIENT_TASK_BIT - 1) as u64);
         let transient_task_id_factory =
-            IdFactoryWithReuse::new(TRANSIENT_TASK_BIT as u64, u32::MAX as u64);
+            IdFactoryWithReuse::new(TRANSIENT_TASK_BIT as u64, MAX_TRANSIENT_TASK_ID as u64);
         let this = Arc::new_cyclic(|this| Self {
             this: this.clone(),
             backend,
@@ -485,12 +591,18 @@ impl<B: Backend + 'static> TurboTasks<B> {
             currently_scheduled_background_jobs: AtomicUsize::new(0),
             currently_scheduled_foreground_jobs: AtomicUsize::new(0),
             scheduled_tasks: AtomicUsize::new(0),
+            currently_scheduled_urgent_tasks: AtomicUsize::new(0),
+            currently_settling_tasks: AtomicUsize::new(0),
+            scheduled_priorities: Default::default(),
             start: Default::default(),
             aggregated_update: Default::default(),
             event: Event::new(|| "TurboTasks::event".to_string()),
             event_start: Event::new(|| "TurboTasks::event_start".to_string()),
             event_foreground: Event::new(|| "TurboTasks::event_foreground".to_string()),
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
+            event_release_held_back: Event::new(|| {
+                "TurboTasks::event_release_held_back".to_string()
+            }),
             program_start: Instant::now(),
         });
         this.backend.startup(&*this);
@@ -753,14 +865,38 @@ impl<B: Backend + 'static> TurboTasks<B> {
 
     #[track_caller]
     pub(crate) fn schedule(&self, task_id: TaskId) {
+        let priority = CURRENT_LOCAL_TASK_STATE
+            .try_with(|ts| ts.priority.get())
+            .unwrap_or_default();
+        self.schedule_with_priority(task_id, priority)
+    }
+
+    pub(crate) fn schedule_with_priority(&self, task_id: TaskId, priority: TaskPriority) {
         self.begin_primary_job();
         self.scheduled_tasks.fetch_add(1, Ordering::AcqRel);
+        if priority == TaskPriority::Urgent {
+            self.currently_scheduled_urgent_tasks
+                .fetch_add(1, Ordering::AcqRel);
+        }
+        let scheduled_priority = Arc::new(ScheduledPriority::new(priority));
+        self.scheduled_priorities
+            .insert(task_id, scheduled_priority.clone());
 
         #[cfg(feature = "tokio_tracing")]
         let description = self.backend.get_task_description(task_id);
 
         let this = self.pin();
         let future = async move {
+            if this.should_hold_back(&scheduled_priority) {
+                let released = this.event_release_held_back.listen();
+                let raised = scheduled_priority.raised.listen();
+                if this.should_hold_back(&scheduled_priority) {
+                    select! {
+                        _ = released => {}
+                        _ = raised => {}
+                    }
+                }
+            }
             let mut schedule_again = true;
             while schedule_again {
                 let backend_state = this.backend.new_task_state(task_id);
@@ -773,6 +909,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     this.backend
                         .try_get_function_id(task_id)
                         .map(|func_id| &get_function(func_id).function_meta),
+                    scheduled_priority.clone(),
                 );
                 let single_execution_future = async {
                     if this.stopped.load(Ordering::Acquire) {
@@ -802,6 +939,13 @@ impl<B: Backend + 'static> TurboTasks<B> {
                                 Err(_) => None,
                             },
                         });
+                        // Local Vcs are only valid during this execution, so they are converted
+                        // into persistent cells of this task before the backend stores them as
+                        // output.
+                        let result = match result {
+                            Ok(Ok(raw_vc)) if raw_vc.is_local() => Ok(raw_vc.resolve().await),
+                            result => result,
+                        };
                         this.backend.task_execution_result(task_id, result, &*this);
                         let stateful = this.finish_current_task_state();
                         let cell_counters = CURRENT_GLOBAL_TASK_STATE
@@ -828,6 +972,21 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     )
                     .await;
             }
+            // Once removed, the priority can't be raised anymore
+            this.scheduled_priorities
+                .remove_if(&task_id, |_, p| Arc::ptr_eq(p, &scheduled_priority));
+            // The task might have failed while waiting for a strongly consistent read
+            if scheduled_priority.settling.swap(false, Ordering::AcqRel) {
+                this.currently_settling_tasks.fetch_sub(1, Ordering::AcqRel);
+            }
+            if scheduled_priority.get() == TaskPriority::Urgent
+                && this
+                    .currently_scheduled_urgent_tasks
+                    .fetch_sub(1, Ordering::AcqRel)
+                    == 1
+            {
+                this.event_release_held_back.notify(usize::MAX);
+            }
             this.finish_primary_job();
             anyhow::Ok(())
         };
@@ -843,6 +1002,64 @@ impl<B: Backend + 'static> TurboTasks<B> {
         tokio::task::spawn(future);
     }
 
+    /// Normal tasks don't start while urgent tasks are running, unless a task waits for a
+    /// strongly consistent read to settle.
+    fn should_hold_back(&self, priority: &ScheduledPriority) -> bool {
+        priority.get() == TaskPriority::Normal
+            && self
+                .currently_scheduled_urgent_tasks
+                .load(Ordering::Acquire)
+                > 0
+            && self.currently_settling_tasks.load(Ordering::Acquire) == 0
+    }
+
+    /// Raises the priority of `task_id` and of the tasks it waits for to
+    /// [`TaskPriority::Urgent`].
+    fn raise_priority(&self, task_id: TaskId) {
+        let mut queue = vec![task_id];
+        while let Some(task_id) = queue.pop() {
+            // The entry is kept locked, so the task can't finish before the counter is updated
+            let Some(priority) = self.scheduled_priorities.get(&task_id) else {
+                continue;
+            };
+            if priority.urgent.swap(true, Ordering::AcqRel) {
+                continue;
+            }
+            self.currently_scheduled_urgent_tasks
+                .fetch_add(1, Ordering::AcqRel);
+            priority.raised.notify(usize::MAX);
+            queue.extend(priority.waited_for.lock().unwrap().iter().copied());
+        }
+    }
+
+    /// Called when the current task has to wait for `task_id`. An urgent task passes its priority
+    /// on, so that it isn't held up by normal tasks. A strongly consistent read also waits for
+    /// dirty tasks below `task_id`, which are not known here and might only be scheduled later.
+    /// So no tasks are held back until the read has settled.
+    fn wait_for(&self, task_id: TaskId, consistency: ReadConsistency) {
+        let Ok(priority) = CURRENT_LOCAL_TASK_STATE.try_with(|ts| ts.priority.clone()) else {
+            return;
+        };
+        priority.waited_for.lock().unwrap().insert(task_id);
+        if priority.get() == TaskPriority::Urgent {
+            self.raise_priority(task_id);
+        }
+        if consistency.waits_for_settled() && !priority.settling.swap(true, Ordering::AcqRel) {
+            self.currently_settling_tasks.fetch_add(1, Ordering::AcqRel);
+            self.event_release_held_back.notify(usize::MAX);
+        }
+    }
+
+    /// Called when a strongly consistent read of the current task has settled.
+    fn read_settled(&self) {
+        let settled = CURRENT_LOCAL_TASK_STATE
+            .try_with(|ts| ts.priority.settling.swap(false, Ordering::AcqRel))
+            .unwrap_or(false);
+        if settled {
+            self.currently_settling_tasks.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+
     fn begin_primary_job(&self) {
         if self
             .currently_scheduled_tasks
@@ -921,6 +1138,14 @@ impl<B: Backend + 'static> TurboTasks<B> {
         self.currently_scheduled_tasks.load(Ordering::Acquire)
     }
 
+    /// Requests cancellation of a task that is currently executing. The running future is not
+    /// aborted, but it can observe the request via [`is_cancelled`] and return early. The result
+    /// of the execution is discarded and dependent tasks are not notified. The task is executed
+    /// again, so readers that are waiting for it still get a result.
+    pub fn cancel_task(&self, task: TaskId) {
+        self.backend.cancel_task(task, self);
+    }
+
     /// Waits for the given task to finish executing. This works by performing an untracked read,
     /// and discarding the value of the task output.
     ///
@@ -930,6 +1155,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
     /// [`ReadConsistency::Strong`] means that this will also wait for the task and all dependencies
     /// to fully settle before returning.
     ///
+    /// [`ReadConsistency::Settled`] waits for the dependencies to settle, but only up to the given
+    /// duration.
+    ///
     /// As this function is typically called in top-level code that waits for results to be ready
     /// for the user to access, most callers should use [`ReadConsistency::Strong`].
     pub async fn wait_task_completion(
@@ -1260,9 +1488,9 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
-            reason_set.insert(reason);
+            reason_set.insert(reason.clone());
         }
-        self.backend.invalidate_task(task, self);
+        self.backend.invalidate_task_with_reason(task, reason, self);
     }
 
     fn invalidate_serialization(&self, task: TaskId) {
@@ -1289,8 +1517,18 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         consistency: ReadConsistency,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend
-            .try_read_task_output(task, current_task("reading Vcs"), consistency, self)
+        let result = self.backend.try_read_task_output(
+            task,
+            current_task("reading Vcs"),
+            consistency,
+            self,
+        )?;
+        if result.is_err() {
+            self.wait_for(task, consistency);
+        } else if consistency.waits_for_settled() {
+            self.read_settled();
+        }
+        Ok(result)
     }
 
     fn try_read_task_output_untracked(
@@ -1298,8 +1536,15 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         consistency: ReadConsistency,
     ) -> Result<Result<RawVc, EventListener>> {
-        self.backend
-            .try_read_task_output_untracked(task, consistency, self)
+        let result = self
+            .backend
+            .try_read_task_output_untracked(task, consistency, self)?;
+        if result.is_err() {
+            self.wait_for(task, consistency);
+        } else if consistency.waits_for_settled() {
+            self.read_settled();
+        }
+        Ok(result)
     }
 
     fn try_read_task_cell(
@@ -1307,8 +1552,13 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<TypedCellContent, EventListener>> {
-        self.backend
-            .try_read_task_cell(task, index, current_task("reading Vcs"), self)
+        let result =
+            self.backend
+                .try_read_task_cell(task, index, current_task("reading Vcs"), self)?;
+        if result.is_err() {
+            self.wait_for(task, ReadConsistency::Eventual);
+        }
+        Ok(result)
     }
 
     fn try_read_task_cell_untracked(
@@ -1316,7 +1566,13 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<TypedCellContent, EventListener>> {
-        self.backend.try_read_task_cell_untracked(task, index, self)
+        let result = self
+            .backend
+            .try_read_task_cell_untracked(task, index, self)?;
+        if result.is_err() {
+            self.wait_for(task, ReadConsistency::Eventual);
+        }
+        Ok(result)
     }
 
     fn try_read_own_task_cell_untracked(
@@ -1357,6 +1613,19 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         )
     }
 
+    fn read_task_collectibles_with_emitters(
+        &self,
+        task: TaskId,
+        trait_id: TraitTypeId,
+    ) -> Vec<EmittedCollectible> {
+        self.backend.read_task_collectibles_with_emitters(
+            task,
+            trait_id,
+            current_task("reading collectibles"),
+            self,
+        )
+    }
+
     fn emit_collectible(&self, trait_type: TraitTypeId, collectible: RawVc) {
         self.backend.emit_collectible(
             trait_type,
@@ -1412,6 +1681,18 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         self.backend.mark_own_task_as_session_dependent(task, self);
     }
 
+    fn mark_own_task_as_weak_reader(&self, task: TaskId) {
+        self.backend.mark_own_task_as_weak_reader(task, self);
+    }
+
+    fn mark_own_task_as_non_persistable(&self, task: TaskId) {
+        self.backend.mark_own_task_as_non_persistable(task, self);
+    }
+
+    fn is_own_task_cancelled(&self, task: TaskId) -> bool {
+        self.backend.is_own_task_cancelled(task, self)
+    }
+
     /// Creates a future that inherits the current task id and task state. The current global task
     /// will wait for this future to be dropped before exiting.
     fn detached_for_testing(
@@ -1536,6 +1817,10 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
         self.schedule(task)
     }
 
+    fn schedule_with_priority(&self, task: TaskId, priority: TaskPriority) {
+        self.schedule_with_priority(task, priority)
+    }
+
     fn program_duration_until(&self, instant: Instant) -> Duration {
         instant - self.program_start
     }
@@ -1555,7 +1840,8 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
     }
 
     unsafe fn reuse_transient_task_id(&self, id: Unused<TaskId>) {
-        unsafe { self.transient_task_id_factory.reuse(id.into()) }
+        let id: TaskId = id.into();
+        unsafe { self.transient_task_id_factory.reuse(id.next_generation()) }
     }
 
     fn read_task_state_dyn(&self, func: &mut dyn FnMut(&B::TaskState)) {
@@ -1685,7 +1971,14 @@ pub fn with_turbo_tasks_for_testing<T>(
                 current_task,
                 Box::new(()),
             ))),
-            CURRENT_LOCAL_TASK_STATE.scope(CurrentLocalTaskState::new(execution_id, None), f),
+            CURRENT_LOCAL_TASK_STATE.scope(
+                CurrentLocalTaskState::new(
+                    execution_id,
+                    None,
+                    Arc::new(ScheduledPriority::new(TaskPriority::Normal)),
+                ),
+                f,
+            ),
         ),
     )
 }
@@ -1709,6 +2002,23 @@ pub fn mark_session_dependent() {
     });
 }
 
+/// Marks all following reads of the current task execution as weak. The task is still
+/// invalidated when the read data changes, but the read tasks can be unloaded from memory. Useful
+/// for telemetry and diagnostics that read large parts of the graph.
+pub fn mark_weak_reader() {
+    with_turbo_tasks(|tt| {
+        tt.mark_own_task_as_weak_reader(current_task("turbo_tasks::mark_weak_reader()"))
+    });
+}
+
+/// Allows the current task to call transient functions although it's persistent. The task and
+/// all persistent tasks reading from it are no longer persisted for the rest of the session.
+pub fn mark_non_persistable() {
+    with_turbo_tasks(|tt| {
+        tt.mark_own_task_as_non_persistable(current_task("turbo_tasks::mark_non_persistable()"))
+    });
+}
+
 /// Marks the current task as finished. This excludes it from waiting for
 /// strongly consistency.
 pub fn mark_finished() {
@@ -1717,6 +2027,12 @@ pub fn mark_finished() {
     });
 }
 
+/// Returns true when the current task has been cancelled via [`TurboTasks::cancel_task`].
+/// Long running tasks can check this to stop early, as their result will be discarded anyway.
+pub fn is_cancelled() -> bool {
+    with_turbo_tasks(|tt| tt.is_own_task_cancelled(current_task("turbo_tasks::is_cancelled()")))
+}
+
 /// Marks the current task as stateful. This prevents the tasks from being
 /// dropped without persisting the state.
 ///
@@ -1777,12 +2093,13 @@ pub fn spawn_thread(func: impl FnOnce() + Send + 'static) {
 pub(crate) async fn read_task_output(
     this: &dyn TurboTasksApi,
     id: TaskId,
-    consistency: ReadConsistency,
+    mut consistency: ReadConsistency,
 ) -> Result<RawVc> {
+    let deadline = consistency.deadline();
     loop {
         match this.try_read_task_output(id, consistency)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener, &mut consistency, deadline).await,
         }
     }
 }
@@ -1792,16 +2109,34 @@ pub(crate) async fn read_task_output(
 pub(crate) async fn read_task_output_untracked(
     this: &dyn TurboTasksApi,
     id: TaskId,
-    consistency: ReadConsistency,
+    mut consistency: ReadConsistency,
 ) -> Result<RawVc> {
+    let deadline = consistency.deadline();
     loop {
         match this.try_read_task_output_untracked(id, consistency)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener, &mut consistency, deadline).await,
         }
     }
 }
 
+/// Waits for the `listener` of a read. A [`ReadConsistency::Settled`] read stops waiting for its
+/// dependencies at the `deadline` and continues eventually consistent.
+async fn wait_for_read(
+    listener: EventListener,
+    consistency: &mut ReadConsistency,
+    deadline: Option<Instant>,
+) {
+    let (Some(deadline), ReadConsistency::Settled(_)) = (deadline, *consistency) else {
+        listener.await;
+        return;
+    };
+    let deadline = tokio::time::Instant::from_std(deadline);
+    if tokio::time::timeout_at(deadline, listener).await.is_err() {
+        *consistency = ReadConsistency::Eventual;
+    }
+}
+
 pub(crate) async fn read_task_cell(
     this: &dyn TurboTasksApi,
     id: TaskId,
@@ -90,6 +90,9 @@ pub struct FunctionMeta {
     /// cached across task executions. Cells can be converted to their non-local
     /// versions by calling `Vc::resolve`.
     pub local_cells: bool,
+    /// The output of the function never changes for the same inputs. The backend doesn't need to
+    /// track which tasks read the output or cells of such tasks.
+    pub immutable: bool,
 }
 
 /// A native (rust) turbo-tasks function. It's used internally by
@@ -4,7 +4,8 @@ use std::{
     hash::Hash,
     pin::Pin,
     sync::Arc,
-    task::Poll,
+    task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -13,7 +14,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
-    backend::{CellContent, TypedCellContent},
+    backend::{CellContent, EmittedCollectible, TypedCellContent},
     event::EventListener,
     id::{ExecutionId, LocalCellId, LocalTaskId},
     manager::{
@@ -99,6 +100,11 @@ impl RawVc {
         ReadRawVcFuture::new_strongly_consistent(self)
     }
 
+    /// See [`ReadConsistency::Settled`].
+    pub(crate) fn into_settled_read(self, max_wait: Duration) -> ReadRawVcFuture {
+        ReadRawVcFuture::new_settled(self, max_wait)
+    }
+
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
     pub(crate) fn into_read_untracked(self) -> ReadRawVcFuture {
@@ -270,6 +276,21 @@ impl CollectiblesSource for RawVc {
             .filter_map(|(raw, count)| (count > 0).then_some(raw.into()))
             .collect()
     }
+
+    fn peek_collectibles_with_emitters<T: VcValueTrait + Send>(
+        self,
+    ) -> Vec<EmittedCollectible<Vc<T>>> {
+        let tt = turbo_tasks();
+        tt.notify_scheduled_tasks();
+        tt.read_task_collectibles_with_emitters(self.get_task_id(), T::get_trait_type_id())
+            .into_iter()
+            .map(|emitted| EmittedCollectible {
+                collectible: emitted.collectible.into(),
+                emitter: emitted.emitter,
+                emitter_description: emitted.emitter_description,
+            })
+            .collect()
+    }
 }
 
 pub struct ReadRawVcFuture {
@@ -278,6 +299,9 @@ pub struct ReadRawVcFuture {
     current: RawVc,
     untracked: bool,
     listener: Option<EventListener>,
+    /// When a [`ReadConsistency::Settled`] read stops waiting for the dependencies to settle.
+    deadline: Option<Instant>,
+    timeout: Option<Pin<Box<tokio::time::Sleep>>>,
 }
 
 impl ReadRawVcFuture {
@@ -289,6 +313,8 @@ impl ReadRawVcFuture {
             current: vc,
             untracked: false,
             listener: None,
+            deadline: None,
+            timeout: None,
         }
     }
 
@@ -300,6 +326,8 @@ impl ReadRawVcFuture {
             current: vc,
             untracked: true,
             listener: None,
+            deadline: None,
+            timeout: None,
         }
     }
 
@@ -311,6 +339,8 @@ impl ReadRawVcFuture {
             current: vc,
             untracked: true,
             listener: None,
+            deadline: None,
+            timeout: None,
         }
     }
 
@@ -322,6 +352,22 @@ impl ReadRawVcFuture {
             current: vc,
             untracked: false,
             listener: None,
+            deadline: None,
+            timeout: None,
+        }
+    }
+
+    fn new_settled(vc: RawVc, max_wait: Duration) -> Self {
+        let tt = turbo_tasks();
+        let consistency = ReadConsistency::Settled(max_wait);
+        ReadRawVcFuture {
+            turbo_tasks: tt,
+            consistency,
+            current: vc,
+            untracked: false,
+            listener: None,
+            deadline: consistency.deadline(),
+            timeout: None,
         }
     }
 
@@ -333,14 +379,38 @@ impl ReadRawVcFuture {
             current: vc,
             untracked: true,
             listener: None,
+            deadline: None,
+            timeout: None,
+        }
+    }
+}
+
+impl ReadRawVcFuture {
+    /// Whether a [`ReadConsistency::Settled`] read has reached its deadline. It continues
+    /// eventually consistent afterwards.
+    fn settled_deadline_reached(&mut self, cx: &mut Context<'_>) -> bool {
+        let (Some(deadline), ReadConsistency::Settled(_)) = (self.deadline, self.consistency)
+        else {
+            return false;
+        };
+        let timeout = self.timeout.get_or_insert_with(|| {
+            Box::pin(tokio::time::sleep_until(tokio::time::Instant::from_std(
+                deadline,
+            )))
+        });
+        if timeout.as_mut().poll(cx).is_pending() {
+            return false;
         }
+        self.consistency = ReadConsistency::Eventual;
+        self.timeout = None;
+        true
     }
 }
 
 impl Future for ReadRawVcFuture {
     type Output = Result<TypedCellContent>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         self.turbo_tasks.notify_scheduled_tasks();
         // SAFETY: we are not moving this
         let this = unsafe { self.get_unchecked_mut() };
@@ -348,7 +418,7 @@ impl Future for ReadRawVcFuture {
             if let Some(listener) = &mut this.listener {
                 // SAFETY: listener is from previous pinned this
                 let listener = unsafe { Pin::new_unchecked(listener) };
-                if listener.poll(cx).is_pending() {
+                if listener.poll(cx).is_pending() && !this.settled_deadline_reached(cx) {
                     return Poll::Pending;
                 }
                 this.listener = None;
@@ -422,6 +492,9 @@ impl Future for ReadRawVcFuture {
             match unsafe { Pin::new_unchecked(&mut listener) }.poll(cx) {
                 Poll::Ready(_) => continue,
                 Poll::Pending => {
+                    if this.settled_deadline_reached(cx) {
+                        continue;
+                    }
                     this.listener = Some(listener);
                     return Poll::Pending;
                 }
@@ -10,6 +10,7 @@ use std::{
     hash::{Hash, Hasher},
     marker::PhantomData,
     ops::Deref,
+    time::Duration,
 };
 
 use anyhow::Result;
@@ -25,6 +26,7 @@ pub use self::{
     traits::{Dynamic, TypedForInput, Upcast, VcValueTrait, VcValueType},
 };
 use crate::{
+    backend::EmittedCollectible,
     debug::{ValueDebug, ValueDebugFormat, ValueDebugFormatString},
     manager::{create_local_cell, try_get_function_meta},
     registry,
@@ -481,6 +483,12 @@ where
     fn peek_collectibles<Vt: VcValueTrait + Send>(self) -> AutoSet<Vc<Vt>> {
         self.node.peek_collectibles()
     }
+
+    fn peek_collectibles_with_emitters<Vt: VcValueTrait + Send>(
+        self,
+    ) -> Vec<EmittedCollectible<Vc<Vt>>> {
+        self.node.peek_collectibles_with_emitters()
+    }
 }
 
 impl<T> From<RawVc> for Vc<T>
@@ -551,6 +559,14 @@ where
     pub fn strongly_consistent(self) -> ReadVcFuture<T> {
         self.node.into_strongly_consistent_read().into()
     }
+
+    /// Returns a read of the value that waits like [`Vc::strongly_consistent`], but for at most
+    /// `max_wait`. Afterwards the value is read without waiting for internal tasks to finish. See
+    /// [`ReadConsistency::Settled`][crate::ReadConsistency::Settled].
+    #[must_use]
+    pub fn settled(self, max_wait: Duration) -> ReadVcFuture<T> {
+        self.node.into_settled_read(max_wait).into()
+    }
 }
 
 impl<T> Unpin for Vc<T> where T: ?Sized + Send {}
@@ -10,7 +10,7 @@ use turbopack_core::{
         Chunk, ChunkGroupResult, ChunkItem, ChunkableModule, ChunkingContext,
         EntryChunkGroupResult, EvaluatableAssets, MinifyType, ModuleId,
     },
-    environment::Environment,
+    environment::{EdgeWorkerEnvironment, Environment, ExecutionEnvironment},
     ident::AssetIdent,
     module::Module,
     output::{OutputAsset, OutputAssets},
@@ -123,6 +123,9 @@ pub struct BrowserChunkingContext {
     enable_hot_module_replacement: bool,
     /// The environment chunks will be evaluated in.
     environment: Vc<Environment>,
+    /// The environment the runtime of evaluated chunk groups is compiled for, if it differs from
+    /// `environment`.
+    runtime_environment: Option<Vc<Environment>>,
     /// The kind of runtime to include in the output.
     runtime_type: RuntimeType,
     /// Whether to minify resulting chunks
@@ -157,6 +160,7 @@ impl BrowserChunkingContext {
                 asset_base_path: Default::default(),
                 enable_hot_module_replacement: false,
                 environment,
+                runtime_environment: None,
                 runtime_type,
                 minify_type: MinifyType::NoMinify,
                 manifest_chunks: false,
@@ -184,6 +188,11 @@ impl BrowserChunkingContext {
     pub fn minify_type(&self) -> MinifyType {
         self.minify_type
     }
+
+    /// Returns the environment the runtime of evaluated chunk groups is compiled for.
+    pub fn runtime_environment(&self) -> Vc<Environment> {
+        self.runtime_environment.unwrap_or(self.environment)
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -272,6 +281,17 @@ impl ChunkingContext for BrowserChunkingContext {
         self.environment
     }
 
+    #[turbo_tasks::function]
+    fn without_dom(&self) -> Vc<Box<dyn ChunkingContext>> {
+        let mut chunking_context = self.clone();
+        // The edge runtime only uses `globalThis`, and expects all chunks of a chunk group to be
+        // loaded by the entry
+        chunking_context.runtime_environment = Some(Environment::new(Value::new(
+            ExecutionEnvironment::EdgeWorker(EdgeWorkerEnvironment {}.into()),
+        )));
+        Vc::upcast(chunking_context.cell())
+    }
+
     #[turbo_tasks::function]
     async fn chunk_path(
         &self,
@@ -65,7 +65,7 @@ impl EcmascriptDevEvaluateChunk {
     async fn code(self: Vc<Self>) -> Result<Vc<Code>> {
         let this = self.await?;
         let chunking_context = this.chunking_context.await?;
-        let environment = this.chunking_context.environment();
+        let environment = chunking_context.runtime_environment();
 
         let output_root = this.chunking_context.output_root().await?;
         let chunk_path_vc = self.ident().path();
@@ -82,6 +82,10 @@ pub trait ChunkingContext {
         Vc::cell(false)
     }
 
+    /// A chunking context whose evaluated chunk groups run in a global scope without a DOM, like
+    /// the ones of worklets. Their runtime doesn't access `self`, `location` or `document`.
+    fn without_dom(self: Vc<Self>) -> Vc<Box<dyn ChunkingContext>>;
+
     fn async_loader_chunk_item(
         &self,
         module: Vc<Box<dyn ChunkableModule>>,
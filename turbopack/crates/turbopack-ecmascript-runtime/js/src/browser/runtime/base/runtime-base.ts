@@ -12,6 +12,7 @@
 /// <reference path="../../../shared/runtime-utils.ts" />
 
 declare var TURBOPACK_WORKER_LOCATION: string;
+declare var TURBOPACK_WORKER_TYPE: "classic" | "module" | undefined;
 declare var CHUNK_BASE_PATH: string;
 declare function instantiateModule(id: ModuleId, source: SourceInfo): Module;
 
@@ -238,8 +239,19 @@ function resolveAbsolutePath(modulePath?: string): string {
   return `/ROOT/${modulePath ?? ""}`;
 }
 
-function getWorkerBlobURL(chunks: ChunkPath[]): string {
-  let bootstrap = `TURBOPACK_WORKER_LOCATION = ${JSON.stringify(location.origin)};importScripts(${chunks.map(c => (`TURBOPACK_WORKER_LOCATION + ${JSON.stringify(getChunkRelativeUrl(c))}`)).join(", ")});`;
+function getWorkerBlobURL(
+  chunks: ChunkPath[],
+  type: "classic" | "module" = "classic"
+): string {
+  let bootstrap;
+  if (type === "module") {
+    // `importScripts` throws in module workers, so chunks are imported one after
+    // another instead. They register themselves on `globalThis`, so it doesn't
+    // matter that they are evaluated as modules.
+    bootstrap = `self.TURBOPACK_WORKER_LOCATION = ${JSON.stringify(location.origin)};self.TURBOPACK_WORKER_TYPE = "module";${chunks.map(c => (`await import(TURBOPACK_WORKER_LOCATION + ${JSON.stringify(getChunkRelativeUrl(c))});`)).join("")}`;
+  } else {
+    bootstrap = `TURBOPACK_WORKER_LOCATION = ${JSON.stringify(location.origin)};importScripts(${chunks.map(c => (`TURBOPACK_WORKER_LOCATION + ${JSON.stringify(getChunkRelativeUrl(c))}`)).join(", ")});`;
+  }
   let blob = new Blob([bootstrap], { type: "text/javascript" });
   return URL.createObjectURL(blob);
 }
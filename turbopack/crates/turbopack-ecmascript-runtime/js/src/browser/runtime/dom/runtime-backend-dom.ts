@@ -146,7 +146,14 @@ const chunkResolvers: Map<ChunkPath, ChunkResolver> = new Map();
       if (chunkPath.endsWith(".css")) {
         // ignore
       } else if (chunkPath.endsWith(".js")) {
-        importScripts(TURBOPACK_WORKER_LOCATION + chunkUrl);
+        if (
+          typeof TURBOPACK_WORKER_TYPE !== "undefined" &&
+          TURBOPACK_WORKER_TYPE === "module"
+        ) {
+          await import(TURBOPACK_WORKER_LOCATION + chunkUrl);
+        } else {
+          importScripts(TURBOPACK_WORKER_LOCATION + chunkUrl);
+        }
       } else {
         throw new Error(`can't infer type of chunk from path ${chunkPath} in worker`);
       }
@@ -181,7 +181,10 @@ function loadWebAssemblyModule(chunkPath: ChunkPath) {
   return compileWebAssemblyFromPath(resolved);
 }
 
-function getWorkerBlobURL(_chunks: ChunkPath[]): string {
+function getWorkerBlobURL(
+  _chunks: ChunkPath[],
+  _type?: "classic" | "module"
+): string {
   throw new Error("Worker blobs are not implemented yet for Node.js");
 }
 
@@ -60,7 +60,10 @@ type AsyncModule = (
 ) => void;
 
 type ResolveAbsolutePath = (modulePath?: string) => string;
-type GetWorkerBlobURL = (chunks: ChunkPath[]) => string;
+type GetWorkerBlobURL = (
+  chunks: ChunkPath[],
+  type?: "classic" | "module"
+) => string;
 
 interface Module {
   exports: Function | Exports | Promise<Exports> | AsyncModulePromise;
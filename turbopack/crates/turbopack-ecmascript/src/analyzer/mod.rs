@@ -1795,6 +1795,18 @@ impl JsValue {
                       "URL".to_string(),
                       "The standard URL constructor: https://developer.mozilla.org/en-US/docs/Web/API/URL/URL"
                     ),
+                    WellKnownFunctionKind::ServiceWorkerRegister => (
+                      "navigator.serviceWorker.register".to_string(),
+                      "The standard method to register a service worker: https://developer.mozilla.org/en-US/docs/Web/API/ServiceWorkerContainer/register"
+                    ),
+                    WellKnownFunctionKind::AudioWorkletAddModule => (
+                      "audioWorklet.addModule".to_string(),
+                      "The standard method to add a module to an audio worklet: https://developer.mozilla.org/en-US/docs/Web/API/Worklet/addModule"
+                    ),
+                    WellKnownFunctionKind::PaintWorkletAddModule => (
+                      "CSS.paintWorklet.addModule".to_string(),
+                      "The standard method to add a module to the paint worklet: https://developer.mozilla.org/en-US/docs/Web/API/Worklet/addModule"
+                    ),
                 };
                 if depth > 0 {
                     let i = hints.len();
@@ -3811,6 +3823,9 @@ pub enum WellKnownFunctionKind {
     NodeProtobufLoad,
     WorkerConstructor,
     URLConstructor,
+    ServiceWorkerRegister,
+    AudioWorkletAddModule,
+    PaintWorkletAddModule,
 }
 
 impl WellKnownFunctionKind {
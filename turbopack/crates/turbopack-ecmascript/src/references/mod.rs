@@ -78,7 +78,7 @@ use turbopack_resolve::{
 };
 use turbopack_swc_utils::emitter::IssueEmitter;
 use unreachable::Unreachable;
-use worker::WorkerAssetReference;
+use worker::{entry_call, worker_entry_function, worker_type_from_options, WorkerAssetReference};
 
 use self::{
     amd::{
@@ -138,6 +138,7 @@ use crate::{
     },
     tree_shake::{find_turbopack_part_id_in_asserts, part_of_module, split},
     utils::{module_value_to_well_known_object, AstPathRange},
+    worker_chunk::module::WorkerEntryKind,
     EcmascriptInputTransforms, EcmascriptModuleAsset, EcmascriptParsable, SpecifiedModuleType,
     TreeShakingMode,
 };
@@ -1075,6 +1076,11 @@ pub(crate) async fn analyse_ecmascript_module_internal(
                         continue;
                     }
                 }
+                let worker_entry_function = if new {
+                    None
+                } else {
+                    worker_entry_function(&obj, &prop)
+                };
                 let mut obj = analysis_state
                     .link_value(obj, ImportAttributes::empty_ref())
                     .await?;
@@ -1117,12 +1123,16 @@ pub(crate) async fn analyse_ecmascript_module_internal(
                     }
                 }
 
-                let func = analysis_state
-                    .link_value(
-                        JsValue::member(Box::new(obj.clone()), Box::new(prop)),
-                        ImportAttributes::empty_ref(),
-                    )
-                    .await?;
+                let func = if let Some(kind) = worker_entry_function {
+                    JsValue::WellKnownFunction(kind)
+                } else {
+                    analysis_state
+                        .link_value(
+                            JsValue::member(Box::new(obj.clone()), Box::new(prop)),
+                            ImportAttributes::empty_ref(),
+                        )
+                        .await?
+                };
 
                 handle_call(
                     &ast_path,
@@ -1352,7 +1362,7 @@ async fn handle_call<G: Fn(Vec<Effect>) + Send + Sync>(
             }
             JsValue::WellKnownFunction(WellKnownFunctionKind::WorkerConstructor) => {
                 let args = linked_args(args).await?;
-                if let [url @ JsValue::Url(_, JsValueUrlKind::Relative)] = &args[..] {
+                if let [url @ JsValue::Url(_, JsValueUrlKind::Relative), rest @ ..] = &args[..] {
                     let pat = js_value_to_pattern(url);
                     if !pat.has_constant_parts() {
                         let (args, hints) = explain_args(&args);
@@ -1375,6 +1385,7 @@ async fn handle_call<G: Fn(Vec<Effect>) + Send + Sync>(
                             Vc::cell(ast_path.to_vec()),
                             issue_source(source, span),
                             in_try,
+                            WorkerEntryKind::Worker(worker_type_from_options(rest.first())),
                         ));
                     }
 
@@ -1496,6 +1507,53 @@ async fn handle_call<G: Fn(Vec<Effect>) + Send + Sync>(
                 DiagnosticId::Error(errors::failed_to_analyse::ecmascript::REQUIRE.to_string()),
             )
         }
+        JsValue::WellKnownFunction(
+            function @ (WellKnownFunctionKind::ServiceWorkerRegister
+            | WellKnownFunctionKind::AudioWorkletAddModule
+            | WellKnownFunctionKind::PaintWorkletAddModule),
+        ) => {
+            let (kind, error_code) = match function {
+                WellKnownFunctionKind::ServiceWorkerRegister => (
+                    WorkerEntryKind::ServiceWorker,
+                    errors::failed_to_analyse::ecmascript::SERVICE_WORKER_REGISTER,
+                ),
+                WellKnownFunctionKind::AudioWorkletAddModule => (
+                    WorkerEntryKind::AudioWorklet,
+                    errors::failed_to_analyse::ecmascript::WORKLET_ADD_MODULE,
+                ),
+                _ => (
+                    WorkerEntryKind::PaintWorklet,
+                    errors::failed_to_analyse::ecmascript::WORKLET_ADD_MODULE,
+                ),
+            };
+            let args = linked_args(args).await?;
+            // Other URLs than `new URL(..., import.meta.url)` are served as they are
+            if let [url @ JsValue::Url(_, JsValueUrlKind::Relative), ..] = &args[..] {
+                let pat = js_value_to_pattern(url);
+                if !pat.has_constant_parts() {
+                    let (args, hints) = explain_args(&args);
+                    handler.span_warn_with_code(
+                        span,
+                        &format!("{}({args}) is very dynamic{hints}", entry_call(kind)),
+                        DiagnosticId::Lint(error_code.to_string()),
+                    );
+                    if ignore_dynamic_requests {
+                        return Ok(());
+                    }
+                }
+
+                if *compile_time_info.environment().rendering().await? == Rendering::Client {
+                    analysis.add_reference(WorkerAssetReference::new(
+                        origin,
+                        Request::parse(Value::new(pat)),
+                        Vc::cell(ast_path.to_vec()),
+                        issue_source(source, span),
+                        in_try,
+                        kind,
+                    ));
+                }
+            }
+        }
         JsValue::WellKnownFunction(WellKnownFunctionKind::Define) => {
             analyze_amd_define(
                 source,
@@ -1,6 +1,6 @@
 use anyhow::{bail, Result};
 use swc_core::{
-    ecma::ast::{Expr, ExprOrSpread, Lit, NewExpr},
+    ecma::ast::{CallExpr, Expr, ExprOrSpread, Lit, NewExpr},
     quote_expr,
 };
 use turbo_tasks::{RcStr, Value, ValueToString, Vc};
@@ -14,12 +14,75 @@ use turbopack_core::{
 };
 
 use crate::{
+    analyzer::{JsValue, ObjectPart, WellKnownFunctionKind},
     code_gen::{CodeGenerateable, CodeGeneration},
     create_visitor,
     references::AstPath,
-    worker_chunk::module::WorkerLoaderModule,
+    worker_chunk::module::{WorkerEntryKind, WorkerLoaderModule, WorkerType},
 };
 
+/// The kind of worker that `new Worker(url, options)` creates, by the `type` of the `options`.
+/// Types that can't be determined statically fall back to classic workers, like in browsers.
+pub(crate) fn worker_type_from_options(options: Option<&JsValue>) -> WorkerType {
+    let Some(JsValue::Object { parts, .. }) = options else {
+        return WorkerType::Classic;
+    };
+    // The last property wins, unless a spread might override it
+    for part in parts.iter().rev() {
+        match part {
+            ObjectPart::KeyValue(key, value) if key.as_str() == Some("type") => {
+                return match value.as_str() {
+                    Some("module") => WorkerType::Module,
+                    _ => WorkerType::Classic,
+                };
+            }
+            ObjectPart::KeyValue(..) => {}
+            ObjectPart::Spread(_) => break,
+        }
+    }
+    WorkerType::Classic
+}
+
+/// The well-known function that is called by a member call which loads a worker entry, like
+/// `navigator.serviceWorker.register(url)`. The unlinked values are matched, so that feature
+/// checks like `if (navigator.serviceWorker)` are not treated as always true. The `AudioContext`
+/// that an audio worklet belongs to is usually created at runtime, so only its property name is
+/// matched.
+pub(crate) fn worker_entry_function(
+    obj: &JsValue,
+    prop: &JsValue,
+) -> Option<WellKnownFunctionKind> {
+    let JsValue::Member(_, box object, box property) = obj else {
+        return None;
+    };
+    let global = match object {
+        JsValue::FreeVar(name) => Some(&**name),
+        _ => None,
+    };
+    match (global, property.as_str(), prop.as_str()) {
+        (Some("navigator"), Some("serviceWorker"), Some("register")) => {
+            Some(WellKnownFunctionKind::ServiceWorkerRegister)
+        }
+        (Some("CSS"), Some("paintWorklet"), Some("addModule")) => {
+            Some(WellKnownFunctionKind::PaintWorkletAddModule)
+        }
+        (_, Some("audioWorklet"), Some("addModule")) => {
+            Some(WellKnownFunctionKind::AudioWorkletAddModule)
+        }
+        _ => None,
+    }
+}
+
+/// The call that loads the entry of the given kind, as it is written in the source code.
+pub(crate) fn entry_call(kind: WorkerEntryKind) -> &'static str {
+    match kind {
+        WorkerEntryKind::Worker(_) => "new Worker",
+        WorkerEntryKind::AudioWorklet => "audioWorklet.addModule",
+        WorkerEntryKind::PaintWorklet => "CSS.paintWorklet.addModule",
+        WorkerEntryKind::ServiceWorker => "navigator.serviceWorker.register",
+    }
+}
+
 #[turbo_tasks::value]
 #[derive(Hash, Debug)]
 pub struct WorkerAssetReference {
@@ -28,6 +91,7 @@ pub struct WorkerAssetReference {
     pub path: Vc<AstPath>,
     pub issue_source: Vc<IssueSource>,
     pub in_try: bool,
+    pub kind: WorkerEntryKind,
 }
 
 #[turbo_tasks::value_impl]
@@ -39,6 +103,7 @@ impl WorkerAssetReference {
         path: Vc<AstPath>,
         issue_source: Vc<IssueSource>,
         in_try: bool,
+        kind: WorkerEntryKind,
     ) -> Vc<Self> {
         Self::cell(WorkerAssetReference {
             origin,
@@ -46,6 +111,7 @@ impl WorkerAssetReference {
             path,
             issue_source,
             in_try,
+            kind,
         })
     }
 }
@@ -54,11 +120,15 @@ impl WorkerAssetReference {
     async fn worker_loader_module(
         self: &WorkerAssetReference,
     ) -> Result<Option<Vc<WorkerLoaderModule>>> {
+        let sub_type = match self.kind {
+            WorkerEntryKind::ServiceWorker => WorkerReferenceSubType::ServiceWorker,
+            // TODO support more worker types
+            _ => WorkerReferenceSubType::WebWorker,
+        };
         let module = url_resolve(
             self.origin,
             self.request,
-            // TODO support more worker types
-            Value::new(ReferenceType::Worker(WorkerReferenceSubType::WebWorker)),
+            Value::new(ReferenceType::Worker(sub_type)),
             Some(self.issue_source),
             self.in_try,
         );
@@ -79,7 +149,7 @@ impl WorkerAssetReference {
             return Ok(None);
         };
 
-        Ok(Some(WorkerLoaderModule::new(chunkable)))
+        Ok(Some(WorkerLoaderModule::new(chunkable, self.kind)))
     }
 }
 
@@ -100,7 +170,12 @@ impl ValueToString for WorkerAssetReference {
     #[turbo_tasks::function]
     async fn to_string(&self) -> Result<Vc<RcStr>> {
         Ok(Vc::cell(
-            format!("new Worker {}", self.request.to_string().await?,).into(),
+            format!(
+                "{} {}",
+                entry_call(self.kind),
+                self.request.to_string().await?,
+            )
+            .into(),
         ))
     }
 }
@@ -124,39 +199,99 @@ impl CodeGenerateable for WorkerAssetReference {
             .await?;
 
         let path = &self.path.await?;
+        let call = entry_call(self.kind);
 
         let visitor = create_visitor!(path, visit_mut_expr(expr: &mut Expr) {
-            let message = if let Expr::New(NewExpr { args, ..}) = expr {
-                if let Some(args) = args {
-                    match args.iter_mut().next() {
-                        Some(ExprOrSpread { spread: None, expr }) => {
-                            let item_id = Expr::Lit(Lit::Str(item_id.to_string().into()));
-                            *expr = quote_expr!(
-                                "__turbopack_require__($item_id)",
-                                item_id: Expr = item_id
-                            );
-                            return;
-                        }
-                        // These are SWC bugs: https://github.com/swc-project/swc/issues/5394
-                        Some(ExprOrSpread { spread: Some(_), expr: _ }) => {
-                            "spread operator is illegal in new Worker() expressions."
-                        }
-                        _ => {
-                            "new Worker() expressions require at least 1 argument"
-                        }
+            let args = match expr {
+                Expr::New(NewExpr { args, .. }) => args.as_mut(),
+                Expr::Call(CallExpr { args, .. }) => Some(args),
+                _ => None,
+            };
+            let message = if let Some(args) = args {
+                match args.iter_mut().next() {
+                    Some(ExprOrSpread { spread: None, expr }) => {
+                        let item_id = Expr::Lit(Lit::Str(item_id.to_string().into()));
+                        *expr = quote_expr!(
+                            "__turbopack_require__($item_id)",
+                            item_id: Expr = item_id
+                        );
+                        return;
+                    }
+                    // These are SWC bugs: https://github.com/swc-project/swc/issues/5394
+                    Some(ExprOrSpread { spread: Some(_), expr: _ }) => {
+                        format!("spread operator is illegal in {call}() expressions.")
+                    }
+                    _ => {
+                        format!("{call}() expressions require at least 1 argument")
                     }
-                } else {
-                    "new Worker() expressions require at least 1 argument"
                 }
             } else {
-                "visitor must be executed on a NewExpr"
+                "visitor must be executed on a NewExpr or CallExpr".to_string()
             };
             *expr = *quote_expr!(
                 "(() => { throw new Error($message); })()",
-                message: Expr = Expr::Lit(Lit::Str(message.into()))
+                message: Expr = Expr::Lit(Lit::Str(message.as_str().into()))
             );
         });
 
         Ok(CodeGeneration::visitors(vec![visitor]))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::worker_type_from_options;
+    use crate::{
+        analyzer::{JsValue, ObjectPart},
+        worker_chunk::module::WorkerType,
+    };
+
+    fn options(parts: Vec<ObjectPart>) -> JsValue {
+        JsValue::object(parts)
+    }
+
+    fn worker_type(value: &str) -> ObjectPart {
+        ObjectPart::KeyValue("type".into(), value.into())
+    }
+
+    #[test]
+    fn test_worker_type_without_options() {
+        assert_eq!(worker_type_from_options(None), WorkerType::Classic);
+    }
+
+    #[test]
+    fn test_worker_type_module() {
+        let options = options(vec![worker_type("module")]);
+        assert_eq!(worker_type_from_options(Some(&options)), WorkerType::Module);
+    }
+
+    #[test]
+    fn test_worker_type_classic() {
+        let options = options(vec![
+            ObjectPart::KeyValue("name".into(), "worker".into()),
+            worker_type("classic"),
+        ]);
+        assert_eq!(
+            worker_type_from_options(Some(&options)),
+            WorkerType::Classic
+        );
+    }
+
+    #[test]
+    fn test_worker_type_last_property_wins() {
+        let options = options(vec![worker_type("classic"), worker_type("module")]);
+        assert_eq!(worker_type_from_options(Some(&options)), WorkerType::Module);
+    }
+
+    #[test]
+    fn test_worker_type_overridden_by_spread() {
+        let options = options(vec![
+            worker_type("module"),
+            ObjectPart::Spread(JsValue::unknown_empty(false, "spread")),
+        ]);
+        assert_eq!(
+            worker_type_from_options(Some(&options)),
+            WorkerType::Classic
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{bail, Result};
 use indoc::formatdoc;
+use serde::Serialize;
 use turbo_tasks::{RcStr, TryJoinIterExt, Value, ValueToString, Vc};
 use turbopack_core::{
     chunk::{
@@ -8,11 +9,14 @@ use turbopack_core::{
     },
     ident::AssetIdent,
     module::Module,
-    output::OutputAssets,
+    output::{OutputAsset, OutputAssets},
     reference::{ModuleReferences, SingleOutputAssetReference},
 };
 
-use super::module::WorkerLoaderModule;
+use super::{
+    module::{WorkerEntryKind, WorkerLoaderModule, WorkerType},
+    output_asset::WorkerEntryAsset,
+};
 use crate::{
     chunk::{
         data::EcmascriptChunkData, EcmascriptChunkItem, EcmascriptChunkItemContent,
@@ -32,6 +36,39 @@ pub fn worker_modifier() -> Vc<RcStr> {
     Vc::cell("worker".into())
 }
 
+#[turbo_tasks::function]
+pub fn worklet_modifier() -> Vc<RcStr> {
+    Vc::cell("worklet".into())
+}
+
+#[turbo_tasks::function]
+pub fn service_worker_modifier() -> Vc<RcStr> {
+    Vc::cell("service worker".into())
+}
+
+#[turbo_tasks::function]
+fn worklet_entry_modifier() -> Vc<RcStr> {
+    Vc::cell("worklet entry".into())
+}
+
+/// The code of a worker loader, which exports a blob URL of a script that loads the `chunks`.
+fn worker_loader_code<T: Serialize + ?Sized>(worker_type: WorkerType, chunks: &T) -> String {
+    match worker_type {
+        WorkerType::Classic => formatdoc! {
+            r#"
+                __turbopack_export_value__(__turbopack_worker_blob_url__({chunks:#}));
+            "#,
+            chunks = StringifyJs(chunks),
+        },
+        WorkerType::Module => formatdoc! {
+            r#"
+                __turbopack_export_value__(__turbopack_worker_blob_url__({chunks:#}, "module"));
+            "#,
+            chunks = StringifyJs(chunks),
+        },
+    }
+}
+
 #[turbo_tasks::value_impl]
 impl WorkerLoaderChunkItem {
     #[turbo_tasks::function]
@@ -47,12 +84,16 @@ impl WorkerLoaderChunkItem {
             );
         };
 
-        Ok(self.chunking_context.evaluated_chunk_group_assets(
-            AssetIdent::from_path(
-                self.chunking_context
-                    .chunk_path(module.inner.ident(), ".js".into()),
-            )
-            .with_modifier(worker_modifier()),
+        let (chunking_context, modifier) = match module.kind {
+            WorkerEntryKind::Worker(_) => (self.chunking_context, worker_modifier()),
+            WorkerEntryKind::AudioWorklet | WorkerEntryKind::PaintWorklet => {
+                (self.chunking_context.without_dom(), worklet_modifier())
+            }
+            WorkerEntryKind::ServiceWorker => (self.chunking_context, service_worker_modifier()),
+        };
+        Ok(chunking_context.evaluated_chunk_group_assets(
+            AssetIdent::from_path(chunking_context.chunk_path(module.inner.ident(), ".js".into()))
+                .with_modifier(modifier),
             EvaluatableAssets::empty().with_entry(evaluatable),
             Value::new(AvailabilityInfo::Root),
         ))
@@ -66,6 +107,37 @@ impl WorkerLoaderChunkItem {
             self.chunks(),
         ))
     }
+
+    /// The script that is passed to `addModule()` of a worklet or is registered as a service
+    /// worker. It loads the chunks directly, as there is no way to bootstrap the runtime from a
+    /// blob like workers do.
+    #[turbo_tasks::function]
+    async fn entry(self: Vc<Self>) -> Result<Vc<WorkerEntryAsset>> {
+        let this = self.await?;
+        let module = this.module.await?;
+        let path = match module.kind {
+            WorkerEntryKind::Worker(_) => {
+                bail!("workers are loaded from a blob URL and have no entry script")
+            }
+            WorkerEntryKind::AudioWorklet | WorkerEntryKind::PaintWorklet => {
+                this.chunking_context.chunk_path(
+                    module.inner.ident().with_modifier(worklet_entry_modifier()),
+                    ".js".into(),
+                )
+            }
+            WorkerEntryKind::ServiceWorker => {
+                let name = module.inner.ident().path().file_stem().await?;
+                let Some(name) = &*name else {
+                    bail!("the service worker module has no file name");
+                };
+                this.chunking_context
+                    .chunk_path(module.inner.ident(), ".js".into())
+                    .parent()
+                    .join(format!("{name}.js").into())
+            }
+        };
+        Ok(WorkerEntryAsset::new(path, module.kind, self.chunks()))
+    }
 }
 
 #[turbo_tasks::value_impl]
@@ -77,18 +149,26 @@ impl EcmascriptChunkItem for WorkerLoaderChunkItem {
 
     #[turbo_tasks::function]
     async fn content(self: Vc<Self>) -> Result<Vc<EcmascriptChunkItemContent>> {
-        let chunks_data = self.chunks_data().await?;
-        let chunks_data = chunks_data.iter().try_join().await?;
-        let chunks_data: Vec<_> = chunks_data
-            .iter()
-            .map(|chunk_data| EcmascriptChunkData::new(chunk_data))
-            .collect();
-
-        let code = formatdoc! {
-            r#"
-                __turbopack_export_value__(__turbopack_worker_blob_url__({chunks:#}));
-            "#,
-            chunks = StringifyJs(&chunks_data),
+        let this = self.await?;
+        let code = match this.module.await?.kind {
+            WorkerEntryKind::Worker(worker_type) => {
+                let chunks_data = self.chunks_data().await?;
+                let chunks_data = chunks_data.iter().try_join().await?;
+                let chunks_data: Vec<_> = chunks_data
+                    .iter()
+                    .map(|chunk_data| EcmascriptChunkData::new(chunk_data))
+                    .collect();
+                worker_loader_code(worker_type, &chunks_data)
+            }
+            WorkerEntryKind::AudioWorklet
+            | WorkerEntryKind::PaintWorklet
+            | WorkerEntryKind::ServiceWorker => {
+                let url = this
+                    .chunking_context
+                    .asset_url(self.entry().ident())
+                    .await?;
+                format!("__turbopack_export_value__({});", StringifyJs(&url))
+            }
         };
 
         Ok(EcmascriptChunkItemContent {
@@ -104,6 +184,11 @@ fn chunk_reference_description() -> Vc<RcStr> {
     Vc::cell("worker chunk".into())
 }
 
+#[turbo_tasks::function]
+fn entry_reference_description() -> Vc<RcStr> {
+    Vc::cell("worker entry".into())
+}
+
 #[turbo_tasks::value_impl]
 impl ChunkItem for WorkerLoaderChunkItem {
     #[turbo_tasks::function]
@@ -118,6 +203,14 @@ impl ChunkItem for WorkerLoaderChunkItem {
 
     #[turbo_tasks::function]
     async fn references(self: Vc<Self>) -> Result<Vc<ModuleReferences>> {
+        // The chunks of worklets and service workers are referenced by their entry
+        if !matches!(self.await?.module.await?.kind, WorkerEntryKind::Worker(_)) {
+            return Ok(Vc::cell(vec![Vc::upcast(SingleOutputAssetReference::new(
+                Vc::upcast(self.entry()),
+                entry_reference_description(),
+            ))]));
+        }
+
         let chunks = self.chunks();
 
         Ok(Vc::cell(
@@ -152,3 +245,28 @@ impl ChunkItem for WorkerLoaderChunkItem {
         Vc::upcast(self.module)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::worker_loader_code;
+    use crate::worker_chunk::module::WorkerType;
+
+    #[test]
+    fn test_classic_worker_loader_code() {
+        let code = worker_loader_code(WorkerType::Classic, &["chunk.js"]);
+        assert_eq!(
+            code,
+            "__turbopack_export_value__(__turbopack_worker_blob_url__([\n  \"chunk.js\"\n]));\n"
+        );
+    }
+
+    #[test]
+    fn test_module_worker_loader_code() {
+        let code = worker_loader_code(WorkerType::Module, &["chunk.js"]);
+        assert_eq!(
+            code,
+            "__turbopack_export_value__(__turbopack_worker_blob_url__([\n  \"chunk.js\"\n], \
+             \"module\"));\n"
+        );
+    }
+}
@@ -1,2 +1,3 @@
 pub mod chunk_item;
 pub mod module;
+pub mod output_asset;
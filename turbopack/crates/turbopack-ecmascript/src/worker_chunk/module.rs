@@ -1,5 +1,5 @@
-use anyhow::Result;
-use turbo_tasks::{RcStr, Vc};
+use anyhow::{bail, Result};
+use turbo_tasks::{RcStr, TaskInput, Vc};
 use turbopack_core::{
     asset::{Asset, AssetContent},
     chunk::{ChunkableModule, ChunkingContext},
@@ -10,28 +10,76 @@ use turbopack_core::{
 
 use super::chunk_item::WorkerLoaderChunkItem;
 
+/// How a worker loads the chunks of its chunk group, by the `type` passed to
+/// `new Worker(url, { type })`.
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Default, Clone, Copy, Hash, TaskInput)]
+pub enum WorkerType {
+    /// A classic worker script that loads its chunks with `importScripts`.
+    #[default]
+    Classic,
+    /// A module worker that loads its chunks with `import()`. This makes `import.meta` and
+    /// top-level await available inside of the worker.
+    Module,
+}
+
+/// The kind of entry that loads the chunk group of a [WorkerLoaderModule].
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Debug, Clone, Copy, Hash, TaskInput)]
+pub enum WorkerEntryKind {
+    /// Loaded with `new Worker(url, { type })`. The chunks are loaded by a script from a blob URL.
+    Worker(WorkerType),
+    /// Loaded with `audioContext.audioWorklet.addModule(url)`. Worklets have no DOM and can't load
+    /// scripts on their own, so their chunks are imported by an entry script and run with a
+    /// runtime that doesn't need a DOM.
+    AudioWorklet,
+    /// Loaded with `CSS.paintWorklet.addModule(url)`.
+    PaintWorklet,
+    /// Registered with `navigator.serviceWorker.register(url)`. Service workers can't be started
+    /// from a blob URL, so their entry script is emitted next to the chunks and named like the
+    /// module, which keeps its URL the same across builds.
+    ServiceWorker,
+}
+
 #[turbo_tasks::function]
-fn modifier() -> Vc<RcStr> {
-    Vc::cell("worker loader".into())
+fn modifier(kind: WorkerEntryKind) -> Vc<RcStr> {
+    Vc::cell(
+        match kind {
+            WorkerEntryKind::Worker(WorkerType::Classic) => "worker loader",
+            WorkerEntryKind::Worker(WorkerType::Module) => "module worker loader",
+            WorkerEntryKind::AudioWorklet => "audio worklet loader",
+            WorkerEntryKind::PaintWorklet => "paint worklet loader",
+            WorkerEntryKind::ServiceWorker => "service worker loader",
+        }
+        .into(),
+    )
 }
 
 /// The WorkerLoaderModule is a module that creates a separate root chunk group for the given module
-/// and exports a URL to pass to the worker constructor.
+/// and exports a URL to pass to the worker constructor, to `addModule()` of a worklet or to
+/// `navigator.serviceWorker.register()`.
 #[turbo_tasks::value]
 pub struct WorkerLoaderModule {
     pub inner: Vc<Box<dyn ChunkableModule>>,
+    pub kind: WorkerEntryKind,
 }
 
 #[turbo_tasks::value_impl]
 impl WorkerLoaderModule {
     #[turbo_tasks::function]
-    pub fn new(module: Vc<Box<dyn ChunkableModule>>) -> Vc<Self> {
-        Self::cell(WorkerLoaderModule { inner: module })
+    pub fn new(module: Vc<Box<dyn ChunkableModule>>, kind: WorkerEntryKind) -> Vc<Self> {
+        Self::cell(WorkerLoaderModule {
+            inner: module,
+            kind,
+        })
     }
 
     #[turbo_tasks::function]
-    pub fn asset_ident_for(module: Vc<Box<dyn ChunkableModule>>) -> Vc<AssetIdent> {
-        module.ident().with_modifier(modifier())
+    pub fn asset_ident_for(
+        module: Vc<Box<dyn ChunkableModule>>,
+        kind: WorkerEntryKind,
+    ) -> Vc<AssetIdent> {
+        module.ident().with_modifier(modifier(kind))
     }
 }
 
@@ -44,7 +92,7 @@ fn inner_module_reference_description() -> Vc<RcStr> {
 impl Module for WorkerLoaderModule {
     #[turbo_tasks::function]
     fn ident(&self) -> Vc<AssetIdent> {
-        Self::asset_ident_for(self.inner)
+        Self::asset_ident_for(self.inner, self.kind)
     }
 
     #[turbo_tasks::function]
@@ -59,8 +107,8 @@ impl Module for WorkerLoaderModule {
 #[turbo_tasks::value_impl]
 impl Asset for WorkerLoaderModule {
     #[turbo_tasks::function]
-    fn content(&self) -> Vc<AssetContent> {
-        todo!()
+    fn content(&self) -> Result<Vc<AssetContent>> {
+        bail!("the worker loader module only exists as a chunk item and has no content")
     }
 }
 
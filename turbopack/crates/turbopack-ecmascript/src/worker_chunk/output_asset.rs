@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use turbo_tasks::{RcStr, Vc};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    ident::AssetIdent,
+    output::{OutputAsset, OutputAssets},
+};
+
+use super::module::WorkerEntryKind;
+use crate::utils::StringifyJs;
+
+/// The script a worklet or a service worker is loaded with. It loads the chunks in order, relative
+/// to its own location.
+#[turbo_tasks::value]
+pub struct WorkerEntryAsset {
+    path: Vc<FileSystemPath>,
+    kind: WorkerEntryKind,
+    chunks: Vc<OutputAssets>,
+}
+
+#[turbo_tasks::value_impl]
+impl WorkerEntryAsset {
+    #[turbo_tasks::function]
+    pub fn new(
+        path: Vc<FileSystemPath>,
+        kind: WorkerEntryKind,
+        chunks: Vc<OutputAssets>,
+    ) -> Vc<Self> {
+        WorkerEntryAsset { path, kind, chunks }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl OutputAsset for WorkerEntryAsset {
+    #[turbo_tasks::function]
+    fn ident(&self) -> Vc<AssetIdent> {
+        AssetIdent::from_path(self.path)
+    }
+
+    #[turbo_tasks::function]
+    fn references(&self) -> Vc<OutputAssets> {
+        self.chunks
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for WorkerEntryAsset {
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<Vc<AssetContent>> {
+        let directory = self.path.parent().await?;
+
+        let mut chunk_urls = Vec::new();
+        for chunk in self.chunks.await?.iter() {
+            let chunk_path = chunk.ident().path().await?;
+            if chunk_path.extension_ref() != Some("js") {
+                continue;
+            }
+            if let Some(relative_path) = directory.get_relative_path_to(&chunk_path) {
+                chunk_urls.push(relative_path);
+            }
+        }
+
+        Ok(AssetContent::file(
+            File::from(entry_code(self.kind, &chunk_urls)?).into(),
+        ))
+    }
+}
+
+/// Loads the chunks in order. The last one is the evaluated chunk containing the runtime, which
+/// instantiates the entry once all of the chunks have been registered.
+fn entry_code(kind: WorkerEntryKind, chunk_urls: &[RcStr]) -> Result<String> {
+    match kind {
+        WorkerEntryKind::Worker(_) => {
+            bail!("workers are loaded from a blob URL and have no entry script")
+        }
+        WorkerEntryKind::AudioWorklet | WorkerEntryKind::PaintWorklet => {
+            worklet_entry_code(chunk_urls)
+        }
+        WorkerEntryKind::ServiceWorker => service_worker_entry_code(chunk_urls),
+    }
+}
+
+/// Worklets can't use `importScripts` and have no `location` to resolve chunk urls against.
+fn worklet_entry_code(chunk_urls: &[RcStr]) -> Result<String> {
+    let mut code = String::new();
+    for chunk_url in chunk_urls {
+        writeln!(code, "import {};", StringifyJs(chunk_url))?;
+    }
+    Ok(code)
+}
+
+/// Chunks that are loaded later on are resolved against the origin, like in other workers.
+fn service_worker_entry_code(chunk_urls: &[RcStr]) -> Result<String> {
+    let mut code = String::new();
+    writeln!(
+        code,
+        "self.TURBOPACK_WORKER_LOCATION = self.location.origin;"
+    )?;
+    writeln!(code, "importScripts(...{});", StringifyJs(chunk_urls))?;
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{entry_code, service_worker_entry_code};
+    use crate::worker_chunk::module::{WorkerEntryKind, WorkerType};
+
+    #[test]
+    fn test_worklet_entry_code() {
+        let code = entry_code(
+            WorkerEntryKind::PaintWorklet,
+            &["./a.js".into(), "../b.js".into()],
+        )
+        .unwrap();
+        assert_eq!(code, "import \"./a.js\";\nimport \"../b.js\";\n");
+    }
+
+    #[test]
+    fn test_service_worker_entry_code() {
+        let code = service_worker_entry_code(&["./a.js".into(), "../b.js".into()]).unwrap();
+        assert_eq!(
+            code,
+            "self.TURBOPACK_WORKER_LOCATION = \
+             self.location.origin;\nimportScripts(...[\"./a.js\",\"../b.js\"]);\n"
+        );
+    }
+
+    #[test]
+    fn test_worker_has_no_entry_code() {
+        assert!(entry_code(WorkerEntryKind::Worker(WorkerType::Classic), &[]).is_err());
+    }
+}
@@ -193,6 +193,12 @@ impl ChunkingContext for NodeJsChunkingContext {
         self.environment
     }
 
+    #[turbo_tasks::function]
+    fn without_dom(self: Vc<Self>) -> Vc<Box<dyn ChunkingContext>> {
+        // The Node.js runtime doesn't use a DOM
+        Vc::upcast(self)
+    }
+
     #[turbo_tasks::function]
     async fn asset_url(self: Vc<Self>, ident: Vc<AssetIdent>) -> Result<Vc<RcStr>> {
         let this = self.await?;
@@ -0,0 +1,173 @@
+#![cfg(test)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+mod util;
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::PathBuf,
+};
+
+use anyhow::{bail, Result};
+use dunce::canonicalize;
+use turbo_tasks::{RcStr, TurboTasks, Value, Vc};
+use turbo_tasks_fs::{util::sys_to_unix, DiskFileSystem, FileContent, FileSystem};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack::{module_options::ModuleOptionsContext, ModuleAssetContext};
+use turbopack_browser::BrowserChunkingContext;
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::{
+        availability_info::AvailabilityInfo, ChunkingContext, ChunkingContextExt, EvaluatableAsset,
+        EvaluatableAssets,
+    },
+    compile_time_info::CompileTimeInfo,
+    context::AssetContext,
+    environment::{BrowserEnvironment, Environment, ExecutionEnvironment},
+    file_source::FileSource,
+    module::Module,
+    output::OutputAsset,
+    reference_type::{EntryReferenceSubType, ReferenceType},
+};
+use turbopack_ecmascript_runtime::RuntimeType;
+use turbopack_resolve::resolve_options_context::ResolveOptionsContext;
+
+use crate::util::REPO_ROOT;
+
+fn register() {
+    turbo_tasks::register();
+    turbo_tasks_fs::register();
+    turbopack::register();
+    turbopack_browser::register();
+    turbopack_ecmascript_runtime::register();
+    turbopack_resolve::register();
+}
+
+/// Builds `tests/worker_entries/<name>/input/index.js` for the browser and returns the contents
+/// of the emitted files by their path inside of the output directory.
+async fn build(name: &str) -> Result<HashMap<String, String>> {
+    register();
+
+    let test_path = canonicalize(
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/worker_entries")
+            .join(name),
+    )?;
+    let relative_path = sys_to_unix(test_path.strip_prefix(&*REPO_ROOT)?.to_str().unwrap());
+    let relative_path: RcStr = relative_path.into_owned().into();
+
+    let tt = TurboTasks::new(MemoryBackend::default());
+    tt.run_once(async move {
+        let project_fs = DiskFileSystem::new("project".into(), REPO_ROOT.clone(), vec![]);
+        let project_root = project_fs.root();
+        let path = project_root.join(relative_path);
+        let output_path = path.join("output".into());
+
+        let env = Environment::new(Value::new(ExecutionEnvironment::Browser(
+            BrowserEnvironment {
+                dom: true,
+                web_worker: false,
+                service_worker: false,
+                browserslist_query: "Chrome 102".into(),
+            }
+            .into(),
+        )));
+        let asset_context: Vc<Box<dyn AssetContext>> = Vc::upcast(ModuleAssetContext::new(
+            Default::default(),
+            CompileTimeInfo::builder(env).cell(),
+            ModuleOptionsContext::default().cell(),
+            ResolveOptionsContext::default().cell(),
+            Vc::cell("test".into()),
+        ));
+        let chunking_context: Vc<Box<dyn ChunkingContext>> = Vc::upcast(
+            BrowserChunkingContext::builder(
+                project_root,
+                path,
+                path,
+                output_path,
+                path.join("static".into()),
+                env,
+                RuntimeType::Dummy,
+            )
+            .build(),
+        );
+
+        let entry_module = asset_context
+            .process(
+                Vc::upcast(FileSource::new(path.join("input/index.js".into()))),
+                Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined)),
+            )
+            .module();
+        let Some(entry) =
+            Vc::try_resolve_sidecast::<Box<dyn EvaluatableAsset>>(entry_module).await?
+        else {
+            bail!("the entry module is not evaluatable");
+        };
+        let chunks = chunking_context.evaluated_chunk_group_assets(
+            entry_module.ident(),
+            EvaluatableAssets::empty().with_entry(entry),
+            Value::new(AvailabilityInfo::Root),
+        );
+
+        let output_path = output_path.await?;
+        let mut files = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<_> = chunks.await?.iter().copied().collect();
+        while let Some(asset) = queue.pop_front() {
+            let path = asset.ident().path().await?;
+            if !seen.insert(path.path.clone()) {
+                continue;
+            }
+            queue.extend(asset.references().await?.iter().copied());
+
+            let Some(file_path) = output_path.get_path_to(&path) else {
+                continue;
+            };
+            if let AssetContent::File(content) = &*asset.content().await? {
+                if let FileContent::Content(file) = &*content.await? {
+                    files.insert(file_path.to_string(), file.content().to_str()?.into_owned());
+                }
+            }
+        }
+        Ok(files)
+    })
+    .await
+}
+
+#[tokio::test]
+async fn service_worker_entry_is_emitted_at_a_fixed_path() {
+    let files = build("service-worker").await.unwrap();
+
+    let entry = files
+        .get("sw.js")
+        .unwrap_or_else(|| panic!("sw.js is not emitted, only {:?}", files.keys()));
+    assert!(entry.contains("importScripts("), "{entry}");
+
+    // The registration is passed the URL of the entry instead of a blob URL
+    assert!(
+        files
+            .values()
+            .any(|content| content.contains("__turbopack_export_value__(\"/output/sw.js\")")),
+        "{files:#?}"
+    );
+}
+
+#[tokio::test]
+async fn worklet_entries_import_their_chunks() {
+    let files = build("worklets").await.unwrap();
+
+    let entries: Vec<_> = files
+        .iter()
+        .filter(|(_, content)| content.starts_with("import "))
+        .collect();
+    assert_eq!(entries.len(), 2, "{files:#?}");
+    for (path, content) in entries {
+        // `addModule()` is passed the URL of the entry
+        assert!(
+            files.values().any(|loader| loader
+                .contains(&format!("__turbopack_export_value__(\"/output/{path}\")"))),
+            "{path} is not loaded"
+        );
+        assert!(!content.contains("importScripts"), "{content}");
+    }
+}
@@ -0,0 +1 @@
+navigator.serviceWorker.register(new URL("./sw.js", import.meta.url));
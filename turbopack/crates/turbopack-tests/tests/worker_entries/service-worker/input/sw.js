@@ -0,0 +1,3 @@
+self.addEventListener("install", () => {
+  console.log("installed");
+});
@@ -0,0 +1,4 @@
+const context = new AudioContext();
+context.audioWorklet.addModule(new URL("./processor.js", import.meta.url));
+
+CSS.paintWorklet.addModule(new URL("./painter.js", import.meta.url));
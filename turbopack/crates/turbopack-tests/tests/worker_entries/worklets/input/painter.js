@@ -0,0 +1,6 @@
+registerPaint(
+  "blank",
+  class {
+    paint() {}
+  }
+);
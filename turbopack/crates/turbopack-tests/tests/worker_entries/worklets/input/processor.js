@@ -0,0 +1,8 @@
+registerProcessor(
+  "silence",
+  class extends AudioWorkletProcessor {
+    process() {
+      return true;
+    }
+  }
+);